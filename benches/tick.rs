@@ -0,0 +1,103 @@
+//! Criterion benchmarks for `Universe::tick`, run natively.
+//!
+//! ```text
+//! cargo bench --bench tick
+//! ```
+//!
+//! To compare a change against the current code, save a baseline before the
+//! change and compare against it afterwards:
+//!
+//! ```text
+//! cargo bench --bench tick -- --save-baseline before
+//! # ...make the change...
+//! cargo bench --bench tick -- --baseline before
+//! ```
+//!
+//! The benchmark IDs (`tick/64`, `tick_bit_parallel/1024`,
+//! `tick_many_100/256` and so on) are kept stable so that numbers from
+//! different changes line up.
+
+#[macro_use]
+extern crate criterion;
+extern crate wasm_game_of_life;
+
+use criterion::{BatchSize, BenchmarkId, Criterion};
+use wasm_game_of_life::{Algorithm, SparseUniverse, Transform, Universe};
+
+const SIZES: [u32; 3] = [64, 256, 1024];
+const DENSITY: f64 = 0.3;
+const SEED: u64 = 42;
+
+/// A fresh random board for each batch, so every measurement starts from
+/// the same soup rather than whatever earlier iterations left behind.
+fn soup(size: u32) -> Universe {
+    Universe::new_random(size, size, DENSITY, SEED)
+}
+
+fn tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    for &size in SIZES.iter() {
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter_batched_ref(|| soup(size), |universe| universe.tick(), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+fn tick_bit_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick_bit_parallel");
+    for &size in SIZES.iter() {
+        let setup = || {
+            let mut universe = soup(size);
+            universe.set_algorithm(Algorithm::BitParallel);
+            universe
+        };
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter_batched_ref(setup, |universe| universe.tick(), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+fn tick_many(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick_many_100");
+    group.sample_size(10);
+    for &size in SIZES.iter() {
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter_batched_ref(|| soup(size), |universe| universe.tick_many(100), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+const GLIDER: &str = "x = 3, y = 3\nbo$2bo$3o!";
+const HUGE: u32 = 4096;
+
+/// A lone glider in a huge, empty space, where the sparse universe only
+/// visits a few dozen cells per tick and the dense one all of them.
+fn glider_in_huge_space(c: &mut Criterion) {
+    let mut group = c.benchmark_group("glider_4096");
+    group.sample_size(10);
+    group.bench_function("dense", |b| {
+        let setup = || {
+            let mut universe = Universe::new();
+            universe.set_width(HUGE);
+            universe.set_height(HUGE);
+            universe.insert_rle(HUGE / 2, HUGE / 2, GLIDER, Transform::Identity).unwrap();
+            universe
+        };
+        b.iter_batched_ref(setup, |universe| universe.tick(), BatchSize::LargeInput)
+    });
+    group.bench_function("sparse", |b| {
+        let setup = || {
+            let mut universe = SparseUniverse::new(HUGE, HUGE);
+            universe.insert_rle(HUGE / 2, HUGE / 2, GLIDER).unwrap();
+            universe
+        };
+        b.iter_batched_ref(setup, |universe| universe.tick(), BatchSize::SmallInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tick, tick_bit_parallel, tick_many, glider_in_huge_space);
+criterion_main!(benches);
@@ -0,0 +1,152 @@
+//! Telling when a board has settled down from its population alone, to
+//! spot methuselahs: small patterns that take a long time to.
+
+use std::collections::VecDeque;
+
+/// The longest period a settled population can swing with. Ash mixing
+/// oscillators of periods 2, 3 and 5, or 2 and 15, repeats every 30
+/// generations.
+pub const MAX_POPULATION_PERIOD: usize = 30;
+
+/// How many generations the population has to keep to a period before the
+/// board counts as settled, unless told otherwise.
+pub const DEFAULT_SETTLE_WINDOW: u64 = 200;
+
+/// Watches a board's population generation by generation for the point
+/// after which it only repeats with some short period. Unlike still-life
+/// detection this takes oscillators, and gliders flying off, as settled.
+#[derive(Clone, Debug)]
+pub struct Activity {
+    /// How long the population must repeat to count as settled.
+    window: u64,
+    /// The populations of the latest generations, newest last.
+    recent: VecDeque<u32>,
+    /// The generation of the newest population.
+    generation: u64,
+    /// The generation of the first population since starting over.
+    start: u64,
+    /// For each period from 1, the generation since which the population
+    /// has repeated with it.
+    since: [u64; MAX_POPULATION_PERIOD],
+}
+
+impl Activity {
+    /// A watcher that counts a population repeating for `window`
+    /// generations as settled.
+    pub fn new(window: u64) -> Activity {
+        Activity {
+            window,
+            recent: VecDeque::with_capacity(MAX_POPULATION_PERIOD + 1),
+            generation: 0,
+            start: 0,
+            since: [0; MAX_POPULATION_PERIOD],
+        }
+    }
+
+    /// Count the population repeating for `window` generations as settled
+    /// from now on.
+    pub fn set_window(&mut self, window: u64) {
+        self.window = window;
+    }
+
+    /// Note a tick to `generation` that took the population from `before`
+    /// to `after`, starting over from `before` unless it follows on from
+    /// the last population noted.
+    pub fn record_tick(&mut self, generation: u64, before: u32, after: u32) {
+        if self.recent.is_empty() || generation != self.generation + 1 {
+            self.record(generation.saturating_sub(1), before);
+        }
+        self.record(generation, after);
+    }
+
+    /// Note the population at `generation`. Anything but the generation
+    /// after the last one noted, such as a step back, starts over from it.
+    pub fn record(&mut self, generation: u64, population: u32) {
+        if self.recent.is_empty() || generation != self.generation + 1 {
+            self.recent.clear();
+            self.start = generation;
+            self.since = [generation; MAX_POPULATION_PERIOD];
+        }
+        self.generation = generation;
+        if self.recent.len() > MAX_POPULATION_PERIOD {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(population);
+        let newest = self.recent.len() - 1;
+        for (i, since) in self.since.iter_mut().enumerate() {
+            let period = i + 1;
+            if period <= newest && self.recent[newest - period] != population {
+                *since = generation;
+            }
+        }
+    }
+
+    /// The generation since which the population has only repeated, the
+    /// last time activity changed it. Still going, that's about now; for a
+    /// settled board, its lifespan.
+    pub fn lifespan_so_far(&self) -> u64 {
+        // Only periods seen through at least once say anything.
+        let seen = usize::min((self.generation - self.start) as usize, MAX_POPULATION_PERIOD);
+        self.since[..seen].iter().cloned().min().unwrap_or(self.generation)
+    }
+
+    /// Whether the population has repeated with a period of up to
+    /// `MAX_POPULATION_PERIOD` for the last `window` generations.
+    pub fn is_settled(&self) -> bool {
+        !self.recent.is_empty() && self.generation - self.lifespan_so_far() >= self.window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashlife::Hashlife;
+
+    /// The generation a pattern settles at, by single steps of hashlife on
+    /// an unbounded plane, so escaping gliders never come back.
+    fn settles_at(cells: &[(i64, i64)], max_generations: u64) -> Option<(u64, u64)> {
+        let mut life = Hashlife::new();
+        for &(row, col) in cells {
+            life.set_cell(row, col).unwrap();
+        }
+        let mut activity = Activity::new(DEFAULT_SETTLE_WINDOW);
+        activity.record(0, life.population() as u32);
+        while life.generation() < max_generations {
+            life.fast_forward(1).unwrap();
+            activity.record(life.generation(), life.population() as u32);
+            if activity.is_settled() {
+                return Some((activity.lifespan_so_far(), life.population()));
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn methuselahs() {
+        let r_pentomino = [(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)];
+        assert_eq!(settles_at(&r_pentomino, 2000), Some((1103, 116)));
+        let acorn = [(0, 1), (1, 3), (2, 0), (2, 1), (2, 4), (2, 5), (2, 6)];
+        assert_eq!(settles_at(&acorn, 6000), Some((5206, 633)));
+    }
+
+    #[test]
+    fn oscillations_count_as_settled() {
+        let mut activity = Activity::new(10);
+        // Growing, then swinging between two values. Generation 6 is the
+        // last that differs from two before.
+        for (generation, &population) in [3, 4, 6, 9, 7, 6, 8, 6, 8, 6, 8, 6, 8, 6, 8].iter().enumerate() {
+            activity.record(generation as u64, population);
+        }
+        assert_eq!(activity.lifespan_so_far(), 6);
+        assert!(!activity.is_settled());
+        for generation in 15..20 {
+            activity.record(generation, if generation % 2 == 0 { 8 } else { 6 });
+        }
+        assert!(activity.is_settled());
+
+        // A jump starts over.
+        activity.record(100, 8);
+        assert_eq!(activity.lifespan_so_far(), 100);
+        assert!(!activity.is_settled());
+    }
+}
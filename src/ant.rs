@@ -0,0 +1,92 @@
+//! Langton's ants, which walk the board as a layer on top of the rule: on
+//! a dead cell an ant turns right, on a live one left, and either way it
+//! flips the cell and steps forward.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Which way an ant is heading, north being up the board.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Direction {
+    North = 0,
+    East = 1,
+    South = 2,
+    West = 3,
+}
+
+impl Direction {
+    const CLOCKWISE: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+
+    pub fn right(self) -> Direction {
+        Direction::CLOCKWISE[(self as usize + 1) % 4]
+    }
+
+    pub fn left(self) -> Direction {
+        Direction::CLOCKWISE[(self as usize + 3) % 4]
+    }
+
+    /// The step to the next cell this way, as rows and columns.
+    pub fn offset(self) -> (i64, i64) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::East => (0, 1),
+            Direction::South => (1, 0),
+            Direction::West => (0, -1),
+        }
+    }
+}
+
+/// Whether ants walk while the rule runs or instead of it.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AntMode {
+    /// Each tick the rule runs, then the ants each take a step.
+    #[default]
+    WithRule,
+    /// While there are ants, only they change the board.
+    Alone,
+}
+
+/// An ant on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ant {
+    pub row: u32,
+    pub col: u32,
+    pub direction: Direction,
+}
+
+impl Ant {
+    /// Turn by the cell under the ant, alive or not, then step forward on
+    /// a `width` by `height` board, wrapping around the edges. Returns the
+    /// cell's new state, flipped.
+    pub fn step(&mut self, alive: bool, width: u32, height: u32) -> bool {
+        self.direction = if alive { self.direction.left() } else { self.direction.right() };
+        let (drow, dcol) = self.direction.offset();
+        self.row = (i64::from(self.row) + drow).rem_euclid(i64::from(height)) as u32;
+        self.col = (i64::from(self.col) + dcol).rem_euclid(i64::from(width)) as u32;
+        !alive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_and_wraps() {
+        assert_eq!(Direction::West.right(), Direction::North);
+        assert_eq!(Direction::North.left(), Direction::West);
+        let mut ant = Ant { row: 0, col: 3, direction: Direction::West };
+        // Right onto north, off the top edge.
+        assert!(ant.step(false, 4, 5));
+        assert_eq!(ant, Ant { row: 4, col: 3, direction: Direction::North });
+        // Left onto west.
+        assert!(!ant.step(true, 4, 5));
+        assert_eq!(ant, Ant { row: 4, col: 2, direction: Direction::West });
+    }
+}
@@ -0,0 +1,2473 @@
+//! The page: a universe drawn on a canvas, with the controls around it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use brush::{self, BrushShape, Stroke, MAX_BRUSH_SIZE};
+use builder::UniverseBuilder;
+use pattern_file::{self, ExportFormat};
+use raster;
+use patterns::Pattern;
+use canvas;
+use chart::Chart;
+use events::EventLog;
+use fade::Fades;
+use fps::Fps;
+use immigration;
+use render::{self, Viewport};
+use selection::Selection;
+use status::StatusBar;
+use storage;
+use theme::Theme;
+use timestep::FixedTimestep;
+use transform::Transform;
+use neighborhood::NeighborhoodKind;
+use universe::{AutoPause, Cell, Mode, Universe};
+use utils::*;
+use wireworld::WireworldPattern;
+use worker::{Remote, Request};
+
+/// The longest board encoding `GameLoop::share` puts in a URL.
+const MAX_HASH_LEN: usize = 8 * 1024;
+
+/// Have the browser download `blob` as a file called `name`.
+fn download(blob: &web_sys::Blob, name: &str) -> Result<(), JsValue> {
+    let url = web_sys::Url::create_object_url_with_blob(blob)?;
+    let link: web_sys::HtmlAnchorElement = document().create_element("a")?.dyn_into()?;
+    link.set_href(&url);
+    link.set_download(name);
+    link.click();
+    // The download has its own reference to the blob by now.
+    web_sys::Url::revoke_object_url(&url)
+}
+
+/// What a plain left-button drag on the board does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    /// Paint cells freehand.
+    Paint,
+    /// Select cells to copy, cut or move.
+    Select,
+    /// Draw a line from where the drag starts to where it ends.
+    Line,
+    /// Draw the edges of the rectangle the drag spans.
+    Rect,
+    /// Fill in the rectangle the drag spans.
+    FilledRect,
+    /// Draw an ellipse centered where the drag starts, reaching as far as
+    /// it goes each way.
+    Ellipse,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 6] = [Tool::Paint, Tool::Select, Tool::Line, Tool::Rect, Tool::FilledRect, Tool::Ellipse];
+
+    /// The name used for the tool by `set_tool` and the page's picker.
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Paint => "paint",
+            Tool::Select => "select",
+            Tool::Line => "line",
+            Tool::Rect => "rect",
+            Tool::FilledRect => "filledRect",
+            Tool::Ellipse => "ellipse",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Tool> {
+        Tool::ALL.iter().cloned().find(|tool| tool.name() == name)
+    }
+
+    /// Whether dragging the tool draws a shape.
+    fn draws_shapes(self) -> bool {
+        self.shape((0, 0), (0, 0)).is_some()
+    }
+
+    /// The cells of the shape dragged from `from` to `to`, which may be
+    /// past the edges of the board.
+    fn shape(self, from: (u32, u32), to: (u32, u32)) -> Option<Vec<(i64, i64)>> {
+        let (r0, c0, r1, c1) = (from.0 as i64, from.1 as i64, to.0 as i64, to.1 as i64);
+        match self {
+            Tool::Paint | Tool::Select => None,
+            Tool::Line => Some(raster::line(r0, c0, r1, c1)),
+            Tool::Rect => Some(raster::rect(r0, c0, r1, c1, false)),
+            Tool::FilledRect => Some(raster::rect(r0, c0, r1, c1, true)),
+            Tool::Ellipse => Some(raster::ellipse(r0, c0, (r1 - r0).abs(), (c1 - c0).abs(), false)),
+        }
+    }
+
+    /// Set the cells of the shape dragged from `from` to `to` alive.
+    fn draw_shape(self, universe: &mut Universe, from: (u32, u32), to: (u32, u32)) {
+        match self {
+            Tool::Paint | Tool::Select => {}
+            Tool::Line => universe.set_line(from.0, from.1, to.0, to.1, Cell::Alive),
+            Tool::Rect => universe.set_rect_outline(from.0, from.1, to.0, to.1, Cell::Alive),
+            Tool::FilledRect => universe.set_rect_filled(from.0, from.1, to.0, to.1, Cell::Alive),
+            Tool::Ellipse => {
+                let (rows, cols) = (from.0.abs_diff(to.0), from.1.abs_diff(to.1));
+                universe.set_ellipse(from.0, from.1, rows, cols, false, Cell::Alive);
+            }
+        }
+    }
+}
+
+/// A pattern from the library, as picked to stamp on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stamp {
+    Life(Pattern),
+    Wireworld(WireworldPattern),
+}
+
+impl Stamp {
+    /// The name used for the pattern in the page's picker.
+    fn name(self) -> &'static str {
+        match self {
+            Stamp::Life(pattern) => pattern.name(),
+            Stamp::Wireworld(pattern) => pattern.name(),
+        }
+    }
+
+    /// The pattern in the page's picker called `name`.
+    fn from_name(name: &str) -> Option<Stamp> {
+        Pattern::from_name(name)
+            .map(Stamp::Life)
+            .or_else(|| WireworldPattern::from_name(name).map(Stamp::Wireworld))
+    }
+
+    /// The cells the pattern covers, relative to its top-left corner. Only
+    /// Life patterns are turned and flipped by `transform`.
+    fn cells(self, transform: Transform) -> Vec<(u32, u32)> {
+        match self {
+            Stamp::Life(pattern) => transform.apply(&pattern.shape()).cells,
+            Stamp::Wireworld(pattern) => pattern.cells().into_iter().map(|(row, col, _)| (row, col)).collect(),
+        }
+    }
+
+    /// Stamp the pattern with its top-left corner at `row` and `col`. A
+    /// Wireworld circuit switches the universe to Wireworld.
+    fn place(self, universe: &mut Universe, row: u32, col: u32, transform: Transform) -> Result<(), String> {
+        match self {
+            Stamp::Life(pattern) => universe.insert_pattern(pattern, row, col, transform),
+            Stamp::Wireworld(pattern) => {
+                universe.insert_wireworld_pattern(pattern, row, col);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// How the page runs and responds to input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// Generations to advance per displayed frame. Zero keeps repainting
+    /// without advancing.
+    pub ticks_per_frame: u32,
+    /// The least time between displayed frames, in milliseconds. Zero draws
+    /// on every animation frame.
+    pub frame_interval_ms: f64,
+    /// Whether a left click toggles the cell under it, rather than setting
+    /// it alive.
+    pub toggle_on_click: bool,
+    /// When the loop pauses by itself.
+    pub auto_pause: AutoPause,
+    /// Whether cells are sized for the whole board to fit the canvas's
+    /// container.
+    pub fit: bool,
+    /// Whether the board shows what the next generation will do to it.
+    pub preview: bool,
+    /// How many cells across from the middle to the edge painting covers,
+    /// the middle included, from 1 to `MAX_BRUSH_SIZE`.
+    pub brush_size: u32,
+    pub brush_shape: BrushShape,
+    /// Whether the loop holds off while the page's tab is hidden, rather
+    /// than keep simulating in the background.
+    pub pause_when_hidden: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            ticks_per_frame: 1,
+            frame_interval_ms: 0.0,
+            toggle_on_click: false,
+            auto_pause: AutoPause::ExtinctionOrStable,
+            fit: false,
+            preview: false,
+            brush_size: 1,
+            brush_shape: BrushShape::Square,
+            pause_when_hidden: true,
+        }
+    }
+}
+
+/// Everything the page's event handlers share.
+struct State {
+    universe: Universe,
+    canvas: web_sys::HtmlCanvasElement,
+    context: web_sys::CanvasRenderingContext2d,
+    /// Where the buttons, sliders and readouts around the board are.
+    controls: Controls,
+    viewport: Viewport,
+    fps: Fps,
+    // The controls are all optional, so the board runs on a page with just
+    // a canvas.
+    fps_div: Option<web_sys::Element>,
+    stats_div: Option<web_sys::Element>,
+    status_bar: StatusBar,
+    /// Where the cell under the mouse is and what's around it.
+    readout_div: Option<web_sys::Element>,
+    play_pause_button: Option<web_sys::Element>,
+    pattern_select: Option<web_sys::HtmlSelectElement>,
+    speed_input: Option<web_sys::HtmlInputElement>,
+    image_data_input: Option<web_sys::HtmlInputElement>,
+    playing: bool,
+    settings: Settings,
+    /// The `requestAnimationFrame` timestamp of the last displayed frame.
+    last_frame: Option<f64>,
+    /// The handle of the frame requested, until it arrives.
+    frame_request: Option<i32>,
+    /// How many generations each frame ticks, when set to a rate rather
+    /// than `ticks_per_frame`.
+    timestep: Option<FixedTimestep>,
+    /// Whether the loop is held off while the tab is hidden. It stays
+    /// playing all the same, and carries on once the tab is shown.
+    suspended: bool,
+    /// How the next stamped pattern is rotated and flipped.
+    transform: Transform,
+    /// Where a middle-button drag to pan the view last was.
+    drag: Option<(f64, f64)>,
+    /// The left-button paint stroke in progress.
+    stroke: Option<Stroke>,
+    /// What a plain left-button drag does.
+    tool: Tool,
+    selection: Selection,
+    /// Where the drag of a shape tool started and where it is now.
+    shape: Option<((u32, u32), (u32, u32))>,
+    /// The pattern a left click stamps, in stamp mode.
+    stamp: Option<Stamp>,
+    /// Where the mouse last was, in client coordinates.
+    pointer: Option<(f64, f64)>,
+    /// The cell the mouse is over, while it's over the board.
+    hover: Option<(u32, u32)>,
+    /// Canvas pixels per CSS pixel, as of the last time the canvas was
+    /// sized.
+    pixel_ratio: f64,
+    /// The pixel ratio to use instead of the display's.
+    pixel_ratio_override: Option<f64>,
+    /// Watches the canvas's container in fit mode, once there has been one,
+    /// and the callback it calls.
+    resize_observer: Option<(web_sys::ResizeObserver, Closure<dyn FnMut()>)>,
+    /// Whether the board is to be laid out again on the next frame.
+    relayout_pending: bool,
+    /// The cells flashing because the last generation changed them.
+    fades: Fades,
+    /// Scratch space for the `ImageData` renderer.
+    pixels: Vec<u8>,
+    /// The worker doing the ticking, if not this thread.
+    remote: Option<Remote>,
+    /// The `localStorage` key to save the board under on every pause.
+    autosave: Option<String>,
+    /// Notable things that happened to the board, and where to list them.
+    events: EventLog,
+    /// The rule as of the last update, to notice it changing.
+    logged_rule: String,
+    /// The population over recent generations, and where to draw it.
+    chart: Chart,
+    chart_canvas: Option<(web_sys::HtmlCanvasElement, web_sys::CanvasRenderingContext2d)>,
+}
+
+impl State {
+    /// The worker drawing on the canvas, if one is.
+    fn drawing_remote(&mut self) -> Option<&mut Remote> {
+        self.remote.as_mut().filter(|remote| remote.draws())
+    }
+
+    /// Redraw the whole board as seen through the viewport. With a worker
+    /// drawing, send it the board instead.
+    fn draw(&mut self) {
+        let universe = &self.universe;
+        if let Some(remote) = self.remote.as_mut().filter(|remote| remote.draws()) {
+            if let Err(err) = remote.push(universe) {
+                web_sys::console::error_1(&err);
+            }
+            return;
+        }
+        canvas::draw(&self.viewport, &self.universe, &self.context);
+        self.draw_overlays();
+    }
+
+    /// Draw the shape being dragged out, or whatever is being moved or
+    /// pasted, over the board, and outline the selection. None of it is on
+    /// the board until the mouse comes up.
+    fn draw_overlays(&self) {
+        let universe = &self.universe;
+        if self.fades.is_enabled() {
+            canvas::draw_fades(&self.viewport, universe, &self.context, &self.fades);
+        }
+        if let Some((from, to)) = self.shape {
+            let cells = self.tool.shape(from, to).unwrap_or_default();
+            let cells = cells.into_iter().filter_map(|(row, col)| universe.on_board(row, col));
+            canvas::draw_ghost(&self.viewport, universe, &self.context, cells);
+        }
+        if let Some((ghost, at)) = self.selection.ghost(universe) {
+            let cells = ghost.iter_occupied().map(|(r, c)| ((at.0 + r) % universe.height(), (at.1 + c) % universe.width()));
+            canvas::draw_ghost(&self.viewport, universe, &self.context, cells);
+        }
+        if let Some(region) = self.selection.region(universe) {
+            canvas::draw_selection(&self.viewport, universe, &self.context, &region);
+        }
+        if let (Some(stamp), Some((row, col))) = (self.stamp, self.hover) {
+            let cells = stamp.cells(self.transform).into_iter().map(|(r, c)| ((row + r) % universe.height(), (col + c) % universe.width()));
+            canvas::draw_ghost(&self.viewport, universe, &self.context, cells);
+        }
+        if let Some(cells) = self.hover_outline() {
+            canvas::draw_outline(&self.viewport, universe, &self.context, &cells);
+        }
+        if self.settings.preview {
+            let mut next = vec![Cell::Dead; (universe.width() * universe.height()) as usize];
+            if universe.compute_next(&mut next).is_ok() {
+                canvas::draw_preview(&self.viewport, universe, &self.context, &next);
+            }
+        }
+    }
+
+    /// Whether anything is drawn over the board, which a redraw of just
+    /// the changed cells would leave behind.
+    fn has_overlays(&self) -> bool {
+        self.shape.is_some() || self.selection.region(&self.universe).is_some() || self.hover.is_some() || self.settings.preview
+    }
+
+    /// Whether the selection tool takes left clicks: when it's chosen, or
+    /// when something is being pasted.
+    fn selecting(&self) -> bool {
+        self.tool == Tool::Select || self.selection.is_pasting()
+    }
+
+    /// Size the canvas for the universe and show all of it, or as much as
+    /// fits in fit mode. This clears the canvas, so the grid and cells are
+    /// drawn again.
+    fn reset_canvas(&mut self) {
+        if self.drawing_remote().is_none() {
+            self.pixel_ratio = self.display_pixel_ratio();
+            match self.fit_to_container() {
+                // Too small for the whole board: show what fits, to pan and
+                // zoom around.
+                Some((width, height)) => canvas::size_canvas(&self.canvas, &self.context, width, height, self.pixel_ratio),
+                None => canvas::init_canvas(&self.universe, &self.canvas, &self.context, self.pixel_ratio),
+            }
+        }
+        let (width, height) = self.canvas_size();
+        let follow = self.viewport.follows();
+        self.viewport = Viewport::new(width, height, self.universe.cell_size());
+        self.viewport.set_follow(follow);
+        self.draw();
+    }
+
+    /// In fit mode, size the cells for the whole board to fit the canvas's
+    /// container. Returns the container's size if even one-pixel cells
+    /// don't fit.
+    fn fit_to_container(&mut self) -> Option<(u32, u32)> {
+        if !self.settings.fit {
+            return None;
+        }
+        let container = self.canvas.parent_element()?;
+        let bounds = (container.client_width().max(0) as u32, container.client_height().max(0) as u32);
+        // A hidden container has no size to fit.
+        if bounds.0 == 0 || bounds.1 == 0 {
+            return None;
+        }
+        let fitted = render::fit_cell_size(self.universe.width(), self.universe.height(), bounds);
+        let size = fitted.unwrap_or(1);
+        if size != self.universe.cell_size() {
+            self.universe.set_cell_size(size);
+        }
+        match fitted {
+            Some(_) => None,
+            None => Some(bounds),
+        }
+    }
+
+    /// The canvas pixels to draw per CSS pixel: the display's
+    /// `devicePixelRatio`, unless overridden.
+    fn display_pixel_ratio(&self) -> f64 {
+        self.pixel_ratio_override.unwrap_or_else(|| window().device_pixel_ratio())
+    }
+
+    /// Size the canvas again if the pixel ratio changed, keeping the view.
+    /// Returns whether it did.
+    fn rescale(&mut self) -> bool {
+        if self.display_pixel_ratio() == self.pixel_ratio || self.drawing_remote().is_some() {
+            return false;
+        }
+        let viewport = self.viewport;
+        self.reset_canvas();
+        self.viewport = viewport;
+        self.draw();
+        true
+    }
+
+    /// The canvas's size in CSS pixels, which drawing is scaled to. A
+    /// canvas handed to a worker keeps its old size attributes, so then
+    /// it's worked out from the universe.
+    fn canvas_size(&self) -> (u32, u32) {
+        if self.remote.as_ref().is_some_and(Remote::draws) {
+            let size = self.universe.cell_size() + 1;
+            (size * self.universe.width() + 1, size * self.universe.height() + 1)
+        } else {
+            let css = |pixels: u32| (pixels as f64 / self.pixel_ratio).round() as u32;
+            (css(self.canvas.width()), css(self.canvas.height()))
+        }
+    }
+
+    /// The point on the canvas under the mouse, in the CSS pixels drawing
+    /// is scaled to.
+    fn canvas_point(&self, client_x: f64, client_y: f64) -> (f64, f64) {
+        let rect = self.canvas.get_bounding_client_rect();
+        client_to_canvas((client_x, client_y), (rect.x(), rect.y(), rect.width(), rect.height()), self.canvas_size())
+    }
+
+    /// Draw whatever changed since the last frame.
+    fn draw_frame(&mut self) {
+        if self.drawing_remote().is_some() {
+            return;
+        }
+        self.viewport.follow(&self.universe);
+        // Cells flashed last frame stay dirty until they're painted over.
+        let flashed = self.fades.flashed();
+        self.fades.update(&self.universe);
+        if !self.viewport.shows_whole(&self.universe) || self.has_overlays() {
+            self.draw();
+        } else if self.image_data_fits() && self.image_data_input.as_ref().is_some_and(|input| input.checked()) {
+            // Paint the whole canvas in one call rather than a rectangle per
+            // cell.
+            let (canvas_width, canvas_height) = (self.canvas.width(), self.canvas.height());
+            self.pixels.resize((canvas_width * canvas_height * 4) as usize, 0);
+            self.universe.render_to_image_data_scaled(&mut self.pixels).unwrap();
+            let image_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(
+                wasm_bindgen::Clamped(&self.pixels[..]),
+                canvas_width,
+                canvas_height,
+            ).unwrap();
+            self.context.put_image_data(&image_data, 0.0, 0.0).unwrap();
+            canvas::draw_ants(&self.universe, &self.context);
+            canvas::draw_trail(&self.universe, &self.context);
+            canvas::draw_fades(&self.viewport, &self.universe, &self.context, &self.fades);
+        } else {
+            canvas::draw_delta(&self.universe, &self.context);
+            canvas::draw_trail(&self.universe, &self.context);
+            canvas::repaint(&self.universe, &self.context, &flashed);
+            canvas::draw_fades(&self.viewport, &self.universe, &self.context, &self.fades);
+        }
+        if self.viewport.shows_whole(&self.universe) {
+            canvas::draw_heatmap(&self.universe, &self.context);
+        }
+        self.universe.mark_drawn();
+    }
+
+    /// Whether the board's image data is the canvas's size. It isn't scaled
+    /// for the pixel ratio, and a board too big to fit takes up more.
+    fn image_data_fits(&self) -> bool {
+        let size = self.universe.cell_size() + 1;
+        let whole = (size * self.universe.width() + 1, size * self.universe.height() + 1);
+        self.pixel_ratio == 1.0 && self.canvas_size() == whole
+    }
+
+    /// The cell under a point in client coordinates, clamped to the canvas.
+    fn cell_at_client(&self, client_x: f64, client_y: f64) -> (u32, u32) {
+        let rect = self.canvas.get_bounding_client_rect();
+        let bounds = (rect.x(), rect.y(), rect.width(), rect.height());
+        let size = self.canvas_size();
+        client_to_cell(&self.viewport, &self.universe, (client_x, client_y), bounds, size)
+    }
+
+    /// Start a paint stroke, setting the cells under the brush to `cell`,
+    /// or to the opposite of the first cell's current state with
+    /// `toggle_on_click`.
+    fn start_stroke(&mut self, row: u32, col: u32, cell: Cell) {
+        // In Wireworld a click cycles the cell through its states instead.
+        if cell == Cell::Alive && self.universe.mode() == Mode::Wireworld {
+            self.universe.toggle_cell(row, col);
+            self.stroke = None;
+            self.draw();
+            return;
+        }
+        let cell = if cell == Cell::Alive && self.settings.toggle_on_click {
+            match self.universe.cell(self.universe.get_index(row, col)) {
+                Cell::Alive => Cell::Dead,
+                Cell::Dead => Cell::Alive,
+            }
+        } else {
+            cell
+        };
+        // The first cell starts the undoable edit, and the rest of the
+        // stroke joins it.
+        self.universe.set_cell(row, col, cell);
+        self.paint_from(row, col, cell as u8);
+    }
+
+    /// Start a paint stroke of Immigration's second color.
+    fn start_second_color_stroke(&mut self, row: u32, col: u32) {
+        if let Err(err) = self.universe.set_cell_state(row, col, immigration::SECOND) {
+            web_sys::console::warn_1(&err.into());
+            return;
+        }
+        self.paint_from(row, col, immigration::SECOND);
+    }
+
+    /// Put the brush down on a cell, painting `state`, to start a stroke.
+    fn paint_from(&mut self, row: u32, col: u32, state: u8) {
+        let footprint = brush::footprint(self.settings.brush_size - 1, self.settings.brush_shape);
+        let mut stroke = Stroke::new(footprint, state, (row, col));
+        let cells = stroke.stamp(&self.universe, (row, col));
+        self.universe.paint_cells(&cells, state);
+        self.stroke = Some(stroke);
+        // A worker only knows dead and alive.
+        let cell = if state == 0 { Cell::Dead } else { Cell::Alive };
+        self.forward_stroke(Request::Paint { cells, cell });
+    }
+
+    /// Drag the brush from the stroke's last cell to this one.
+    fn continue_stroke(&mut self, row: u32, col: u32) {
+        let universe = &mut self.universe;
+        let (cells, state) = match self.stroke {
+            Some(ref mut stroke) if stroke.last != (row, col) => (stroke.drag_to(universe, (row, col)), stroke.state),
+            _ => return,
+        };
+        universe.paint_cells(&cells, state);
+        let cell = if state == 0 { Cell::Dead } else { Cell::Alive };
+        self.forward_stroke(Request::Paint { cells, cell });
+    }
+
+    /// The cells to highlight under the mouse: those the brush would paint
+    /// with the paint tool, and otherwise just the one cell.
+    fn hover_outline(&self) -> Option<Vec<(u32, u32)>> {
+        let (row, col) = self.hover?;
+        if self.stamp.is_some() {
+            // The pattern's ghost shows where it lands instead.
+            return None;
+        }
+        if self.tool != Tool::Paint {
+            return Some(vec![(row, col)]);
+        }
+        let footprint = brush::footprint(self.settings.brush_size - 1, self.settings.brush_shape);
+        let cells = footprint.into_iter().filter_map(|(dr, dc)| self.universe.on_board(row as i64 + dr, col as i64 + dc));
+        Some(cells.collect())
+    }
+
+    /// The cell under a point in client coordinates, if the point is on
+    /// the canvas.
+    fn cell_under(&self, client_x: f64, client_y: f64) -> Option<(u32, u32)> {
+        let (x, y) = self.canvas_point(client_x, client_y);
+        let (width, height) = self.canvas_size();
+        if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+            return None;
+        }
+        Some(self.cell_at_client(client_x, client_y))
+    }
+
+    /// Stamp `stamp` with its top-left corner at `row` and `col`, turned
+    /// and flipped as the next stamp is.
+    fn place_stamp(&mut self, stamp: Stamp, row: u32, col: u32) {
+        match stamp.place(&mut self.universe, row, col, self.transform) {
+            Ok(()) => self.log_event(&format!("pattern '{}' stamped at ({}, {})", stamp.name(), row, col)),
+            Err(err) => web_sys::console::warn_1(&err.into()),
+        }
+        if let Stamp::Wireworld(_) = stamp {
+            self.sync_mode();
+        }
+        self.draw();
+        self.update_stats();
+    }
+
+    /// Show the universe's mode in the mode picker, after something other
+    /// than the picker changed it.
+    fn sync_mode(&self) {
+        if let Some(select) = self.controls.get::<web_sys::HtmlSelectElement>("mode") {
+            select.set_value(self.universe.mode().name());
+        }
+    }
+
+    /// Show the play/pause button's label for the current play state.
+    fn sync_button(&self) {
+        if let Some(ref button) = self.play_pause_button {
+            button.set_inner_html(if self.playing { "▐▐" } else { "▶" });
+        }
+    }
+
+    /// Draw the stroke just painted, or have the worker drawing paint it
+    /// too. The worker's board may be ahead of this one, so sending the
+    /// stroke rather than the board keeps the generations in between.
+    fn forward_stroke(&mut self, request: Request) {
+        let universe = &self.universe;
+        match self.remote.as_mut().filter(|remote| remote.draws()) {
+            Some(remote) => {
+                if let Err(err) = remote.send(&request) {
+                    web_sys::console::error_1(&err);
+                }
+                remote.mark_synced(universe);
+            }
+            None => self.draw(),
+        }
+    }
+
+    /// Tell the worker drawing, if there is one, to play or pause to match.
+    fn sync_remote_play(&mut self) {
+        let (playing, ticks, auto_pause) = (self.playing && !self.suspended, self.settings.ticks_per_frame, self.settings.auto_pause);
+        if let Some(remote) = self.drawing_remote() {
+            let request = if playing {
+                Request::Play { generations: ticks, auto_pause }
+            } else {
+                Request::Pause
+            };
+            if let Err(err) = remote.send(&request) {
+                web_sys::console::error_1(&err);
+            }
+        }
+    }
+
+    /// Add the current population to the chart, if the page has one.
+    fn sample_population(&mut self) {
+        if self.chart_canvas.is_some() {
+            self.chart.record(self.universe.generation(), self.universe.population());
+        }
+    }
+
+    /// Move the highlight to the cell under the mouse, or take it away, and
+    /// redraw so it shows even while paused.
+    fn set_hover(&mut self, hover: Option<(u32, u32)>) {
+        if hover == self.hover {
+            return;
+        }
+        self.hover = hover;
+        self.update_readout();
+        self.render_status();
+        self.draw();
+    }
+
+    /// Describe the cell under the mouse in the readout element, if there
+    /// is one.
+    fn update_readout(&self) {
+        if let Some(ref readout_div) = self.readout_div {
+            let text = self.hover.map(|(row, col)| cell_readout(&self.universe, row, col));
+            readout_div.set_text_content(text.as_deref());
+        }
+    }
+
+    /// Show a message in the status bar, after the board's status.
+    fn set_status(&mut self, message: &str) {
+        self.status_bar.set_message(message);
+        self.render_status();
+    }
+
+    /// Add `message` to the event log, at the current generation.
+    fn log_event(&mut self, message: &str) {
+        self.events.record(js_sys::Date::now(), self.universe.generation(), message);
+    }
+
+    /// Bring the status bar up to date now.
+    fn render_status(&mut self) {
+        let hover = self.hover;
+        self.status_bar.render(&self.universe, hover);
+    }
+
+    /// Fire a `playstatechange` event on the canvas, with whether the loop
+    /// is now playing as its `detail`.
+    fn dispatch_play_state(&self) {
+        let init = web_sys::CustomEventInit::new();
+        init.set_detail(&JsValue::from_bool(self.playing));
+        if let Ok(event) = web_sys::CustomEvent::new_with_event_init_dict("playstatechange", &init) {
+            let _ = self.canvas.dispatch_event(&event);
+        }
+    }
+
+    fn update_stats(&mut self) {
+        self.sample_population();
+        let rule = self.universe.rule();
+        if rule != self.logged_rule {
+            self.log_event(&format!("rule changed to {}", rule));
+            self.logged_rule = rule;
+        }
+        // The cell under the mouse may have changed with the board.
+        self.update_readout();
+        // Running, the board changes every frame, faster than anyone reads.
+        if self.playing {
+            let hover = self.hover;
+            self.status_bar.render_throttled(&self.universe, hover);
+        } else {
+            self.render_status();
+        }
+        if let Some((ref canvas, ref context)) = self.chart_canvas {
+            let (background, line) = (self.universe.dead_color(), self.universe.alive_color());
+            self.chart.draw(context, canvas.width() as f64, canvas.height() as f64, &background, &line);
+        }
+        let universe = &self.universe;
+        if let Some(ref stats_div) = self.stats_div {
+            let mut stats = format!("generation = {}
+population = {}
+    births = {}
+    deaths = {}
+", universe.generation(), universe.population(), universe.last_births(), universe.last_deaths());
+            if universe.mode() == Mode::Immigration {
+                stats.push_str(&format!("     first = {}
+    second = {}
+", universe.population_of(immigration::FIRST), universe.population_of(immigration::SECOND)));
+            }
+            stats_div.set_inner_html(&stats);
+        }
+
+        // Grey out the step back button when there is nothing to step back
+        // to.
+        if let Some(button) = self.controls.get::<web_sys::Element>("step-back") {
+            button.toggle_attribute_with_force("disabled", universe.history_len() == 0).unwrap();
+        }
+    }
+}
+
+/// A cell's position and what's around it, as "row, col (alive, n
+/// neighbors)".
+fn cell_readout(universe: &Universe, row: u32, col: u32) -> String {
+    let state = if universe.cell_state(row, col) == 0 { "dead" } else { "alive" };
+    let neighbors = universe.live_neighbor_count(row, col);
+    let plural = if neighbors == 1 { "" } else { "s" };
+    format!("{}, {} ({}, {} neighbor{})", row, col, state, neighbors, plural)
+}
+
+/// Convert a point in client coordinates to canvas pixels, given the
+/// canvas's bounding rectangle in client coordinates as `(x, y, width,
+/// height)` and its size in pixels, which differ when CSS scales it.
+fn client_to_canvas(client: (f64, f64), bounds: (f64, f64, f64, f64), size: (u32, u32)) -> (f64, f64) {
+    let (x, y, width, height) = bounds;
+    (
+        (client.0 - x) * size.0 as f64 / width,
+        (client.1 - y) * size.1 as f64 / height,
+    )
+}
+
+/// The cell under a point in client coordinates, or the nearest one on the
+/// canvas if the point is outside it, so strokes stay on the board.
+fn client_to_cell(
+    viewport: &Viewport,
+    universe: &Universe,
+    client: (f64, f64),
+    bounds: (f64, f64, f64, f64),
+    size: (u32, u32),
+) -> (u32, u32) {
+    // The last pixel is the closing gridline, past the last cell.
+    let (x, y) = client_to_canvas(client, bounds, size);
+    let x = x.clamp(0.0, f64::max(size.0 as f64 - 2.0, 0.0));
+    let y = y.clamp(0.0, f64::max(size.1 as f64 - 2.0, 0.0));
+    viewport.cell_at(universe, x, y)
+}
+
+/// The element with the given ID, if the page has one of the right type.
+fn optional_element<T: JsCast>(id: &str) -> Option<T> {
+    document().get_element_by_id(id)?.dyn_into::<T>().ok()
+}
+
+/// The element with the given ID, or an error naming it.
+fn element<T: JsCast>(id: &str) -> Result<T, JsValue> {
+    document()
+        .get_element_by_id(id)
+        .ok_or_else(|| JsValue::from_str(&format!("missing #{} element", id)))?
+        .dyn_into::<T>()
+        .map_err(|_| JsValue::from_str(&format!("#{} is the wrong kind of element", id)))
+}
+
+/// `value` if it's an element of the right type, or else the element with
+/// `value` as its ID.
+fn element_or_id<T: JsCast>(value: JsValue) -> Result<T, JsValue> {
+    match value.as_string() {
+        Some(id) => element(&id),
+        None => value.dyn_into::<T>().map_err(|_| JsValue::from_str("expected an element or an element ID")),
+    }
+}
+
+/// The property `key` of the object `config`, if it's set.
+fn config_field(config: &JsValue, key: &str) -> Result<Option<JsValue>, JsValue> {
+    if config.is_undefined() || config.is_null() {
+        return Ok(None);
+    }
+    let value = js_sys::Reflect::get(config, &key.into())?;
+    Ok(Some(value).filter(|value| !value.is_undefined() && !value.is_null()))
+}
+
+/// Where a loop finds the controls around its board, by the IDs the page
+/// gives them, such as `play-pause` or `speed`.
+enum Controls {
+    /// The elements with those IDs in the document, for the one board on
+    /// the page.
+    Page,
+    /// Only the elements given, by the ID they stand in for, so several
+    /// boards can each have their own.
+    Given(HashMap<String, web_sys::Element>),
+}
+
+impl Controls {
+    /// The controls listed in `config`, an object from IDs such as
+    /// `play-pause` to an element or the actual ID of one.
+    fn from_config(config: &JsValue) -> Result<Controls, JsValue> {
+        let mut elements = HashMap::new();
+        if !config.is_undefined() && !config.is_null() {
+            for key in js_sys::Object::keys(config.unchecked_ref::<js_sys::Object>()).iter() {
+                let name = key.as_string().unwrap_or_default();
+                let element = element_or_id(js_sys::Reflect::get(config, &key)?)
+                    .map_err(|_| JsValue::from_str(&format!("control '{}' is not an element or an element ID", name)))?;
+                elements.insert(name, element);
+            }
+        }
+        Ok(Controls::Given(elements))
+    }
+
+    /// The control standing for `id`, if there is one of the right type.
+    fn get<T: JsCast>(&self, id: &str) -> Option<T> {
+        match self {
+            Controls::Page => optional_element(id),
+            Controls::Given(elements) => elements.get(id)?.clone().dyn_into::<T>().ok(),
+        }
+    }
+}
+
+/// Runs a universe on the page's canvas, animating it with
+/// `requestAnimationFrame` while playing.
+///
+/// Clones share the same state, so event handlers can each hold one.
+#[derive(Clone)]
+pub struct GameLoop {
+    state: Rc<RefCell<State>>,
+    /// The closure passed to `requestAnimationFrame`. It holds a clone of
+    /// the loop, so it lives until `destroy`.
+    frame: Rc<RefCell<Option<FrameClosure>>>,
+    /// Every event listener added by `on`, each holding a clone of the loop,
+    /// until `destroy` removes them.
+    listeners: Rc<RefCell<Vec<Listener>>>,
+    /// The listener for the next change of device pixel ratio, replaced
+    /// each time the ratio changes.
+    pixel_ratio_listener: Rc<RefCell<Option<Listener>>>,
+}
+
+/// An event listener, removed when dropped.
+struct Listener {
+    target: web_sys::EventTarget,
+    event_type: String,
+    closure: Closure<dyn FnMut(JsValue)>,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = self.target.remove_event_listener_with_callback(&self.event_type, self.closure.as_ref().unchecked_ref());
+    }
+}
+
+impl GameLoop {
+    /// Set up a universe on the canvas with the given ID, paused. The other
+    /// controls are found by their IDs, and left out if they are missing.
+    pub fn new(canvas_id: &str) -> Result<GameLoop, JsValue> {
+        let mut universe = Universe::new();
+        universe.enable_history(100);
+        GameLoop::with_controls(element(canvas_id)?, universe, Controls::Page)
+    }
+
+    /// Set up a loop as `config` describes, paused, with only the controls
+    /// it lists. `config` is an object with:
+    ///
+    /// - `canvas`: the canvas element, or its ID
+    /// - `controls`: an object from the usual control IDs, such as
+    ///   `play-pause`, `step` or `speed`, to this board's element or its ID
+    /// - `width` and `height`: the size of the board
+    /// - `rule`: a rulestring such as `B36/S23`
+    /// - `pattern`: a Life pattern from the library by name, or a pattern in
+    ///   the RLE, plaintext or Life 1.06 format, in the middle of an
+    ///   otherwise empty board
+    ///
+    /// Only `canvas` is needed. Keys go to the board while its canvas has
+    /// the focus.
+    pub fn from_config(config: &JsValue) -> Result<GameLoop, JsValue> {
+        let canvas = config_field(config, "canvas")?.ok_or_else(|| JsValue::from_str("the config has no canvas"))?;
+        let canvas: web_sys::HtmlCanvasElement = element_or_id(canvas)?;
+        let controls = Controls::from_config(&config_field(config, "controls")?.unwrap_or(JsValue::UNDEFINED))?;
+
+        let mut builder = UniverseBuilder::new();
+        if let Some(width) = config_field(config, "width")?.and_then(|width| width.as_f64()) {
+            builder = builder.width(width as u32);
+        }
+        if let Some(height) = config_field(config, "height")?.and_then(|height| height.as_f64()) {
+            builder = builder.height(height as u32);
+        }
+        if let Some(rule) = config_field(config, "rule")?.and_then(|rule| rule.as_string()) {
+            builder = builder.rule(&rule);
+        }
+        let pattern = config_field(config, "pattern")?.and_then(|pattern| pattern.as_string());
+        // A pattern file isn't one the builder knows, so it goes on an
+        // empty board after.
+        let text = match pattern.as_ref().map(|pattern| (pattern, Pattern::from_name(pattern))) {
+            Some((_, Some(pattern))) => {
+                builder = builder.pattern(pattern);
+                None
+            }
+            Some((text, None)) => {
+                builder = builder.density(0.0);
+                Some(text)
+            }
+            None => None,
+        };
+        let mut universe = builder.build()?;
+        if let Some(text) = text {
+            let shape = pattern_file::parse("pattern", text)?;
+            if shape.width > universe.width() || shape.height > universe.height() {
+                let (width, height) = (universe.width(), universe.height());
+                return Err(JsValue::from_str(&format!(
+                    "pattern of size {}x{} does not fit in a {}x{} universe",
+                    shape.width, shape.height, width, height
+                )));
+            }
+            universe.place_shape((universe.height() - shape.height) / 2, (universe.width() - shape.width) / 2, &shape);
+            universe.save_initial();
+        }
+        universe.enable_history(100);
+        canvas.set_tab_index(0);
+        GameLoop::with_controls(canvas, universe, controls)
+    }
+
+    fn with_controls(canvas: web_sys::HtmlCanvasElement, mut universe: Universe, controls: Controls) -> Result<GameLoop, JsValue> {
+        // For the lifespan in the status bar.
+        universe.set_settle_tracking(true);
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("2d canvas context is not available"))?
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()?;
+        let viewport = Viewport::new(0, 0, universe.cell_size());
+        let rule = universe.rule();
+
+        let mut state = State {
+            universe,
+            canvas,
+            context,
+            viewport,
+            fps: Fps::new(),
+            fps_div: controls.get("fps"),
+            stats_div: controls.get("stats"),
+            status_bar: StatusBar::new(controls.get("status")),
+            events: EventLog::new(controls.get("event-log")),
+            logged_rule: rule,
+            readout_div: controls.get("readout"),
+            play_pause_button: controls.get("play-pause"),
+            pattern_select: controls.get("pattern"),
+            speed_input: controls.get("speed"),
+            image_data_input: controls.get("image-data"),
+            playing: false,
+            settings: Settings::default(),
+            last_frame: None,
+            frame_request: None,
+            timestep: None,
+            suspended: false,
+            transform: Transform::Identity,
+            drag: None,
+            stroke: None,
+            tool: Tool::Paint,
+            selection: Selection::new(),
+            shape: None,
+            stamp: None,
+            pointer: None,
+            hover: None,
+            pixel_ratio: 1.0,
+            pixel_ratio_override: None,
+            resize_observer: None,
+            relayout_pending: false,
+            fades: Fades::default(),
+            pixels: vec![],
+            remote: None,
+            autosave: None,
+            chart: Chart::new(),
+            chart_canvas: controls.get::<web_sys::HtmlCanvasElement>("population-chart").and_then(|canvas| {
+                let context = canvas.get_context("2d").ok()??.dyn_into::<web_sys::CanvasRenderingContext2d>().ok()?;
+                Some((canvas, context))
+            }),
+            controls,
+        };
+        if let Some(speed) = state.speed_input.as_ref().and_then(|input| input.value().parse().ok()) {
+            state.settings.ticks_per_frame = speed;
+        }
+        state.reset_canvas();
+
+        let game_loop = GameLoop {
+            state: Rc::new(RefCell::new(state)),
+            frame: Rc::new(RefCell::new(None)),
+            listeners: Rc::new(RefCell::new(vec![])),
+            pixel_ratio_listener: Rc::new(RefCell::new(None)),
+        };
+        let frame_loop = game_loop.clone();
+        *game_loop.frame.borrow_mut() = Some(Closure::wrap(Box::new(move |timestamp| frame_loop.frame(timestamp)) as Box<dyn FnMut(f64)>));
+        Ok(game_loop)
+    }
+
+    /// Start animating, if not already.
+    pub fn play(&self) {
+        self.set_playing(true);
+    }
+
+    /// Stop animating after the current frame.
+    pub fn pause(&self) {
+        self.set_playing(false);
+    }
+
+    /// Pause if playing, or play if paused.
+    pub fn toggle(&self) {
+        let playing = self.is_playing();
+        self.set_playing(!playing);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state.borrow().playing
+    }
+
+    /// The one place the play state changes, so the button and listeners
+    /// always hear about it.
+    fn set_playing(&self, playing: bool) {
+        {
+            let mut state = self.state.borrow_mut();
+            if state.playing == playing {
+                return;
+            }
+            state.playing = playing;
+            state.sync_button();
+            if playing {
+                state.set_status("");
+                state.fps.skip_gap();
+                state.last_frame = None;
+                if let Some(ref mut timestep) = state.timestep {
+                    timestep.reset();
+                }
+                // A frame requested before a quick pause is still coming.
+                if state.drawing_remote().is_none() && state.frame_request.is_none() && !state.suspended {
+                    state.frame_request = Some(request_animation_frame(self.frame.borrow().as_ref().unwrap()));
+                }
+            } else {
+                state.render_status();
+            }
+            state.sync_remote_play();
+        }
+        // Listeners run synchronously and may call back into the loop, so
+        // the state must not be borrowed mutably while they do.
+        let state = self.state.borrow();
+        state.dispatch_play_state();
+        if let (false, Some(key)) = (playing, state.autosave.as_ref()) {
+            if let Err(err) = storage::save(key, &state.universe) {
+                web_sys::console::error_1(&err);
+            }
+        }
+    }
+
+    /// Kill every cell, noting it in the event log.
+    pub fn clear(&self) {
+        self.update(|state| state.log_event("cleared"));
+        self.edit(Universe::clear);
+    }
+
+    /// The event log, oldest first, as an array of objects with the `time`
+    /// in milliseconds since the epoch, the `generation` and a `message`.
+    pub fn events(&self) -> JsValue {
+        self.state.borrow().events.to_js()
+    }
+
+    /// Stop for good: cancel the frame on its way, remove every listener,
+    /// stop the worker and watchers, and let go of the closures holding the
+    /// loop, so the state and universe are freed once the last clone goes.
+    pub fn destroy(&self) {
+        self.listeners.borrow_mut().clear();
+        *self.pixel_ratio_listener.borrow_mut() = None;
+        *self.frame.borrow_mut() = None;
+        let mut state = self.state.borrow_mut();
+        state.playing = false;
+        if let Some(handle) = state.frame_request.take() {
+            if let Err(err) = window().cancel_animation_frame(handle) {
+                web_sys::console::error_1(&err);
+            }
+        }
+        state.relayout_pending = false;
+        if let Some((observer, _)) = state.resize_observer.take() {
+            observer.disconnect();
+        }
+        state.remote = None;
+    }
+
+    /// Pause, then advance a single generation and draw it.
+    pub fn step(&self) {
+        self.pause();
+        let mut state = self.state.borrow_mut();
+        if let Some(remote) = state.drawing_remote() {
+            // The board comes back in `on_worker_message`.
+            let request = Request::Tick { generations: 1, auto_pause: AutoPause::Off };
+            if let Err(err) = remote.send(&request) {
+                web_sys::console::error_1(&err);
+            }
+            return;
+        }
+        state.universe.tick();
+        state.draw_frame();
+        state.update_stats();
+    }
+
+    /// Set how many generations to advance per displayed frame, and move the
+    /// speed slider to match.
+    pub fn set_speed(&self, ticks_per_frame: u32) {
+        let mut state = self.state.borrow_mut();
+        state.settings.ticks_per_frame = ticks_per_frame;
+        if let Some(ref input) = state.speed_input {
+            input.set_value(&ticks_per_frame.to_string());
+        }
+        if state.playing {
+            state.sync_remote_play();
+        }
+    }
+
+    /// Set the least time between displayed frames, in milliseconds.
+    pub fn set_frame_interval(&self, ms: f64) {
+        self.state.borrow_mut().settings.frame_interval_ms = f64::max(ms, 0.0);
+    }
+
+    /// Tick `gps` generations per second whatever the display's refresh
+    /// rate, drawing once per frame, and show the rate in the `gps` input.
+    /// Rates are capped at `timestep::MAX_GPS`. Zero, or anything not above
+    /// it, goes back to ticking the speed's generations every frame.
+    pub fn set_target_gps(&self, gps: f64) {
+        let mut state = self.state.borrow_mut();
+        state.timestep = FixedTimestep::new(gps);
+        if let Some(input) = state.controls.get::<web_sys::HtmlInputElement>("gps") {
+            input.set_value(&state.timestep.as_ref().map_or(0.0, FixedTimestep::gps).to_string());
+        }
+    }
+
+    /// Choose whether a left click toggles cells or only sets them alive.
+    pub fn set_toggle_on_click(&self, toggle: bool) {
+        self.state.borrow_mut().settings.toggle_on_click = toggle;
+    }
+
+    /// Enter stamp mode with the library pattern called `name`: its ghost
+    /// follows the mouse, turned and flipped as it will be, and a left click
+    /// stamps it there. Shows the pattern in the page's picker too.
+    pub fn start_stamp(&self, name: &str) -> Result<(), JsValue> {
+        let stamp = Stamp::from_name(name).ok_or_else(|| JsValue::from_str(&format!("there is no pattern called '{}'", name)))?;
+        let mut state = self.state.borrow_mut();
+        state.stamp = Some(stamp);
+        state.shape = None;
+        if let Some(ref select) = state.pattern_select {
+            select.set_value(name);
+        }
+        state.draw();
+        Ok(())
+    }
+
+    /// Leave stamp mode, if in it.
+    pub fn stop_stamp(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.stamp.take().is_some() {
+            state.draw();
+        }
+    }
+
+    /// Choose what a plain left-button drag on the board does, by the
+    /// tool's name: `paint`, `select`, `line`, `rect`, `filledRect` or
+    /// `ellipse`. Shows the tool in the page's picker too, and leaves stamp
+    /// mode.
+    pub fn set_tool(&self, name: &str) -> Result<(), JsValue> {
+        let tool = Tool::from_name(name).ok_or_else(|| JsValue::from_str(&format!("there is no tool called '{}'", name)))?;
+        let mut state = self.state.borrow_mut();
+        state.tool = tool;
+        state.shape = None;
+        state.stamp = None;
+        if tool != Tool::Select {
+            state.selection.cancel();
+        }
+        if let Some(select) = state.controls.get::<web_sys::HtmlSelectElement>("tool") {
+            select.set_value(tool.name());
+        }
+        state.draw();
+        Ok(())
+    }
+
+    /// Choose how many cells across from the middle to the edge painting
+    /// covers, the middle included, from 1 to `MAX_BRUSH_SIZE`.
+    pub fn set_brush_size(&self, size: u32) {
+        let mut state = self.state.borrow_mut();
+        state.settings.brush_size = size.clamp(1, MAX_BRUSH_SIZE);
+        state.draw();
+    }
+
+    /// Choose whether painting covers a square or a circle.
+    pub fn set_brush_shape(&self, shape: BrushShape) {
+        let mut state = self.state.borrow_mut();
+        state.settings.brush_shape = shape;
+        state.draw();
+    }
+
+    /// Choose when the loop pauses by itself.
+    pub fn set_auto_pause(&self, mode: AutoPause) {
+        let mut state = self.state.borrow_mut();
+        state.settings.auto_pause = mode;
+        if state.playing {
+            state.sync_remote_play();
+        }
+    }
+
+    /// Hold off while the page's tab is hidden, or keep simulating in the
+    /// background too, and tick the `pause-when-hidden` checkbox to match.
+    /// Either way the board stays playing or paused as it was.
+    pub fn set_pause_when_hidden(&self, pause: bool) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.settings.pause_when_hidden = pause;
+            if let Some(checkbox) = state.controls.get::<web_sys::HtmlInputElement>("pause-when-hidden") {
+                checkbox.set_checked(pause);
+            }
+        }
+        self.sync_visibility();
+    }
+
+    /// Suspend the loop if the tab is hidden and the settings say to, or
+    /// resume it if it was suspended and shouldn't be now. Resuming skips
+    /// the time away, so it isn't taken for one long frame.
+    fn sync_visibility(&self) {
+        let mut state = self.state.borrow_mut();
+        let suspend = state.settings.pause_when_hidden && document().hidden();
+        if suspend == state.suspended {
+            return;
+        }
+        state.suspended = suspend;
+        if suspend {
+            if let Some(handle) = state.frame_request.take() {
+                if let Err(err) = window().cancel_animation_frame(handle) {
+                    web_sys::console::error_1(&err);
+                }
+            }
+        } else if state.playing {
+            state.fps.skip_gap();
+            state.last_frame = None;
+            if let Some(ref mut timestep) = state.timestep {
+                timestep.reset();
+            }
+            if state.drawing_remote().is_none() && state.frame_request.is_none() {
+                state.frame_request = Some(request_animation_frame(self.frame.borrow().as_ref().unwrap()));
+            }
+        }
+        if state.playing {
+            state.sync_remote_play();
+        }
+    }
+
+    /// Keep the view centered on the live cells as they move, or stop, and
+    /// tick the `follow` checkbox to match.
+    pub fn set_follow(&self, follow: bool) {
+        let mut state = self.state.borrow_mut();
+        state.viewport.set_follow(follow);
+        if let Some(checkbox) = state.controls.get::<web_sys::HtmlInputElement>("follow") {
+            checkbox.set_checked(follow);
+        }
+        state.draw_frame();
+    }
+
+    /// Show what the next generation will do to the board, or stop, and
+    /// tick the `preview` checkbox to match. The board itself stays as it
+    /// is.
+    pub fn set_preview_mode(&self, preview: bool) {
+        let mut state = self.state.borrow_mut();
+        state.settings.preview = preview;
+        if let Some(checkbox) = state.controls.get::<web_sys::HtmlInputElement>("preview") {
+            checkbox.set_checked(preview);
+        }
+        state.draw();
+    }
+
+    /// Flash the cells each generation brings to life, and let those it
+    /// kills fade back to dead over `frames` frames, up to
+    /// `MAX_FADE_FRAMES`. Zero stops flashing. Moves the `change-highlight`
+    /// slider to match.
+    pub fn set_change_highlight(&self, frames: u32) {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        state.fades.set_frames(frames);
+        state.fades.update(&state.universe);
+        if let Some(input) = state.controls.get::<web_sys::HtmlInputElement>("change-highlight") {
+            input.set_value(&state.fades.frames().to_string());
+        }
+        state.draw();
+    }
+
+    /// Leave a trail behind cells as they die, fading by half every
+    /// `half_life` generations, or none for zero. Moves the `trail` slider
+    /// to match.
+    pub fn set_trail(&self, half_life: u32) {
+        let mut state = self.state.borrow_mut();
+        state.universe.set_trail(half_life);
+        if let Some(input) = state.controls.get::<web_sys::HtmlInputElement>("trail") {
+            input.set_value(&half_life.to_string());
+        }
+        state.draw();
+    }
+
+    /// Show or hide the gridlines, and tick the `grid` checkbox to match.
+    /// They stay hidden while the cells are too small for them either way.
+    pub fn set_grid_visible(&self, visible: bool) {
+        let mut state = self.state.borrow_mut();
+        state.universe.set_grid_visible(visible);
+        if let Some(checkbox) = state.controls.get::<web_sys::HtmlInputElement>("grid") {
+            checkbox.set_checked(visible);
+        }
+        state.draw();
+    }
+
+    /// Draw at `ratio` canvas pixels per CSS pixel instead of the display's
+    /// `devicePixelRatio`, or at the display's again for a ratio that isn't
+    /// positive.
+    pub fn set_pixel_ratio_override(&self, ratio: f64) {
+        let mut state = self.state.borrow_mut();
+        state.pixel_ratio_override = if ratio > 0.0 { Some(ratio) } else { None };
+        state.rescale();
+    }
+
+    /// Size the canvas again when the pixel ratio changes, as when the
+    /// window moves to another monitor. A media query only matches one
+    /// ratio, so each change watches for the next with a new one.
+    fn watch_pixel_ratio(&self) -> Result<(), JsValue> {
+        let ratio = self.state.borrow().pixel_ratio;
+        let query = match window().match_media(&format!("(resolution: {}dppx)", ratio))? {
+            Some(query) => query,
+            None => return Ok(()),
+        };
+        let listener = self.listener(&query, "change", |game_loop, _: web_sys::Event| {
+            if game_loop.state.borrow_mut().rescale() {
+                if let Err(err) = game_loop.watch_pixel_ratio() {
+                    web_sys::console::error_1(&err);
+                }
+            }
+        })?;
+        let previous = self.pixel_ratio_listener.borrow_mut().replace(listener);
+        // Dropped from inside its own callback, which is fine: wasm-bindgen
+        // frees the closure once the call returns.
+        drop(previous);
+        Ok(())
+    }
+
+    /// Size the cells for the whole board to fit the canvas's container,
+    /// following the container as it resizes, or stop and keep the cells
+    /// the size they are. The universe is left as it is either way.
+    pub fn set_fit_mode(&self, fit: bool) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        state.settings.fit = fit;
+        if fit && state.resize_observer.is_none() {
+            let game_loop = self.clone();
+            let callback = Closure::wrap(Box::new(move || game_loop.schedule_relayout()) as Box<dyn FnMut()>);
+            let observer = web_sys::ResizeObserver::new(callback.as_ref().unchecked_ref())?;
+            state.resize_observer = Some((observer, callback));
+        }
+        if let Some((ref observer, _)) = state.resize_observer {
+            observer.disconnect();
+            match state.canvas.parent_element() {
+                Some(ref container) if fit => observer.observe(container),
+                _ => {}
+            }
+        }
+        if let Some(checkbox) = state.controls.get::<web_sys::HtmlInputElement>("fit") {
+            checkbox.set_checked(fit);
+        }
+        state.reset_canvas();
+        Ok(())
+    }
+
+    /// Lay the board out again for the container's new size on the next
+    /// frame, once however many times it resizes before then.
+    fn schedule_relayout(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.relayout_pending {
+            return;
+        }
+        state.relayout_pending = true;
+        let game_loop = self.clone();
+        let relayout = Closure::once_into_js(move |_: f64| {
+            let mut state = game_loop.state.borrow_mut();
+            // Destroyed since.
+            if !state.relayout_pending {
+                return;
+            }
+            state.relayout_pending = false;
+            state.reset_canvas();
+        });
+        if let Err(err) = window().request_animation_frame(relayout.unchecked_ref()) {
+            web_sys::console::error_1(&err);
+        }
+    }
+
+    /// Save the board in `localStorage` under `key`.
+    pub fn save_state(&self, key: &str) -> Result<(), JsValue> {
+        storage::save(key, &self.state.borrow().universe)
+    }
+
+    /// Replace the board with the one saved under `key`, and size the
+    /// canvas for it.
+    pub fn load_state(&self, key: &str) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        storage::load(key, &mut state.universe)?;
+        state.reset_canvas();
+        state.update_stats();
+        Ok(())
+    }
+
+    /// Save the board under `key` whenever the loop pauses, or with `None`
+    /// stop doing so.
+    pub fn set_autosave(&self, key: Option<String>) {
+        self.state.borrow_mut().autosave = key;
+    }
+
+    /// Put the board in the page's URL hash, so the address can be shared,
+    /// without reloading. Boards that encode to more than `MAX_HASH_LEN`
+    /// characters are refused, since long URLs get cut off.
+    pub fn share(&self) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        let hash = state.universe.encode_to_hash();
+        if hash.len() > MAX_HASH_LEN {
+            let message = format!("the board is too big to share as a link ({} characters)", hash.len());
+            web_sys::console::warn_1(&message.as_str().into());
+            state.set_status(&message);
+            return Ok(());
+        }
+        window()
+            .history()?
+            .replace_state_with_url(&JsValue::NULL, "", Some(&format!("#{}", hash)))
+    }
+
+    /// Replace the board with the one in the page's URL hash, if there is
+    /// one. A malformed hash leaves the board alone, with a warning.
+    pub fn load_hash(&self) -> Result<(), JsValue> {
+        let hash = window().location().hash()?;
+        if !hash.starts_with("#life=") {
+            return Ok(());
+        }
+        let mut state = self.state.borrow_mut();
+        if let Err(err) = state.universe.load_hash(&hash) {
+            web_sys::console::warn_1(&format!("ignoring the board in the URL: {}", err).into());
+            return Ok(());
+        }
+        state.reset_canvas();
+        state.update_stats();
+        Ok(())
+    }
+
+    /// Stamp the pattern in the file named `name` in the middle of the board,
+    /// growing the board and canvas if it doesn't fit. A pattern that can't
+    /// be read is reported in the status element as well.
+    pub fn insert_pattern_file(&self, name: &str, text: &str) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        let size = (state.universe.width(), state.universe.height());
+        if let Err(err) = state.universe.insert_pattern_file(name, text) {
+            state.set_status(&err);
+            return Err(err.into());
+        }
+        if size != (state.universe.width(), state.universe.height()) {
+            state.reset_canvas();
+        }
+        state.draw();
+        state.update_stats();
+        Ok(())
+    }
+
+    /// The board in the given format, as a text `Blob`.
+    pub fn export_blob(&self, format: ExportFormat) -> Result<web_sys::Blob, JsValue> {
+        let text = self.state.borrow().universe.export(format);
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("text/plain");
+        web_sys::Blob::new_with_str_sequence_and_options(&js_sys::Array::of1(&text.into()), &options)
+    }
+
+    /// Download the board in the given format, named with its size and
+    /// generation.
+    pub fn export_file(&self, format: ExportFormat) -> Result<(), JsValue> {
+        let blob = self.export_blob(format)?;
+        let name = self.state.borrow().universe.export_file_name(format);
+        download(&blob, &name)
+    }
+
+    /// A PNG `Blob` of the canvas, or with `whole_board` of the whole board at
+    /// one pixel per cell, even if the canvas only shows part of it.
+    pub fn snapshot_png(&self, whole_board: bool) -> Result<js_sys::Promise, JsValue> {
+        let state = self.state.borrow();
+        let canvas = if whole_board {
+            state.universe.render_to_canvas()?
+        } else {
+            state.canvas.clone()
+        };
+        let mut to_blob = |resolve: js_sys::Function, reject: js_sys::Function| {
+            let failed = reject.clone();
+            let callback = Closure::once_into_js(move |blob: JsValue| {
+                let _ = if blob.is_null() {
+                    failed.call1(&JsValue::NULL, &"the canvas couldn't be encoded as a PNG".into())
+                } else {
+                    resolve.call1(&JsValue::NULL, &blob)
+                };
+            });
+            if let Err(err) = canvas.to_blob(callback.unchecked_ref()) {
+                let _ = reject.call1(&JsValue::NULL, &err);
+            }
+        };
+        Ok(js_sys::Promise::new(&mut to_blob))
+    }
+
+    /// Download `snapshot_png`, named with the board's size and generation.
+    pub fn download_png(&self, whole_board: bool) -> Result<(), JsValue> {
+        let name = self.state.borrow().universe.file_name("png");
+        let save = Closure::once(move |blob: JsValue| {
+            if let Err(err) = download(blob.unchecked_ref(), &name) {
+                web_sys::console::error_1(&err);
+            }
+        });
+        let report = Closure::once(|err: JsValue| web_sys::console::error_1(&err));
+        let _ = self.snapshot_png(whole_board)?.then2(&save, &report);
+        // Only one of them runs, so neither can free the other.
+        save.forget();
+        report.forget();
+        Ok(())
+    }
+
+    /// Draw, then advance as many generations as the settings ask for, unless
+    /// the last frame was too recent. Pauses instead if the board dies out or
+    /// stops changing, as far as the settings allow.
+    fn frame(&self, timestamp: f64) {
+        let settled = {
+            let mut state = self.state.borrow_mut();
+            state.frame_request = None;
+            // A frame requested before pausing still arrives, as may one
+            // requested before a worker took over the canvas.
+            if !state.playing || state.suspended || state.drawing_remote().is_some() {
+                return;
+            }
+            if let Some(last_frame) = state.last_frame {
+                if timestamp - last_frame < state.settings.frame_interval_ms {
+                    state.frame_request = Some(request_animation_frame(self.frame.borrow().as_ref().unwrap()));
+                    return;
+                }
+            }
+            state.last_frame = Some(timestamp);
+            #[cfg(feature = "profile")]
+            let _span = Span::new("frame");
+            state.draw_frame();
+            let state = &mut *state;
+            let mut settled = None;
+            let ticks = match state.timestep {
+                Some(ref mut timestep) => timestep.advance(timestamp),
+                None => state.settings.ticks_per_frame,
+            };
+            if let Some(ref mut remote) = state.remote {
+                // The answer arrives in `on_worker_message`, to be drawn
+                // next frame.
+                if let Err(err) = remote.request_tick(&state.universe, ticks, state.settings.auto_pause) {
+                    web_sys::console::error_1(&err);
+                }
+            }
+            for _ in 0..ticks {
+                if state.remote.is_some() {
+                    break;
+                }
+                state.universe.tick();
+                state.sample_population();
+                settled = state.universe.settled().filter(|&how| state.settings.auto_pause.pauses_for(how));
+                if settled.is_some() {
+                    break;
+                }
+            }
+            if let Some(ref fps_div) = state.fps_div {
+                state.fps.tick(fps_div);
+            }
+            state.update_stats();
+            match settled {
+                Some(how) => {
+                    // There won't be another frame to show the final board.
+                    state.draw_frame();
+                    state.set_status(&format!("{} at generation {}", how.describe(), state.universe.generation()));
+                    state.log_event(how.describe());
+                }
+                None => state.frame_request = Some(request_animation_frame(self.frame.borrow().as_ref().unwrap())),
+            }
+            settled
+        };
+        if settled.is_some() {
+            self.pause();
+        }
+    }
+
+    /// Tick in a worker started from the script at `url`, leaving this thread
+    /// free for drawing and input. Returns false, and keeps ticking here, if
+    /// the browser can't start one.
+    pub fn use_worker(&self, url: &str) -> Result<bool, JsValue> {
+        let remote = match Remote::spawn(url) {
+            Some(remote) => remote,
+            None => return Ok(false),
+        };
+        let mut remote = remote;
+        self.on(remote.worker(), "message", GameLoop::on_worker_message)?;
+        self.on(remote.worker(), "error", |game_loop, event: web_sys::Event| {
+            // Most likely the script failed to load. Carry on without it.
+            web_sys::console::error_2(&"simulation worker failed; ticking on the page instead".into(), &event);
+            game_loop.state.borrow_mut().remote = None;
+        })?;
+        if let Some(canvas) = self.hand_over_canvas()? {
+            remote.attach(canvas)?;
+        }
+        let mut state = self.state.borrow_mut();
+        state.remote = Some(remote);
+        // Send the board, and with a canvas, start the worker's own loop.
+        state.draw();
+        state.sync_remote_play();
+        Ok(true)
+    }
+
+    /// Swap the canvas for a fresh copy and turn that over to an
+    /// `OffscreenCanvas` for a worker to draw on. A canvas that has been
+    /// drawn on can't be turned over, hence the copy. Returns `None`, and
+    /// leaves the canvas alone, if the browser doesn't support this.
+    fn hand_over_canvas(&self) -> Result<Option<web_sys::OffscreenCanvas>, JsValue> {
+        let old = self.state.borrow().canvas.clone();
+        if !js_sys::Reflect::has(&old, &JsValue::from_str("transferControlToOffscreen"))? {
+            return Ok(None);
+        }
+        let canvas = old.clone_node()?.dyn_into::<web_sys::HtmlCanvasElement>()?;
+        old.replace_with_with_node_1(&canvas)?;
+        let offscreen = canvas.transfer_control_to_offscreen()?;
+        self.attach_canvas_listeners(&canvas)?;
+        self.state.borrow_mut().canvas = canvas;
+        Ok(Some(offscreen))
+    }
+
+    /// Take the worker's board, unless it no longer follows on from this
+    /// one because of edits or a pause since it was asked for.
+    fn on_worker_message(&self, event: web_sys::MessageEvent) {
+        let settled = {
+            let mut state = self.state.borrow_mut();
+            let state = &mut *state;
+            let remote = match state.remote {
+                Some(ref mut remote) => remote,
+                None => return,
+            };
+            let board = match remote.receive(&event.data()) {
+                Ok(Some(board)) => board,
+                Ok(None) => return,
+                Err(err) => return web_sys::console::error_1(&err),
+            };
+            // A worker that draws has every edit, sent as it happened, so
+            // its board is always the latest.
+            if !remote.draws() && (!state.playing || !remote.matches(&state.universe)) {
+                remote.mark_stale();
+                return;
+            }
+            if let Err(err) = state.universe.load(board.width, board.height, board.generation, &board.cells) {
+                return web_sys::console::error_1(&err.into());
+            }
+            remote.mark_synced(&state.universe);
+            state.update_stats();
+            if let Some(how) = board.settled {
+                state.draw_frame();
+                state.set_status(&format!("{} at generation {}", how.describe(), state.universe.generation()));
+                state.log_event(how.describe());
+            }
+            board.settled
+        };
+        if settled.is_some() {
+            self.pause();
+        }
+    }
+
+    /// Call `handler` with this loop and the event whenever `target` fires
+    /// `event_type`, until `destroy`.
+    fn on<E, F>(&self, target: &web_sys::EventTarget, event_type: &str, handler: F) -> Result<(), JsValue>
+    where
+        E: JsCast + 'static,
+        F: Fn(&GameLoop, E) + 'static,
+    {
+        let listener = self.listener(target, event_type, handler)?;
+        self.listeners.borrow_mut().push(listener);
+        Ok(())
+    }
+
+    /// Like `on`, but the handler is only called until the returned
+    /// listener is dropped.
+    fn listener<E, F>(&self, target: &web_sys::EventTarget, event_type: &str, handler: F) -> Result<Listener, JsValue>
+    where
+        E: JsCast + 'static,
+        F: Fn(&GameLoop, E) + 'static,
+    {
+        let game_loop = self.clone();
+        let closure = Closure::wrap(Box::new(move |event: JsValue| handler(&game_loop, event.unchecked_into())) as Box<dyn FnMut(JsValue)>);
+        target.add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())?;
+        Ok(Listener {
+            target: target.clone(),
+            event_type: event_type.to_string(),
+            closure,
+        })
+    }
+
+    /// Like `on`, for the control with the given ID. Does nothing if the
+    /// loop doesn't have it.
+    fn on_element<E, F>(&self, id: &str, event_type: &str, handler: F) -> Result<(), JsValue>
+    where
+        E: JsCast + 'static,
+        F: Fn(&GameLoop, E) + 'static,
+    {
+        let control = self.state.borrow().controls.get::<web_sys::Element>(id);
+        match control {
+            Some(element) => self.on(&element, event_type, handler),
+            None => Ok(()),
+        }
+    }
+
+    /// Run `action` on the universe when the button with the given ID is
+    /// clicked, then redraw.
+    fn on_click<F>(&self, id: &str, action: F) -> Result<(), JsValue>
+    where
+        F: Fn(&mut Universe) + 'static,
+    {
+        self.on_element(id, "click", move |game_loop, _: web_sys::Event| game_loop.edit(&action))
+    }
+
+    /// Wire up the canvas, buttons and keyboard. The one board on a page
+    /// takes keys from anywhere on it; one of several only while its canvas
+    /// has the focus.
+    pub fn attach_listeners(&self) -> Result<(), JsValue> {
+        let (canvas, on_page) = {
+            let state = self.state.borrow();
+            (state.canvas.clone(), matches!(state.controls, Controls::Page))
+        };
+        self.attach_canvas_listeners(&canvas)?;
+        self.on(&document(), "mousemove", GameLoop::on_mousemove)?;
+        self.on(&document(), "mouseup", GameLoop::on_mouseup)?;
+        self.on(&document(), "visibilitychange", |game_loop, _: web_sys::Event| game_loop.sync_visibility())?;
+        if on_page {
+            self.on(&window(), "keydown", GameLoop::on_keydown)?;
+        } else {
+            self.on(&canvas, "keydown", GameLoop::on_keydown)?;
+        }
+        self.watch_pixel_ratio()?;
+
+        self.on_element("play-pause", "click", |game_loop, _: web_sys::Event| game_loop.toggle())?;
+        self.on_element("step", "click", |game_loop, _: web_sys::Event| game_loop.step())?;
+        self.on_element("gps", "change", |game_loop, event: web_sys::Event| {
+            let input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            game_loop.set_target_gps(input.value_as_number());
+        })?;
+        self.on_element("speed", "input", |game_loop, event: web_sys::Event| {
+            let input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            if let Ok(speed) = input.value().parse() {
+                game_loop.set_speed(speed);
+            }
+        })?;
+        self.on_click("random", |universe| universe.randomize(0.3, js_sys::Date::now() as u64))?;
+        self.on_element("clear", "click", |game_loop, _: web_sys::Event| game_loop.clear())?;
+        self.on_click("reset", Universe::reset)?;
+        self.on_click("step-back", |universe| {
+            // Critters can run back past the ticks kept for rewinding.
+            if !universe.step_back() && universe.mode() == Mode::Critters {
+                let _ = universe.tick_reverse();
+            }
+        })?;
+        self.on_element("save", "click", |game_loop, _: web_sys::Event| {
+            if let Err(err) = game_loop.save_state(storage::DEFAULT_KEY) {
+                web_sys::console::error_1(&err);
+            }
+        })?;
+        self.on_element("load", "click", |game_loop, _: web_sys::Event| {
+            if let Err(err) = game_loop.load_state(storage::DEFAULT_KEY) {
+                web_sys::console::error_1(&err);
+            }
+        })?;
+        self.on_element("export", "click", |game_loop, _: web_sys::Event| {
+            if let Err(err) = game_loop.export_file(ExportFormat::Rle) {
+                web_sys::console::error_1(&err);
+            }
+        })?;
+        self.on_element("snapshot", "click", |game_loop, _: web_sys::Event| {
+            if let Err(err) = game_loop.download_png(true) {
+                web_sys::console::error_1(&err);
+            }
+        })?;
+        self.on_element("share", "click", |game_loop, _: web_sys::Event| {
+            if let Err(err) = game_loop.share() {
+                web_sys::console::error_1(&err);
+            }
+        })?;
+
+        self.on_element("toggle-mode", "change", |game_loop, event: web_sys::Event| {
+            let checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            game_loop.set_toggle_on_click(checkbox.checked());
+        })?;
+
+        self.on_element("fit", "change", |game_loop, event: web_sys::Event| {
+            let checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            if let Err(err) = game_loop.set_fit_mode(checkbox.checked()) {
+                web_sys::console::error_1(&err);
+            }
+        })?;
+
+        self.on_element("preview", "change", |game_loop, event: web_sys::Event| {
+            let checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            game_loop.set_preview_mode(checkbox.checked());
+        })?;
+        self.on_element("change-highlight", "input", |game_loop, event: web_sys::Event| {
+            let input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            if let Ok(frames) = input.value().parse() {
+                game_loop.set_change_highlight(frames);
+            }
+        })?;
+        self.on_element("trail", "input", |game_loop, event: web_sys::Event| {
+            let input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            if let Ok(half_life) = input.value().parse() {
+                game_loop.set_trail(half_life);
+            }
+        })?;
+
+        self.on_element("grid", "change", |game_loop, event: web_sys::Event| {
+            let checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            game_loop.set_grid_visible(checkbox.checked());
+        })?;
+
+        self.on_element("follow", "change", |game_loop, event: web_sys::Event| {
+            let checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            game_loop.set_follow(checkbox.checked());
+        })?;
+
+        self.on_element("pause-when-hidden", "change", |game_loop, event: web_sys::Event| {
+            let checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            game_loop.set_pause_when_hidden(checkbox.checked());
+        })?;
+
+        self.on_element("brush-size", "input", |game_loop, event: web_sys::Event| {
+            let input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            if let Ok(size) = input.value().parse() {
+                game_loop.set_brush_size(size);
+            }
+        })?;
+        self.on_element("pattern", "change", |game_loop, event: web_sys::Event| {
+            let select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            if let Err(err) = game_loop.start_stamp(&select.value()) {
+                web_sys::console::error_1(&err);
+            }
+        })?;
+        self.on_element("brush-shape", "change", |game_loop, event: web_sys::Event| {
+            let select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            game_loop.set_brush_shape(if select.value() == "circle" { BrushShape::Circle } else { BrushShape::Square });
+        })?;
+
+        self.on_element("tool", "change", |game_loop, event: web_sys::Event| {
+            let select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            if let Err(err) = game_loop.set_tool(&select.value()) {
+                web_sys::console::warn_1(&err);
+            }
+        })?;
+
+        self.on_element("age-colors", "change", |game_loop, event: web_sys::Event| {
+            let checkbox = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+            let mut state = game_loop.state.borrow_mut();
+            if checkbox.checked() {
+                state.universe.enable_age_tracking();
+            } else {
+                state.universe.disable_age_tracking();
+            }
+            state.draw();
+        })?;
+
+        self.on_element("mode", "change", |game_loop, event: web_sys::Event| {
+            let select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            if let Some(mode) = Mode::from_name(&select.value()) {
+                game_loop.edit(|universe| {
+                    if let Err(err) = universe.set_mode(mode) {
+                        web_sys::console::warn_1(&err.into());
+                        select.set_value(universe.mode().name());
+                    }
+                });
+            }
+        })?;
+
+        self.on_element("neighborhood", "change", |game_loop, event: web_sys::Event| {
+            let select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            if let Some(kind) = NeighborhoodKind::from_name(&select.value()) {
+                game_loop.edit(|universe| {
+                    if let Err(err) = universe.set_neighborhood(kind) {
+                        web_sys::console::warn_1(&err.into());
+                        select.set_value(universe.neighborhood().name());
+                    }
+                });
+            }
+        })?;
+
+        self.on_element("theme", "change", |game_loop, event: web_sys::Event| {
+            let select = event.target().unwrap().dyn_into::<web_sys::HtmlSelectElement>().unwrap();
+            if let Some(theme) = Theme::from_name(&select.value()) {
+                let mut state = game_loop.state.borrow_mut();
+                state.universe.set_theme(theme);
+                // Redraw the gridlines too, in the new color.
+                let viewport = state.viewport;
+                state.reset_canvas();
+                state.viewport = viewport;
+                state.draw();
+            }
+        })
+    }
+
+    /// Wire up painting, panning and zooming on the canvas.
+    fn attach_canvas_listeners(&self, canvas: &web_sys::HtmlCanvasElement) -> Result<(), JsValue> {
+        self.on(canvas, "mousedown", GameLoop::on_mousedown)?;
+        self.on(canvas, "mouseleave", |game_loop, _: web_sys::MouseEvent| {
+            game_loop.state.borrow_mut().set_hover(None);
+        })?;
+        self.on(canvas, "wheel", GameLoop::on_wheel)?;
+        self.on(canvas, "touchstart", GameLoop::on_touchstart)?;
+        self.on(canvas, "touchmove", GameLoop::on_touchmove)?;
+        for &event_type in ["touchend", "touchcancel"].iter() {
+            self.on(canvas, event_type, |game_loop, _: web_sys::TouchEvent| {
+                game_loop.state.borrow_mut().stroke = None;
+            })?;
+        }
+        // Right click erases, so keep the menu off the board.
+        self.on(canvas, "contextmenu", |_, event: web_sys::MouseEvent| event.prevent_default())?;
+        // Without this the browser opens a dropped file instead.
+        self.on(canvas, "dragover", |_, event: web_sys::DragEvent| event.prevent_default())?;
+        self.on(canvas, "drop", GameLoop::on_drop)?;
+        Ok(())
+    }
+
+    /// Read the first file dropped on the canvas and stamp its pattern in
+    /// the middle of the board.
+    fn on_drop(&self, event: web_sys::DragEvent) {
+        event.prevent_default();
+        let file = event.data_transfer().and_then(|data| data.files()).and_then(|files| files.get(0));
+        if let Some(file) = file {
+            if let Err(err) = self.read_pattern_file(file) {
+                web_sys::console::error_1(&err);
+            }
+        }
+    }
+
+    /// Start reading `file` as text, and insert its pattern once it loads.
+    fn read_pattern_file(&self, file: web_sys::File) -> Result<(), JsValue> {
+        let reader = web_sys::FileReader::new()?;
+        let game_loop = self.clone();
+        let name = file.name();
+        let loaded = reader.clone();
+        let onload = Closure::once_into_js(move || {
+            let text = loaded.result().ok().and_then(|result| result.as_string());
+            let result = match text {
+                Some(text) => game_loop.insert_pattern_file(&name, &text),
+                None => Err(JsValue::from_str(&format!("couldn't read '{}' as text", name))),
+            };
+            if let Err(err) = result {
+                web_sys::console::error_1(&err);
+            }
+        });
+        reader.set_onload(Some(onload.unchecked_ref()));
+        reader.read_as_text(&file)
+    }
+
+    /// Left click sets a cell alive and starts a paint stroke, Ctrl+click or
+    /// right click erases instead, Shift+click stamps the selected pattern,
+    /// as does a plain left click in stamp mode,
+    /// and in Immigration, Alt+click paints the second color. With `toggle_on_click`, a left click toggles the first cell
+    /// and the stroke continues with its new state. The middle button starts
+    /// panning the view. With another tool chosen, a plain left click goes
+    /// to the tool instead.
+    fn on_mousedown(&self, event: web_sys::MouseEvent) {
+        let mut state = self.state.borrow_mut();
+        let (x, y) = state.canvas_point(event.client_x() as f64, event.client_y() as f64);
+        if event.button() == 1 {
+            event.prevent_default();
+            // A worker drawing shows the whole board, so there's no view
+            // to pan.
+            if state.drawing_remote().is_none() {
+                state.drag = Some((x, y));
+            }
+            return;
+        }
+        if event.button() != 0 && event.button() != 2 {
+            return;
+        }
+
+        let (row, col) = state.cell_at_client(event.client_x() as f64, event.client_y() as f64);
+        let plain = !(event.shift_key() || event.ctrl_key() || event.alt_key() || event.meta_key());
+        if let (0, true, Some(stamp)) = (event.button(), plain, state.stamp) {
+            state.place_stamp(stamp, row, col);
+            return;
+        }
+        if event.button() == 0 && plain && state.selecting() {
+            let state = &mut *state;
+            state.selection.press(&mut state.universe, (row, col));
+            state.draw();
+            state.update_stats();
+            return;
+        }
+        if event.button() == 0 && plain && state.tool.draws_shapes() {
+            state.shape = Some(((row, col), (row, col)));
+            state.draw();
+            return;
+        }
+        let erase = event.button() == 2 || event.ctrl_key();
+        if event.shift_key() && !erase {
+            let name = state.pattern_select.as_ref().map(|select| select.value()).unwrap_or_default();
+            if let Some(stamp) = Stamp::from_name(&name) {
+                state.place_stamp(stamp, row, col);
+            }
+        } else if event.alt_key() && !erase && state.universe.mode() == Mode::Immigration {
+            state.start_second_color_stroke(row, col);
+        } else {
+            state.start_stroke(row, col, if erase { Cell::Dead } else { Cell::Alive });
+        }
+    }
+
+    /// Dragging with the left button paints a line from the last cell, and
+    /// with the middle button pans the view a whole cell at a time, keeping
+    /// the leftover movement for the next event. The selection tool follows
+    /// the mouse with or without a button down.
+    fn on_mousemove(&self, event: web_sys::MouseEvent) {
+        let mut state = self.state.borrow_mut();
+        let (client_x, client_y) = (event.client_x() as f64, event.client_y() as f64);
+        state.pointer = Some((client_x, client_y));
+        let hover = state.cell_under(client_x, client_y);
+        state.set_hover(hover);
+        if state.selecting() {
+            let cell = state.cell_at_client(client_x, client_y);
+            if state.selection.hover(cell) {
+                state.draw();
+            }
+        }
+        if let Some((from, to)) = state.shape {
+            let cell = state.cell_at_client(client_x, client_y);
+            if cell != to {
+                state.shape = Some((from, cell));
+                state.draw();
+            }
+            return;
+        }
+        if state.stroke.is_some() {
+            let (row, col) = state.cell_at_client(event.client_x() as f64, event.client_y() as f64);
+            state.continue_stroke(row, col);
+            return;
+        }
+        let (last_x, last_y) = match state.drag {
+            Some(point) => point,
+            None => return,
+        };
+        let (x, y) = state.canvas_point(event.client_x() as f64, event.client_y() as f64);
+        let pitch = (state.viewport.zoom() + 1) as f64;
+        let drow = ((last_y - y) / pitch).trunc();
+        let dcol = ((last_x - x) / pitch).trunc();
+        if drow == 0.0 && dcol == 0.0 {
+            return;
+        }
+        let state = &mut *state;
+        state.viewport.pan(&state.universe, drow as i32, dcol as i32);
+        state.drag = Some((last_x - dcol * pitch, last_y - drow * pitch));
+        state.draw();
+    }
+
+    /// Letting go of a button ends a paint stroke or pan, draws the shape
+    /// dragged out, and finishes dragging out or moving a selection.
+    fn on_mouseup(&self, event: web_sys::MouseEvent) {
+        let mut state = self.state.borrow_mut();
+        state.drag = None;
+        state.stroke = None;
+        if let Some((from, to)) = state.shape.take() {
+            let state = &mut *state;
+            state.tool.draw_shape(&mut state.universe, from, to);
+            state.draw();
+            state.update_stats();
+        }
+        if event.button() == 0 && state.selecting() {
+            let cell = state.cell_at_client(event.client_x() as f64, event.client_y() as f64);
+            let state = &mut *state;
+            state.selection.release(&mut state.universe, cell);
+            state.draw();
+            state.update_stats();
+        }
+    }
+
+    /// A single finger paints like the left mouse button. A second finger
+    /// cancels the stroke.
+    fn on_touchstart(&self, event: web_sys::TouchEvent) {
+        event.prevent_default();
+        let mut state = self.state.borrow_mut();
+        let touches = event.touches();
+        if touches.length() != 1 {
+            state.stroke = None;
+            return;
+        }
+        if let Some(touch) = touches.get(0) {
+            let (row, col) = state.cell_at_client(touch.client_x() as f64, touch.client_y() as f64);
+            state.start_stroke(row, col, Cell::Alive);
+        }
+    }
+
+    fn on_touchmove(&self, event: web_sys::TouchEvent) {
+        event.prevent_default();
+        let mut state = self.state.borrow_mut();
+        let touches = event.touches();
+        if touches.length() != 1 {
+            state.stroke = None;
+            return;
+        }
+        if let Some(touch) = touches.get(0) {
+            let (row, col) = state.cell_at_client(touch.client_x() as f64, touch.client_y() as f64);
+            state.continue_stroke(row, col);
+        }
+    }
+
+    /// The mouse wheel zooms in and out around the cell under the cursor.
+    fn on_wheel(&self, event: web_sys::WheelEvent) {
+        event.prevent_default();
+        let mut state = self.state.borrow_mut();
+        if state.drawing_remote().is_some() {
+            return;
+        }
+        let (x, y) = state.canvas_point(event.client_x() as f64, event.client_y() as f64);
+        let zoom = state.viewport.zoom();
+        let zoom = if event.delta_y() < 0.0 { zoom + 1 } else { zoom - 1 };
+        let state = &mut *state;
+        state.viewport.zoom_at(&state.universe, x, y, zoom);
+        state.draw();
+    }
+
+    /// Keyboard shortcuts, all in one place:
+    ///
+    /// - Space plays or pauses, and `s` or `.` pauses and advances one
+    ///   generation.
+    /// - `c` clears the board and `r` fills it at random.
+    /// - `e` downloads the board as an RLE file.
+    /// - `g` shows or hides the grid, `h` the heatmap and `p` the preview
+    ///   of the next generation, and `+` and `-` change the cell size.
+    /// - `t` turns and `f` flips the next stamped pattern, and Shift+F
+    ///   makes the view follow the live cells.
+    /// - The arrow keys nudge the board or, with Shift, pan the view.
+    /// - Ctrl+Z and Ctrl+Y undo and redo.
+    /// - Ctrl+C and Ctrl+X copy and cut the selection, Ctrl+V pastes where
+    ///   the mouse is, and Escape leaves stamp mode, or drops the selection
+    ///   or paste, or the shape being dragged out.
+    ///
+    /// Keys typed into text fields, sliders and menus are left alone.
+    fn on_keydown(&self, event: web_sys::KeyboardEvent) {
+        let in_control = event
+            .target()
+            .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+            .is_some_and(|element| match element.tag_name().as_str() {
+                "INPUT" | "SELECT" | "TEXTAREA" => true,
+                _ => element.has_attribute("contenteditable"),
+            });
+        if in_control {
+            return;
+        }
+
+        let command = event.ctrl_key() || event.meta_key();
+        let key = event.key();
+        match (command, key.as_str()) {
+            (true, "z") => self.edit(|universe| {
+                universe.undo();
+            }),
+            (true, "y") | (true, "Z") => self.edit(|universe| {
+                universe.redo();
+            }),
+            (true, "c") | (true, "x") | (true, "v") => {
+                let handled = self.update_selection(|state| {
+                    let state = &mut *state;
+                    match key.as_str() {
+                        "c" => state.selection.copy(&state.universe),
+                        "x" => state.selection.cut(&mut state.universe),
+                        _ => {
+                            let (x, y) = state.pointer.unwrap_or_default();
+                            let cell = state.cell_at_client(x, y);
+                            state.selection.start_paste(cell)
+                        }
+                    }
+                });
+                // With nothing selected, the browser's copy and paste still
+                // work.
+                if !handled {
+                    return;
+                }
+            }
+            // Leave the browser's own shortcuts alone.
+            (true, _) => return,
+            (false, "Escape") => {
+                let cancel = |state: &mut State| state.stamp.take().is_some() || state.shape.take().is_some() || state.selection.cancel();
+                if !self.update_selection(cancel) {
+                    return;
+                }
+            }
+            (false, " ") => self.toggle(),
+            (false, "s") | (false, ".") => self.step(),
+            (false, "c") => self.clear(),
+            (false, "r") => self.edit(|universe| universe.randomize(0.3, js_sys::Date::now() as u64)),
+            (false, "e") => {
+                if let Err(err) = self.export_file(ExportFormat::Rle) {
+                    web_sys::console::error_1(&err);
+                }
+            }
+            (false, "g") => {
+                let visible = self.state.borrow().universe.grid_visible();
+                self.set_grid_visible(!visible);
+            }
+            (false, "p") => {
+                let preview = self.state.borrow().settings.preview;
+                self.set_preview_mode(!preview);
+            }
+            (false, "h") => self.update(|state| {
+                if state.universe.heatmap_enabled() {
+                    state.universe.disable_heatmap();
+                } else {
+                    state.universe.enable_heatmap(16);
+                }
+                state.draw();
+            }),
+            (false, "+") | (false, "=") | (false, "-") => self.update(|state| {
+                let size = state.universe.cell_size();
+                state.universe.set_cell_size(if key == "-" { size - 1 } else { size + 1 });
+                state.reset_canvas();
+            }),
+            (false, "t") | (false, "f") => self.update(|state| {
+                let transform = state.transform;
+                state.transform = if key == "t" { transform.rotated() } else { transform.flipped() };
+                if state.stamp.is_some() {
+                    state.draw();
+                }
+            }),
+            (false, "F") => {
+                let follow = !self.state.borrow().viewport.follows();
+                self.set_follow(follow);
+            }
+            (false, "ArrowUp") => self.nudge(-1, 0, event.shift_key()),
+            (false, "ArrowDown") => self.nudge(1, 0, event.shift_key()),
+            (false, "ArrowLeft") => self.nudge(0, -1, event.shift_key()),
+            (false, "ArrowRight") => self.nudge(0, 1, event.shift_key()),
+            _ => return,
+        }
+        // Keeps Space from scrolling the page or pressing a focused button.
+        event.prevent_default();
+    }
+
+    /// Run `action` on the shared state.
+    fn update<F: FnOnce(&mut State)>(&self, action: F) {
+        action(&mut self.state.borrow_mut());
+    }
+
+    /// Run `action` on the selection tool, then redraw if it returns true,
+    /// for done.
+    fn update_selection<F: FnOnce(&mut State) -> bool>(&self, action: F) -> bool {
+        let mut state = self.state.borrow_mut();
+        let done = action(&mut state);
+        if done {
+            state.draw();
+            state.update_stats();
+        }
+        done
+    }
+
+    /// Run `action` on the universe, then redraw.
+    fn edit<F: FnOnce(&mut Universe)>(&self, action: F) {
+        self.update(|state| {
+            action(&mut state.universe);
+            state.draw();
+            state.update_stats();
+        });
+    }
+
+    /// Move the board, or with `pan` the view, by whole cells.
+    fn nudge(&self, drow: i32, dcol: i32, pan: bool) {
+        self.update(|state| {
+            if pan {
+                if state.drawing_remote().is_some() {
+                    return;
+                }
+                state.viewport.pan(&state.universe, drow, dcol);
+            } else {
+                state.universe.translate(drow, dcol);
+            }
+            state.draw();
+        });
+    }
+}
+
+/// One board on a page that may have several, returned by `init`. Its
+/// methods control just that board.
+#[wasm_bindgen]
+pub struct App {
+    game_loop: GameLoop,
+}
+
+#[wasm_bindgen]
+impl App {
+    pub fn play(&self) {
+        self.game_loop.play();
+    }
+
+    pub fn pause(&self) {
+        self.game_loop.pause();
+    }
+
+    pub fn toggle(&self) {
+        self.game_loop.toggle();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.game_loop.is_playing()
+    }
+
+    /// Pause, then advance a single generation and draw it.
+    pub fn step(&self) {
+        self.game_loop.step();
+    }
+
+    pub fn clear(&self) {
+        self.game_loop.clear();
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.game_loop.state.borrow().universe.generation()
+    }
+
+    pub fn population(&self) -> u32 {
+        self.game_loop.state.borrow().universe.population()
+    }
+
+    pub fn rule(&self) -> String {
+        self.game_loop.state.borrow().universe.rule()
+    }
+
+    /// Set how many generations to advance per displayed frame.
+    pub fn set_speed(&self, ticks_per_frame: u32) {
+        self.game_loop.set_speed(ticks_per_frame);
+    }
+
+    /// Tick this many generations per second, up to 10,000, or with zero,
+    /// the speed's generations every frame.
+    pub fn set_target_gps(&self, gps: f64) {
+        self.game_loop.set_target_gps(gps);
+    }
+
+    /// Set the rule, in `B3/S23` or `23/3` notation.
+    pub fn set_rule(&self, rule: &str) -> Result<(), JsValue> {
+        let mut result = Ok(());
+        self.game_loop.edit(|universe| result = universe.set_rule(rule));
+        result.map_err(JsValue::from)
+    }
+
+    /// The board's event log, like the page's `events`.
+    pub fn events(&self) -> JsValue {
+        self.game_loop.events()
+    }
+
+    /// Stop the board and free it, removing its listeners from the page.
+    /// The handle can't be used after.
+    pub fn destroy(self) {
+        self.game_loop.destroy();
+    }
+}
+
+/// Set up a board as `config` describes, as for `GameLoop::from_config`,
+/// and start it playing.
+pub fn init(config: &JsValue) -> Result<App, JsValue> {
+    let game_loop = GameLoop::from_config(config)?;
+    game_loop.attach_listeners()?;
+    game_loop.play();
+    Ok(App { game_loop })
+}
+
+thread_local! {
+    /// The loop started by `start`, for the exported settings functions.
+    static RUNNING: RefCell<Option<GameLoop>> = const { RefCell::new(None) };
+}
+
+/// Make `game_loop` the one `running` returns.
+pub fn set_running(game_loop: &GameLoop) {
+    RUNNING.with(|running| *running.borrow_mut() = Some(game_loop.clone()));
+}
+
+/// The loop started by `start`, or an error if there isn't one.
+pub fn running() -> Result<GameLoop, JsValue> {
+    RUNNING.with(|running| running.borrow().clone()).ok_or_else(|| JsValue::from_str("the game loop has not been started"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_to_canvas_scales() {
+        // A 200x100 canvas shown at half size, 10 pixels from the corner.
+        let bounds = (10.0, 10.0, 100.0, 50.0);
+        assert_eq!(client_to_canvas((10.0, 10.0), bounds, (200, 100)), (0.0, 0.0));
+        assert_eq!(client_to_canvas((60.0, 35.0), bounds, (200, 100)), (100.0, 50.0));
+        assert_eq!(client_to_canvas((0.0, 70.0), bounds, (200, 100)), (-20.0, 120.0));
+    }
+
+    #[test]
+    fn cell_readouts() {
+        let mut universe = Universe::new();
+        universe.set_width(6);
+        universe.set_height(5);
+        universe.set_cells(&[(0, 0), (4, 5), (2, 2)]).unwrap();
+        assert_eq!(cell_readout(&universe, 0, 0), "0, 0 (alive, 1 neighbor)");
+        assert_eq!(cell_readout(&universe, 0, 5), "0, 5 (dead, 2 neighbors)");
+        assert_eq!(cell_readout(&universe, 3, 3), "3, 3 (dead, 1 neighbor)");
+        assert_eq!(cell_readout(&universe, 2, 2), "2, 2 (alive, 0 neighbors)");
+    }
+
+    #[test]
+    fn shape_tools_draw_what_they_preview() {
+        for &tool in Tool::ALL.iter() {
+            assert_eq!(Tool::from_name(tool.name()), Some(tool));
+            for &(from, to) in [((2, 3), (2, 3)), ((1, 1), (6, 4)), ((7, 8), (0, 6)), ((8, 1), (6, 3))].iter() {
+                let mut universe = Universe::new();
+                universe.set_width(10);
+                universe.set_height(9);
+                tool.draw_shape(&mut universe, from, to);
+                let mut preview: Vec<_> = tool.shape(from, to).unwrap_or_default().into_iter()
+                    .filter_map(|(row, col)| universe.on_board(row, col))
+                    .collect();
+                preview.sort_unstable();
+                preview.dedup();
+                assert_eq!(universe.iter_live().collect::<Vec<_>>(), preview, "{:?} from {:?} to {:?}", tool, from, to);
+                assert_eq!(tool.draws_shapes(), !preview.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn stamps_land_where_their_ghost_is() {
+        for &pattern in Pattern::ALL.iter() {
+            let stamp = Stamp::from_name(pattern.name()).unwrap();
+            assert_eq!(stamp, Stamp::Life(pattern));
+            for &transform in [Transform::Identity, Transform::Rotate90, Transform::Identity.flipped()].iter() {
+                let mut universe = Universe::new();
+                universe.set_width(40);
+                universe.set_height(40);
+                // Near the corner, so the bigger patterns wrap around.
+                stamp.place(&mut universe, 30, 35, transform).unwrap();
+                let mut ghost: Vec<_> = stamp.cells(transform).into_iter().map(|(r, c)| ((30 + r) % 40, (35 + c) % 40)).collect();
+                ghost.sort_unstable();
+                assert_eq!(universe.iter_live().collect::<Vec<_>>(), ghost, "{:?} {:?}", pattern, transform);
+            }
+        }
+        assert_eq!(Stamp::from_name("wireworld-diode"), Some(Stamp::Wireworld(WireworldPattern::Diode)));
+        assert_eq!(Stamp::from_name("nothing"), None);
+    }
+
+    #[test]
+    fn client_to_cell_clamps() {
+        let mut universe = Universe::new();
+        universe.set_width(10);
+        universe.set_height(5);
+        // Cells are 5 pixels with 1-pixel gridlines, so the canvas is
+        // 61x31, shown at its own size at the origin.
+        let viewport = Viewport::new(61, 31, 5);
+        let bounds = (0.0, 0.0, 61.0, 31.0);
+        let cell = |x, y| client_to_cell(&viewport, &universe, (x, y), bounds, (61, 31));
+        assert_eq!(cell(3.0, 3.0), (0, 0));
+        assert_eq!(cell(14.0, 8.0), (1, 2));
+        assert_eq!(cell(-40.0, 500.0), (4, 0));
+        assert_eq!(cell(500.0, -3.0), (0, 9));
+    }
+}
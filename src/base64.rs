@@ -0,0 +1,116 @@
+//! Base64, for keeping packed cells in text such as `localStorage` or a
+//! URL.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// The URL and filename safe alphabet of RFC 4648.
+#[cfg(any(feature = "web", test))]
+const URL_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Standard base64, with padding.
+pub fn encode(bytes: &[u8]) -> String {
+    encode_with(bytes, ALPHABET, true)
+}
+
+/// The bytes encoded in `text`, or an error if it isn't padded base64.
+pub fn decode(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(4) {
+        return Err(format!("base64 of {} characters is not a whole number of groups", text.len()));
+    }
+    decode_with(text, ALPHABET)
+}
+
+/// URL-safe base64, without padding, so it can go in a link as it is.
+#[cfg(any(feature = "web", test))]
+pub fn encode_url(bytes: &[u8]) -> String {
+    encode_with(bytes, URL_ALPHABET, false)
+}
+
+/// The bytes encoded in `text` by `encode_url`.
+#[cfg(any(feature = "web", test))]
+pub fn decode_url(text: &str) -> Result<Vec<u8>, String> {
+    if text.len() % 4 == 1 || text.contains('=') {
+        return Err(format!("'{}' is not unpadded base64", text));
+    }
+    decode_with(text, URL_ALPHABET)
+}
+
+fn encode_with(bytes: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| group | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(alphabet[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else if pad {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Decode groups of four characters, the last of which may be short or
+/// padded.
+fn decode_with(text: &str, alphabet: &[u8; 64]) -> Result<Vec<u8>, String> {
+    let text = text.as_bytes();
+    let groups = text.len().div_ceil(4);
+    let mut bytes = Vec::with_capacity(groups * 3);
+    for (n, chunk) in text.chunks(4).enumerate() {
+        let last = n == groups - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count() + 4 - chunk.len();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err("base64 has padding in the wrong place".to_string());
+        }
+        let mut group = 0u32;
+        for (i, &c) in chunk[..4 - padding].iter().enumerate() {
+            let value = alphabet
+                .iter()
+                .position(|&a| a == c)
+                .ok_or_else(|| format!("'{}' is not a base64 character", c as char))?;
+            group |= (value as u32) << (18 - 6 * i);
+        }
+        for i in 0..3 - padding {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+        let bytes: Vec<u8> = (0..=255).collect();
+        for len in 0..10 {
+            assert_eq!(decode(&encode(&bytes[..len])).unwrap(), &bytes[..len]);
+        }
+        assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn url_safe() {
+        assert_eq!(encode_url(&[0xfb, 0xff]), "-_8");
+        assert_eq!(encode_url(b"f"), "Zg");
+        let bytes: Vec<u8> = (0..=255).rev().collect();
+        for len in 0..10 {
+            assert_eq!(decode_url(&encode_url(&bytes[..len])).unwrap(), &bytes[..len]);
+        }
+        assert!(decode_url("Zg==").is_err());
+        assert!(decode_url("Zm9vY").is_err());
+        assert!(decode_url("+/").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(decode("Zg=").is_err());
+        assert!(decode("Zg==Zg==").is_err());
+        assert!(decode("Z===").is_err());
+        assert!(decode("Zm9v!mFy").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Run a universe natively and print the final board.
+//!
+//! ```text
+//! life [--width W] [--height H] [-n GENERATIONS] [--rle FILE] [--seed SEED]
+//! ```
+//!
+//! With `--rle`, the pattern is placed in the middle of an otherwise empty
+//! board. Without it, the board is filled at random.
+
+extern crate wasm_game_of_life;
+
+use std::env;
+use std::fs;
+use std::process;
+use std::time::Instant;
+
+use wasm_game_of_life::{Anchor, Universe, UniverseBuilder};
+
+const USAGE: &str = "usage: life [--width W] [--height H] [-n GENERATIONS] [--rle FILE] [--seed SEED]";
+
+struct Options {
+    width: u32,
+    height: u32,
+    generations: u32,
+    rle: Option<String>,
+    seed: u64,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        width: 64,
+        height: 64,
+        generations: 100,
+        rle: None,
+        seed: 1,
+    };
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Err(USAGE.to_string());
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value\n{}", arg, USAGE))?;
+        let number = |value: &str| value.parse().map_err(|_| format!("{} expects a number, not '{}'", arg, value));
+        match arg.as_str() {
+            "--width" => options.width = number(&value)? as u32,
+            "--height" => options.height = number(&value)? as u32,
+            "-n" | "--generations" => options.generations = number(&value)? as u32,
+            "--seed" => options.seed = number(&value)?,
+            "--rle" => options.rle = Some(value),
+            _ => return Err(format!("unknown option '{}'\n{}", arg, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+fn run() -> Result<(), String> {
+    let options = parse_args(env::args().skip(1))?;
+
+    let mut universe = match options.rle {
+        Some(ref path) => {
+            let rle = fs::read_to_string(path).map_err(|err| format!("cannot read {}: {}", path, err))?;
+            let mut universe = Universe::from_rle(&rle)?;
+            if universe.width() > options.width || universe.height() > options.height {
+                return Err(format!(
+                    "pattern of size {}x{} does not fit in a {}x{} universe",
+                    universe.width(), universe.height(), options.width, options.height
+                ));
+            }
+            universe.resize(options.width, options.height, Anchor::Center)?;
+            universe
+        }
+        None => UniverseBuilder::new()
+            .width(options.width)
+            .height(options.height)
+            .density(0.5)
+            .seed(options.seed)
+            .build()?,
+    };
+
+    let start = Instant::now();
+    universe.tick_many(options.generations);
+    let elapsed = start.elapsed();
+
+    print!("{:#}", universe);
+    let seconds = elapsed.as_secs_f64();
+    println!(
+        "{} generations in {:.3} ms ({:.0} generations/s), population {}",
+        options.generations,
+        seconds * 1000.0,
+        options.generations as f64 / seconds,
+        universe.population()
+    );
+    Ok(())
+}
+
+fn main() {
+    if let Err(message) = run() {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,164 @@
+//! The next generation of a row 64 cells at a time, with each row stored as
+//! `u64` words and the neighbor counts added up by boolean full adders.
+//!
+//! Bit `c % 64` of word `c / 64` is the cell in column `c`, and the bits past
+//! the end of the row in its last word are always clear.
+
+use rule::Rule;
+
+/// The rows around one row of the universe, and what lies past its ends.
+pub struct Rows<'a> {
+    pub above: &'a [u64],
+    pub row: &'a [u64],
+    pub below: &'a [u64],
+    pub width: usize,
+    /// Whether the ends of the row are joined, or the cells past them dead.
+    pub wrap: bool,
+}
+
+/// The counts of neighbors, 0 to 8, as four bit planes of a word each.
+struct Counts {
+    ones: u64,
+    twos: u64,
+    fours: u64,
+    eights: u64,
+}
+
+impl Counts {
+    /// The cells whose count is exactly `n`.
+    #[inline]
+    fn equal_to(&self, n: usize) -> u64 {
+        let plane = |bits: u64, bit: usize| if n >> bit & 1 != 0 { bits } else { !bits };
+        plane(self.ones, 0) & plane(self.twos, 1) & plane(self.fours, 2) & plane(self.eights, 3)
+    }
+}
+
+#[inline]
+fn half_add(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+#[inline]
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let (sum, carry) = half_add(a, b);
+    let (sum, carry2) = half_add(sum, c);
+    (sum, carry | carry2)
+}
+
+/// Whether column `col` of `row` is alive, as the lowest bit of a word.
+#[inline]
+fn bit(row: &[u64], col: usize) -> u64 {
+    row[col / 64] >> (col % 64) & 1
+}
+
+/// Word `k` of `row` moved one column east, so each cell lines up with its
+/// west neighbor.
+#[inline]
+fn west_neighbors(row: &[u64], k: usize, width: usize, wrap: bool) -> u64 {
+    let carry = if k > 0 {
+        row[k - 1] >> 63
+    } else if wrap {
+        bit(row, width - 1)
+    } else {
+        0
+    };
+    row[k] << 1 | carry
+}
+
+/// Word `k` of `row` moved one column west, so each cell lines up with its
+/// east neighbor.
+#[inline]
+fn east_neighbors(row: &[u64], k: usize, width: usize, wrap: bool) -> u64 {
+    let mut bits = row[k] >> 1;
+    if k + 1 < row.len() {
+        bits |= row[k + 1] << 63;
+    } else if wrap {
+        bits |= bit(row, 0) << ((width - 1) % 64);
+    }
+    bits
+}
+
+/// The neighbor counts of the cells in word `k` of the middle row.
+#[inline]
+fn counts(rows: &Rows, k: usize) -> Counts {
+    let (width, wrap) = (rows.width, rows.wrap);
+    let (above, above_carry) = full_add(
+        west_neighbors(rows.above, k, width, wrap),
+        rows.above[k],
+        east_neighbors(rows.above, k, width, wrap),
+    );
+    let (middle, middle_carry) = half_add(
+        west_neighbors(rows.row, k, width, wrap),
+        east_neighbors(rows.row, k, width, wrap),
+    );
+    let (below, below_carry) = full_add(
+        west_neighbors(rows.below, k, width, wrap),
+        rows.below[k],
+        east_neighbors(rows.below, k, width, wrap),
+    );
+    let (ones, ones_carry) = full_add(above, middle, below);
+    let (twos, twos_carry) = full_add(above_carry, middle_carry, below_carry);
+    let (twos, twos_carry2) = half_add(twos, ones_carry);
+    let (fours, eights) = half_add(twos_carry, twos_carry2);
+    Counts { ones, twos, fours, eights }
+}
+
+/// Write the next generation of the middle row into `out`.
+pub fn next_row(rows: &Rows, rule: &Rule, out: &mut Vec<u64>) {
+    let (birth, survival) = rule.masks();
+    out.clear();
+    for k in 0..rows.row.len() {
+        let counts = counts(rows, k);
+        let alive = rows.row[k];
+        let mut next = 0;
+        for n in 0..=8 {
+            let born = if birth >> n & 1 != 0 { !alive } else { 0 };
+            let survives = if survival >> n & 1 != 0 { alive } else { 0 };
+            if born | survives != 0 {
+                next |= counts.equal_to(n) & (born | survives);
+            }
+        }
+        out.push(next);
+    }
+    if let Some(last) = out.last_mut() {
+        if !rows.width.is_multiple_of(64) {
+            *last &= (1 << (rows.width % 64)) - 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(above: &[u64], row: &[u64], below: &[u64], width: usize, wrap: bool) -> Vec<u64> {
+        let mut out = Vec::new();
+        let rows = Rows { above, row, below, width, wrap };
+        next_row(&rows, &Rule::conway(), &mut out);
+        out
+    }
+
+    #[test]
+    fn blinker_across_a_word_boundary() {
+        // Live cells in columns 63, 64 and 65 of the rows above and below
+        // give the middle row's column 64 six neighbors and 63 and 65 four.
+        let three = [1 << 63, 0b11];
+        let empty = [0, 0];
+        assert_eq!(next(&three, &empty, &three, 100, false), vec![0, 0]);
+        // A vertical blinker turns horizontal.
+        let one = [0, 1];
+        assert_eq!(next(&one, &one, &one, 100, false), vec![1 << 63, 0b11]);
+    }
+
+    #[test]
+    fn wraps_at_the_row_ends() {
+        // A vertical blinker in the last column of a 65-column row reaches
+        // round to column 0 only on a torus.
+        let last = [0, 1];
+        assert_eq!(next(&last, &last, &last, 65, true), vec![1 << 63 | 1, 1]);
+        assert_eq!(next(&last, &last, &last, 65, false), vec![1 << 63, 1]);
+        let first = [1, 0];
+        assert_eq!(next(&first, &first, &first, 128, true), vec![0b11, 1 << 63]);
+        assert_eq!(next(&first, &first, &first, 128, false), vec![0b11, 0]);
+    }
+}
@@ -0,0 +1,281 @@
+/// A fixed-size set of bits packed into 64-bit words.
+///
+/// Bit `i` lives in word `i / 64` at position `i % 64`, so on little-endian
+/// targets such as wasm32 it is also bit `i % 8` of byte `i / 8`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitSet {
+    /// Create a set of `len` bits, all cleared.
+    pub fn new(len: usize) -> BitSet {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn get(&self, i: usize) -> bool {
+        debug_assert!(i < self.len);
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    #[inline]
+    pub fn set(&mut self, i: usize, value: bool) {
+        debug_assert!(i < self.len);
+        if value {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    #[inline]
+    pub fn toggle(&mut self, i: usize) {
+        debug_assert!(i < self.len);
+        self.words[i / 64] ^= 1 << (i % 64);
+    }
+
+    /// Clear every bit.
+    pub fn clear(&mut self) {
+        for word in self.words.iter_mut() {
+            *word = 0;
+        }
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// An FNV-1a hash of the bits, for cheaply telling sets apart.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        for &word in self.words.iter() {
+            hash ^= word;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+
+    pub fn as_ptr(&self) -> *const u64 {
+        self.words.as_ptr()
+    }
+
+    /// The bits as bytes in memory order, `len / 8` rounded up of them. Only
+    /// matches `to_bytes` on little-endian targets.
+    #[cfg(any(feature = "web", test))]
+    pub fn as_bytes(&self) -> &[u8] {
+        let bytes = unsafe { std::slice::from_raw_parts(self.words.as_ptr() as *const u8, self.words.len() * 8) };
+        &bytes[..self.len.div_ceil(8)]
+    }
+
+    /// A copy of the bits with bit `i` at bit `i % 8` of byte `i / 8`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        bytes.truncate(self.len.div_ceil(8));
+        bytes
+    }
+
+    /// The bits set in one of the sets but not the other.
+    pub fn xor(&self, other: &BitSet) -> BitSet {
+        debug_assert_eq!(self.len, other.len);
+        BitSet {
+            words: self.words.iter().zip(other.words.iter()).map(|(a, b)| a ^ b).collect(),
+            len: self.len,
+        }
+    }
+
+    /// The inverse of `to_bytes`, if there are the right number of bytes
+    /// for `len` bits. Bits past `len` in the last byte are ignored.
+    #[cfg(any(feature = "web", feature = "serde", test))]
+    pub fn from_bytes(len: usize, bytes: &[u8]) -> Option<BitSet> {
+        if bytes.len() != len.div_ceil(8) {
+            return None;
+        }
+        let mut set = BitSet::new(len);
+        for (word, chunk) in set.words.iter_mut().zip(bytes.chunks(8)) {
+            let mut le_bytes = [0; 8];
+            le_bytes[..chunk.len()].copy_from_slice(chunk);
+            *word = u64::from_le_bytes(le_bytes);
+        }
+        if !len.is_multiple_of(64) {
+            if let Some(last) = set.words.last_mut() {
+                *last &= (1 << (len % 64)) - 1;
+            }
+        }
+        Some(set)
+    }
+
+    /// Read the `len` bits from `start` into `out`, 64 to a word, with the
+    /// bits past `len` in the last word cleared.
+    pub fn read_bits(&self, start: usize, len: usize, out: &mut Vec<u64>) {
+        debug_assert!(start + len <= self.len);
+        out.clear();
+        for offset in (0..len).step_by(64) {
+            let (word, shift) = ((start + offset) / 64, (start + offset) % 64);
+            let mut bits = self.words[word] >> shift;
+            if shift > 0 && word + 1 < self.words.len() {
+                bits |= self.words[word + 1] << (64 - shift);
+            }
+            out.push(bits & low_bits(len - offset));
+        }
+    }
+
+    /// The inverse of `read_bits`: write the `len` bits of `bits` from
+    /// `start`, leaving the bits around them alone.
+    pub fn write_bits(&mut self, start: usize, len: usize, bits: &[u64]) {
+        debug_assert!(start + len <= self.len);
+        for (k, &value) in bits.iter().enumerate().take(len.div_ceil(64)) {
+            let (word, shift) = ((start + k * 64) / 64, (start + k * 64) % 64);
+            let mask = low_bits(len - k * 64);
+            let value = value & mask;
+            self.words[word] = self.words[word] & !(mask << shift) | value << shift;
+            if shift > 0 && mask >> (64 - shift) != 0 {
+                let spill = 64 - shift;
+                self.words[word + 1] = self.words[word + 1] & !(mask >> spill) | value >> spill;
+            }
+        }
+    }
+
+    /// The indices of the set bits, in increasing order.
+    pub fn ones(&self) -> Ones<'_> {
+        Ones {
+            words: &self.words,
+            word_index: 0,
+            word: self.words.first().cloned().unwrap_or(0),
+            remaining: self.count_ones(),
+        }
+    }
+}
+
+/// A word with the lowest `n` bits set, or all of them from 64 up.
+fn low_bits(n: usize) -> u64 {
+    if n >= 64 {
+        !0
+    } else {
+        (1 << n) - 1
+    }
+}
+
+/// Iterator over the set bits of a `BitSet`, skipping a word at a time
+/// through runs of cleared bits.
+pub struct Ones<'a> {
+    words: &'a [u64],
+    word_index: usize,
+    word: u64,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Ones<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.remaining == 0 {
+            return None;
+        }
+        while self.word == 0 {
+            self.word_index += 1;
+            self.word = self.words[self.word_index];
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        self.remaining -= 1;
+        Some(self.word_index * 64 + bit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Ones<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_set_toggle() {
+        let mut bits = BitSet::new(130);
+        assert_eq!(bits.len(), 130);
+        bits.set(0, true);
+        bits.set(64, true);
+        bits.toggle(129);
+        assert!(bits.get(0) && bits.get(64) && bits.get(129));
+        assert!(!bits.get(1) && !bits.get(63) && !bits.get(128));
+        assert_eq!(bits.count_ones(), 3);
+        bits.set(64, false);
+        bits.toggle(129);
+        assert_eq!(bits.count_ones(), 1);
+        bits.clear();
+        assert_eq!(bits.count_ones(), 0);
+        assert_eq!(bits.len(), 130);
+    }
+
+    #[test]
+    fn fingerprint() {
+        let mut a = BitSet::new(100);
+        let mut b = BitSet::new(100);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        a.set(3, true);
+        assert_ne!(a.fingerprint(), b.fingerprint());
+        b.set(3, true);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn bytes_and_ones() {
+        let mut bits = BitSet::new(70);
+        for &i in &[0, 9, 63, 64, 69] {
+            bits.set(i, true);
+        }
+        assert_eq!(bits.to_bytes(), vec![0x01, 0x02, 0, 0, 0, 0, 0, 0x80, 0x21]);
+        if cfg!(target_endian = "little") {
+            assert_eq!(bits.as_bytes(), &bits.to_bytes()[..]);
+        }
+        assert_eq!(bits.ones().len(), 5);
+        assert_eq!(bits.ones().collect::<Vec<_>>(), vec![0, 9, 63, 64, 69]);
+        assert_eq!(BitSet::new(200).ones().next(), None);
+
+        assert_eq!(BitSet::from_bytes(70, &bits.to_bytes()), Some(bits.clone()));
+        assert_eq!(BitSet::from_bytes(70, &[0; 8]), None);
+        // Stray bits past the end don't make it in.
+        assert_eq!(BitSet::from_bytes(3, &[0xff]).unwrap().count_ones(), 3);
+    }
+
+    #[test]
+    fn read_and_write_bits() {
+        let mut bits = BitSet::new(300);
+        for i in (0..300).filter(|i| i % 3 == 0) {
+            bits.set(i, true);
+        }
+        let mut words = Vec::new();
+        // Straddling a word boundary, with a partial last word.
+        bits.read_bits(60, 70, &mut words);
+        assert_eq!(words.len(), 2);
+        for i in 0..70 {
+            assert_eq!(words[i / 64] >> (i % 64) & 1 != 0, (60 + i) % 3 == 0, "bit {}", i);
+        }
+        assert_eq!(words[1] >> 6, 0);
+
+        let mut copy = BitSet::new(300);
+        for &(start, len) in [(0, 64), (64, 1), (65, 127), (192, 108)].iter() {
+            bits.read_bits(start, len, &mut words);
+            copy.write_bits(start, len, &words);
+        }
+        assert_eq!(copy, bits);
+
+        // Writing leaves the bits on either side alone.
+        copy.write_bits(62, 5, &[0]);
+        assert_eq!(copy.ones().filter(|i| (62..67).contains(i)).count(), 0);
+        assert!(copy.get(60) && copy.get(69));
+    }
+}
@@ -0,0 +1,138 @@
+//! Brushes for painting a blob of cells at a time.
+
+#[cfg(any(feature = "web", test))]
+use std::collections::HashSet;
+
+use wasm_bindgen::prelude::*;
+
+use raster;
+#[cfg(any(feature = "web", test))]
+use universe::Universe;
+
+/// The largest brush, as the cells across from the middle to the edge,
+/// the middle included.
+#[cfg(any(feature = "web", test))]
+pub const MAX_BRUSH_SIZE: u32 = 10;
+
+/// The shape of the cells a brush covers.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrushShape {
+    Square,
+    Circle,
+}
+
+/// The cells a brush covers, relative to the one it's centered on, out to
+/// `radius` cells away. A radius of 0 covers just the one cell.
+pub fn footprint(radius: u32, shape: BrushShape) -> Vec<(i64, i64)> {
+    let r = radius as i64;
+    match shape {
+        BrushShape::Square => raster::rect(-r, -r, r, r, true),
+        BrushShape::Circle => raster::ellipse(0, 0, r, r, true),
+    }
+}
+
+/// A paint stroke in progress. It paints each cell once, however often the
+/// brush passes over it, so a stroke is the same however the mouse wanders.
+#[cfg(any(feature = "web", test))]
+pub struct Stroke {
+    /// The cell the brush was last on.
+    pub last: (u32, u32),
+    /// The state the stroke paints cells.
+    pub state: u8,
+    footprint: Vec<(i64, i64)>,
+    touched: HashSet<(u32, u32)>,
+}
+
+#[cfg(any(feature = "web", test))]
+impl Stroke {
+    /// A stroke with the brush `footprint` painting `state`, starting on
+    /// the cell `at`, where nothing is painted yet.
+    pub fn new(footprint: Vec<(i64, i64)>, state: u8, at: (u32, u32)) -> Stroke {
+        Stroke { last: at, state, footprint, touched: HashSet::new() }
+    }
+
+    /// Put the brush on the cell `at`. Returns the cells under it that the
+    /// stroke hasn't painted yet, now counted as painted.
+    pub fn stamp(&mut self, universe: &Universe, at: (u32, u32)) -> Vec<(u32, u32)> {
+        self.last = at;
+        let mut cells = vec![];
+        for &(dr, dc) in self.footprint.iter() {
+            if let Some(cell) = universe.on_board(at.0 as i64 + dr, at.1 as i64 + dc) {
+                if self.touched.insert(cell) {
+                    cells.push(cell);
+                }
+            }
+        }
+        cells
+    }
+
+    /// Drag the brush from the last cell to `to`, along `stroke_line`.
+    /// Returns the cells it passes over that the stroke hasn't painted yet.
+    pub fn drag_to(&mut self, universe: &Universe, to: (u32, u32)) -> Vec<(u32, u32)> {
+        let line = universe.stroke_line(self.last, to);
+        line.into_iter().flat_map(|at| self.stamp(universe, at)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picture(radius: u32, shape: BrushShape) -> String {
+        let cells = footprint(radius, shape);
+        let r = radius as i64;
+        let mut picture = String::new();
+        for row in -r..=r {
+            for col in -r..=r {
+                picture.push(if cells.contains(&(row, col)) { '#' } else { '.' });
+            }
+            picture.push('\n');
+        }
+        picture
+    }
+
+    #[test]
+    fn footprints() {
+        for &shape in [BrushShape::Square, BrushShape::Circle].iter() {
+            assert_eq!(footprint(0, shape), [(0, 0)]);
+        }
+        assert_eq!(picture(1, BrushShape::Square), "###\n###\n###\n");
+        assert_eq!(picture(1, BrushShape::Circle), ".#.\n###\n.#.\n");
+        assert_eq!(picture(2, BrushShape::Circle), ".###.\n#####\n#####\n#####\n.###.\n");
+        assert_eq!(picture(4, BrushShape::Circle), "...###...\n.#######.\n.#######.\n#########\n#########\n#########\n.#######.\n.#######.\n...###...\n");
+        for radius in 0..MAX_BRUSH_SIZE {
+            let side = 2 * radius as usize + 1;
+            assert_eq!(footprint(radius, BrushShape::Square).len(), side * side);
+            let circle = footprint(radius, BrushShape::Circle);
+            assert!(circle.len() <= side * side);
+            assert!(circle.contains(&(radius as i64, 0)) && circle.contains(&(0, -(radius as i64))));
+        }
+    }
+
+    #[test]
+    fn strokes_paint_each_cell_once() {
+        let mut universe = Universe::new();
+        universe.set_width(8);
+        universe.set_height(8);
+        let mut stroke = Stroke::new(footprint(1, BrushShape::Square), 1, (0, 0));
+        // Wrapping around the corner.
+        let first = stroke.stamp(&universe, (0, 0));
+        assert_eq!(first.len(), 9);
+        assert!(first.contains(&(7, 7)));
+        assert_eq!(stroke.stamp(&universe, (0, 0)), []);
+        // One column further, only the new column is left to paint.
+        assert_eq!(stroke.drag_to(&universe, (0, 1)), [(7, 2), (0, 2), (1, 2)]);
+        // Back and forth over the same ground paints nothing more.
+        assert_eq!(stroke.drag_to(&universe, (0, 0)), []);
+        assert_eq!(stroke.drag_to(&universe, (0, 1)), []);
+        assert_eq!(stroke.last, (0, 1));
+        // The same cells in the end, whichever way the stroke went.
+        let mut direct = Stroke::new(footprint(1, BrushShape::Square), 2, (0, 0));
+        let mut cells = direct.stamp(&universe, (0, 0));
+        cells.extend(direct.drag_to(&universe, (0, 1)));
+        assert_eq!(cells.len(), 12);
+        // The stroke keeps the state it paints, and where it got to.
+        assert_eq!((direct.state, direct.last), (2, (0, 1)));
+    }
+}
@@ -0,0 +1,218 @@
+//! Validated construction of universes that aren't the default.
+
+use wasm_bindgen::prelude::*;
+
+use patterns::Pattern;
+use rule::Rule;
+use shape::Shape;
+use rng::Rng;
+use sparse::SparseUniverse;
+use universe::{self, Algorithm, Topology, Universe};
+
+/// Configures a universe and creates it in one step, e.g.
+/// `UniverseBuilder.new().width(256).height(256).density(0.4).build()`.
+///
+/// With neither a density nor a pattern, the cells start the same as
+/// `Universe::new`. A density fills the board at random, using `seed`. A
+/// pattern is stamped in the middle of the board, over the random fill if
+/// there is one, or on an empty board otherwise.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct UniverseBuilder {
+    width: u32,
+    height: u32,
+    rule: Option<String>,
+    topology: Topology,
+    algorithm: Algorithm,
+    seed: u64,
+    density: Option<f64>,
+    pattern: Option<Pattern>,
+}
+
+#[wasm_bindgen]
+impl UniverseBuilder {
+    pub fn new() -> UniverseBuilder {
+        UniverseBuilder {
+            width: universe::DEFAULT_SIZE,
+            height: universe::DEFAULT_SIZE,
+            rule: None,
+            topology: Topology::Torus,
+            algorithm: Algorithm::CellByCell,
+            seed: 0,
+            density: None,
+            pattern: None,
+        }
+    }
+
+    pub fn width(mut self, width: u32) -> UniverseBuilder {
+        self.width = width;
+        self
+    }
+
+    pub fn height(mut self, height: u32) -> UniverseBuilder {
+        self.height = height;
+        self
+    }
+
+    /// The rule in `B3/S23` or `23/3` notation, checked by `build`.
+    pub fn rule(mut self, rulestring: &str) -> UniverseBuilder {
+        self.rule = Some(rulestring.to_string());
+        self
+    }
+
+    pub fn topology(mut self, topology: Topology) -> UniverseBuilder {
+        self.topology = topology;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> UniverseBuilder {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// The seed for the random fill. The same seed always produces the same
+    /// cells.
+    pub fn seed(mut self, seed: u64) -> UniverseBuilder {
+        self.seed = seed;
+        self
+    }
+
+    /// Fill the board at random, each cell alive with probability `density`.
+    pub fn density(mut self, density: f64) -> UniverseBuilder {
+        self.density = Some(density);
+        self
+    }
+
+    pub fn pattern(mut self, pattern: Pattern) -> UniverseBuilder {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// Create the universe, or return an error if the configuration is
+    /// invalid.
+    pub fn build(&self) -> Result<Universe, String> {
+        let shape = self.validate()?;
+        let mut universe = Universe::empty(self.width, self.height)?;
+        if let Some(ref rulestring) = self.rule {
+            universe.set_rule(rulestring)?;
+        }
+        universe.set_topology(self.topology);
+        universe.set_algorithm(self.algorithm);
+        match self.density {
+            Some(density) => universe.fill_random(density, self.seed),
+            None if shape.is_none() => universe.fill_default(),
+            None => {}
+        }
+        if let Some(shape) = shape {
+            universe.place_shape((self.height - shape.height) / 2, (self.width - shape.width) / 2, &shape);
+        }
+        universe.save_initial();
+        Ok(universe)
+    }
+
+    /// Create a `SparseUniverse` with the same cells `build` would give, or
+    /// return an error if the configuration is invalid or the rule can't
+    /// run sparse.
+    pub fn build_sparse(&self) -> Result<SparseUniverse, String> {
+        let shape = self.validate()?;
+        let mut universe = SparseUniverse::new(self.width, self.height);
+        if let Some(ref rulestring) = self.rule {
+            universe.set_rule(rulestring)?;
+        }
+        universe.set_topology(self.topology);
+        // The same cells as `fill_random` and `fill_default`.
+        let width = self.width;
+        let live: Vec<_> = match self.density {
+            Some(density) => {
+                let mut rng = Rng::new(self.seed);
+                let density = density.clamp(0.0, 1.0);
+                (0..self.width * self.height).filter(|_| rng.next_f64() < density).collect()
+            }
+            None if shape.is_none() => (0..self.width * self.height).filter(|idx| idx % 2 == 0 || idx % 7 == 0).collect(),
+            None => vec![],
+        };
+        let live: Vec<_> = live.into_iter().map(|idx| (idx / width, idx % width)).collect();
+        universe.set_cells(&live)?;
+        if let Some(shape) = shape {
+            universe.place_shape((self.height - shape.height) / 2, (self.width - shape.width) / 2, &shape);
+        }
+        Ok(universe)
+    }
+}
+
+impl UniverseBuilder {
+    /// Check the configuration, and return the pattern's shape if there is
+    /// one.
+    fn validate(&self) -> Result<Option<Shape>, String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!("universe must not be empty, but is {}x{}", self.width, self.height));
+        }
+        if self.width.checked_mul(self.height).is_none() {
+            return Err(format!("universe of {}x{} has too many cells", self.width, self.height));
+        }
+        if let Some(density) = self.density {
+            if !(0.0..=1.0).contains(&density) {
+                return Err(format!("density {} is not between 0 and 1", density));
+            }
+        }
+        if let Some(ref rulestring) = self.rule {
+            Rule::parse(rulestring)?;
+        }
+        let shape = self.pattern.map(Pattern::shape);
+        if let Some(ref shape) = shape {
+            if shape.width > self.width || shape.height > self.height {
+                return Err(format!(
+                    "pattern of size {}x{} does not fit in a {}x{} universe",
+                    shape.width, shape.height, self.width, self.height
+                ));
+            }
+        }
+        Ok(shape)
+    }
+}
+
+impl Default for UniverseBuilder {
+    fn default() -> UniverseBuilder {
+        UniverseBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_match_new() {
+        let universe = UniverseBuilder::new().build().unwrap();
+        let expected = Universe::new();
+        assert_eq!((universe.width(), universe.height()), (expected.width(), expected.height()));
+        assert_eq!(universe.rule(), expected.rule());
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn configured() {
+        let universe = UniverseBuilder::new().width(20).height(10).density(0.4).seed(7).rule("B36/S23").build().unwrap();
+        assert_eq!((universe.width(), universe.height()), (20, 10));
+        assert_eq!(universe.rule(), "B36/S23");
+        assert_eq!(universe.get_cells(), Universe::new_random(20, 10, 0.4, 7).get_cells());
+
+        let universe = UniverseBuilder::new().width(5).height(5).pattern(Pattern::Blinker).build().unwrap();
+        let mut expected = Universe::empty(5, 5).unwrap();
+        expected.set_cells(&[(2, 1), (2, 2), (2, 3)]).unwrap();
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        assert!(UniverseBuilder::new().width(0).build().is_err());
+        assert!(UniverseBuilder::new().height(0).build().is_err());
+        assert_eq!(
+            UniverseBuilder::new().width(u32::MAX).height(2).build().err(),
+            Some("universe of 4294967295x2 has too many cells".to_string())
+        );
+        assert!(UniverseBuilder::new().density(1.5).build().is_err());
+        assert!(UniverseBuilder::new().rule("B3/S9").build().is_err());
+        assert!(UniverseBuilder::new().width(2).height(2).pattern(Pattern::Glider).build().is_err());
+    }
+}
@@ -0,0 +1,673 @@
+//! Drawing universes on a canvas element, or on an offscreen canvas from a
+//! worker.
+
+extern crate web_sys;
+
+use std::collections::HashSet;
+
+use wasm_bindgen::{Clamped, JsCast, JsValue};
+
+use ant::Direction;
+use fade::Fades;
+use render::{self, Viewport};
+use selection::Region;
+use theme;
+use universe::{Cell, Mode, Universe};
+
+/// Colors for live cells by age, from newborn to long-lived. Each color
+/// covers twice as many generations as the one before.
+pub const AGE_COLORS: [&str; 8] = [
+    "#2B50D6", "#4B3FC4", "#6B32AE", "#8A2896", "#A6207C", "#C01A60", "#D61742", "#E81C1C",
+];
+/// Translucent overlay colors for the heatmap, from cool to hot.
+pub const HEAT_COLORS: [&str; 5] = [
+    "rgba(255, 230, 0, 0.25)",
+    "rgba(255, 170, 0, 0.35)",
+    "rgba(255, 110, 0, 0.45)",
+    "rgba(255, 50, 0, 0.55)",
+    "rgba(220, 0, 0, 0.65)",
+];
+
+/// The color Langton's ants are drawn in.
+pub const ANT_COLOR: &str = "#F59E0B";
+
+/// The color of the selection's dashed outline.
+pub const SELECTION_COLOR: &str = "#0EA5E9";
+/// The colors of cells the next generation brings to life, and of those it
+/// kills.
+pub const BIRTH_COLOR: &str = "#22C55E";
+pub const DEATH_COLOR: &str = "#EF4444";
+/// The color a cell that just died fades from, back to the dead color.
+pub const TOMBSTONE_COLOR: &str = "#A8A29E";
+/// The color of the outline of the cells under the mouse.
+pub const HOVER_COLOR: &str = "#64748B";
+/// How opaque the outline of the cells under the mouse is drawn.
+pub const HOVER_ALPHA: f64 = 0.7;
+/// How opaque cells being moved or pasted are drawn.
+const GHOST_ALPHA: f64 = 0.5;
+/// How many shades trails are drawn in, and how opaque the freshest are.
+const TRAIL_SHADES: usize = 8;
+const TRAIL_ALPHA: f64 = 0.75;
+
+/// The drawing calls used here, which the contexts of page and offscreen
+/// canvases share.
+pub trait Context2d {
+    fn set_fill_style_str(&self, color: &str);
+    fn set_stroke_style_str(&self, color: &str);
+    fn begin_path(&self);
+    fn move_to(&self, x: f64, y: f64);
+    fn line_to(&self, x: f64, y: f64);
+    fn rect(&self, x: f64, y: f64, w: f64, h: f64);
+    fn fill(&self);
+    fn stroke(&self);
+    fn set_line_dash(&self, segments: &[f64]);
+    fn set_global_alpha(&self, alpha: f64);
+    /// Scale everything drawn afterwards by `ratio`, replacing any earlier
+    /// scale.
+    fn set_scale(&self, ratio: f64);
+}
+
+/// A canvas whose size in pixels can be set.
+pub trait Canvas {
+    fn set_width(&self, width: u32);
+    fn set_height(&self, height: u32);
+    /// Show the canvas at this size in CSS pixels, however many pixels it
+    /// has.
+    fn set_display_size(&self, width: u32, height: u32);
+}
+
+impl Canvas for web_sys::HtmlCanvasElement {
+    fn set_width(&self, width: u32) {
+        web_sys::HtmlCanvasElement::set_width(self, width)
+    }
+    fn set_height(&self, height: u32) {
+        web_sys::HtmlCanvasElement::set_height(self, height)
+    }
+    fn set_display_size(&self, width: u32, height: u32) {
+        let style = self.style();
+        // Only fails for a read-only style.
+        let _ = style.set_property("width", &format!("{}px", width));
+        let _ = style.set_property("height", &format!("{}px", height));
+    }
+}
+
+impl Canvas for web_sys::OffscreenCanvas {
+    fn set_width(&self, width: u32) {
+        web_sys::OffscreenCanvas::set_width(self, width)
+    }
+    fn set_height(&self, height: u32) {
+        web_sys::OffscreenCanvas::set_height(self, height)
+    }
+    /// An offscreen canvas is shown by the page's placeholder element,
+    /// sized before it was handed over.
+    fn set_display_size(&self, _: u32, _: u32) {}
+}
+
+macro_rules! impl_context2d {
+    ($context:ty) => {
+        impl Context2d for $context {
+            fn set_fill_style_str(&self, color: &str) {
+                <$context>::set_fill_style_str(self, color)
+            }
+            fn set_stroke_style_str(&self, color: &str) {
+                <$context>::set_stroke_style_str(self, color)
+            }
+            fn begin_path(&self) {
+                <$context>::begin_path(self)
+            }
+            fn move_to(&self, x: f64, y: f64) {
+                <$context>::move_to(self, x, y)
+            }
+            fn line_to(&self, x: f64, y: f64) {
+                <$context>::line_to(self, x, y)
+            }
+            fn rect(&self, x: f64, y: f64, w: f64, h: f64) {
+                <$context>::rect(self, x, y, w, h)
+            }
+            fn fill(&self) {
+                <$context>::fill(self)
+            }
+            fn stroke(&self) {
+                <$context>::stroke(self)
+            }
+            fn set_line_dash(&self, segments: &[f64]) {
+                let segments: ::js_sys::Array = segments.iter().map(|&length| JsValue::from_f64(length)).collect();
+                // Only fails for segments that aren't numbers.
+                let _ = <$context>::set_line_dash(self, &segments);
+            }
+            fn set_global_alpha(&self, alpha: f64) {
+                <$context>::set_global_alpha(self, alpha)
+            }
+            fn set_scale(&self, ratio: f64) {
+                // Only fails for a ratio that isn't finite.
+                let _ = <$context>::set_transform(self, ratio, 0.0, 0.0, ratio, 0.0, 0.0);
+            }
+        }
+    };
+}
+
+impl_context2d!(web_sys::CanvasRenderingContext2d);
+impl_context2d!(web_sys::OffscreenCanvasRenderingContext2d);
+
+/// Size the canvas for the whole universe, with `ratio` canvas pixels to
+/// each CSS pixel, as `window.devicePixelRatio` gives, so lines stay crisp
+/// on high-density displays. Drawing is scaled to match, so everything is
+/// still drawn in CSS pixels.
+pub fn init_canvas<C: Canvas, X: Context2d>(universe: &Universe, canvas: &C, ctx: &X, ratio: f64) {
+    let size = universe.cell_size();
+    size_canvas(canvas, ctx, (size + 1) * universe.width() + 1, (size + 1) * universe.height() + 1, ratio);
+}
+
+/// Size the canvas to `width` by `height` CSS pixels, like `init_canvas`,
+/// for showing part of a universe through a viewport.
+pub fn size_canvas<C: Canvas, X: Context2d>(canvas: &C, ctx: &X, width: u32, height: u32, ratio: f64) {
+    canvas.set_width((width as f64 * ratio).round() as u32);
+    canvas.set_height((height as f64 * ratio).round() as u32);
+    canvas.set_display_size(width, height);
+    // Resizing a canvas resets its context, scale and all.
+    ctx.set_scale(ratio);
+}
+
+/// Fill a `width` by `height` pixel area with the dead cell color, under
+/// the gridlines and cells.
+fn clear<C: Context2d>(universe: &Universe, ctx: &C, width: u32, height: u32) {
+    ctx.set_fill_style_str(&universe.dead_color());
+    ctx.begin_path();
+    ctx.rect(0.0, 0.0, width as f64, height as f64);
+    ctx.fill();
+}
+
+/// Draw the gridlines around every cell, unless they're hidden or the
+/// cells are too small for them.
+pub fn draw_grid<C: Context2d>(universe: &Universe, ctx: &C) {
+    #[cfg(feature = "profile")]
+    let _timer = ::utils::Timer::new("draw_grid");
+    let size = universe.cell_size();
+    if !render::shows_grid(universe, size) {
+        return;
+    }
+    ctx.set_stroke_style_str(&universe.grid_color());
+    ctx.begin_path();
+
+    // Vertical lines.
+    for i in 0..universe.width() {
+        ctx.move_to((i * (size + 1) + 1) as f64, 0.0);
+        ctx.line_to((i * (size + 1) + 1) as f64, ((size + 1) * universe.height() + 1) as f64);
+    }
+
+    // Horizontal lines.
+    for j in 0..universe.height() {
+        ctx.move_to(0.0, (j * (size + 1) + 1) as f64);
+        ctx.line_to(((size + 1) * universe.width() + 1) as f64, (j * (size + 1) + 1) as f64);
+    }
+
+    ctx.stroke();
+}
+
+/// The index into `AGE_COLORS` for a live cell of the given age.
+fn age_color(age: u16) -> usize {
+    let bucket = 16 - (age.max(1) - 1).leading_zeros() as usize;
+    usize::min(bucket, AGE_COLORS.len() - 1)
+}
+
+/// Add the square for a cell to the current path.
+fn add_cell_rect<C: Context2d>(ctx: &C, size: u32, row: u32, col: u32) {
+    ctx.rect(
+        (col * (size + 1) + 1) as f64,
+        (row * (size + 1) + 1) as f64,
+        size as f64,
+        size as f64
+    );
+}
+
+/// Fill every cell for which `include` is true with `color`, building a
+/// single path so the canvas only has to fill once.
+fn fill_cells<C: Context2d, F>(universe: &Universe, ctx: &C, color: &str, include: F)
+where
+    F: Fn(u32, u32) -> bool,
+{
+    let size = universe.cell_size();
+    ctx.set_fill_style_str(color);
+    ctx.begin_path();
+    for row in 0..universe.height() {
+        for col in 0..universe.width() {
+            if include(row, col) {
+                add_cell_rect(ctx, size, row, col);
+            }
+        }
+    }
+    ctx.fill();
+}
+
+/// Like `fill_cells`, but only visits the live cells.
+fn fill_live_cells<C: Context2d, F>(universe: &Universe, ctx: &C, color: &str, include: F)
+where
+    F: Fn(u32, u32) -> bool,
+{
+    let size = universe.cell_size();
+    ctx.set_fill_style_str(color);
+    ctx.begin_path();
+    for (row, col) in universe.iter_live() {
+        if include(row, col) {
+            add_cell_rect(ctx, size, row, col);
+        }
+    }
+    ctx.fill();
+}
+
+/// The CSS color of each cell state, as in `render::state_colors` but
+/// keeping the universe's own dead and live cell colors as they were given.
+fn state_colors(universe: &Universe) -> Vec<String> {
+    let mut colors: Vec<String> = render::state_colors(universe).into_iter().map(theme::to_hex_color).collect();
+    colors[0] = universe.dead_color();
+    if universe.mode() != Mode::Wireworld {
+        colors[1] = universe.alive_color();
+    }
+    colors
+}
+
+/// Fill the cells among `cells`, given as their index and where to draw
+/// them, that are in states other than dead and alive, a color at a time.
+fn fill_other_states<C: Context2d, I>(universe: &Universe, ctx: &C, colors: &[String], size: u32, cells: I)
+where
+    I: Iterator<Item = (usize, u32, u32)>,
+{
+    if colors.len() <= 2 {
+        return;
+    }
+    let mut by_state = vec![vec![]; colors.len()];
+    for (idx, row, col) in cells {
+        let state = universe.state(idx) as usize;
+        if state >= 2 {
+            by_state[state].push((row, col));
+        }
+    }
+    for (color, cells) in colors.iter().zip(by_state).skip(2) {
+        if cells.is_empty() {
+            continue;
+        }
+        ctx.set_fill_style_str(color);
+        ctx.begin_path();
+        for (row, col) in cells {
+            add_cell_rect(ctx, size, row, col);
+        }
+        ctx.fill();
+    }
+}
+
+/// Draw the cells, coloring live cells by age if the universe tracks it.
+pub fn draw_cells<C: Context2d>(universe: &Universe, ctx: &C) {
+    #[cfg(feature = "profile")]
+    let _timer = ::utils::Timer::new("draw_cells");
+    let colors = state_colors(universe);
+    // Alive cells.
+    if universe.age_tracking() {
+        draw_aged_cells(universe, ctx);
+    } else {
+        fill_live_cells(universe, ctx, &colors[1], |_, _| true);
+    }
+
+    // Dead cells. These have to be painted one by one rather than by
+    // clearing the board, because the gridlines bleed into the cells' edges.
+    fill_cells(universe, ctx, &colors[0], |row, col| {
+        universe.state(universe.get_index(row, col)) == 0
+    });
+
+    let width = universe.width();
+    let cells = (0..universe.width() * universe.height()).map(|idx| (idx as usize, idx / width, idx % width));
+    fill_other_states(universe, ctx, &colors, universe.cell_size(), cells);
+    draw_ants(universe, ctx);
+}
+
+/// Draw the ants over the cells of the whole board.
+pub fn draw_ants<C: Context2d>(universe: &Universe, ctx: &C) {
+    fill_ants(universe, ctx, universe.cell_size(), |row, col| Some((row, col)));
+}
+
+/// Draw each ant as a triangle pointing the way it's heading, over its
+/// cell. `place` gives where on the canvas a cell is drawn, if it is.
+fn fill_ants<C: Context2d, F>(universe: &Universe, ctx: &C, size: u32, place: F)
+where
+    F: Fn(u32, u32) -> Option<(u32, u32)>,
+{
+    if universe.ant_count() == 0 {
+        return;
+    }
+    ctx.set_fill_style_str(ANT_COLOR);
+    ctx.begin_path();
+    for ant in universe.iter_ants() {
+        let (row, col) = match place(ant.row, ant.col) {
+            Some(cell) => cell,
+            None => continue,
+        };
+        let (left, top) = ((col * (size + 1) + 1) as f64, (row * (size + 1) + 1) as f64);
+        let (right, bottom, size) = (left + size as f64, top + size as f64, size as f64);
+        let (middle, center) = (left + size / 2.0, top + size / 2.0);
+        let corners = match ant.direction {
+            Direction::North => [(middle, top), (right, bottom), (left, bottom)],
+            Direction::East => [(right, center), (left, bottom), (left, top)],
+            Direction::South => [(middle, bottom), (left, top), (right, top)],
+            Direction::West => [(left, center), (right, top), (right, bottom)],
+        };
+        ctx.move_to(corners[0].0, corners[0].1);
+        ctx.line_to(corners[1].0, corners[1].1);
+        ctx.line_to(corners[2].0, corners[2].1);
+    }
+    ctx.fill();
+}
+
+fn draw_aged_cells<C: Context2d>(universe: &Universe, ctx: &C) {
+    for (i, color) in AGE_COLORS.iter().enumerate() {
+        fill_live_cells(universe, ctx, color, |row, col| {
+            let age = universe.age_at(row, col);
+            age != 0 && age_color(age) == i
+        });
+    }
+}
+
+/// Draw the universe's heatmap over the cells. Does nothing if the heatmap
+/// is disabled.
+pub fn draw_heatmap<C: Context2d>(universe: &Universe, ctx: &C) {
+    if !universe.heatmap_enabled() {
+        return;
+    }
+    for (i, color) in HEAT_COLORS.iter().enumerate() {
+        fill_cells(universe, ctx, color, |row, col| {
+            let activity = universe.activity_at(row, col);
+            activity != 0.0 && f64::ceil(activity * HEAT_COLORS.len() as f64) as usize - 1 == i
+        });
+    }
+}
+
+/// Draw the trails behind cells that died recently over the whole board, in
+/// the live cell color fading into the dead color.
+pub fn draw_trail<C: Context2d>(universe: &Universe, ctx: &C) {
+    if universe.trail_half_life() == 0 {
+        return;
+    }
+    let mut shades = vec![vec![]; TRAIL_SHADES];
+    for row in 0..universe.height() {
+        for col in 0..universe.width() {
+            let trail = universe.trail_at(row, col);
+            if trail > 0.0 && universe.state(universe.get_index(row, col)) == 0 {
+                shades[f64::ceil(trail * TRAIL_SHADES as f64) as usize - 1].push((row, col));
+            }
+        }
+    }
+    ctx.set_fill_style_str(&state_colors(universe)[1]);
+    for (i, cells) in shades.iter().enumerate() {
+        ctx.set_global_alpha(TRAIL_ALPHA * (i + 1) as f64 / TRAIL_SHADES as f64);
+        ctx.begin_path();
+        for &(row, col) in cells {
+            add_cell_rect(ctx, universe.cell_size(), row, col);
+        }
+        ctx.fill();
+    }
+    ctx.set_global_alpha(1.0);
+}
+
+/// Repaint only the cells that changed since the universe was last drawn,
+/// falling back to `draw_cells` when that isn't enough. Call
+/// `Universe::mark_drawn` afterwards.
+pub fn draw_delta<C: Context2d>(universe: &Universe, ctx: &C) {
+    // Ages, the heatmap and trails can change without a cell changing
+    // state.
+    if universe.needs_full_redraw() || universe.age_tracking() || universe.heatmap_enabled() || universe.trail_half_life() > 0 {
+        draw_cells(universe, ctx);
+        return;
+    }
+
+    let changed: Vec<usize> = universe.changed().iter().map(|&idx| idx as usize).collect();
+    repaint(universe, ctx, &changed);
+    // Each ant leaves a flipped cell behind, which was just repainted.
+    draw_ants(universe, ctx);
+}
+
+/// Paint over the cells among `cells`, as `row * width + col`, in the
+/// colors of their states, with the whole board in view.
+pub fn repaint<C: Context2d>(universe: &Universe, ctx: &C, cells: &[usize]) {
+    let colors = state_colors(universe);
+    let width = universe.width() as usize;
+    for &(state, color) in [(Cell::Alive, &colors[1]), (Cell::Dead, &colors[0])].iter() {
+        ctx.set_fill_style_str(color);
+        ctx.begin_path();
+        for &idx in cells {
+            if universe.state(idx) == state as u8 {
+                add_cell_rect(ctx, universe.cell_size(), (idx / width) as u32, (idx % width) as u32);
+            }
+        }
+        ctx.fill();
+    }
+    let cells = cells.iter().map(|&idx| (idx, (idx / width) as u32, (idx % width) as u32));
+    fill_other_states(universe, ctx, &colors, universe.cell_size(), cells);
+}
+
+/// Draw the gridlines within the viewport, unless they're hidden or the
+/// cells are too small for them.
+pub fn draw_grid_in<C: Context2d>(viewport: &Viewport, universe: &Universe, ctx: &C) {
+    if !render::shows_grid(universe, viewport.zoom()) {
+        return;
+    }
+    let pitch = viewport.zoom() + 1;
+    let (rows, cols) = viewport.visible_cells();
+    ctx.set_stroke_style_str(&universe.grid_color());
+    ctx.begin_path();
+    for i in 0..cols {
+        ctx.move_to((i * pitch + 1) as f64, 0.0);
+        ctx.line_to((i * pitch + 1) as f64, viewport.height() as f64);
+    }
+    for j in 0..rows {
+        ctx.move_to(0.0, (j * pitch + 1) as f64);
+        ctx.line_to(viewport.width() as f64, (j * pitch + 1) as f64);
+    }
+    ctx.stroke();
+}
+
+/// Draw the cells within the viewport.
+pub fn draw_cells_in<C: Context2d>(viewport: &Viewport, universe: &Universe, ctx: &C) {
+    let (rows, cols) = viewport.visible_cells();
+    let colors = state_colors(universe);
+    for &(state, color) in [(Cell::Alive, &colors[1]), (Cell::Dead, &colors[0])].iter() {
+        ctx.set_fill_style_str(color);
+        ctx.begin_path();
+        for drow in 0..rows {
+            let row = (viewport.origin_row() + drow) % universe.height();
+            for dcol in 0..cols {
+                let col = (viewport.origin_col() + dcol) % universe.width();
+                if universe.state(universe.get_index(row, col)) == state as u8 {
+                    add_cell_rect(ctx, viewport.zoom(), drow, dcol);
+                }
+            }
+        }
+        ctx.fill();
+    }
+    let cells = (0..rows).flat_map(|drow| {
+        let row = (viewport.origin_row() + drow) % universe.height();
+        (0..cols).map(move |dcol| {
+            let col = (viewport.origin_col() + dcol) % universe.width();
+            (universe.get_index(row, col), drow, dcol)
+        })
+    });
+    fill_other_states(universe, ctx, &colors, viewport.zoom(), cells);
+    fill_ants(universe, ctx, viewport.zoom(), |row, col| {
+        let drow = (row + universe.height() - viewport.origin_row() % universe.height()) % universe.height();
+        let dcol = (col + universe.width() - viewport.origin_col() % universe.width()) % universe.width();
+        if drow < rows && dcol < cols {
+            Some((drow, dcol))
+        } else {
+            None
+        }
+    });
+}
+
+/// Redraw the whole board from scratch, on a canvas sized by
+/// `init_canvas`: clear it, then draw the grid, then the cells.
+pub fn draw_board<C: Context2d>(universe: &Universe, ctx: &C) {
+    let size = universe.cell_size();
+    clear(universe, ctx, (size + 1) * universe.width() + 1, (size + 1) * universe.height() + 1);
+    draw_grid(universe, ctx);
+    draw_cells(universe, ctx);
+}
+
+/// Redraw the whole board as seen through the viewport.
+pub fn draw<C: Context2d>(viewport: &Viewport, universe: &Universe, ctx: &C) {
+    if viewport.shows_whole(universe) {
+        draw_board(universe, ctx);
+        draw_trail(universe, ctx);
+        draw_heatmap(universe, ctx);
+    } else {
+        clear(universe, ctx, viewport.width(), viewport.height());
+        draw_grid_in(viewport, universe, ctx);
+        draw_cells_in(viewport, universe, ctx);
+    }
+}
+
+/// Outline a rectangle of cells with a dashed line.
+pub fn draw_selection<C: Context2d>(viewport: &Viewport, universe: &Universe, ctx: &C, region: &Region) {
+    ctx.set_stroke_style_str(SELECTION_COLOR);
+    ctx.set_line_dash(&[4.0, 3.0]);
+    ctx.begin_path();
+    for (x, y, width, height) in viewport.region_rects(universe, region.row, region.col, region.height, region.width) {
+        ctx.rect(x, y, width, height);
+    }
+    ctx.stroke();
+    ctx.set_line_dash(&[]);
+}
+
+/// Outline a group of cells translucently, along the sides of each that
+/// don't border another cell of the group.
+pub fn draw_outline<C: Context2d>(viewport: &Viewport, universe: &Universe, ctx: &C, cells: &[(u32, u32)]) {
+    let group: HashSet<(u32, u32)> = cells.iter().cloned().collect();
+    let (height, width) = (universe.height(), universe.width());
+    let size = viewport.zoom() as f64;
+    ctx.set_stroke_style_str(HOVER_COLOR);
+    ctx.set_global_alpha(HOVER_ALPHA);
+    ctx.begin_path();
+    for &(row, col) in cells {
+        let (x, y) = match viewport.cell_position(universe, row, col) {
+            Some(position) => position,
+            None => continue,
+        };
+        // Along the gridlines around the cell.
+        let (left, top, right, bottom) = (x - 0.5, y - 0.5, x + size + 0.5, y + size + 0.5);
+        let sides = [
+            ((row + height - 1) % height, col, (left, top), (right, top)),
+            ((row + 1) % height, col, (left, bottom), (right, bottom)),
+            (row, (col + width - 1) % width, (left, top), (left, bottom)),
+            (row, (col + 1) % width, (right, top), (right, bottom)),
+        ];
+        for &(r, c, from, to) in sides.iter() {
+            if !group.contains(&(r, c)) {
+                ctx.move_to(from.0, from.1);
+                ctx.line_to(to.0, to.1);
+            }
+        }
+    }
+    ctx.stroke();
+    ctx.set_global_alpha(1.0);
+}
+
+/// Draw cells faintly over the board, as a preview of cells to come.
+pub fn draw_ghost<C: Context2d, I>(viewport: &Viewport, universe: &Universe, ctx: &C, cells: I)
+where
+    I: IntoIterator<Item = (u32, u32)>,
+{
+    ctx.set_global_alpha(GHOST_ALPHA);
+    ctx.set_fill_style_str(&state_colors(universe)[1]);
+    ctx.begin_path();
+    let size = viewport.zoom() as f64;
+    for (row, col) in cells {
+        if let Some((x, y)) = viewport.cell_position(universe, row, col) {
+            ctx.rect(x, y, size, size);
+        }
+    }
+    ctx.fill();
+    ctx.set_global_alpha(1.0);
+}
+
+/// Show what the next generation, `next`, does to the board: outline the
+/// cells it brings to life, and cover those it kills translucently.
+pub fn draw_preview<C: Context2d>(viewport: &Viewport, universe: &Universe, ctx: &C, next: &[Cell]) {
+    let (rows, cols) = viewport.visible_cells();
+    let size = viewport.zoom() as f64;
+    let visible = (0..rows).flat_map(|drow| (0..cols).map(move |dcol| (drow, dcol)));
+    let mut births = vec![];
+    let mut deaths = vec![];
+    for (drow, dcol) in visible {
+        let row = (viewport.origin_row() + drow) % universe.height();
+        let col = (viewport.origin_col() + dcol) % universe.width();
+        let idx = universe.get_index(row, col);
+        match (universe.cell(idx), next[idx]) {
+            (Cell::Dead, Cell::Alive) => births.push((row, col)),
+            (Cell::Alive, Cell::Dead) => deaths.push((row, col)),
+            _ => {}
+        }
+    }
+    ctx.set_stroke_style_str(BIRTH_COLOR);
+    ctx.begin_path();
+    for &(row, col) in births.iter() {
+        if let Some((x, y)) = viewport.cell_position(universe, row, col) {
+            // Inside the cell, clear of the gridlines.
+            ctx.rect(x + 0.5, y + 0.5, size - 1.0, size - 1.0);
+        }
+    }
+    ctx.stroke();
+    ctx.set_global_alpha(GHOST_ALPHA);
+    ctx.set_fill_style_str(DEATH_COLOR);
+    ctx.begin_path();
+    for &(row, col) in deaths.iter() {
+        if let Some((x, y)) = viewport.cell_position(universe, row, col) {
+            ctx.rect(x, y, size, size);
+        }
+    }
+    ctx.fill();
+    ctx.set_global_alpha(1.0);
+}
+
+/// Flash the cells `fades` has just seen born, and cover those that just
+/// died with a tombstone, the fainter the less time it has left.
+pub fn draw_fades<C: Context2d>(viewport: &Viewport, universe: &Universe, ctx: &C, fades: &Fades) {
+    let size = viewport.zoom() as f64;
+    let width = universe.width() as usize;
+    let add_cells = |cells: &mut dyn Iterator<Item = usize>| {
+        ctx.begin_path();
+        for idx in cells {
+            if let Some((x, y)) = viewport.cell_position(universe, (idx / width) as u32, (idx % width) as u32) {
+                ctx.rect(x, y, size, size);
+            }
+        }
+        ctx.fill();
+    };
+    ctx.set_fill_style_str(BIRTH_COLOR);
+    add_cells(&mut fades.born().iter().cloned());
+
+    // Each frame's deaths fade together, so fill them a batch at a time.
+    let mut tombstones: Vec<(usize, u32)> = fades.tombstones().collect();
+    tombstones.sort_unstable_by_key(|&(_, left)| left);
+    ctx.set_fill_style_str(TOMBSTONE_COLOR);
+    for batch in tombstones.chunk_by(|a, b| a.1 == b.1) {
+        ctx.set_global_alpha(batch[0].1 as f64 / fades.frames() as f64);
+        add_cells(&mut batch.iter().map(|&(idx, _)| idx));
+    }
+    ctx.set_global_alpha(1.0);
+}
+
+/// A new canvas, not on the page, with the whole board drawn at one pixel
+/// per cell.
+pub fn draw_whole_board(universe: &Universe) -> Result<web_sys::HtmlCanvasElement, JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("no document to make a canvas in"))?;
+    let canvas: web_sys::HtmlCanvasElement = document.create_element("canvas")?.dyn_into()?;
+    canvas.set_width(universe.width());
+    canvas.set_height(universe.height());
+    let context = canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("2d canvas context is not available"))?
+        .dyn_into::<web_sys::CanvasRenderingContext2d>()?;
+    let mut pixels = vec![0; universe.width() as usize * universe.height() as usize * 4];
+    render::write_pixels(universe, &mut pixels)?;
+    let image_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(&pixels[..]), universe.width(), universe.height())?;
+    context.put_image_data(&image_data, 0.0, 0.0)?;
+    Ok(canvas)
+}
@@ -0,0 +1,183 @@
+//! A rolling line chart of the population, one sample per generation.
+
+use std::collections::VecDeque;
+
+use canvas::Context2d;
+
+/// How many generations the chart shows.
+const SAMPLES: usize = 500;
+
+/// The population of the last `SAMPLES` generations, in a ring buffer.
+pub struct Chart {
+    samples: VecDeque<u32>,
+    capacity: usize,
+    /// The generation of the newest sample.
+    generation: Option<u64>,
+}
+
+impl Chart {
+    pub fn new() -> Chart {
+        Chart::with_capacity(SAMPLES)
+    }
+
+    /// A chart of the last `capacity` generations.
+    pub fn with_capacity(capacity: usize) -> Chart {
+        Chart {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            generation: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.generation = None;
+    }
+
+    /// Record the population at `generation`. A repeat of the newest
+    /// generation, such as a paused frame or an edit, replaces its sample
+    /// rather than adding one, and going back to an earlier generation, as
+    /// clearing the board does, starts the chart again.
+    pub fn record(&mut self, generation: u64, population: u32) {
+        match self.generation {
+            Some(newest) if generation == newest => {
+                self.samples.pop_back();
+            }
+            Some(newest) if generation < newest => self.clear(),
+            _ => {}
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(population);
+        self.generation = Some(generation);
+    }
+
+    /// The smallest and largest samples, or `None` with no samples.
+    pub fn range(&self) -> Option<(u32, u32)> {
+        let min = self.samples.iter().min()?;
+        let max = self.samples.iter().max()?;
+        Some((*min, *max))
+    }
+
+    /// The samples as points on a `width` by `height` pixel chart, oldest
+    /// at the left edge and the newest at the right once the chart fills.
+    /// The smallest sample is at the bottom and the largest at the top, or
+    /// all are halfway up if they're equal.
+    pub fn points(&self, width: f64, height: f64) -> Vec<(f64, f64)> {
+        let (min, max) = match self.range() {
+            Some(range) => range,
+            None => return vec![],
+        };
+        let step = width / (self.capacity.max(2) - 1) as f64;
+        let spread = (max - min) as f64;
+        self.samples
+            .iter()
+            .enumerate()
+            .map(|(i, &sample)| {
+                let y = if spread == 0.0 {
+                    height / 2.0
+                } else {
+                    height - (sample - min) as f64 / spread * height
+                };
+                (i as f64 * step, y)
+            })
+            .collect()
+    }
+
+    /// Clear a `width` by `height` pixel canvas to `background` and draw the
+    /// samples as a line in `color`.
+    pub fn draw<C: Context2d>(&self, ctx: &C, width: f64, height: f64, background: &str, color: &str) {
+        ctx.set_fill_style_str(background);
+        ctx.begin_path();
+        ctx.rect(0.0, 0.0, width, height);
+        ctx.fill();
+
+        let points = self.points(width, height);
+        if let Some(&(x, y)) = points.first() {
+            ctx.set_stroke_style_str(color);
+            ctx.begin_path();
+            ctx.move_to(x, y);
+            for &(x, y) in points.iter().skip(1) {
+                ctx.line_to(x, y);
+            }
+            ctx.stroke();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_one_sample_per_generation() {
+        let mut chart = Chart::with_capacity(3);
+        chart.record(0, 10);
+        chart.record(1, 12);
+        // Paused, then edited.
+        chart.record(1, 12);
+        chart.record(1, 15);
+        assert_eq!(chart.samples, vec![10, 15]);
+        chart.record(2, 9);
+        chart.record(3, 20);
+        assert_eq!(chart.samples, vec![15, 9, 20]);
+        assert_eq!(chart.range(), Some((9, 20)));
+
+        // Clearing the board goes back to generation 0.
+        chart.record(0, 0);
+        assert_eq!(chart.samples, vec![0]);
+    }
+
+    #[test]
+    fn scales_to_the_range() {
+        let mut chart = Chart::with_capacity(5);
+        assert_eq!(chart.points(100.0, 50.0), vec![]);
+        chart.record(0, 7);
+        assert_eq!(chart.points(100.0, 50.0), vec![(0.0, 25.0)]);
+        chart.record(1, 9);
+        chart.record(2, 11);
+        assert_eq!(chart.points(100.0, 50.0), vec![(0.0, 50.0), (25.0, 25.0), (50.0, 0.0)]);
+    }
+
+    /// Counts the lines drawn, and checks they stay on the canvas.
+    struct Recorder {
+        lines: std::cell::Cell<usize>,
+    }
+
+    impl Context2d for Recorder {
+        fn set_fill_style_str(&self, _: &str) {}
+        fn set_stroke_style_str(&self, _: &str) {}
+        fn begin_path(&self) {}
+        fn move_to(&self, x: f64, y: f64) {
+            assert!((0.0..=100.0).contains(&x) && (0.0..=50.0).contains(&y));
+        }
+        fn line_to(&self, x: f64, y: f64) {
+            assert!((0.0..=100.0).contains(&x) && (0.0..=50.0).contains(&y));
+            self.lines.set(self.lines.get() + 1);
+        }
+        fn rect(&self, _: f64, _: f64, _: f64, _: f64) {}
+        fn fill(&self) {}
+        fn stroke(&self) {}
+        fn set_line_dash(&self, _: &[f64]) {}
+        fn set_global_alpha(&self, _: f64) {}
+        fn set_scale(&self, _: f64) {}
+    }
+
+    #[test]
+    fn draws_any_number_of_samples() {
+        let mut chart = Chart::with_capacity(50);
+        let lines = |chart: &Chart| {
+            let ctx = Recorder { lines: std::cell::Cell::new(0) };
+            chart.draw(&ctx, 100.0, 50.0, "#fff", "#000");
+            ctx.lines.get()
+        };
+        assert_eq!(lines(&chart), 0);
+        chart.record(0, 5);
+        assert_eq!(lines(&chart), 0);
+        for generation in 1..80 {
+            chart.record(generation, (generation * 7 % 13) as u32);
+        }
+        assert_eq!(lines(&chart), 49);
+    }
+}
@@ -0,0 +1,117 @@
+//! A log of notable things that happened to the board, such as it dying
+//! out or a pattern being stamped.
+
+use std::collections::VecDeque;
+
+use wasm_bindgen::JsValue;
+
+/// The most events kept. Older ones are dropped first.
+pub const MAX_EVENTS: usize = 1000;
+/// The most events shown in the page's list.
+pub const DISPLAYED_EVENTS: usize = 50;
+
+/// Something that happened, when, and at which generation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Milliseconds since the Unix epoch.
+    pub time: f64,
+    pub generation: u64,
+    pub message: String,
+}
+
+impl Event {
+    /// The event as a JavaScript object with `time`, `generation` and
+    /// `message` properties.
+    fn to_js(&self) -> JsValue {
+        let object = js_sys::Object::new();
+        let set = |key: &str, value: JsValue| js_sys::Reflect::set(&object, &key.into(), &value).unwrap();
+        set("time", self.time.into());
+        set("generation", (self.generation as f64).into());
+        set("message", self.message.as_str().into());
+        object.into()
+    }
+}
+
+/// Events in the order they happened, up to `MAX_EVENTS` of them, with the
+/// latest `DISPLAYED_EVENTS` mirrored into a list element if there is one.
+pub struct EventLog {
+    events: VecDeque<Event>,
+    list: Option<web_sys::Element>,
+}
+
+impl EventLog {
+    pub fn new(list: Option<web_sys::Element>) -> EventLog {
+        EventLog {
+            events: VecDeque::new(),
+            list,
+        }
+    }
+
+    /// Add an event at the end of the log, and of the list.
+    pub fn record(&mut self, time: f64, generation: u64, message: &str) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        let event = Event {
+            time,
+            generation,
+            message: message.to_string(),
+        };
+        if let Some(ref list) = self.list {
+            if let Err(err) = append_item(list, &event) {
+                web_sys::console::error_1(&err);
+            }
+        }
+        self.events.push_back(event);
+    }
+
+    /// The events, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.events.iter()
+    }
+
+    /// The events as a JavaScript array of objects, oldest first.
+    pub fn to_js(&self) -> JsValue {
+        self.iter().map(Event::to_js).collect::<js_sys::Array>().into()
+    }
+}
+
+/// Add `event` to the end of `list` as an item, dropping the first items
+/// past `DISPLAYED_EVENTS`.
+fn append_item(list: &web_sys::Element, event: &Event) -> Result<(), JsValue> {
+    let document = list.owner_document().ok_or_else(|| JsValue::from_str("the event list isn't in a document"))?;
+    let item = document.create_element("li")?;
+    item.set_text_content(Some(&format!("gen {}: {}", event.generation, event.message)));
+    list.append_child(&item)?;
+    while list.child_element_count() as usize > DISPLAYED_EVENTS {
+        if let Some(first) = list.first_element_child() {
+            first.remove();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_events_in_order() {
+        let mut log = EventLog::new(None);
+        log.record(1.0, 0, "pattern 'glider' stamped at (4, 5)");
+        log.record(2.0, 230, "stabilized");
+        assert_eq!(log.iter().map(|event| event.message.as_str()).collect::<Vec<_>>(), ["pattern 'glider' stamped at (4, 5)", "stabilized"]);
+        assert_eq!(log.iter().map(|event| event.generation).collect::<Vec<_>>(), [0, 230]);
+
+        for i in 0..MAX_EVENTS as u64 {
+            log.record(3.0 + i as f64, 230 + i, "cleared");
+        }
+        assert_eq!(log.iter().count(), MAX_EVENTS);
+        // The first two are gone, and the rest keep their order.
+        let first = log.iter().next().unwrap();
+        assert_eq!((first.time, first.generation), (3.0, 230));
+        let last = log.iter().last().unwrap();
+        assert_eq!((last.time, last.generation), (3.0 + (MAX_EVENTS - 1) as f64, 230 + MAX_EVENTS as u64 - 1));
+        assert!(log.iter().zip(log.iter().skip(1)).all(|(a, b)| a.time < b.time));
+    }
+}
@@ -0,0 +1,234 @@
+//! Flashing the cells each generation changes: those just born in a birth
+//! color, and those that just died under a tombstone that fades back to
+//! dead over a number of frames.
+
+use bitset::BitSet;
+use universe::{Cell, Universe};
+
+/// The most frames a tombstone can take to fade, as much as a counter
+/// holds.
+pub const MAX_FADE_FRAMES: u32 = 255;
+
+/// The cells to flash as a universe ticks, with a fade counter per cell.
+#[derive(Clone, Debug, Default)]
+pub struct Fades {
+    /// How many frames a tombstone lasts. Zero flashes nothing.
+    frames: u32,
+    /// The live cells as of the last update.
+    alive: BitSet,
+    generation: u64,
+    /// Frames left before each cell's tombstone has faded, or zero.
+    left: Vec<u8>,
+    /// The cells born in the last generation seen.
+    born: Vec<usize>,
+    /// The cells whose tombstone hasn't faded yet.
+    fading: Vec<usize>,
+}
+
+impl Fades {
+    /// Fades lasting `frames` frames, up to `MAX_FADE_FRAMES`.
+    pub fn new(frames: u32) -> Fades {
+        Fades {
+            frames: u32::min(frames, MAX_FADE_FRAMES),
+            ..Fades::default()
+        }
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.frames > 0
+    }
+
+    /// Move on a frame. Every tombstone fades a step, then if `universe` has
+    /// advanced since the last update, the cells born since are flashed and
+    /// those that died get a tombstone. Any other change, such as an edit,
+    /// is taken as it is, and a step back or a resize starts over.
+    pub fn update(&mut self, universe: &Universe) {
+        if !self.is_enabled() {
+            return;
+        }
+        let len = (universe.width() * universe.height()) as usize;
+        if self.alive.len() != len || universe.generation() < self.generation {
+            self.restart(universe);
+            return;
+        }
+
+        for &idx in self.fading.iter() {
+            self.left[idx] -= 1;
+        }
+        let left = &self.left;
+        self.fading.retain(|&idx| left[idx] > 0);
+
+        let advanced = universe.generation() > self.generation;
+        self.generation = universe.generation();
+        if advanced {
+            self.born.clear();
+        }
+        let changed: Vec<usize> = if universe.needs_full_redraw() {
+            (0..len).collect()
+        } else {
+            universe.changed().iter().map(|&idx| idx as usize).collect()
+        };
+        for idx in changed {
+            let alive = universe.cell(idx) == Cell::Alive;
+            if alive == self.alive.get(idx) {
+                continue;
+            }
+            self.alive.set(idx, alive);
+            if !advanced {
+                continue;
+            }
+            if alive {
+                self.born.push(idx);
+                self.left[idx] = 0;
+            } else {
+                if self.left[idx] == 0 {
+                    self.fading.push(idx);
+                }
+                self.left[idx] = self.frames as u8;
+            }
+        }
+        let (alive, left) = (&self.alive, &self.left);
+        self.born.retain(|&idx| alive.get(idx));
+        self.fading.retain(|&idx| left[idx] > 0);
+    }
+
+    /// Fade for `frames` frames from now on, forgetting what's flashing.
+    #[cfg(feature = "web")]
+    pub fn set_frames(&mut self, frames: u32) {
+        *self = Fades::new(frames);
+    }
+
+    /// The cells born in the latest generation, as `row * width + col`.
+    pub fn born(&self) -> &[usize] {
+        &self.born
+    }
+
+    /// The cells under a tombstone, and how many frames it has left, out
+    /// of `frames`.
+    pub fn tombstones(&self) -> impl Iterator<Item = (usize, u32)> + '_ {
+        self.fading.iter().map(move |&idx| (idx, self.left[idx] as u32))
+    }
+
+    /// Every cell flashing now. They must be repainted before they're
+    /// flashed again, or they'd stay flashed once they stop.
+    pub fn flashed(&self) -> Vec<usize> {
+        self.born.iter().chain(self.fading.iter()).cloned().collect()
+    }
+
+    fn restart(&mut self, universe: &Universe) {
+        let len = (universe.width() * universe.height()) as usize;
+        self.alive = BitSet::new(len);
+        for (row, col) in universe.iter_live() {
+            self.alive.set(universe.get_index(row, col), true);
+        }
+        self.generation = universe.generation();
+        self.left = vec![0; len];
+        self.born.clear();
+        self.fading.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blinker() -> Universe {
+        let mut universe = Universe::new();
+        universe.set_width(5);
+        universe.set_height(5);
+        universe.set_cells(&[(2, 1), (2, 2), (2, 3)]).unwrap();
+        universe
+    }
+
+    #[test]
+    fn tombstones_fade_and_births_last_a_generation() {
+        let mut universe = blinker();
+        let mut fades = Fades::new(3);
+        fades.update(&universe);
+        universe.mark_drawn();
+        assert!(fades.flashed().is_empty());
+
+        universe.tick();
+        fades.update(&universe);
+        universe.mark_drawn();
+        let mut born = fades.born().to_vec();
+        born.sort_unstable();
+        assert_eq!(born, [7, 17]);
+        let mut tombstones: Vec<_> = fades.tombstones().collect();
+        tombstones.sort_unstable();
+        assert_eq!(tombstones, [(11, 3), (13, 3)]);
+
+        // Without another generation, the births stay and the tombstones
+        // count down until they're gone.
+        fades.update(&universe);
+        assert_eq!(fades.born().len(), 2);
+        assert_eq!(fades.tombstones().map(|(_, left)| left).collect::<Vec<_>>(), [2, 2]);
+        fades.update(&universe);
+        fades.update(&universe);
+        assert_eq!(fades.tombstones().count(), 0);
+        assert_eq!(fades.flashed().len(), 2);
+
+        // Back again, the births die and the tombstones rise.
+        universe.tick();
+        fades.update(&universe);
+        let mut born = fades.born().to_vec();
+        born.sort_unstable();
+        assert_eq!(born, [11, 13]);
+        let mut tombstones: Vec<_> = fades.tombstones().map(|(idx, _)| idx).collect();
+        tombstones.sort_unstable();
+        assert_eq!(tombstones, [7, 17]);
+    }
+
+    #[test]
+    fn rebirth_lifts_a_tombstone() {
+        let mut universe = blinker();
+        let mut fades = Fades::new(10);
+        fades.update(&universe);
+        universe.tick();
+        fades.update(&universe);
+        universe.mark_drawn();
+        universe.tick();
+        fades.update(&universe);
+        let mut flashed = fades.flashed();
+        flashed.sort_unstable();
+        // 11 and 13 died and were born again; 7 and 17 the other way.
+        assert_eq!(fades.tombstones().count(), 2);
+        assert_eq!(flashed, [7, 11, 13, 17]);
+        assert!(fades.tombstones().all(|(idx, _)| idx == 7 || idx == 17));
+    }
+
+    #[test]
+    fn edits_and_steps_back_dont_flash() {
+        let mut universe = blinker();
+        universe.enable_history(4);
+        let mut fades = Fades::new(5);
+        fades.update(&universe);
+        universe.mark_drawn();
+        universe.set_cell(0, 0, Cell::Alive);
+        fades.update(&universe);
+        assert!(fades.flashed().is_empty());
+
+        universe.tick();
+        fades.update(&universe);
+        assert!(!fades.flashed().is_empty());
+        universe.mark_drawn();
+        assert!(universe.step_back());
+        fades.update(&universe);
+        assert!(fades.flashed().is_empty());
+    }
+
+    #[test]
+    fn zero_frames_flashes_nothing() {
+        let mut universe = blinker();
+        let mut fades = Fades::new(0);
+        fades.update(&universe);
+        universe.tick();
+        fades.update(&universe);
+        assert!(fades.flashed().is_empty());
+        assert_eq!(Fades::new(1000).frames(), MAX_FADE_FRAMES);
+    }
+}
@@ -0,0 +1,196 @@
+//! Frame timing for the FPS panel.
+
+use std::fmt;
+
+use utils::*;
+
+/// How many recent frames the statistics cover.
+const SAMPLES: usize = 100;
+/// The least time between updates of the FPS panel, in milliseconds.
+const DISPLAY_INTERVAL_MS: f64 = 250.0;
+
+/// Frame rates over the recent frames, in frames per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FpsStats {
+    pub latest: f64,
+    pub average: f64,
+    /// The rate of the slowest 1% of frames, or the frame at the 99th
+    /// percentile of frame times.
+    pub low_1_percent: f64,
+    /// The rate of the slowest frame.
+    pub worst: f64,
+    /// How many frames these cover.
+    pub frames: usize,
+}
+
+impl fmt::Display for FpsStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Frames per Second:")?;
+        writeln!(f, "         latest = {:.0}", self.latest)?;
+        writeln!(f, "avg of last {:>3} = {:.0}", self.frames, self.average)?;
+        writeln!(f, "         1% low = {:.0}", self.low_1_percent)?;
+        writeln!(f, "          worst = {:.0}", self.worst)
+    }
+}
+
+/// The times of the last `SAMPLES` frames, in a ring buffer so recording a
+/// frame never moves the others.
+pub struct Fps {
+    frame_times: [f64; SAMPLES],
+    len: usize,
+    /// Where the next frame time goes.
+    next: usize,
+    last_frame_time_stamp: Option<f64>,
+    last_display: Option<f64>,
+}
+
+impl Fps {
+    pub fn new() -> Fps {
+        Fps {
+            frame_times: [0.0; SAMPLES],
+            len: 0,
+            next: 0,
+            last_frame_time_stamp: None,
+            last_display: None,
+        }
+    }
+
+    /// Record a frame shown at `now` milliseconds, and say whether it's
+    /// time to update the display.
+    pub fn record(&mut self, now: f64) -> bool {
+        if let Some(last) = self.last_frame_time_stamp {
+            self.frame_times[self.next] = now - last;
+            self.next = (self.next + 1) % SAMPLES;
+            self.len = usize::min(self.len + 1, SAMPLES);
+        }
+        self.last_frame_time_stamp = Some(now);
+        if self.len == 0 || self.last_display.is_some_and(|shown| now - shown < DISPLAY_INTERVAL_MS) {
+            return false;
+        }
+        self.last_display = Some(now);
+        true
+    }
+
+    /// Forget when the last frame was, so a pause or a hidden tab doesn't
+    /// count as one long frame. The next frame only starts the timing again.
+    pub fn skip_gap(&mut self) {
+        self.last_frame_time_stamp = None;
+    }
+
+    /// Statistics over the recorded frames, once there are any.
+    pub fn stats(&self) -> Option<FpsStats> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut times = self.frame_times[..self.len].to_vec();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let latest = self.frame_times[(self.next + SAMPLES - 1) % SAMPLES];
+        let total: f64 = times.iter().sum();
+        // The nearest-rank 99th percentile.
+        let rank = (self.len as f64 * 0.99).ceil() as usize;
+        let rate = |ms: f64| 1000.0 / ms;
+        Some(FpsStats {
+            latest: rate(latest),
+            average: rate(total / self.len as f64),
+            low_1_percent: rate(times[rank - 1]),
+            worst: rate(times[self.len - 1]),
+            frames: self.len,
+        })
+    }
+
+    /// Record a frame shown now, updating `div` at most every
+    /// `DISPLAY_INTERVAL_MS`.
+    pub fn tick(&mut self, div: &web_sys::Element) {
+        let now = window().performance().unwrap().now();
+        if self.record(now) {
+            if let Some(stats) = self.stats() {
+                div.set_text_content(Some(&stats.to_string()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer_keeps_the_last_frames() {
+        let mut fps = Fps::new();
+        assert!(!fps.record(0.0));
+        assert_eq!(fps.stats(), None);
+
+        // 150 frames at 10 ms, then 50 at 20 ms: only the last 100 count.
+        let mut now = 0.0;
+        for i in 0..200 {
+            now += if i < 150 { 10.0 } else { 20.0 };
+            fps.record(now);
+        }
+        let stats = fps.stats().unwrap();
+        assert_eq!(stats.frames, 100);
+        assert_eq!(stats.latest, 50.0);
+        assert!((stats.average - 1000.0 / 15.0).abs() < 1e-9);
+        assert_eq!(stats.worst, 50.0);
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut fps = Fps::new();
+        let mut now = 0.0;
+        fps.record(now);
+        // One frame in a hundred takes 100 ms, the rest 10 ms.
+        for i in 0..100 {
+            now += if i == 40 { 100.0 } else { 10.0 };
+            fps.record(now);
+        }
+        let stats = fps.stats().unwrap();
+        assert_eq!(stats.worst, 10.0);
+        assert_eq!(stats.low_1_percent, 100.0);
+        assert_eq!(stats.latest, 100.0);
+
+        // Two slow frames push the 99th percentile onto one of them.
+        now += 50.0;
+        fps.record(now);
+        let stats = fps.stats().unwrap();
+        assert_eq!(stats.low_1_percent, 20.0);
+        assert_eq!(stats.worst, 10.0);
+    }
+
+    #[test]
+    fn display_is_throttled() {
+        let mut fps = Fps::new();
+        assert!(!fps.record(0.0));
+        assert!(fps.record(16.0));
+        assert!(!fps.record(32.0));
+        assert!(!fps.record(250.0));
+        assert!(fps.record(266.0));
+
+        // After a pause, the gap isn't a frame.
+        fps.skip_gap();
+        fps.record(10_000.0);
+        fps.record(10_016.0);
+        assert_eq!(fps.stats().unwrap().worst, 1000.0 / 218.0);
+    }
+
+    #[test]
+    fn time_in_a_hidden_tab_is_not_a_frame() {
+        let mut fps = Fps::new();
+        let mut now = 0.0;
+        for _ in 0..=10 {
+            fps.record(now);
+            now += 20.0;
+        }
+        // Hidden for five seconds, then resumed.
+        fps.skip_gap();
+        now += 5000.0;
+        fps.record(now);
+        let stats = fps.stats().unwrap();
+        assert_eq!(stats.frames, 10);
+        assert_eq!((stats.worst, stats.average), (50.0, 50.0));
+        now += 20.0;
+        fps.record(now);
+        let stats = fps.stats().unwrap();
+        assert_eq!(stats.frames, 11);
+        assert_eq!((stats.latest, stats.worst), (50.0, 50.0));
+    }
+}
@@ -0,0 +1,460 @@
+//! Hashlife: a quadtree of shared, immutable nodes whose futures are
+//! memoized, so repetitive patterns can be run for millions of generations
+//! in a few steps.
+//!
+//! The cells live on an unbounded plane rather than a torus. The root node
+//! is centered on the origin, and grows as the pattern does.
+
+use std::collections::HashMap;
+use std::mem;
+
+use wasm_bindgen::prelude::*;
+
+use rule::Rule;
+use universe::Cell;
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// The deepest root, so that coordinates always fit in an `i64`.
+const MAX_LEVEL: u8 = 60;
+
+/// The default of `set_node_limit`.
+const NODE_LIMIT: usize = 1 << 20;
+
+/// A square of `2^level` cells on a side, made of four squares of half the
+/// size. Level 0 nodes are the two single cells.
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    level: u8,
+    /// The north-west, north-east, south-west and south-east quarters.
+    children: [NodeId; 4],
+    population: u64,
+}
+
+/// A pattern on an unbounded plane, advanced by the Hashlife algorithm.
+#[wasm_bindgen]
+pub struct Hashlife {
+    nodes: Vec<Node>,
+    /// Every node above level 0 by its children, so equal squares share a
+    /// node.
+    index: HashMap<[NodeId; 4], NodeId>,
+    /// The center of a node advanced by `2^j` generations, by node and `j`.
+    results: HashMap<(NodeId, u8), NodeId>,
+    /// The empty node of each level.
+    empty: Vec<NodeId>,
+    root: NodeId,
+    rule: Rule,
+    generation: u64,
+    node_limit: usize,
+}
+
+#[wasm_bindgen]
+impl Hashlife {
+    /// An empty plane running Conway's Game of Life.
+    pub fn new() -> Hashlife {
+        let mut life = Hashlife {
+            nodes: vec![],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![],
+            root: DEAD,
+            rule: Rule::default(),
+            generation: 0,
+            node_limit: NODE_LIMIT,
+        };
+        life.reset_nodes();
+        life.root = life.empty_node(3);
+        life
+    }
+
+    /// The rule in canonical `B3/S23` notation.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Set the rule from `B3/S23` or `23/3` notation. Rules with `B0` would
+    /// fill the infinite plane, so they are rejected.
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), String> {
+        let rule = Rule::parse(rulestring)?;
+        if rule.next(Cell::Dead, 0) == Cell::Alive {
+            return Err(format!("rule '{}' brings empty space to life, so hashlife can't run it", rule));
+        }
+        if rule.states() > 2 {
+            return Err(format!("rule '{}' has decaying states, so hashlife can't run it", rule));
+        }
+        if rule.is_extended() {
+            return Err(format!("rule '{}' looks beyond the eight neighbors, so hashlife can't run it", rule));
+        }
+        if rule != self.rule {
+            self.rule = rule;
+            self.results.clear();
+        }
+        Ok(())
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    /// The number of nodes stored, which is what `set_node_limit` limits.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Cap the number of stored nodes. Past half of this, results are
+    /// forgotten and unreachable nodes dropped between steps; if a single
+    /// step needs more than this, `fast_forward` returns an error.
+    pub fn set_node_limit(&mut self, limit: usize) {
+        self.node_limit = limit;
+    }
+
+    /// Make the cell at `row` and `col` alive, counted from the origin.
+    pub fn set_cell(&mut self, row: i64, col: i64) -> Result<(), String> {
+        loop {
+            let half = 1i64 << (self.level(self.root) - 1);
+            if (-half..half).contains(&row) && (-half..half).contains(&col) {
+                break;
+            }
+            self.expand()?;
+        }
+        let level = self.level(self.root);
+        let half = 1i64 << (level - 1);
+        self.root = self.set((self.root, level), (row + half) as u64, (col + half) as u64)?;
+        Ok(())
+    }
+
+    /// Advance `generations` generations, in steps of the largest powers of
+    /// two that fit. On an error, the generations done so far are kept.
+    pub fn fast_forward(&mut self, generations: u64) -> Result<(), String> {
+        let mut remaining = generations;
+        while remaining > 0 {
+            let j = u8::min(63 - remaining.leading_zeros() as u8, MAX_LEVEL - 3);
+            if self.nodes.len() > self.node_limit / 2 {
+                self.collect_garbage();
+            }
+            self.expand_for(j)?;
+            self.root = self.step(self.root, j)?;
+            remaining -= 1 << j;
+            self.generation += 1 << j;
+        }
+        Ok(())
+    }
+}
+
+impl Hashlife {
+    /// The live cells as `(row, col)` from the origin, in row-major order.
+    pub fn live_cells(&self) -> Vec<(i64, i64)> {
+        let mut cells = Vec::with_capacity(self.population() as usize);
+        let level = self.level(self.root);
+        let half = 1i64 << (level - 1);
+        self.collect(self.root, level, -half, -half, &mut cells);
+        cells.sort_unstable();
+        cells
+    }
+
+    fn collect(&self, node: NodeId, level: u8, row: i64, col: i64, cells: &mut Vec<(i64, i64)>) {
+        if self.nodes[node as usize].population == 0 {
+            return;
+        }
+        if level == 0 {
+            cells.push((row, col));
+            return;
+        }
+        let half = 1i64 << (level - 1);
+        for (i, &child) in self.children(node).iter().enumerate() {
+            self.collect(child, level - 1, row + (i / 2) as i64 * half, col + (i % 2) as i64 * half, cells);
+        }
+    }
+
+    /// Start over with only the two cells and the empty nodes.
+    fn reset_nodes(&mut self) {
+        self.nodes = vec![
+            Node { level: 0, children: [DEAD; 4], population: 0 },
+            Node { level: 0, children: [DEAD; 4], population: 1 },
+        ];
+        self.index.clear();
+        self.results.clear();
+        self.empty = vec![DEAD];
+    }
+
+    fn level(&self, node: NodeId) -> u8 {
+        self.nodes[node as usize].level
+    }
+
+    fn children(&self, node: NodeId) -> [NodeId; 4] {
+        self.nodes[node as usize].children
+    }
+
+    /// The node with these quarters, made if it doesn't exist yet.
+    fn join(&mut self, children: [NodeId; 4]) -> Result<NodeId, String> {
+        if let Some(&node) = self.index.get(&children) {
+            return Ok(node);
+        }
+        if self.nodes.len() >= self.node_limit {
+            return Err(format!("hashlife needs more than its limit of {} nodes", self.node_limit));
+        }
+        Ok(self.insert(children))
+    }
+
+    fn insert(&mut self, children: [NodeId; 4]) -> NodeId {
+        let population = children
+            .iter()
+            .fold(0u64, |sum, &child| sum.saturating_add(self.nodes[child as usize].population));
+        let node = self.nodes.len() as NodeId;
+        self.nodes.push(Node { level: self.level(children[0]) + 1, children, population });
+        self.index.insert(children, node);
+        node
+    }
+
+    fn empty_node(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let node = self.index.get(&[below; 4]).cloned().unwrap_or_else(|| self.insert([below; 4]));
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Set a cell `row` and `col` from the top-left corner of `node`.
+    fn set(&mut self, (node, level): (NodeId, u8), row: u64, col: u64) -> Result<NodeId, String> {
+        if level == 0 {
+            return Ok(ALIVE);
+        }
+        let half = 1u64 << (level - 1);
+        let mut children = self.children(node);
+        let i = (row >= half) as usize * 2 + (col >= half) as usize;
+        children[i] = self.set((children[i], level - 1), row % half, col % half)?;
+        self.join(children)
+    }
+
+    /// Surround the root with empty space, doubling its size and keeping
+    /// it centered on the origin.
+    fn expand(&mut self) -> Result<(), String> {
+        let level = self.level(self.root);
+        if level >= MAX_LEVEL {
+            return Err("pattern has grown too large for hashlife".to_string());
+        }
+        let [nw, ne, sw, se] = self.children(self.root);
+        let e = self.empty_node(level - 1);
+        let children = [
+            self.join([e, e, e, nw])?,
+            self.join([e, e, ne, e])?,
+            self.join([e, sw, e, e])?,
+            self.join([se, e, e, e])?,
+        ];
+        self.root = self.join(children)?;
+        Ok(())
+    }
+
+    /// Expand the root until stepping it `2^j` generations can't lose any
+    /// cells: it must be at least three levels deeper than `j`, with every
+    /// live cell in the middle quarter.
+    fn expand_for(&mut self, j: u8) -> Result<(), String> {
+        loop {
+            if self.level(self.root) >= j + 3 {
+                let center = self.center(self.root)?;
+                let middle = self.center(center)?;
+                if self.nodes[middle as usize].population == self.population() {
+                    return Ok(());
+                }
+            }
+            self.expand()?;
+        }
+    }
+
+    /// The middle half of a node, a level down.
+    fn center(&mut self, node: NodeId) -> Result<NodeId, String> {
+        let [nw, ne, sw, se] = self.children(node);
+        self.join([self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]])
+    }
+
+    /// The middle half of a node of level `k`, advanced `2^j` generations,
+    /// where `j` is at most `k - 2`.
+    fn step(&mut self, node: NodeId, j: u8) -> Result<NodeId, String> {
+        let level = self.level(node);
+        if self.nodes[node as usize].population == 0 {
+            return Ok(self.empty_node(level - 1));
+        }
+        if level == 2 {
+            return self.step_base(node);
+        }
+        if let Some(&result) = self.results.get(&(node, j)) {
+            return Ok(result);
+        }
+
+        // Nine overlapping squares of half the size, in rows.
+        let [a, b, c, d] = self.children(node);
+        let [a, b, c, d] = [self.children(a), self.children(b), self.children(c), self.children(d)];
+        let nine = [
+            self.join(a)?,
+            self.join([a[1], b[0], a[3], b[2]])?,
+            self.join(b)?,
+            self.join([a[2], a[3], c[0], c[1]])?,
+            self.join([a[3], b[2], c[1], d[0]])?,
+            self.join([b[2], b[3], d[0], d[1]])?,
+            self.join(c)?,
+            self.join([c[1], d[0], c[3], d[2]])?,
+            self.join(d)?,
+        ];
+        // At full speed both halves of the step advance time; slower, only
+        // the second does.
+        let full_speed = j == level - 2;
+        let mut r = [DEAD; 9];
+        for (r, &square) in r.iter_mut().zip(nine.iter()) {
+            *r = if full_speed { self.step(square, j - 1)? } else { self.center(square)? };
+        }
+        let quarters = [
+            self.join([r[0], r[1], r[3], r[4]])?,
+            self.join([r[1], r[2], r[4], r[5]])?,
+            self.join([r[3], r[4], r[6], r[7]])?,
+            self.join([r[4], r[5], r[7], r[8]])?,
+        ];
+        let next_j = if full_speed { j - 1 } else { j };
+        let mut children = [DEAD; 4];
+        for (child, &quarter) in children.iter_mut().zip(quarters.iter()) {
+            *child = self.step(quarter, next_j)?;
+        }
+        let result = self.join(children)?;
+        self.results.insert((node, j), result);
+        Ok(result)
+    }
+
+    /// The middle two by two cells of a four by four node, a generation on.
+    fn step_base(&mut self, node: NodeId) -> Result<NodeId, String> {
+        let mut cells = [[false; 4]; 4];
+        for (i, &quarter) in self.children(node).iter().enumerate() {
+            for (k, &leaf) in self.children(quarter).iter().enumerate() {
+                cells[i / 2 * 2 + k / 2][i % 2 * 2 + k % 2] = leaf == ALIVE;
+            }
+        }
+        let mut next = [DEAD; 4];
+        for (k, next) in next.iter_mut().enumerate() {
+            let (row, col) = (1 + k / 2, 1 + k % 2);
+            let neighbors = cells[row - 1..=row + 1]
+                .iter()
+                .flat_map(|cells| cells[col - 1..=col + 1].iter())
+                .filter(|&&alive| alive)
+                .count() as u8
+                - cells[row][col] as u8;
+            let cell = if cells[row][col] { Cell::Alive } else { Cell::Dead };
+            if self.rule.next(cell, neighbors) == Cell::Alive {
+                *next = ALIVE;
+            }
+        }
+        self.join(next)
+    }
+
+    /// Forget the memoized results and keep only the nodes the root uses.
+    fn collect_garbage(&mut self) {
+        let old = mem::take(&mut self.nodes);
+        self.reset_nodes();
+        let mut copies = HashMap::new();
+        self.root = self.copy(&old, self.root, &mut copies);
+    }
+
+    fn copy(&mut self, old: &[Node], node: NodeId, copies: &mut HashMap<NodeId, NodeId>) -> NodeId {
+        if node == DEAD || node == ALIVE {
+            return node;
+        }
+        if let Some(&copy) = copies.get(&node) {
+            return copy;
+        }
+        let mut children = old[node as usize].children;
+        for child in children.iter_mut() {
+            *child = self.copy(old, *child, copies);
+        }
+        // There are never more nodes than before, so the limit can't be hit.
+        let copy = self.index.get(&children).cloned().unwrap_or_else(|| self.insert(children));
+        copies.insert(node, copy);
+        copy
+    }
+}
+
+impl Default for Hashlife {
+    fn default() -> Hashlife {
+        Hashlife::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_cells(cells: &[(i64, i64)]) -> Hashlife {
+        let mut life = Hashlife::new();
+        for &(row, col) in cells {
+            life.set_cell(row, col).unwrap();
+        }
+        life
+    }
+
+    #[test]
+    fn glider_after_a_million_generations() {
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        let mut life = with_cells(&glider);
+        life.fast_forward(1 << 20).unwrap();
+        // A glider moves one cell down and right every four generations.
+        let shift = 1 << 18;
+        let expected: Vec<_> = glider.iter().map(|&(row, col)| (row + shift, col + shift)).collect();
+        assert_eq!(life.live_cells(), expected);
+        assert_eq!(life.generation(), 1 << 20);
+    }
+
+    #[test]
+    fn r_pentomino_stabilizes() {
+        let mut life = with_cells(&[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)]);
+        life.fast_forward(1103).unwrap();
+        assert_eq!(life.population(), 116);
+        // From then on only the still lifes, oscillators and six escaping
+        // gliders remain, so the population is periodic.
+        life.fast_forward(1000).unwrap();
+        assert_eq!(life.population(), 116);
+    }
+
+    #[test]
+    fn matches_single_steps() {
+        let mut fast = with_cells(&[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)]);
+        let mut slow = with_cells(&[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)]);
+        fast.fast_forward(77).unwrap();
+        for _ in 0..77 {
+            slow.fast_forward(1).unwrap();
+        }
+        assert_eq!(fast.live_cells(), slow.live_cells());
+    }
+
+    #[test]
+    fn node_limit_errors_and_collects() {
+        let mut life = with_cells(&[(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)]);
+        life.set_node_limit(50);
+        assert!(life.fast_forward(1000).is_err());
+
+        // Garbage collection keeps a glider's memory use bounded.
+        let mut life = with_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        life.set_node_limit(2000);
+        for _ in 0..100 {
+            life.fast_forward(100).unwrap();
+        }
+        assert!(life.node_count() <= 2000);
+        assert_eq!(life.population(), 5);
+    }
+
+    #[test]
+    fn rejects_b0() {
+        let mut life = Hashlife::new();
+        assert!(life.set_rule("B03/S23").is_err());
+        life.set_rule("B36/S23").unwrap();
+        assert_eq!(life.rule(), "B36/S23");
+    }
+}
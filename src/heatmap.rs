@@ -0,0 +1,99 @@
+//! Per-cell activity counters, for showing where a universe is busiest.
+
+use bitset::BitSet;
+
+/// Counts how often each cell changes state, halving every count every
+/// `decay_interval` generations so old activity fades.
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    counts: Vec<u16>,
+    decay_interval: u32,
+    ticks: u32,
+}
+
+impl Heatmap {
+    /// A heatmap of `len` cells that decays every `decay_interval`
+    /// generations. An interval of zero is treated as one.
+    pub fn new(len: usize, decay_interval: u32) -> Heatmap {
+        Heatmap {
+            counts: vec![0; len],
+            decay_interval: u32::max(decay_interval, 1),
+            ticks: 0,
+        }
+    }
+
+    pub fn decay_interval(&self) -> u32 {
+        self.decay_interval
+    }
+
+    /// Count the cells that differ between two consecutive generations.
+    pub fn record(&mut self, before: &BitSet, after: &BitSet) {
+        for (idx, count) in self.counts.iter_mut().enumerate() {
+            if before.get(idx) != after.get(idx) {
+                *count = count.saturating_add(1);
+            }
+        }
+        self.ticks += 1;
+        if self.ticks == self.decay_interval {
+            self.ticks = 0;
+            for count in self.counts.iter_mut() {
+                *count /= 2;
+            }
+        }
+    }
+
+    pub fn count(&self, idx: usize) -> u16 {
+        self.counts[idx]
+    }
+
+    /// The activity of a cell scaled to `[0, 1]`. A cell that changes every
+    /// generation settles just under twice the decay interval, which maps
+    /// to 1.
+    pub fn intensity(&self, idx: usize) -> f64 {
+        f64::min(self.count(idx) as f64 / (2 * self.decay_interval) as f64, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halves_every_interval() {
+        let before = BitSet::new(2);
+        let mut after = BitSet::new(2);
+        after.set(0, true);
+
+        let mut heatmap = Heatmap::new(2, 4);
+        for _ in 0..3 {
+            heatmap.record(&before, &after);
+        }
+        assert_eq!(heatmap.count(0), 3);
+        assert_eq!(heatmap.count(1), 0);
+        heatmap.record(&before, &after);
+        assert_eq!(heatmap.count(0), 2);
+        for _ in 0..4 {
+            heatmap.record(&before, &before);
+        }
+        assert_eq!(heatmap.count(0), 1);
+        for _ in 0..4 {
+            heatmap.record(&before, &before);
+        }
+        assert_eq!(heatmap.count(0), 0);
+    }
+
+    #[test]
+    fn constant_activity_stays_bounded() {
+        let before = BitSet::new(1);
+        let mut after = BitSet::new(1);
+        after.set(0, true);
+
+        let mut heatmap = Heatmap::new(1, 8);
+        for _ in 0..1000 {
+            heatmap.record(&before, &after);
+            assert!(heatmap.count(0) < 16);
+        }
+        assert!(heatmap.intensity(0) > 0.4);
+        assert!(heatmap.intensity(0) <= 1.0);
+    }
+}
@@ -0,0 +1,44 @@
+//! Immigration, Life with two colors of live cell competing for the board.
+
+use rule::Rule;
+
+/// The states of an Immigration cell. Both colors are alive.
+pub const FIRST: u8 = 1;
+pub const SECOND: u8 = 2;
+pub const STATES: u16 = 3;
+
+/// The color of the second kind of live cell as RGBA bytes. The first is
+/// drawn in the live cell color.
+pub const SECOND_COLOR: [u8; 4] = [0xD6, 0x28, 0x28, 0xFF];
+
+/// The state after `state` under `rule`, given the cell's live neighbors
+/// and how many of them are the second color. A survivor keeps its color,
+/// and a newborn takes the color most of its neighbors have, the first on a
+/// tie, which B3 never has.
+#[inline]
+pub fn next_state(rule: &Rule, state: u8, live_neighbors: u16, second_neighbors: u16) -> u8 {
+    match state {
+        0 if !rule.births(live_neighbors) => 0,
+        0 if second_neighbors * 2 > live_neighbors => SECOND,
+        0 => FIRST,
+        state if rule.survives(live_neighbors) => state,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn births_take_the_majority_color() {
+        let rule = Rule::conway();
+        assert_eq!(next_state(&rule, 0, 3, 0), FIRST);
+        assert_eq!(next_state(&rule, 0, 3, 1), FIRST);
+        assert_eq!(next_state(&rule, 0, 3, 2), SECOND);
+        assert_eq!(next_state(&rule, 0, 2, 2), 0);
+        assert_eq!(next_state(&rule, SECOND, 2, 0), SECOND);
+        assert_eq!(next_state(&rule, FIRST, 3, 3), FIRST);
+        assert_eq!(next_state(&rule, SECOND, 4, 4), 0);
+    }
+}
@@ -1,157 +1,416 @@
+#[cfg(feature = "web")]
+extern crate js_sys;
 extern crate wasm_bindgen;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(all(test, feature = "serde"))]
+extern crate bincode;
+#[cfg(feature = "web")]
 extern crate web_sys;
 
+#[cfg(feature = "web")]
 mod utils;
+#[cfg(feature = "web")]
+mod app;
+#[cfg(feature = "web")]
+mod canvas;
+#[cfg(feature = "web")]
+mod fps;
+#[cfg(feature = "web")]
+mod chart;
+#[cfg(feature = "web")]
+mod worker;
+#[cfg(feature = "web")]
+mod storage;
+#[cfg(feature = "web")]
+mod status;
+#[cfg(feature = "web")]
+mod events;
+#[cfg(any(feature = "web", feature = "serde", test))]
+mod base64;
 mod universe;
+mod render;
+mod shape;
+mod rle;
+mod plaintext;
+mod life106;
+mod pattern_file;
+mod rule;
+mod simd;
+mod bitset;
+mod bitparallel;
+mod rng;
+mod patterns;
+mod transform;
+mod heatmap;
+mod trail;
+mod soup;
+mod activity;
+#[cfg(any(feature = "web", test))]
+mod timestep;
+mod hashlife;
+mod theme;
+mod builder;
+mod sparse;
+mod raster;
+mod wireworld;
+mod immigration;
+mod neighborhood;
+mod margolus;
+mod noise;
+mod ant;
+#[cfg(any(feature = "web", test))]
+mod fade;
+mod brush;
+#[cfg(any(feature = "web", test))]
+mod selection;
 
-use utils::*;
-use universe::*;
+pub use ant::{Ant, AntMode, Direction};
+pub use brush::BrushShape;
+pub use builder::UniverseBuilder;
+pub use universe::{Algorithm, Anchor, AutoPause, Cell, Mode, PasteMode, Settled, StabilityResult, Topology, Universe};
+pub use hashlife::Hashlife;
+pub use pattern_file::ExportFormat;
+pub use patterns::Pattern;
+pub use render::Viewport;
+pub use sparse::SparseUniverse;
+pub use soup::{run_soups, SoupReport, SoupResult};
+#[cfg(feature = "web")]
+pub use soup::search_soups;
+pub use theme::Theme;
+pub use transform::Transform;
+pub use neighborhood::NeighborhoodKind;
+pub use wireworld::WireworldPattern;
 
-use std::rc::Rc;
-use std::cell::RefCell;
-use wasm_bindgen::JsCast;
+#[cfg(feature = "web")]
+pub use app::App;
+#[cfg(feature = "web")]
+use app::GameLoop;
+
+#[cfg(feature = "web")]
 use wasm_bindgen::prelude::*;
 
-struct Fps {
-    frames: Vec<f64>,
-    last_frame_time_stamp: f64,
-}
-
-impl Fps {
-    pub fn new() -> Fps {
-        Fps {
-            frames: vec![],
-            last_frame_time_stamp: 0.0,
-        }
-    }
-
-    pub fn tick(&mut self, div: &web_sys::Element) {
-        let now = window().performance().unwrap().now();
-        let elapsed = now - self.last_frame_time_stamp;
-        let fps = 1.0 / elapsed * 1000.0;
-        if self.last_frame_time_stamp != 0.0 {
-            self.frames.push(fps);
-            if self.frames.len() > 100 {
-                self.frames.remove(0);
-            }
-            let sum: f64 = self.frames.iter().sum();
-            let ave_fps = sum / self.frames.len() as f64;
-            let min_fps = self.frames.iter().min_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal)).unwrap();
-            let max_fps = self.frames.iter().max_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal)).unwrap();
-            div.set_inner_html(&format!("Frames per Second:
-         latest = {:.0}
-avg of last 100 = {:.0}
-min of last 100 = {:.0}
-max of last 100 = {:.0}
-", fps, ave_fps, min_fps, max_fps));
-        }
-        self.last_frame_time_stamp = now;
-    }
-}
-
-#[wasm_bindgen(start)]
-pub fn main() {
+/// Run the page's board on the canvas with the ID `game-of-life-canvas`,
+/// with the controls around it found by their usual IDs, such as
+/// `play-pause` and `fps`. The other exported functions control this board.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn start() -> Result<(), JsValue> {
+    utils::set_panic_hook();
+    web_sys::console::log_1(&"start".into());
+
+    let game_loop = GameLoop::new("game-of-life-canvas")?;
+    game_loop.load_hash()?;
+    game_loop.attach_listeners()?;
+    game_loop.play();
+    app::set_running(&game_loop);
+    Ok(())
+}
+
+/// Run a board as `config` describes, alongside any others on the page, and
+/// return a handle to control it. `config` is an object with the `canvas`
+/// element or its ID, and optionally:
+///
+/// - `controls`: an object from the usual control IDs, such as
+///   `play-pause`, `step` or `speed`, to this board's element or its ID
+/// - `width` and `height`
+/// - `rule`, such as `B36/S23`
+/// - `pattern`: a Life pattern from the library by name, or the text of an
+///   RLE, plaintext or Life 1.06 file, in the middle of an empty board
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn init(config: JsValue) -> Result<App, JsValue> {
     utils::set_panic_hook();
-    web_sys::console::log_1(&format!("start").into());
-
-    let universe = Universe::new();
-    let mut fps = Fps::new();
-    let fps_div = document().get_element_by_id("fps").unwrap();
-    let canvas = document().get_element_by_id("game-of-life-canvas").unwrap();
-    let canvas: web_sys::HtmlCanvasElement = canvas
-        .dyn_into::<web_sys::HtmlCanvasElement>()
-        .map_err(|_| ())
-        .unwrap();
-    let context = canvas
-        .get_context("2d")
-        .unwrap()
-        .unwrap()
-        .dyn_into::<web_sys::CanvasRenderingContext2d>()
-        .unwrap();
-
-    universe.init_canvas(&canvas);
-
-    // Here we want to call `requestAnimationFrame` repeatedly to run game of life.
-    // After it's done we want all our resources cleaned up. To
-    // achieve this we're using an `Rc`. The `Rc` will eventually store the
-    // closure we want to execute on each frame, but to start out it contains
-    // `None`.
-    //
-    // After the `Rc` is made we'll actually create the closure, and the closure
-    // will reference one of the `Rc` instances. The other `Rc` reference is
-    // used to store the closure, request the first frame, and then is dropped
-    // by this function.
-    //
-    // Inside the closure we've got a persistent `Rc` reference, which we use
-    // for all future iterations of the loop
-    let f = Rc::new(RefCell::new(None));
-    let g = f.clone();
-    let h = f.clone();
-
-    let universe_width = universe.width();
-    let universe_height = universe.height();
-
-    universe.draw_grid(context.as_ref());
-
-    let rc1 = Rc::new(RefCell::new(universe));
-    let rc2 = rc1.clone();
-    let rc3 = Rc::new(RefCell::new(canvas));
-    let rc4 = rc3.clone();
-    let rc5 = Rc::new(RefCell::new(true));
-    let rc6 = rc5.clone();
-
-    *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
-        let mut universe = rc1.borrow_mut();
-        universe.draw_cells(context.as_ref());
-        universe.tick();
-        fps.tick(&fps_div);
-        let playing = *rc5.borrow();
-        if playing {
-            request_animation_frame(f.borrow().as_ref().unwrap());
-        }
-    }) as Box<dyn FnMut()>));
-
-    request_animation_frame(g.borrow().as_ref().unwrap());
-
-    {
-        let closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
-            let canvas = rc3.borrow();
-            let bounding_rect = canvas.get_bounding_client_rect();
-            let scale_x = canvas.width() as f64 / bounding_rect.width() as f64;
-            let scale_y = canvas.height() as f64 / bounding_rect.height() as f64;
-            let canvas_left: f64 = (event.client_x() as f64 - bounding_rect.x() as f64) * scale_x;
-            let canvas_top: f64 = (event.client_y() as f64 - bounding_rect.y() as f64) * scale_y;
-
-            let row = u32::min(f64::round(canvas_top / (Universe::CELL_SIZE + 1) as f64) as u32, universe_height - 1 as u32);
-            let col = u32::min(f64::floor(canvas_left / (Universe::CELL_SIZE + 1) as f64) as u32, universe_width - 1 as u32);
-            let mut universe = rc2.borrow_mut();
-            universe.toggle_cell(row, col);
-        }) as Box<dyn FnMut(_)>);
-
-        let canvas = rc4.borrow();
-        canvas.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref()).unwrap();
-        closure.forget();
-    }
-
-    let play_pause_button = document().get_element_by_id("play-pause").unwrap();
-    let rc7 = Rc::new(RefCell::new(play_pause_button));
-    let rc8 = rc7.clone();
-    {
-        let closure = Closure::wrap(Box::new(move || {
-            let play_pause_button = rc8.borrow();
-            let mut playing = rc6.borrow_mut();
-            if *playing {
-                *playing = false;
-                play_pause_button.set_inner_html("▶");
-            } else {
-                *playing = true;
-                play_pause_button.set_inner_html("▐▐");
-                request_animation_frame(h.borrow().as_ref().unwrap());
-            }
-        }) as Box<dyn FnMut()>);
-
-        let play_pause_button = rc7.borrow();
-        play_pause_button.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref()).unwrap();
-        closure.forget();
-    }
+    app::init(&config)
+}
+
+/// Answer simulation requests from the page. Call this from the script of
+/// a worker passed to `use_worker`.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn worker_main() -> Result<(), JsValue> {
+    utils::set_panic_hook();
+    worker::run()
+}
+
+/// Tick the page's board in a worker started from the script at `url`, so
+/// big boards don't slow down drawing and input. Returns whether the
+/// worker started; without one, the page keeps ticking as before.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn use_worker(url: &str) -> Result<bool, JsValue> {
+    app::running()?.use_worker(url)
+}
+
+/// Choose when the page's animation pauses by itself: never, once the
+/// board dies out, or also once a generation changes nothing.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_auto_pause(mode: AutoPause) -> Result<(), JsValue> {
+    app::running()?.set_auto_pause(mode);
+    Ok(())
+}
+
+/// Save the page's board in `localStorage` under `key`.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn save_state(key: &str) -> Result<(), JsValue> {
+    app::running()?.save_state(key)
+}
+
+/// Replace the page's board with the one saved under `key`, resizing the
+/// canvas to fit.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn load_state(key: &str) -> Result<(), JsValue> {
+    app::running()?.load_state(key)
+}
+
+/// Stamp the pattern in a file named `name`, with contents `text`, in the
+/// middle of the page's board, as dropping the file on the canvas does.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn insert_pattern_file(name: &str, text: &str) -> Result<(), JsValue> {
+    app::running()?.insert_pattern_file(name, text)
+}
+
+/// Download the page's board in the given format.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn export_file(format: ExportFormat) -> Result<(), JsValue> {
+    app::running()?.export_file(format)
+}
+
+/// The page's board in the given format, as a `Blob` of text.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn export_blob(format: ExportFormat) -> Result<web_sys::Blob, JsValue> {
+    app::running()?.export_blob(format)
+}
+
+/// A promise of a PNG `Blob` of the page's canvas, or with `whole_board` of
+/// the whole board at one pixel per cell.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn snapshot_png(whole_board: bool) -> Result<js_sys::Promise, JsValue> {
+    app::running()?.snapshot_png(whole_board)
+}
+
+/// Download `snapshot_png` as a file named with the generation.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn download_png(whole_board: bool) -> Result<(), JsValue> {
+    app::running()?.download_png(whole_board)
+}
+
+/// Save the page's board under `key` whenever it pauses, or stop with
+/// `undefined`.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_autosave(key: Option<String>) -> Result<(), JsValue> {
+    app::running()?.set_autosave(key);
+    Ok(())
+}
+
+/// Show how many frames have run and how long they took, with
+/// `console.table`, and start counting again.
+#[cfg(feature = "profile")]
+#[wasm_bindgen]
+pub fn log_profile() {
+    utils::log_spans();
+}
+
+/// Start the page's animation, if it isn't running.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn play() -> Result<(), JsValue> {
+    app::running()?.play();
+    Ok(())
+}
+
+/// Stop the page's animation after the current frame.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn pause() -> Result<(), JsValue> {
+    app::running()?.pause();
+    Ok(())
+}
+
+/// The notable things that have happened to the page's board, oldest
+/// first, as objects with the `time` in milliseconds since the epoch, the
+/// `generation` and a `message` such as "extinct" or "cleared".
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn events() -> Result<JsValue, JsValue> {
+    Ok(app::running()?.events())
+}
+
+/// Play the page's animation if it is paused, or pause it if it is playing.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn toggle() -> Result<(), JsValue> {
+    app::running()?.toggle();
+    Ok(())
+}
+
+/// Whether the page's animation is running. Listen for `playstatechange`
+/// on the canvas to hear when this changes.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn is_playing() -> Result<bool, JsValue> {
+    Ok(app::running()?.is_playing())
+}
+
+/// Pause the page, then advance it a single generation and draw it.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn step() -> Result<(), JsValue> {
+    app::running()?.step();
+    Ok(())
+}
+
+/// Choose whether a left click on the page's board toggles cells, as it
+/// used to, or only sets them alive.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_toggle_on_click(toggle: bool) -> Result<(), JsValue> {
+    app::running()?.set_toggle_on_click(toggle);
+    Ok(())
+}
+
+/// Show the library pattern called `name` following the mouse over the
+/// page's board, as it will be turned and flipped, and stamp it with a left
+/// click until `stop_stamp`, Escape or choosing a tool.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn start_stamp(name: &str) -> Result<(), JsValue> {
+    app::running()?.start_stamp(name)
+}
+
+/// Stop stamping patterns on the page's board with a left click.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn stop_stamp() -> Result<(), JsValue> {
+    app::running()?.stop_stamp();
+    Ok(())
+}
+
+/// Choose what dragging on the page's board does: `paint`, `select`,
+/// `line`, `rect`, `filledRect` or `ellipse`.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_tool(name: &str) -> Result<(), JsValue> {
+    app::running()?.set_tool(name)
+}
+
+/// Draw the page's board at `ratio` canvas pixels per CSS pixel instead
+/// of the display's `devicePixelRatio`, or at the display's again for a
+/// ratio that isn't positive.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_pixel_ratio_override(ratio: f64) -> Result<(), JsValue> {
+    app::running()?.set_pixel_ratio_override(ratio);
+    Ok(())
+}
+
+/// Size the cells of the page's board for all of it to fit the canvas's
+/// container, following the container as it resizes, or stop.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_fit_mode(fit: bool) -> Result<(), JsValue> {
+    app::running()?.set_fit_mode(fit)
+}
+
+/// Show what the next generation will do to the page's board: the cells it
+/// brings to life outlined, and those it kills covered over.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_preview_mode(preview: bool) -> Result<(), JsValue> {
+    app::running()?.set_preview_mode(preview);
+    Ok(())
+}
+
+/// Flash the cells each generation brings to life on the page's board,
+/// and let those it kills fade back to dead over `frames` frames, up to
+/// 255. Zero stops flashing.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_change_highlight(frames: u32) -> Result<(), JsValue> {
+    app::running()?.set_change_highlight(frames);
+    Ok(())
+}
+
+/// Leave trails behind the cells of the page's board as they die, fading
+/// by half every `half_life_ticks` generations. Zero turns trails off.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_trail(half_life_ticks: u32) -> Result<(), JsValue> {
+    app::running()?.set_trail(half_life_ticks);
+    Ok(())
+}
+
+/// Hold the page's board off while its tab is hidden, as by default, or
+/// keep it simulating in the background too, for wall-clock simulations.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_pause_when_hidden(pause: bool) -> Result<(), JsValue> {
+    app::running()?.set_pause_when_hidden(pause);
+    Ok(())
+}
+
+/// Show or hide the gridlines on the page's board. They stay hidden while
+/// the cells are under 3 pixels either way.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_grid_visible(visible: bool) -> Result<(), JsValue> {
+    app::running()?.set_grid_visible(visible);
+    Ok(())
+}
+
+/// Choose how big a blob painting on the page's board sets: the cells
+/// across from the middle to the edge, the middle included, from 1 to 10.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_brush_size(size: u32) -> Result<(), JsValue> {
+    app::running()?.set_brush_size(size);
+    Ok(())
+}
+
+/// Choose whether painting on the page's board covers a square or a
+/// circle.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_brush_shape(shape: BrushShape) -> Result<(), JsValue> {
+    app::running()?.set_brush_shape(shape);
+    Ok(())
+}
+
+/// Set how many generations the page advances per displayed frame. Zero
+/// keeps repainting without advancing.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_speed(ticks_per_frame: u32) -> Result<(), JsValue> {
+    app::running()?.set_speed(ticks_per_frame);
+    Ok(())
+}
+
+/// Tick the page's board `gps` generations per second, however often the
+/// display refreshes, drawing once per frame. Zero goes back to ticking
+/// `set_speed` generations every frame, as by default.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_target_gps(gps: f64) -> Result<(), JsValue> {
+    app::running()?.set_target_gps(gps);
+    Ok(())
+}
+
+/// Set the least time between the page's displayed frames, in
+/// milliseconds, e.g. 250 for a leisurely pace.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_frame_interval(ms: f64) -> Result<(), JsValue> {
+    app::running()?.set_frame_interval(ms);
+    Ok(())
 }
@@ -0,0 +1,114 @@
+//! Reader and writer for the Life 1.06 pattern format:
+//! <https://conwaylife.com/wiki/Life_1.06>
+
+use shape::Shape;
+
+const HEADER: &str = "#Life 1.06";
+
+/// Parse a Life 1.06 pattern.
+///
+/// Each line after the header is an `x y` coordinate pair. The pattern is
+/// translated so that its leftmost and topmost cells land in column and row
+/// zero, which takes care of negative coordinates. Duplicate pairs are
+/// merged.
+pub fn parse(text: &str) -> Result<Shape, String> {
+    let mut lines = text.lines().map(|line| line.trim()).filter(|line| !line.is_empty());
+    match lines.next() {
+        Some(HEADER) => {}
+        _ => return Err(format!("missing '{}' header", HEADER)),
+    }
+
+    let mut coords: Vec<(i64, i64)> = vec![];
+    for line in lines {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let x = parts.next().and_then(|x| x.parse::<i32>().ok());
+        let y = parts.next().and_then(|y| y.parse::<i32>().ok());
+        match (x, y, parts.next()) {
+            (Some(x), Some(y), None) => coords.push((y as i64, x as i64)),
+            _ => return Err(format!("invalid Life 1.06 coordinate line '{}'", line)),
+        }
+    }
+    if coords.is_empty() {
+        return Ok(Shape::default());
+    }
+
+    let top = coords.iter().map(|&(row, _)| row).min().unwrap();
+    let left = coords.iter().map(|&(_, col)| col).min().unwrap();
+    let bottom = coords.iter().map(|&(row, _)| row).max().unwrap();
+    let right = coords.iter().map(|&(_, col)| col).max().unwrap();
+    let width = right - left + 1;
+    let height = bottom - top + 1;
+    if width.checked_mul(height).is_none_or(|area| area > u32::MAX as i64) {
+        return Err(format!("pattern of size {}x{} is too large", width, height));
+    }
+
+    let mut cells: Vec<(u32, u32)> = coords
+        .iter()
+        .map(|&(row, col)| ((row - top) as u32, (col - left) as u32))
+        .collect();
+    cells.sort();
+    cells.dedup();
+
+    Ok(Shape {
+        width: width as u32,
+        height: height as u32,
+        cells,
+    })
+}
+
+/// Write a pattern in the Life 1.06 format.
+pub fn write(pattern: &Shape) -> String {
+    let mut out = format!("{}\n", HEADER);
+    for &(row, col) in pattern.cells.iter() {
+        out.push_str(&format!("{} {}\n", col, row));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "#Life 1.06
+0 -1
+1 0
+-1 1
+0 1
+1 1
+";
+
+    #[test]
+    fn glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(write(&pattern), "#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n");
+        assert_eq!(parse(&write(&pattern)).unwrap(), pattern);
+        assert_eq!(parse(&write(&Shape::default())).unwrap(), Shape::default());
+    }
+
+    #[test]
+    fn duplicates_are_merged() {
+        let pattern = parse("#Life 1.06\n5 5\n6 5\n5 5\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (2, 1));
+        assert_eq!(pattern.cells, vec![(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("").is_err());
+        assert!(parse("0 0\n").is_err());
+        assert!(parse("#Life 1.06\n0\n").is_err());
+        assert!(parse("#Life 1.06\n0 0 0\n").is_err());
+        assert!(parse("#Life 1.06\na b\n").is_err());
+        assert!(parse("#Life 1.06\n0 0\n100000 100000\n").is_err());
+    }
+}
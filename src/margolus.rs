@@ -0,0 +1,95 @@
+//! Block cellular automata on the Margolus neighborhood: the board is cut
+//! into 2×2 blocks, each replaced as a whole, and the cut shifts by a cell
+//! diagonally every generation so the blocks mix.
+
+use bitset::BitSet;
+
+/// A block as four bits: top left, top right, bottom left, bottom right,
+/// from the lowest bit up.
+pub type Block = u8;
+
+/// What each of the 16 blocks becomes.
+pub type Table = [Block; 16];
+
+/// Critters: a block with exactly two live cells stays as it is, any other
+/// has all its cells flipped, and one that had three live cells is also
+/// turned half way round. Every block comes from exactly one other, so it
+/// can be run backwards.
+pub const CRITTERS: Table = critters();
+
+const fn critters() -> Table {
+    let mut table = [0; 16];
+    let mut block = 0;
+    while block < 16 {
+        table[block] = match (block as u8).count_ones() {
+            2 => block as u8,
+            3 => rotate_half(!(block as u8) & 0xF),
+            _ => !(block as u8) & 0xF,
+        };
+        block += 1;
+    }
+    table
+}
+
+/// The block turned 180 degrees, swapping opposite corners.
+const fn rotate_half(block: Block) -> Block {
+    (block & 1) << 3 | (block & 2) << 1 | (block & 4) >> 1 | (block & 8) >> 3
+}
+
+/// The table that undoes `table`, or `None` if two blocks become the same
+/// one, so it can't be undone.
+pub fn inverse(table: &Table) -> Option<Table> {
+    let mut inverse = [0; 16];
+    let mut seen = 0u16;
+    for (block, &next) in table.iter().enumerate() {
+        seen |= 1 << next;
+        inverse[next as usize] = block as u8;
+    }
+    if seen == 0xFFFF {
+        Some(inverse)
+    } else {
+        None
+    }
+}
+
+/// Apply `table` to every block of a `width` by `height` board, writing the
+/// result to `next`. With `offset` 1 the blocks start a cell down and to
+/// the right, and those on the edges wrap around to the other side. A row
+/// or column left over on a board of odd size belongs to no block and
+/// stays as it is.
+pub fn step(cells: &BitSet, width: u32, height: u32, offset: u32, table: &Table, next: &mut BitSet) {
+    next.clone_from(cells);
+    let index = |row: u32, col: u32| ((row % height) * width + col % width) as usize;
+    for row in (offset..height - height % 2 + offset).step_by(2) {
+        for col in (offset..width - width % 2 + offset).step_by(2) {
+            let corners = [index(row, col), index(row, col + 1), index(row + 1, col), index(row + 1, col + 1)];
+            let block = corners.iter().enumerate().fold(0, |block, (bit, &idx)| block | (cells.get(idx) as u8) << bit);
+            let replaced = table[block as usize];
+            for (bit, &idx) in corners.iter().enumerate() {
+                next.set(idx, replaced & (1 << bit) != 0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn critters_table() {
+        assert_eq!(CRITTERS[0b0000], 0b1111);
+        assert_eq!(CRITTERS[0b0001], 0b1110);
+        assert_eq!(CRITTERS[0b0110], 0b0110);
+        // Three live cells flip to one, which turns to the opposite corner.
+        assert_eq!(CRITTERS[0b0111], 0b0001);
+        assert_eq!(CRITTERS[0b1111], 0b0000);
+        let inverse = inverse(&CRITTERS).unwrap();
+        for block in 0..16 {
+            assert_eq!(inverse[CRITTERS[block] as usize], block as u8);
+        }
+        let mut merging = CRITTERS;
+        merging[0] = merging[1];
+        assert_eq!(super::inverse(&merging), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Which cells around a cell count as its neighbors, and counting them for
+//! a whole board at once.
+
+use wasm_bindgen::prelude::*;
+
+/// The largest neighborhood radius a rule can have.
+pub const MAX_RADIUS: u8 = 10;
+
+/// The shape of a neighborhood.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeighborhoodKind {
+    /// The square around the cell.
+    Moore,
+    /// The diamond of cells within `radius` steps, not counting diagonals.
+    VonNeumann,
+    /// A hexagon on a square grid: the square, less the corners towards
+    /// the top right and bottom left, as if each row were shifted half a
+    /// cell left of the one below.
+    Hexagonal,
+}
+
+/// The cells within `radius` of a cell, in the shape of `kind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Neighborhood {
+    pub radius: u8,
+    pub kind: NeighborhoodKind,
+}
+
+impl NeighborhoodKind {
+    pub const ALL: [NeighborhoodKind; 3] = [NeighborhoodKind::Moore, NeighborhoodKind::VonNeumann, NeighborhoodKind::Hexagonal];
+
+    /// The name used for the neighborhood in the page's picker.
+    pub fn name(self) -> &'static str {
+        match self {
+            NeighborhoodKind::Moore => "moore",
+            NeighborhoodKind::VonNeumann => "von-neumann",
+            NeighborhoodKind::Hexagonal => "hexagonal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<NeighborhoodKind> {
+        NeighborhoodKind::ALL.iter().cloned().find(|kind| kind.name() == name)
+    }
+}
+
+impl Neighborhood {
+    /// The eight cells around a cell.
+    pub fn moore() -> Neighborhood {
+        Neighborhood { radius: 1, kind: NeighborhoodKind::Moore }
+    }
+
+    /// The columns, relative to the cell, of the neighborhood's cells `dr`
+    /// rows above or below it.
+    pub fn span(&self, dr: i32) -> (i32, i32) {
+        let r = self.radius as i32;
+        match self.kind {
+            NeighborhoodKind::Moore => (-r, r),
+            NeighborhoodKind::VonNeumann => (-(r - dr.abs()), r - dr.abs()),
+            NeighborhoodKind::Hexagonal => (i32::max(-r, dr - r), i32::min(r, dr + r)),
+        }
+    }
+
+    /// How many cells there are in the neighborhood, not counting the cell
+    /// in the middle.
+    pub fn size(&self) -> u16 {
+        let r = self.radius as i32;
+        let cells: i32 = (-r..=r).map(|dr| self.span(dr)).map(|(lo, hi)| hi - lo + 1).sum();
+        cells as u16 - 1
+    }
+}
+
+/// For each cell of a `width` by `height` board, how many of its neighbors
+/// are occupied, not counting the cell itself. `occupied` holds a byte per
+/// cell, 1 for occupied. On a torus the neighborhood wraps around the
+/// edges, and otherwise the cells beyond them count as empty.
+///
+/// Each row is summed once into a running total, so a neighborhood row
+/// costs one subtraction however wide it is.
+pub fn counts(width: u32, height: u32, wrap: bool, neighborhood: &Neighborhood, occupied: &[u8]) -> Vec<u16> {
+    let (width, height) = (width as i32, height as i32);
+    let r = neighborhood.radius as i32;
+    let mut counts = vec![0; occupied.len()];
+    if width == 0 || height == 0 {
+        return counts;
+    }
+
+    // Each row padded by `r` columns on both sides, as prefix sums.
+    let padded = width + 2 * r;
+    let mut sums = vec![0u32; (height * (padded + 1)) as usize];
+    for row in 0..height {
+        let base = (row * (padded + 1)) as usize;
+        for i in 0..padded {
+            let col = i - r;
+            let value = if wrap {
+                occupied[(row * width + col.rem_euclid(width)) as usize]
+            } else if col >= 0 && col < width {
+                occupied[(row * width + col) as usize]
+            } else {
+                0
+            };
+            sums[base + i as usize + 1] = sums[base + i as usize] + u32::from(value);
+        }
+    }
+
+    for row in 0..height {
+        for dr in -r..=r {
+            let source = row + dr;
+            let source = if wrap {
+                source.rem_euclid(height)
+            } else if source >= 0 && source < height {
+                source
+            } else {
+                continue;
+            };
+            let base = (source * (padded + 1)) as usize;
+            let (lo, hi) = neighborhood.span(dr);
+            for col in 0..width {
+                let (start, end) = ((col + r + lo) as usize, (col + r + hi + 1) as usize);
+                counts[(row * width + col) as usize] += (sums[base + end] - sums[base + start]) as u16;
+            }
+        }
+        for col in 0..width {
+            let idx = (row * width + col) as usize;
+            counts[idx] -= u16::from(occupied[idx]);
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Count each cell's neighbors one offset at a time.
+    fn naive_counts(width: u32, height: u32, wrap: bool, neighborhood: &Neighborhood, occupied: &[u8]) -> Vec<u16> {
+        let (w, h, r) = (width as i32, height as i32, neighborhood.radius as i32);
+        let mut counts = vec![];
+        for row in 0..h {
+            for col in 0..w {
+                let mut count = 0;
+                for dr in -r..=r {
+                    let (lo, hi) = neighborhood.span(dr);
+                    for dc in lo..=hi {
+                        let (nr, nc) = (row + dr, col + dc);
+                        if (dr, dc) == (0, 0) || (!wrap && (nr < 0 || nr >= h || nc < 0 || nc >= w)) {
+                            continue;
+                        }
+                        count += u16::from(occupied[(nr.rem_euclid(h) * w + nc.rem_euclid(w)) as usize]);
+                    }
+                }
+                counts.push(count);
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(Neighborhood::moore().size(), 8);
+        assert_eq!(Neighborhood { radius: 5, kind: NeighborhoodKind::Moore }.size(), 120);
+        assert_eq!(Neighborhood { radius: 1, kind: NeighborhoodKind::VonNeumann }.size(), 4);
+        assert_eq!(Neighborhood { radius: 2, kind: NeighborhoodKind::VonNeumann }.size(), 12);
+        assert_eq!(Neighborhood { radius: 1, kind: NeighborhoodKind::Hexagonal }.size(), 6);
+        assert_eq!(Neighborhood { radius: 2, kind: NeighborhoodKind::Hexagonal }.size(), 18);
+    }
+
+    #[test]
+    fn matches_naive_counts() {
+        let (width, height) = (13, 9);
+        let occupied: Vec<u8> = (0..width * height).map(|i| (i * 7 % 5 < 2) as u8).collect();
+        for &kind in NeighborhoodKind::ALL.iter() {
+            for radius in 1..=6 {
+                let neighborhood = Neighborhood { radius, kind };
+                for &wrap in [true, false].iter() {
+                    assert_eq!(
+                        counts(width, height, wrap, &neighborhood, &occupied),
+                        naive_counts(width, height, wrap, &neighborhood, &occupied),
+                        "{:?} wrap {}", neighborhood, wrap
+                    );
+                }
+            }
+        }
+    }
+}
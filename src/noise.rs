@@ -0,0 +1,65 @@
+//! Random events on top of the rule, for seeing how patterns hold up when
+//! the world isn't perfect.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use rng::Rng;
+
+/// The chance of each kind of random event, per cell per tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Noise {
+    /// That a cell the rule brings to life stays dead.
+    pub birth_failure: f64,
+    /// That a live cell dies, whatever the rule says.
+    pub spontaneous_death: f64,
+    /// That a dead cell comes to life, whatever the rule says.
+    pub spontaneous_birth: f64,
+}
+
+impl Noise {
+    /// Noise with each chance clamped to between 0 and 1, with anything
+    /// that isn't a number taken as 0.
+    pub fn new(birth_failure: f64, spontaneous_death: f64, spontaneous_birth: f64) -> Noise {
+        let clamp = |p: f64| if p > 0.0 { p.min(1.0) } else { 0.0 };
+        Noise {
+            birth_failure: clamp(birth_failure),
+            spontaneous_death: clamp(spontaneous_death),
+            spontaneous_birth: clamp(spontaneous_birth),
+        }
+    }
+
+    /// Whether nothing random can happen.
+    pub fn is_quiet(&self) -> bool {
+        *self == Noise::default()
+    }
+
+    /// Whether a cell that was `was_alive` and that the rule makes `alive`
+    /// ends up alive. Only draws from `rng` for events that can happen.
+    #[inline]
+    pub fn apply(&self, rng: &mut Rng, was_alive: bool, alive: bool) -> bool {
+        let mut happens = |p: f64| p > 0.0 && rng.next_f64() < p;
+        match (was_alive, alive) {
+            (false, true) if happens(self.birth_failure) => false,
+            (_, true) => !happens(self.spontaneous_death),
+            (_, false) => happens(self.spontaneous_birth),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn certain_events() {
+        let mut rng = Rng::new(1);
+        assert!(!Noise::new(1.0, 0.0, 0.0).apply(&mut rng, false, true));
+        assert!(Noise::new(1.0, 0.0, 0.0).apply(&mut rng, true, true));
+        assert!(!Noise::new(0.0, 1.0, 0.0).apply(&mut rng, true, true));
+        assert!(Noise::new(0.0, 0.0, 1.0).apply(&mut rng, true, false));
+        assert_eq!(Noise::new(2.0, -1.0, f64::NAN), Noise::new(1.0, 0.0, 0.0));
+        assert!(Noise::new(0.0, 0.0, 0.0).is_quiet());
+    }
+}
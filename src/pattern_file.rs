@@ -0,0 +1,93 @@
+//! Reading a pattern file in whichever format it is in, such as one dropped
+//! on the page.
+
+use wasm_bindgen::prelude::*;
+
+use life106;
+use plaintext;
+use rle;
+use shape::Shape;
+
+/// The formats a board can be exported in.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Rle,
+    Plaintext,
+    Life106,
+}
+
+impl ExportFormat {
+    /// The usual file extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Rle => "rle",
+            ExportFormat::Plaintext => "cells",
+            ExportFormat::Life106 => "lif",
+        }
+    }
+}
+
+/// The formats a pattern file can be in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Rle,
+    Plaintext,
+    Life106,
+}
+
+/// The format of a file named `name`, by its extension, or failing that by
+/// what it starts with.
+fn detect(name: &str, text: &str) -> Format {
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("rle") => return Format::Rle,
+        Some("cells") => return Format::Plaintext,
+        Some("lif") | Some("life") => return Format::Life106,
+        _ => {}
+    }
+    // RLE comments start with `#` too, but the Life 1.06 header comes first.
+    if text.trim_start().starts_with("#Life 1.06") {
+        return Format::Life106;
+    }
+    let first = text.lines().map(|line| line.trim()).find(|line| !line.is_empty() && !line.starts_with('#'));
+    match first {
+        Some(line) if line.starts_with('x') && line.contains('=') => Format::Rle,
+        _ => Format::Plaintext,
+    }
+}
+
+/// Parse the pattern in a file named `name`.
+pub fn parse(name: &str, text: &str) -> Result<Shape, String> {
+    let result = match detect(name, text) {
+        Format::Rle => rle::parse(text),
+        Format::Plaintext => plaintext::parse(text),
+        Format::Life106 => life106::parse(text),
+    };
+    result.map_err(|err| format!("couldn't read '{}': {}", name, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_the_format() {
+        assert_eq!(detect("glider.RLE", ""), Format::Rle);
+        assert_eq!(detect("glider.cells", "x = 3"), Format::Plaintext);
+        assert_eq!(detect("glider.lif", ""), Format::Life106);
+        assert_eq!(detect("glider", "#N Glider\nx = 3, y = 3\nbo$2bo$3o!"), Format::Rle);
+        assert_eq!(detect("glider.txt", "#Life 1.06\n0 0\n"), Format::Life106);
+        assert_eq!(detect("glider.txt", "!Name: Glider\n.O\n..O\nOOO\n"), Format::Plaintext);
+    }
+
+    #[test]
+    fn parses_each_format() {
+        let glider = rle::parse("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+        assert_eq!(parse("a.rle", "x = 3, y = 3\nbo$2bo$3o!").unwrap(), glider);
+        assert_eq!(parse("a.cells", ".O\n..O\nOOO\n").unwrap(), glider);
+        assert_eq!(parse("a", "#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n").unwrap(), glider);
+        let err = parse("a.rle", "x = 3, y = 3\nbo$2bo$3q!").unwrap_err();
+        assert!(err.starts_with("couldn't read 'a.rle'"), "{}", err);
+    }
+}
@@ -0,0 +1,89 @@
+//! A small library of well-known patterns, embedded as RLE.
+
+use wasm_bindgen::prelude::*;
+
+use rle;
+use shape::Shape;
+
+/// A named pattern that can be stamped with `Universe::insert_pattern`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    Glider,
+    Lwss,
+    Blinker,
+    Pulsar,
+    Pentadecathlon,
+    GosperGliderGun,
+    RPentomino,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 7] = [
+        Pattern::Glider,
+        Pattern::Lwss,
+        Pattern::Blinker,
+        Pattern::Pulsar,
+        Pattern::Pentadecathlon,
+        Pattern::GosperGliderGun,
+        Pattern::RPentomino,
+    ];
+
+    /// The name used for the pattern in the page's pattern picker.
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::Glider => "glider",
+            Pattern::Lwss => "lwss",
+            Pattern::Blinker => "blinker",
+            Pattern::Pulsar => "pulsar",
+            Pattern::Pentadecathlon => "pentadecathlon",
+            Pattern::GosperGliderGun => "gosper-glider-gun",
+            Pattern::RPentomino => "r-pentomino",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pattern> {
+        Pattern::ALL.iter().cloned().find(|pattern| pattern.name() == name)
+    }
+
+    pub fn rle(self) -> &'static str {
+        match self {
+            Pattern::Glider => "x = 3, y = 3\nbob$2bo$3o!",
+            Pattern::Lwss => "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!",
+            Pattern::Blinker => "x = 3, y = 1\n3o!",
+            Pattern::Pulsar => "x = 13, y = 13
+2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$o4b
+obo4bo$o4bobo4bo2$2b3o3b3o!",
+            Pattern::Pentadecathlon => "x = 10, y = 3\n2bo4bo$2ob4ob2o$2bo4bo!",
+            Pattern::GosperGliderGun => "x = 36, y = 9
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!",
+            Pattern::RPentomino => "x = 3, y = 3\nb2o$2o$bo!",
+        }
+    }
+
+    pub(crate) fn shape(self) -> Shape {
+        rle::parse(self.rle()).expect("library patterns are valid RLE")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_counts() {
+        let counts = [5, 9, 3, 48, 12, 36, 5];
+        for (pattern, &count) in Pattern::ALL.iter().zip(counts.iter()) {
+            assert_eq!(pattern.shape().cells.len(), count, "{:?}", pattern);
+        }
+    }
+
+    #[test]
+    fn names() {
+        for &pattern in Pattern::ALL.iter() {
+            assert_eq!(Pattern::from_name(pattern.name()), Some(pattern));
+        }
+        assert_eq!(Pattern::from_name("spaceship"), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Reader and writer for the plaintext `.cells` pattern format:
+//! <https://conwaylife.com/wiki/Plaintext>
+
+use shape::Shape;
+
+/// Parse a plaintext pattern.
+///
+/// `!` lines are comments. Blank lines before and after the pattern are
+/// ignored, short lines are padded with dead cells, and whitespace within a
+/// line is skipped.
+pub fn parse(text: &str) -> Result<Shape, String> {
+    let rows: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter(|&(_, line)| !line.starts_with('!'))
+        .collect();
+    let first = rows.iter().position(|&(_, line)| !line.trim().is_empty());
+    let last = rows.iter().rposition(|&(_, line)| !line.trim().is_empty());
+    let rows = match (first, last) {
+        (Some(first), Some(last)) => &rows[first..=last],
+        _ => return Ok(Shape::default()),
+    };
+
+    let mut pattern = Shape {
+        width: 0,
+        height: rows.len() as u32,
+        cells: vec![],
+    };
+    for (row, &(line_no, line)) in rows.iter().enumerate() {
+        let mut col = 0;
+        for (char_no, ch) in line.chars().enumerate() {
+            match ch {
+                '.' => col += 1,
+                'O' => {
+                    pattern.cells.push((row as u32, col));
+                    col += 1;
+                }
+                c if c.is_whitespace() => {}
+                c => return Err(format!(
+                    "unexpected character '{}' at line {}, column {}; expected '.' or 'O'",
+                    c, line_no + 1, char_no + 1
+                )),
+            }
+        }
+        pattern.width = u32::max(pattern.width, col);
+    }
+
+    Ok(pattern)
+}
+
+/// Write a pattern in the plaintext format, one line per row.
+pub fn write(pattern: &Shape) -> String {
+    let mut rows = vec![vec!['.'; pattern.width as usize]; pattern.height as usize];
+    for &(row, col) in pattern.cells.iter() {
+        rows[row as usize][col as usize] = 'O';
+    }
+
+    let mut out = String::new();
+    for row in rows {
+        out.extend(row);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "!Name: Glider
+!
+.O
+..O
+OOO
+";
+
+    const BLINKER: &str = "!Name: Blinker
+!
+OOO
+";
+
+    #[test]
+    fn glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(write(&pattern), ".O.\n..O\nOOO\n");
+    }
+
+    #[test]
+    fn blinker() {
+        let pattern = parse(BLINKER).unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 1));
+        assert_eq!(pattern.cells, vec![(0, 0), (0, 1), (0, 2)]);
+        assert_eq!(parse(&write(&pattern)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn blank_lines() {
+        let pattern = parse("\n\n!comment\nO\n\n.O  \n\n\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (2, 3));
+        assert_eq!(pattern.cells, vec![(0, 0), (2, 1)]);
+        assert_eq!(parse("!only a comment\n\n").unwrap(), Shape::default());
+    }
+
+    #[test]
+    fn rejects_other_characters() {
+        let err = parse("!Name: Bad\n.O\n.*\n").unwrap_err();
+        assert!(err.contains("'*'"));
+        assert!(err.contains("line 3, column 2"));
+    }
+}
@@ -0,0 +1,203 @@
+//! Turning geometric shapes into the cells they cover.
+
+/// The cells on a line from `(r0, c0)` to `(r1, c1)`, both ends included,
+/// using Bresenham's algorithm so consecutive cells always touch.
+pub fn line(r0: i64, c0: i64, r1: i64, c1: i64) -> Vec<(i64, i64)> {
+    let drow = (r1 - r0).abs();
+    let dcol = -(c1 - c0).abs();
+    let step_row = if r0 < r1 { 1 } else { -1 };
+    let step_col = if c0 < c1 { 1 } else { -1 };
+    let mut err = drow + dcol;
+    let (mut row, mut col) = (r0, c0);
+    let mut cells = Vec::with_capacity(i64::max(drow, -dcol) as usize + 1);
+    loop {
+        cells.push((row, col));
+        if row == r1 && col == c1 {
+            return cells;
+        }
+        let err2 = 2 * err;
+        if err2 >= dcol {
+            err += dcol;
+            row += step_row;
+        }
+        if err2 <= drow {
+            err += drow;
+            col += step_col;
+        }
+    }
+}
+
+/// The cells of a rectangle with corners `(r0, c0)` and `(r1, c1)`, in
+/// either order: all of them if `filled`, or else just its edges.
+pub fn rect(r0: i64, c0: i64, r1: i64, c1: i64, filled: bool) -> Vec<(i64, i64)> {
+    let (top, bottom) = (r0.min(r1), r0.max(r1));
+    let (left, right) = (c0.min(c1), c0.max(c1));
+    let mut cells = vec![];
+    for row in top..=bottom {
+        if filled || row == top || row == bottom {
+            cells.extend((left..=right).map(|col| (row, col)));
+        } else {
+            cells.push((row, left));
+            if right != left {
+                cells.push((row, right));
+            }
+        }
+    }
+    cells
+}
+
+/// The cells of an ellipse centered on `(row, col)` reaching `rows` cells
+/// above and below it and `cols` to either side: all of them if `filled`,
+/// or else an outline with no gaps, from the midpoint algorithm. Each cell
+/// is listed once, in order.
+pub fn ellipse(row: i64, col: i64, rows: i64, cols: i64, filled: bool) -> Vec<(i64, i64)> {
+    if rows == 0 {
+        return (col - cols..=col + cols).map(|col| (row, col)).collect();
+    }
+    // The widest column offset on each row offset of a quarter of the
+    // outline, from the center down.
+    let mut widths = vec![0; rows as usize + 1];
+    let mut outline = vec![];
+    let mut plot = |x: i64, y: i64| {
+        widths[y as usize] = widths[y as usize].max(x);
+        outline.push((y, x));
+    };
+    // The midpoint algorithm with every term scaled by 4 to keep it in
+    // integers: steps along x while the curve is shallow, then along y.
+    let (a2, b2) = (cols * cols, rows * rows);
+    let (mut x, mut y) = (0, rows);
+    let (mut dx, mut dy) = (0, 2 * a2 * y);
+    let mut d = 4 * b2 - 4 * a2 * rows + a2;
+    while dx < dy {
+        plot(x, y);
+        x += 1;
+        dx += 2 * b2;
+        if d < 0 {
+            d += 4 * (dx + b2);
+        } else {
+            y -= 1;
+            dy -= 2 * a2;
+            d += 4 * (dx - dy + b2);
+        }
+    }
+    d = b2 * (4 * x * x + 4 * x + 1) + 4 * a2 * (y - 1) * (y - 1) - 4 * a2 * b2;
+    while y >= 0 {
+        plot(x, y);
+        y -= 1;
+        dy -= 2 * a2;
+        if d > 0 {
+            d += 4 * (a2 - dy);
+        } else {
+            x += 1;
+            dx += 2 * b2;
+            d += 4 * (dx - dy + a2);
+        }
+    }
+
+    let mut cells = vec![];
+    if filled {
+        for (y, &width) in widths.iter().enumerate() {
+            for &dy in [-(y as i64), y as i64].iter() {
+                cells.extend((-width..=width).map(|dx| (row + dy, col + dx)));
+            }
+        }
+    } else {
+        for (y, x) in outline {
+            for &(dy, dx) in [(y, x), (y, -x), (-y, x), (-y, -x)].iter() {
+                cells.push((row + dy, col + dx));
+            }
+        }
+    }
+    cells.sort_unstable();
+    cells.dedup();
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_and_diagonal() {
+        assert_eq!(line(2, 2, 2, 2), vec![(2, 2)]);
+        assert_eq!(line(0, 0, 0, 3), vec![(0, 0), (0, 1), (0, 2), (0, 3)]);
+        assert_eq!(line(3, 1, 0, 1), vec![(3, 1), (2, 1), (1, 1), (0, 1)]);
+        assert_eq!(line(0, 3, 3, 0), vec![(0, 3), (1, 2), (2, 1), (3, 0)]);
+    }
+
+    /// Draw cells as text, a line per row from `top` to `bottom`.
+    fn picture(cells: &[(i64, i64)], top: i64, left: i64, bottom: i64, right: i64) -> String {
+        let mut picture = String::new();
+        for row in top..=bottom {
+            for col in left..=right {
+                picture.push(if cells.contains(&(row, col)) { '#' } else { '.' });
+            }
+            picture.push('\n');
+        }
+        picture
+    }
+
+    #[test]
+    fn rects() {
+        assert_eq!(rect(1, 1, 1, 1, false), vec![(1, 1)]);
+        assert_eq!(rect(1, 4, 1, 2, false), vec![(1, 2), (1, 3), (1, 4)]);
+        assert_eq!(rect(3, 0, 0, 0, false), vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        let outline = rect(2, 3, 0, 0, false);
+        assert_eq!(picture(&outline, 0, 0, 2, 3), "####\n#..#\n####\n");
+        assert_eq!(rect(0, 0, 2, 3, true).len(), 12);
+    }
+
+    #[test]
+    fn ellipses() {
+        assert_eq!(ellipse(5, 5, 0, 0, false), vec![(5, 5)]);
+        assert_eq!(ellipse(5, 5, 0, 0, true), vec![(5, 5)]);
+        assert_eq!(ellipse(0, 0, 0, 2, false), vec![(0, -2), (0, -1), (0, 0), (0, 1), (0, 2)]);
+        assert_eq!(ellipse(0, 0, 1, 0, true), vec![(-1, 0), (0, 0), (1, 0)]);
+        assert_eq!(picture(&ellipse(0, 0, 1, 1, false), -1, -1, 1, 1), ".#.\n#.#\n.#.\n");
+        assert_eq!(picture(&ellipse(0, 0, 1, 1, true), -1, -1, 1, 1), ".#.\n###\n.#.\n");
+        assert_eq!(
+            picture(&ellipse(0, 0, 3, 3, false), -3, -3, 3, 3),
+            "..###..\n.#...#.\n#.....#\n#.....#\n#.....#\n.#...#.\n..###..\n"
+        );
+        assert_eq!(
+            picture(&ellipse(0, 0, 2, 5, true), -2, -5, 2, 5),
+            "..#######..\n.#########.\n###########\n.#########.\n..#######..\n"
+        );
+    }
+
+    #[test]
+    fn ellipse_outlines_are_closed() {
+        for &(rows, cols) in [(1, 7), (4, 4), (9, 2), (6, 13), (20, 20)].iter() {
+            let outline = ellipse(0, 0, rows, cols, false);
+            let filled = ellipse(0, 0, rows, cols, true);
+            for &(row, col) in outline.iter() {
+                // Symmetric both ways, inside the bounding box and the
+                // filled ellipse.
+                assert!(outline.contains(&(-row, col)) && outline.contains(&(row, -col)));
+                assert!(row.abs() <= rows && col.abs() <= cols);
+                assert!(filled.contains(&(row, col)));
+                // Touching two other cells of the outline, at least.
+                let touching = outline.iter().filter(|&&(r, c)| (r, c) != (row, col) && (r - row).abs() <= 1 && (c - col).abs() <= 1).count();
+                assert!(touching >= 2, "{:?} in {}x{}", (row, col), rows, cols);
+            }
+            assert!(outline.contains(&(rows, 0)) && outline.contains(&(0, cols)));
+            // Near the area of the ellipse.
+            let area = std::f64::consts::PI * (rows as f64 + 0.5) * (cols as f64 + 0.5);
+            assert!((filled.len() as f64 - area).abs() < area * 0.2, "{} for {}x{}", filled.len(), rows, cols);
+        }
+    }
+
+    #[test]
+    fn shallow_line_has_no_gaps() {
+        let cells = line(0, 0, 2, 7);
+        assert_eq!(cells.len(), 8);
+        assert_eq!(cells.first(), Some(&(0, 0)));
+        assert_eq!(cells.last(), Some(&(2, 7)));
+        for pair in cells.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert!((a.0 - b.0).abs() <= 1 && b.1 - a.1 == 1, "{:?} to {:?}", a, b);
+        }
+        // Reversing the ends covers the same number of cells.
+        assert_eq!(line(2, 7, 0, 0).len(), 8);
+    }
+}
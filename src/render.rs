@@ -0,0 +1,445 @@
+use wasm_bindgen::prelude::*;
+
+use theme;
+use universe::{Mode, Universe};
+use wireworld;
+use immigration;
+
+/// The cell size of a new universe, in pixels.
+pub const DEFAULT_CELL_SIZE: u32 = 5;
+/// The largest cell size a viewport can zoom in to, in pixels.
+pub const MAX_ZOOM: u32 = 64;
+/// The smallest cell size the grid is drawn at, in pixels. Below it the
+/// gridlines would crowd out the cells.
+pub const MIN_GRID_CELL_SIZE: u32 = 3;
+/// The light theme's colors as RGBA bytes, used when rendering into
+/// `ImageData` with colors that aren't written in hex.
+pub const GRID_RGBA: [u8; 4] = [0xCC, 0xCC, 0xCC, 0xFF];
+pub const DEAD_RGBA: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+pub const ALIVE_RGBA: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+/// The largest cell size, up to `MAX_ZOOM`, at which a `width` by `height`
+/// board and its gridlines fit in `bounds` pixels, or `None` if even
+/// one-pixel cells don't fit.
+#[cfg(any(feature = "web", test))]
+pub fn fit_cell_size(width: u32, height: u32, bounds: (u32, u32)) -> Option<u32> {
+    // Each cell takes its size and a gridline, and there's one more
+    // gridline at the end.
+    let pitch = |space: u32, cells: u32| space.saturating_sub(1) / cells.max(1);
+    match pitch(bounds.0, width).min(pitch(bounds.1, height)) {
+        0 | 1 => None,
+        pitch => Some((pitch - 1).min(MAX_ZOOM)),
+    }
+}
+
+/// Whether to draw the gridlines around cells `size` pixels square: when
+/// they're shown and the cells are big enough. Hidden gridlines take the
+/// dead cell color, and the cells stay where they were.
+pub fn shows_grid(universe: &Universe, size: u32) -> bool {
+    universe.grid_visible() && size >= MIN_GRID_CELL_SIZE
+}
+
+fn check_buffer_len(buffer: &[u8], width: u32, height: u32) -> Result<(), String> {
+    let expected = width as usize * height as usize * 4;
+    if buffer.len() != expected {
+        return Err(format!(
+            "buffer of {} bytes does not fit a {}x{} RGBA image, which needs {}",
+            buffer.len(), width, height, expected
+        ));
+    }
+    Ok(())
+}
+
+/// The universe's grid, dead cell and live cell colors as RGBA bytes.
+fn rgba_colors(universe: &Universe) -> ([u8; 4], [u8; 4], [u8; 4]) {
+    (
+        theme::parse_hex_color(&universe.grid_color()).unwrap_or(GRID_RGBA),
+        theme::parse_hex_color(&universe.dead_color()).unwrap_or(DEAD_RGBA),
+        theme::parse_hex_color(&universe.alive_color()).unwrap_or(ALIVE_RGBA),
+    )
+}
+
+/// The color of each cell state as RGBA bytes, indexed by state: dead,
+/// alive, then any decaying states fading from the live cell color towards
+/// the dead one. Wireworld has its own colors after the dead one, and
+/// Immigration's second color follows the live one.
+pub fn state_colors(universe: &Universe) -> Vec<[u8; 4]> {
+    let (_, dead, alive) = rgba_colors(universe);
+    let mut colors = vec![dead];
+    match universe.mode() {
+        Mode::Life | Mode::Critters => {
+            let count = universe.state_count();
+            colors.push(alive);
+            colors.extend((2..count).map(|state| theme::mix(alive, dead, (state - 1) as f64 / (count - 1) as f64)));
+        }
+        Mode::Wireworld => colors.extend_from_slice(&wireworld::COLORS),
+        Mode::Immigration => colors.extend_from_slice(&[alive, immigration::SECOND_COLOR]),
+    }
+    colors
+}
+
+/// Write one RGBA pixel per cell into `buffer`, in row-major order.
+pub fn write_pixels(universe: &Universe, buffer: &mut [u8]) -> Result<(), String> {
+    check_buffer_len(buffer, universe.width(), universe.height())?;
+    let colors = state_colors(universe);
+    for (idx, pixel) in buffer.chunks_mut(4).enumerate() {
+        pixel.copy_from_slice(&colors[universe.state(idx) as usize]);
+    }
+    Ok(())
+}
+
+/// Write RGBA pixels matching the layout of `draw_grid` and `draw_cells`:
+/// square cells of the universe's cell size with one-pixel gridlines
+/// between and around them, the same size as the canvas from `init_canvas`.
+pub fn write_scaled_pixels(universe: &Universe, buffer: &mut [u8]) -> Result<(), String> {
+    let size = universe.cell_size();
+    let width = (size + 1) * universe.width() + 1;
+    let height = (size + 1) * universe.height() + 1;
+    check_buffer_len(buffer, width, height)?;
+    let (grid, dead, _) = rgba_colors(universe);
+    let grid = if shows_grid(universe, size) { grid } else { dead };
+    let colors = state_colors(universe);
+    for y in 0..height {
+        for x in 0..width {
+            let color = if x % (size + 1) == 0 || y % (size + 1) == 0 {
+                grid
+            } else {
+                let idx = universe.get_index(y / (size + 1), x / (size + 1));
+                colors[universe.state(idx) as usize]
+            };
+            let offset = (y * width + x) as usize * 4;
+            buffer[offset..offset + 4].copy_from_slice(&color);
+        }
+    }
+    Ok(())
+}
+
+/// The part of a universe shown on a canvas: the cell at the top-left
+/// corner, and how many pixels square each cell is drawn.
+///
+/// The view wraps around the edges of the universe, so panning past one
+/// edge shows the cells from the opposite one.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    origin_row: u32,
+    origin_col: u32,
+    zoom: u32,
+    /// The size of the canvas, in pixels.
+    width: u32,
+    height: u32,
+    /// Whether `follow` keeps the live cells in the middle of the view.
+    follow: bool,
+}
+
+/// The fraction of the way to the live cells' center that `follow` moves
+/// the view each frame, so it glides rather than jumps.
+const FOLLOW_SMOOTHING: f64 = 0.25;
+
+#[wasm_bindgen]
+impl Viewport {
+    /// A view of a `width` by `height` pixel canvas, starting at the
+    /// top-left cell with cells `zoom` pixels square.
+    pub fn new(width: u32, height: u32, zoom: u32) -> Viewport {
+        Viewport {
+            origin_row: 0,
+            origin_col: 0,
+            zoom: zoom.clamp(1, MAX_ZOOM),
+            width,
+            height,
+            follow: false,
+        }
+    }
+
+    pub fn origin_row(&self) -> u32 {
+        self.origin_row
+    }
+
+    pub fn origin_col(&self) -> u32 {
+        self.origin_col
+    }
+
+    pub fn zoom(&self) -> u32 {
+        self.zoom
+    }
+
+    /// The width of the canvas, in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the canvas, in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Move the view `drow` cells down and `dcol` cells right, wrapping
+    /// around the universe.
+    pub fn pan(&mut self, universe: &Universe, drow: i32, dcol: i32) {
+        self.origin_row = (self.origin_row as i64 + drow as i64).rem_euclid(universe.height() as i64) as u32;
+        self.origin_col = (self.origin_col as i64 + dcol as i64).rem_euclid(universe.width() as i64) as u32;
+    }
+
+    /// Change the zoom, clamped to `[1, MAX_ZOOM]`, keeping the cell under
+    /// the canvas point `x`, `y` in place.
+    pub fn zoom_at(&mut self, universe: &Universe, x: f64, y: f64, zoom: u32) {
+        let (row, col) = self.cell_at(universe, x, y);
+        self.zoom = zoom.clamp(1, MAX_ZOOM);
+        let pitch = (self.zoom + 1) as f64;
+        self.origin_row = row;
+        self.origin_col = col;
+        let drow = f64::floor(f64::max(y, 0.0) / pitch) as i32;
+        let dcol = f64::floor(f64::max(x, 0.0) / pitch) as i32;
+        self.pan(universe, -drow, -dcol);
+    }
+
+    /// Whether the view follows the live cells around.
+    pub fn follows(&self) -> bool {
+        self.follow
+    }
+
+    pub fn set_follow(&mut self, follow: bool) {
+        self.follow = follow;
+    }
+
+    /// With following on, move the view part of the way toward centering
+    /// the universe's center of mass, taking the short way around a torus.
+    /// Call once a frame.
+    pub fn follow(&mut self, universe: &Universe) {
+        let (row, col) = match universe.center_of_mass() {
+            Some(center) if self.follow => center,
+            _ => return,
+        };
+        let (rows, cols) = self.visible_cells();
+        let step = |origin: u32, center: f64, visible: u32, len: u32| {
+            let len = len as f64;
+            let target = center - visible as f64 / 2.0;
+            // The signed distance to the target, wrapped into [-len/2, len/2).
+            let delta = (target - origin as f64 + len / 2.0).rem_euclid(len) - len / 2.0;
+            let step = (delta * FOLLOW_SMOOTHING).round();
+            if step == 0.0 && delta.abs() >= 1.0 { delta.signum() } else { step }
+        };
+        let drow = step(self.origin_row, row, rows, universe.height());
+        let dcol = step(self.origin_col, col, cols, universe.width());
+        self.pan(universe, drow as i32, dcol as i32);
+    }
+
+    /// The row of the cell under the canvas point `x`, `y`.
+    pub fn row_at(&self, universe: &Universe, x: f64, y: f64) -> u32 {
+        self.cell_at(universe, x, y).0
+    }
+
+    /// The column of the cell under the canvas point `x`, `y`.
+    pub fn col_at(&self, universe: &Universe, x: f64, y: f64) -> u32 {
+        self.cell_at(universe, x, y).1
+    }
+}
+
+impl Viewport {
+    /// The row and column of the cell under a point on the canvas.
+    pub fn cell_at(&self, universe: &Universe, x: f64, y: f64) -> (u32, u32) {
+        let pitch = (self.zoom + 1) as f64;
+        let drow = f64::floor(f64::max(y, 0.0) / pitch) as u64;
+        let dcol = f64::floor(f64::max(x, 0.0) / pitch) as u64;
+        (
+            ((self.origin_row as u64 + drow) % universe.height() as u64) as u32,
+            ((self.origin_col as u64 + dcol) % universe.width() as u64) as u32,
+        )
+    }
+
+    /// The canvas position of the top-left pixel of a cell, if it is in
+    /// view.
+    pub fn cell_position(&self, universe: &Universe, row: u32, col: u32) -> Option<(f64, f64)> {
+        let (rows, cols) = self.visible_cells();
+        let drow = (row + universe.height() - self.origin_row) % universe.height();
+        let dcol = (col + universe.width() - self.origin_col) % universe.width();
+        if drow >= rows || dcol >= cols {
+            return None;
+        }
+        Some(((dcol * (self.zoom + 1) + 1) as f64, (drow * (self.zoom + 1) + 1) as f64))
+    }
+
+    /// The parts of a rectangle of cells that are in view, as canvas
+    /// rectangles `(x, y, width, height)` running along the gridlines
+    /// around them. A rectangle that wraps around the edges of the universe
+    /// can be split into as many as four.
+    pub fn region_rects(&self, universe: &Universe, row: u32, col: u32, height: u32, width: u32) -> Vec<(f64, f64, f64, f64)> {
+        let (rows, cols) = self.visible_cells();
+        // The runs of on-screen rows or columns from `start`, `len` long.
+        let runs = |start: u32, len: u32, origin: u32, size: u32, visible: u32| {
+            let first = (start % size + size - origin) % size;
+            let len = len.min(size);
+            let mut runs = vec![(first, len.min(size - first))];
+            if first + len > size {
+                runs.push((0, first + len - size));
+            }
+            runs.into_iter()
+                .filter(move |&(from, _)| from < visible)
+                .map(move |(from, len)| (from, len.min(visible - from)))
+        };
+        let pitch = (self.zoom + 1) as f64;
+        let mut rects = vec![];
+        for (drow, h) in runs(row, height, self.origin_row, universe.height(), rows) {
+            for (dcol, w) in runs(col, width, self.origin_col, universe.width(), cols) {
+                rects.push((dcol as f64 * pitch + 0.5, drow as f64 * pitch + 0.5, w as f64 * pitch, h as f64 * pitch));
+            }
+        }
+        rects
+    }
+
+    /// The number of rows and columns that are at least partly in view.
+    pub(crate) fn visible_cells(&self) -> (u32, u32) {
+        let pitch = self.zoom + 1;
+        (self.height.div_ceil(pitch), self.width.div_ceil(pitch))
+    }
+
+    /// Whether the view is the plain layout of `draw_grid` and
+    /// `draw_cells`, so those can be used instead.
+    pub fn shows_whole(&self, universe: &Universe) -> bool {
+        self.origin_row == 0 && self.origin_col == 0 && self.zoom == universe.cell_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fitting_cell_sizes() {
+        assert_eq!(fit_cell_size(10, 5, (61, 31)), Some(5));
+        assert_eq!(fit_cell_size(10, 5, (60, 31)), Some(4));
+        assert_eq!(fit_cell_size(10, 5, (61, 30)), Some(4));
+        assert_eq!(fit_cell_size(10, 5, (21, 11)), Some(1));
+        assert_eq!(fit_cell_size(10, 5, (20, 100)), None);
+        assert_eq!(fit_cell_size(10, 5, (0, 0)), None);
+        assert_eq!(fit_cell_size(10, 5, (10_000, 10_000)), Some(MAX_ZOOM));
+        for &(width, height) in [(1, 1), (7, 3), (64, 64), (128, 40)].iter() {
+            for bound_width in (0..600).step_by(7) {
+                for bound_height in (0..600).step_by(11) {
+                    let laid_out = |size: u32| ((size + 1) * width + 1, (size + 1) * height + 1);
+                    let fits = |size: u32| {
+                        let (w, h) = laid_out(size);
+                        w <= bound_width && h <= bound_height
+                    };
+                    match fit_cell_size(width, height, (bound_width, bound_height)) {
+                        Some(size) => assert!(fits(size) && (size == MAX_ZOOM || !fits(size + 1))),
+                        None => assert!(!fits(1)),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_hides_at_small_sizes() {
+        let mut universe = Universe::new();
+        assert!(shows_grid(&universe, MIN_GRID_CELL_SIZE));
+        assert!(!shows_grid(&universe, MIN_GRID_CELL_SIZE - 1));
+        universe.set_grid_visible(false);
+        assert!(!shows_grid(&universe, DEFAULT_CELL_SIZE));
+
+        // Small cells keep their gridlines' pixels, in the dead color.
+        universe.set_grid_visible(true);
+        universe.set_width(2);
+        universe.set_height(1);
+        universe.set_cell_size(2);
+        universe.set_cells(&[(0, 1)]).unwrap();
+        let mut buffer = vec![0; 7 * 4 * 4];
+        write_scaled_pixels(&universe, &mut buffer).unwrap();
+        let row: Vec<u8> = buffer.chunks(4).skip(7).take(7).map(|pixel| pixel[0]).collect();
+        assert_eq!(row, [0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn viewport_coordinates() {
+        let mut universe = Universe::new();
+        universe.set_width(20);
+        universe.set_height(10);
+        for &zoom in [1, 5, 12].iter() {
+            let pitch = (zoom + 1) as f64;
+            for &(origin_row, origin_col) in [(0, 0), (3, 7), (9, 19)].iter() {
+                let mut viewport = Viewport::new(200, 100, zoom);
+                viewport.pan(&universe, origin_row, origin_col);
+                assert_eq!(viewport.cell_at(&universe, 0.0, 0.0), (origin_row as u32, origin_col as u32));
+
+                // Two rows down and three columns right of the origin,
+                // wrapping around the universe.
+                let (x, y) = (3.0 * pitch + 1.0, 2.0 * pitch + 1.0);
+                let cell = ((origin_row as u32 + 2) % 10, (origin_col as u32 + 3) % 20);
+                assert_eq!(viewport.cell_at(&universe, x, y), cell);
+                assert_eq!(viewport.cell_at(&universe, x + zoom as f64 - 0.5, y + zoom as f64 - 0.5), cell);
+                assert_eq!(viewport.cell_position(&universe, cell.0, cell.1), Some((x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn viewport_pan_wraps() {
+        let mut universe = Universe::new();
+        universe.set_width(20);
+        universe.set_height(10);
+        let mut viewport = Viewport::new(60, 60, 5);
+        assert!(viewport.shows_whole(&universe));
+        viewport.pan(&universe, -1, -2);
+        assert_eq!((viewport.origin_row(), viewport.origin_col()), (9, 18));
+        assert!(!viewport.shows_whole(&universe));
+        viewport.pan(&universe, 11, 22);
+        assert_eq!((viewport.origin_row(), viewport.origin_col()), (0, 0));
+        // Ten columns of six pixels fit, so the eleventh is out of view.
+        assert_eq!(viewport.cell_position(&universe, 0, 10), None);
+        assert_eq!(viewport.cell_position(&universe, 9, 9), Some((55.0, 55.0)));
+    }
+
+    #[test]
+    fn viewport_region_rects() {
+        let mut universe = Universe::new();
+        universe.set_width(20);
+        universe.set_height(10);
+        // Ten cells of six pixels each way.
+        let mut viewport = Viewport::new(60, 60, 5);
+        assert_eq!(viewport.region_rects(&universe, 1, 2, 3, 4), [(12.5, 6.5, 24.0, 18.0)]);
+        // Off the right of the view, and wrapping around the bottom.
+        assert_eq!(viewport.region_rects(&universe, 8, 8, 4, 4), [(48.5, 48.5, 12.0, 12.0), (48.5, 0.5, 12.0, 12.0)]);
+        // The view wraps too, so only its left edge splits a rectangle.
+        viewport.pan(&universe, 0, 15);
+        assert_eq!(viewport.region_rects(&universe, 0, 18, 1, 4), [(18.5, 0.5, 24.0, 6.0)]);
+        assert_eq!(viewport.region_rects(&universe, 0, 12, 1, 5), [(0.5, 0.5, 12.0, 6.0)]);
+        assert_eq!(viewport.region_rects(&universe, 0, 5, 1, 4), []);
+    }
+
+    #[test]
+    fn viewport_follows_the_center_of_mass() {
+        let mut universe = Universe::new();
+        universe.set_width(40);
+        universe.set_height(40);
+        // A blinker in the middle of row 2, the view ten cells square.
+        universe.set_cells(&[(2, 1), (2, 2), (2, 3)]).unwrap();
+        let mut viewport = Viewport::new(60, 60, 5);
+        viewport.follow(&universe);
+        assert_eq!((viewport.origin_row(), viewport.origin_col()), (0, 0));
+
+        viewport.set_follow(true);
+        viewport.follow(&universe);
+        // A quarter of the way, the short way round to (-3, -3).
+        assert_eq!((viewport.origin_row(), viewport.origin_col()), (39, 39));
+        for _ in 0..20 {
+            viewport.follow(&universe);
+        }
+        assert_eq!((viewport.origin_row(), viewport.origin_col()), (37, 37));
+    }
+
+    #[test]
+    fn viewport_zooms_on_point() {
+        let mut universe = Universe::new();
+        universe.set_width(64);
+        universe.set_height(64);
+        let mut viewport = Viewport::new(300, 300, 5);
+        let (x, y) = (100.0, 50.0);
+        let cell = viewport.cell_at(&universe, x, y);
+        for &zoom in [10, 2, 64, 1].iter() {
+            viewport.zoom_at(&universe, x, y, zoom);
+            assert_eq!(viewport.cell_at(&universe, x, y), cell);
+        }
+        viewport.zoom_at(&universe, x, y, 1000);
+        assert_eq!(viewport.zoom(), MAX_ZOOM);
+    }
+}
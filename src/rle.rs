@@ -0,0 +1,294 @@
+//! Reader and writer for the run-length encoded pattern format used by Golly
+//! and the LifeWiki: <https://conwaylife.com/wiki/Run_Length_Encoded>
+
+use shape::Shape;
+use rule::Rule;
+
+/// Parse an RLE pattern.
+///
+/// `#` lines are comments and are skipped. The `x = .., y = ..` header is
+/// required; its `rule` field is read by `rule_field`. Cells placed outside
+/// the extent declared by the header are an error.
+pub fn parse(rle: &str) -> Result<Shape, String> {
+    let mut lines = lines(rle);
+    let header = lines.next().ok_or_else(|| "missing RLE header".to_string())?;
+    let (width, height) = parse_header(header)?;
+    let mut pattern = Shape {
+        width,
+        height,
+        cells: vec![],
+    };
+
+    let mut row: u32 = 0;
+    let mut col: u32 = 0;
+    let mut count: Option<u32> = None;
+
+    'body: for line in lines {
+        for ch in line.chars() {
+            match ch {
+                '0'..='9' => {
+                    let digit = ch.to_digit(10).unwrap();
+                    let run = count.unwrap_or(0);
+                    count = Some(
+                        run.checked_mul(10)
+                            .and_then(|run| run.checked_add(digit))
+                            .ok_or_else(|| "run count too large".to_string())?
+                    );
+                }
+                'b' | 'o' => {
+                    let run = count.take().unwrap_or(1);
+                    let end = match col.checked_add(run) {
+                        Some(end) if end <= width && row < height => end,
+                        _ => {
+                            return Err(format!(
+                                "pattern exceeds declared size {}x{} at row {}",
+                                width, height, row
+                            ))
+                        }
+                    };
+                    if ch == 'o' {
+                        pattern.cells.extend((col..end).map(|c| (row, c)));
+                    }
+                    col = end;
+                }
+                '$' => {
+                    row = row
+                        .checked_add(count.take().unwrap_or(1))
+                        .ok_or_else(|| format!("pattern exceeds declared size {}x{}", width, height))?;
+                    col = 0;
+                }
+                '!' => break 'body,
+                c if c.is_whitespace() => {}
+                c => return Err(format!("unexpected character '{}' in RLE data", c)),
+            }
+        }
+    }
+
+    Ok(pattern)
+}
+
+/// The rulestring in the `rule` field of an RLE pattern's header, if it
+/// has one.
+pub fn rule_field(rle: &str) -> Option<&str> {
+    let header = lines(rle).next()?;
+    header_fields(header).find(|&(key, _)| key == "rule").and_then(|(_, value)| value)
+}
+
+/// The lines of an RLE pattern that aren't blank or comments.
+fn lines(rle: &str) -> impl Iterator<Item = &str> {
+    rle.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Lines of pattern data are wrapped at this width, as Golly does.
+const LINE_WIDTH: usize = 70;
+
+/// Write a pattern in the RLE format.
+///
+/// Dead cells at the end of a row and blank rows at the end of the pattern
+/// are left implicit.
+pub fn write(pattern: &Shape, rule: &Rule) -> String {
+    let mut cells = pattern.cells.clone();
+    cells.sort();
+    cells.dedup();
+
+    let mut tokens = vec![];
+    let mut last_row = 0;
+    let mut col = 0;
+    let mut i = 0;
+    while i < cells.len() {
+        let (row, start) = cells[i];
+        let mut end = start + 1;
+        i += 1;
+        while i < cells.len() && cells[i] == (row, end) {
+            end += 1;
+            i += 1;
+        }
+
+        if row != last_row {
+            tokens.push(token(row - last_row, '$'));
+            last_row = row;
+            col = 0;
+        }
+        if start > col {
+            tokens.push(token(start - col, 'b'));
+        }
+        tokens.push(token(end - start, 'o'));
+        col = end;
+    }
+    tokens.push("!".to_string());
+
+    let mut out = format!("x = {}, y = {}, rule = {}\n", pattern.width, pattern.height, rule);
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len + token.len() > LINE_WIDTH {
+            out.push('\n');
+            line_len = 0;
+        }
+        line_len += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+    out
+}
+
+fn token(run: u32, tag: char) -> String {
+    if run == 1 {
+        tag.to_string()
+    } else {
+        format!("{}{}", run, tag)
+    }
+}
+
+fn parse_header(header: &str) -> Result<(u32, u32), String> {
+    let mut width = None;
+    let mut height = None;
+
+    for (key, value) in header_fields(header) {
+        let value = value.ok_or_else(|| format!("malformed RLE header field '{}'", key))?;
+        match key {
+            "x" => width = Some(parse_dimension(value)?),
+            "y" => height = Some(parse_dimension(value)?),
+            _ => {}
+        }
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err(format!("RLE header '{}' must declare both x and y", header)),
+    }
+}
+
+/// The `key = value` fields of a header, with `None` for a field that has
+/// no `=`. The rule is always the last field, and keeps any commas of its
+/// own.
+fn header_fields(header: &str) -> impl Iterator<Item = (&str, Option<&str>)> {
+    let (sizes, rule) = match header.find("rule") {
+        Some(start) => (&header[..start], Some(&header[start..])),
+        None => (header, None),
+    };
+    sizes
+        .split(',')
+        .filter(|field| !field.trim().is_empty())
+        .chain(rule)
+        .map(|field| {
+            let mut parts = field.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            (key, parts.next().map(|value| value.trim()))
+        })
+}
+
+fn parse_dimension(value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid RLE dimension '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "#N Glider
+#C The smallest, most common, and first discovered spaceship.
+x = 3, y = 3, rule = B3/S23
+bob$2bo$3o!
+";
+
+    const GOSPER_GUN: &str = "#N Gosper glider gun
+#C This was the first gun discovered.
+x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!
+";
+
+    #[test]
+    fn glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.width, 3);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(pattern.cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn gosper_gun_spans_lines() {
+        let pattern = parse(GOSPER_GUN).unwrap();
+        assert_eq!((pattern.width, pattern.height), (36, 9));
+        assert_eq!(pattern.cells.len(), 36);
+        assert!(pattern.cells.contains(&(0, 24)));
+        assert!(pattern.cells.contains(&(5, 22)));
+        assert!(pattern.cells.contains(&(8, 13)));
+    }
+
+    #[test]
+    fn trailing_blank_rows() {
+        let pattern = parse("x = 2, y = 5\n2o3$!").unwrap();
+        assert_eq!(pattern.height, 5);
+        assert_eq!(pattern.cells, vec![(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn multi_digit_runs() {
+        let pattern = parse("x = 12, y = 1\n10b2o!").unwrap();
+        assert_eq!(pattern.cells, vec![(0, 10), (0, 11)]);
+    }
+
+    #[test]
+    fn round_trip() {
+        for rle in [GLIDER, GOSPER_GUN, "x = 2, y = 5\n2o3$!"].iter() {
+            let pattern = parse(rle).unwrap();
+            assert_eq!(parse(&write(&pattern, &Rule::conway())).unwrap(), pattern);
+        }
+    }
+
+    #[test]
+    fn rule_field() {
+        assert_eq!(super::rule_field(GLIDER), Some("B3/S23"));
+        assert_eq!(super::rule_field("x = 2, y = 5\n2o3$!"), None);
+        assert_eq!(super::rule_field("x = 1, y = 1, rule = 23/36\no!"), Some("23/36"));
+        assert_eq!(super::rule_field("x = 1, y = 1, rule = R2,C0,M1,S2..3,B3..3,NM\no!"), Some("R2,C0,M1,S2..3,B3..3,NM"));
+    }
+
+    #[test]
+    fn write_glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(write(&pattern, &Rule::conway()), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+    }
+
+    #[test]
+    fn write_skips_blank_rows_and_trailing_dead_cells() {
+        let pattern = Shape {
+            width: 5,
+            height: 6,
+            cells: vec![(0, 0), (3, 4)],
+        };
+        assert_eq!(write(&pattern, &Rule::conway()), "x = 5, y = 6, rule = B3/S23\no3$4bo!\n");
+    }
+
+    #[test]
+    fn write_wraps_long_lines() {
+        let pattern = parse(GOSPER_GUN).unwrap();
+        let rle = write(&pattern, &Rule::conway());
+        assert!(rle.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert!(rle.lines().count() > 2);
+    }
+
+    #[test]
+    fn write_empty() {
+        let rle = write(&Shape::default(), &Rule::conway());
+        assert_eq!(rle, "x = 0, y = 0, rule = B3/S23\n!\n");
+        assert_eq!(parse(&rle).unwrap(), Shape::default());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("").is_err());
+        assert!(parse("#C only a comment").is_err());
+        assert!(parse("x = 3\n3o!").is_err());
+        assert!(parse("x = 2, y = 1\n3o!").is_err());
+        assert!(parse("x = 2, y = 1\no$o!").is_err());
+        assert!(parse("x = 3, y = 1\n3z!").is_err());
+        assert!(parse("x = 2, y = 1\nb4294967295b!").is_err());
+        assert!(parse("x = 2, y = 1\n4294967295$4294967295$!").is_err());
+    }
+}
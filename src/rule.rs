@@ -0,0 +1,496 @@
+use std::fmt;
+
+use neighborhood::{Neighborhood, NeighborhoodKind, MAX_RADIUS};
+use universe::Cell;
+
+/// The most states a Generations rule can have.
+pub const MAX_STATES: u16 = 256;
+
+/// An outer-totalistic rule in B/S notation, stored as bitmasks where bit
+/// `n` is set if a cell with `n` live neighbors is born or survives.
+///
+/// A rule with more than two states is a Generations rule, like Brian's
+/// Brain, `B2/S/3`: a live cell that doesn't survive decays through states
+/// 2, 3 and so on before it dies, and only state 1 counts as alive.
+///
+/// A classic rule ending in `V` counts the four von Neumann neighbors, as
+/// in `B2/S013V`, and one ending in `H` the six of a hexagonal grid.
+///
+/// Larger than Life rules, like Bugs, `R5,C0,M1,S34..58,B34..45,NM`, count
+/// the neighbors within a larger radius and give the counts for birth and
+/// survival as ranges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    birth: u16,
+    survival: u16,
+    states: u16,
+    neighborhood: Neighborhood,
+    /// The counts of a Larger than Life rule, used instead of the bitmasks.
+    ranges: Option<Ranges>,
+}
+
+/// Inclusive ranges of neighbor counts for birth and survival.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Ranges {
+    birth: (u16, u16),
+    survival: (u16, u16),
+    /// Whether a live cell counts itself.
+    middle: bool,
+}
+
+impl Rule {
+    /// Conway's Game of Life, B3/S23.
+    pub fn conway() -> Rule {
+        Rule {
+            birth: 1 << 3,
+            survival: 1 << 2 | 1 << 3,
+            states: 2,
+            neighborhood: Neighborhood::moore(),
+            ranges: None,
+        }
+    }
+
+    /// Parse a rulestring in either `B3/S23` or `23/3` notation, optionally
+    /// followed by a number of states, as in `B2/S/3` or `B2/S/C3`, and a
+    /// neighborhood, as in `B3/S23V`, or a Larger than Life rule as in
+    /// `R5,C0,M1,S34..58,B34..45,NM`.
+    pub fn parse(rulestring: &str) -> Result<Rule, String> {
+        let rulestring = rulestring.trim();
+        if rulestring.contains(',') {
+            return parse_larger_than_life(rulestring);
+        }
+        let (counts, kind) = match rulestring.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('V') => (&rulestring[..rulestring.len() - 1], NeighborhoodKind::VonNeumann),
+            Some('H') => (&rulestring[..rulestring.len() - 1], NeighborhoodKind::Hexagonal),
+            _ => (rulestring, NeighborhoodKind::Moore),
+        };
+        let mut parts = counts.split('/');
+        let (first, second, states) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(first), Some(second), states, None) => (first, second, states),
+            _ => return Err(format!("rule '{}' must have the form B3/S23 or 23/3", rulestring)),
+        };
+
+        let (birth, survival) = match (strip_prefix(first, 'b'), strip_prefix(second, 's')) {
+            (Some(birth), Some(survival)) => (birth, survival),
+            (None, None) => (second, first),
+            _ => return Err(format!("rule '{}' must have the form B3/S23 or 23/3", rulestring)),
+        };
+
+        let rule = Rule {
+            birth: parse_counts(birth, rulestring)?,
+            survival: parse_counts(survival, rulestring)?,
+            states: match states {
+                Some(states) => parse_states(states, rulestring)?,
+                None => 2,
+            },
+            neighborhood: Neighborhood { radius: 1, kind },
+            ranges: None,
+        };
+        rule.check_counts()?;
+        Ok(rule)
+    }
+
+    /// The two-state rule on the eight neighbors, or the smaller `kind` of
+    /// neighborhood, given by `lives(alive, live_neighbors)`, which says
+    /// whether a cell is alive in the next generation. It is asked once for
+    /// each combination, and the first error it returns is passed on.
+    #[cfg(any(feature = "web", test))]
+    pub fn from_fn<E, F: FnMut(bool, u16) -> Result<bool, E>>(kind: NeighborhoodKind, mut lives: F) -> Result<Rule, E> {
+        let neighborhood = Neighborhood { radius: 1, kind };
+        let (mut birth, mut survival) = (0, 0);
+        for count in 0..=neighborhood.size() {
+            if lives(false, count)? {
+                birth |= 1 << count;
+            }
+            if lives(true, count)? {
+                survival |= 1 << count;
+            }
+        }
+        Ok(Rule {
+            birth,
+            survival,
+            states: 2,
+            neighborhood,
+            ranges: None,
+        })
+    }
+
+    /// The cells counted as neighbors.
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// The same rule counting a neighborhood of shape `kind` instead, or an
+    /// error if it has more neighbors than `kind` does.
+    pub fn with_neighborhood(&self, kind: NeighborhoodKind) -> Result<Rule, String> {
+        let mut rule = *self;
+        rule.neighborhood.kind = kind;
+        rule.check_counts()?;
+        Ok(rule)
+    }
+
+    /// Fail if the rule asks for more neighbors than its neighborhood has.
+    fn check_counts(&self) -> Result<(), String> {
+        let size = self.neighborhood.size();
+        let most = match self.ranges {
+            Some(ranges) => u16::max(ranges.birth.1, ranges.survival.1.saturating_sub(ranges.middle as u16)),
+            None => 15 - (self.birth | self.survival | 1).leading_zeros() as u16,
+        };
+        if most > size {
+            return Err(format!("rule '{}' counts {} neighbors, but its neighborhood only has {}", self, most, size));
+        }
+        Ok(())
+    }
+
+    /// Whether the rule needs more than the eight neighbors and bitmasks
+    /// of a classic rule, so only the general path of `tick` can run it.
+    pub fn is_extended(&self) -> bool {
+        self.neighborhood != Neighborhood::moore() || self.ranges.is_some()
+    }
+
+    /// Whether a dead cell with `live_neighbors` live neighbors is born.
+    #[inline]
+    pub fn births(&self, live_neighbors: u16) -> bool {
+        match self.ranges {
+            Some(ranges) => in_range(live_neighbors, ranges.birth),
+            None => live_neighbors < 16 && self.birth & (1 << live_neighbors) != 0,
+        }
+    }
+
+    /// Whether a live cell with `live_neighbors` live neighbors survives.
+    #[inline]
+    pub fn survives(&self, live_neighbors: u16) -> bool {
+        match self.ranges {
+            Some(ranges) => in_range(live_neighbors + ranges.middle as u16, ranges.survival),
+            None => live_neighbors < 16 && self.survival & (1 << live_neighbors) != 0,
+        }
+    }
+
+    /// How many states a cell can be in: 2 for dead and alive, or more for
+    /// a Generations rule.
+    pub fn states(&self) -> u16 {
+        self.states
+    }
+
+    /// The state after `state` in a Generations rule, where 0 is dead, 1 is
+    /// alive and higher states are decaying, given the cell's live
+    /// neighbors. With two states this is the same as `next`.
+    #[inline]
+    pub fn next_state(&self, state: u8, live_neighbors: u16) -> u8 {
+        let decayed = |state: u8| if u16::from(state) + 1 < self.states { state + 1 } else { 0 };
+        match state {
+            0 if self.births(live_neighbors) => 1,
+            0 => 0,
+            1 if self.survives(live_neighbors) => 1,
+            state => decayed(state),
+        }
+    }
+
+    /// The state of a cell in the next generation.
+    #[inline]
+    pub fn next(&self, cell: Cell, live_neighbors: u8) -> Cell {
+        let live = match cell {
+            Cell::Alive => self.survives(live_neighbors.into()),
+            Cell::Dead => self.births(live_neighbors.into()),
+        };
+        if live {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    /// The birth and survival bitmasks.
+    pub(crate) fn masks(&self) -> (u16, u16) {
+        (self.birth, self.survival)
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::conway()
+    }
+}
+
+/// Formats the rule in canonical `B3/S23` notation, or Larger than Life's.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ranges) = self.ranges {
+            let states = if self.states > 2 { self.states } else { 0 };
+            let kind = match self.neighborhood.kind {
+                NeighborhoodKind::Moore => 'M',
+                NeighborhoodKind::VonNeumann => 'N',
+                NeighborhoodKind::Hexagonal => 'H',
+            };
+            return write!(
+                f,
+                "R{},C{},M{},S{}..{},B{}..{},N{}",
+                self.neighborhood.radius, states, ranges.middle as u8,
+                ranges.survival.0, ranges.survival.1, ranges.birth.0, ranges.birth.1, kind
+            );
+        }
+        write!(f, "B")?;
+        write_counts(f, self.birth)?;
+        write!(f, "/S")?;
+        write_counts(f, self.survival)?;
+        if self.states > 2 {
+            write!(f, "/{}", self.states)?;
+        }
+        match self.neighborhood.kind {
+            NeighborhoodKind::Moore => Ok(()),
+            NeighborhoodKind::VonNeumann => write!(f, "V"),
+            NeighborhoodKind::Hexagonal => write!(f, "H"),
+        }
+    }
+}
+
+fn strip_prefix(part: &str, prefix: char) -> Option<&str> {
+    let mut chars = part.chars();
+    match chars.next() {
+        Some(c) if c.to_ascii_lowercase() == prefix => Some(chars.as_str()),
+        _ => None,
+    }
+}
+
+fn parse_counts(counts: &str, rulestring: &str) -> Result<u16, String> {
+    let mut mask = 0;
+    for c in counts.chars() {
+        match c.to_digit(10) {
+            Some(n) if n <= 8 => mask |= 1 << n,
+            Some(n) => return Err(format!("neighbor count {} in rule '{}' is greater than 8", n, rulestring)),
+            None => return Err(format!("unexpected character '{}' in rule '{}'", c, rulestring)),
+        }
+    }
+    Ok(mask)
+}
+
+fn in_range(count: u16, (min, max): (u16, u16)) -> bool {
+    min <= count && count <= max
+}
+
+/// Parse Larger than Life notation, `R5,C0,M1,S34..58,B34..45,NM`: the
+/// radius, the number of states with 0 meaning 2, whether a live cell
+/// counts itself, the survival and birth ranges, and the neighborhood,
+/// `NM` for Moore, `NN` for von Neumann or `NH` for hexagonal. All but `R`,
+/// `S` and `B` are optional.
+fn parse_larger_than_life(rulestring: &str) -> Result<Rule, String> {
+    let err = |what: &str| format!("{} in Larger than Life rule '{}'", what, rulestring);
+    let (mut radius, mut states, mut middle, mut kind) = (None, 2, false, NeighborhoodKind::Moore);
+    let (mut survival, mut birth) = (None, None);
+    for part in rulestring.split(',') {
+        let part = part.trim();
+        let mut chars = part.chars();
+        let key = chars.next().map(|c| c.to_ascii_uppercase());
+        let value = chars.as_str();
+        let number = || value.parse::<u16>().map_err(|_| err(&format!("'{}' is not a number", value)));
+        let range = || match value.split_once("..") {
+            Some((min, max)) => match (min.parse::<u16>(), max.parse::<u16>()) {
+                (Ok(min), Ok(max)) if min <= max => Ok((min, max)),
+                _ => Err(err(&format!("'{}' is not a range like 34..58", value))),
+            },
+            None => Err(err(&format!("'{}' is not a range like 34..58", value))),
+        };
+        match key {
+            Some('R') => match number()? {
+                r @ 1..=10 => radius = Some(r as u8),
+                _ => return Err(err(&format!("the radius must be between 1 and {}", MAX_RADIUS))),
+            },
+            Some('C') => {
+                states = match value {
+                    "0" | "2" => 2,
+                    _ => parse_states(value, rulestring)?,
+                }
+            }
+            Some('M') => {
+                middle = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(err("M must be 0 or 1")),
+                }
+            }
+            Some('S') => survival = Some(range()?),
+            Some('B') => birth = Some(range()?),
+            Some('N') => {
+                kind = match value.to_ascii_uppercase().as_str() {
+                    "M" => NeighborhoodKind::Moore,
+                    "N" => NeighborhoodKind::VonNeumann,
+                    "H" => NeighborhoodKind::Hexagonal,
+                    _ => return Err(err(&format!("unknown neighborhood '{}'", value))),
+                }
+            }
+            _ => return Err(err(&format!("unexpected '{}'", part))),
+        }
+    }
+    let (radius, survival, birth) = match (radius, survival, birth) {
+        (Some(radius), Some(survival), Some(birth)) => (radius, survival, birth),
+        _ => return Err(err("missing R, S or B")),
+    };
+    let rule = Rule {
+        birth: 0,
+        survival: 0,
+        states,
+        neighborhood: Neighborhood { radius, kind },
+        ranges: Some(Ranges { birth, survival, middle }),
+    };
+    rule.check_counts()?;
+    Ok(rule)
+}
+
+fn parse_states(states: &str, rulestring: &str) -> Result<u16, String> {
+    let digits = strip_prefix(states, 'c').unwrap_or(states);
+    match digits.parse::<u16>() {
+        Ok(n) if (2..=MAX_STATES).contains(&n) => Ok(n),
+        Ok(_) => Err(format!("rule '{}' must have between 2 and {} states", rulestring, MAX_STATES)),
+        Err(_) => Err(format!("state count '{}' in rule '{}' is not a number", states, rulestring)),
+    }
+}
+
+fn write_counts(f: &mut fmt::Formatter, mask: u16) -> fmt::Result {
+    for n in 0..=8 {
+        if mask & (1 << n) != 0 {
+            write!(f, "{}", n)?;
+        }
+    }
+    Ok(())
+}
+
+/// Written as its rulestring, like `B3/S23`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Rule {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Rule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Rule, D::Error> {
+        let rulestring = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Rule::parse(&rulestring).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        assert_eq!(Rule::parse("B3/S23").unwrap(), Rule::conway());
+        assert_eq!(Rule::parse("b3/s32").unwrap(), Rule::conway());
+        assert_eq!(Rule::parse("23/3").unwrap(), Rule::conway());
+        assert_eq!(Rule::parse("B36/S23").unwrap().to_string(), "B36/S23");
+        assert_eq!(Rule::parse("34678/3678").unwrap().to_string(), "B3678/S34678");
+        assert_eq!(Rule::parse("B2/S").unwrap().to_string(), "B2/S");
+        assert_eq!(Rule::parse("/2").unwrap().to_string(), "B2/S");
+        assert_eq!(Rule::parse("B2/S/3").unwrap().to_string(), "B2/S/3");
+        assert_eq!(Rule::parse("/2/3").unwrap().to_string(), "B2/S/3");
+        assert_eq!(Rule::parse("B2/S/C3").unwrap().states(), 3);
+        assert_eq!(Rule::parse("B3/S23/2").unwrap(), Rule::conway());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Rule::parse("").is_err());
+        assert!(Rule::parse("B3").is_err());
+        assert!(Rule::parse("B3/S23/1").is_err());
+        assert!(Rule::parse("B39/S23").is_err());
+        assert!(Rule::parse("B3/23").is_err());
+        assert!(Rule::parse("S23/B3").is_err());
+        assert!(Rule::parse("B3x/S23").is_err());
+        assert!(Rule::parse("B2/S/1").is_err());
+        assert!(Rule::parse("B2/S/0").is_err());
+        assert!(Rule::parse("B2/S/257").is_err());
+        assert!(Rule::parse("B2/S/x").is_err());
+        assert!(Rule::parse("B2/S/3/4").is_err());
+        assert_eq!(Rule::parse("B2/S/256").unwrap().states(), 256);
+    }
+
+    #[test]
+    fn next() {
+        let rule = Rule::conway();
+        assert_eq!(rule.next(Cell::Alive, 1), Cell::Dead);
+        assert_eq!(rule.next(Cell::Alive, 2), Cell::Alive);
+        assert_eq!(rule.next(Cell::Alive, 3), Cell::Alive);
+        assert_eq!(rule.next(Cell::Alive, 4), Cell::Dead);
+        assert_eq!(rule.next(Cell::Dead, 2), Cell::Dead);
+        assert_eq!(rule.next(Cell::Dead, 3), Cell::Alive);
+    }
+
+    #[test]
+    fn next_state() {
+        let brain = Rule::parse("B2/S/3").unwrap();
+        assert_eq!(brain.next_state(0, 2), 1);
+        assert_eq!(brain.next_state(0, 3), 0);
+        assert_eq!(brain.next_state(1, 2), 2);
+        assert_eq!(brain.next_state(2, 2), 0);
+        let conway = Rule::conway();
+        assert_eq!(conway.next_state(1, 2), 1);
+        assert_eq!(conway.next_state(1, 4), 0);
+        assert_eq!(conway.next_state(0, 3), 1);
+    }
+
+    #[test]
+    fn neighborhoods() {
+        let rule = Rule::parse("B2/S013V").unwrap();
+        assert_eq!(rule.neighborhood().kind, NeighborhoodKind::VonNeumann);
+        assert_eq!(rule.to_string(), "B2/S013V");
+        assert!(rule.is_extended());
+        assert_eq!(Rule::parse("b2/s34h").unwrap().to_string(), "B2/S34H");
+        assert_eq!(Rule::parse("B2/S/3V").unwrap().to_string(), "B2/S/3V");
+        assert!(Rule::parse("B5/S23V").is_err());
+        assert!(Rule::parse("B3/S7H").is_err());
+        assert!(Rule::parse("V").is_err());
+
+        let hex = Rule::conway().with_neighborhood(NeighborhoodKind::Hexagonal).unwrap();
+        assert_eq!(hex.to_string(), "B3/S23H");
+        assert!(Rule::parse("B36/S23").unwrap().with_neighborhood(NeighborhoodKind::VonNeumann).is_err());
+        let ltl = Rule::parse("R2,S2..3,B3..12").unwrap();
+        assert!(ltl.with_neighborhood(NeighborhoodKind::VonNeumann).is_ok());
+        assert!(ltl.with_neighborhood(NeighborhoodKind::Hexagonal).is_ok());
+        assert_eq!(ltl.with_neighborhood(NeighborhoodKind::Hexagonal).unwrap().to_string(), "R2,C0,M0,S2..3,B3..12,NH");
+    }
+
+    #[test]
+    fn from_fn() {
+        let conway = Rule::from_fn(NeighborhoodKind::Moore, |alive, count| Ok::<_, ()>(count == 3 || alive && count == 2));
+        assert_eq!(conway, Ok(Rule::conway()));
+        let mut asked = vec![];
+        let rule = Rule::from_fn(NeighborhoodKind::VonNeumann, |alive, count| {
+            asked.push((alive, count));
+            Ok::<_, ()>(count % 2 == 1)
+        });
+        assert_eq!(rule.unwrap().to_string(), "B13/S13V");
+        assert_eq!(asked.len(), 10);
+        assert_eq!(Rule::from_fn(NeighborhoodKind::Moore, |_, count| if count < 4 { Ok(false) } else { Err(count) }), Err(4));
+    }
+
+    #[test]
+    fn larger_than_life() {
+        let bugs = Rule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!(bugs.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(bugs.neighborhood(), Neighborhood { radius: 5, kind: NeighborhoodKind::Moore });
+        assert!(bugs.is_extended());
+        assert!(!Rule::conway().is_extended());
+        assert!(bugs.births(34) && bugs.births(45) && !bugs.births(46));
+        // The cell itself counts towards survival with M1.
+        assert!(bugs.survives(33) && !bugs.survives(32) && !bugs.survives(58));
+
+        let diamond = Rule::parse("r2, b3..4, s2..3, nn, c4").unwrap();
+        assert_eq!(diamond.to_string(), "R2,C4,M0,S2..3,B3..4,NN");
+        assert_eq!(diamond.neighborhood().size(), 12);
+        assert_eq!(diamond.states(), 4);
+
+        assert!(Rule::parse("R0,S1..2,B1..2").is_err());
+        assert!(Rule::parse("R11,S1..2,B1..2").is_err());
+        assert!(Rule::parse("R1,S1..2").is_err());
+        assert!(Rule::parse("R1,S2..1,B3..3").is_err());
+        assert!(Rule::parse("R1,S1..9,B3..3").is_err());
+        assert!(Rule::parse("R1,M1,S1..9,B3..3").is_ok());
+        assert!(Rule::parse("R1,S1..2,B9..9").is_err());
+        assert!(Rule::parse("R1,C1,S1..2,B3..3").is_err());
+        assert!(Rule::parse("R1,M2,S1..2,B3..3").is_err());
+        assert!(Rule::parse("R1,S1..2,B3..3,NX").is_err());
+        assert!(Rule::parse("R1,S1..2,B3..3,Q1").is_err());
+    }
+}
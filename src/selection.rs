@@ -0,0 +1,323 @@
+//! The selection tool: drag out a rectangle of cells, then copy, cut or
+//! move what's in it, and paste the copy somewhere else.
+
+use std::mem;
+
+use universe::{PasteMode, Universe};
+
+/// A rectangle of cells. It may run off the bottom or right edge of the
+/// board and wrap around to the other side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub row: u32,
+    pub col: u32,
+    pub height: u32,
+    pub width: u32,
+}
+
+impl Region {
+    /// The rectangle with cells `a` and `b` at opposite corners.
+    pub fn spanning(a: (u32, u32), b: (u32, u32)) -> Region {
+        Region {
+            row: a.0.min(b.0),
+            col: a.1.min(b.1),
+            height: a.0.max(b.0) - a.0.min(b.0) + 1,
+            width: a.1.max(b.1) - a.1.min(b.1) + 1,
+        }
+    }
+
+    /// Whether the cell is in the rectangle, on `universe`'s board.
+    pub fn contains(&self, universe: &Universe, row: u32, col: u32) -> bool {
+        let drow = (row + universe.height() - self.row % universe.height()) % universe.height();
+        let dcol = (col + universe.width() - self.col % universe.width()) % universe.width();
+        drow < self.height && dcol < self.width
+    }
+}
+
+/// What the tool is doing.
+pub enum Tool {
+    /// Nothing is selected.
+    Idle,
+    /// The mouse went down on `anchor` and has been dragged to `corner`.
+    Selecting { anchor: (u32, u32), corner: (u32, u32) },
+    /// A rectangle is selected.
+    Selected(Region),
+    /// The selection's contents were grabbed at `grab` and have been
+    /// dragged to `at`. They move when the mouse comes up.
+    Moving { region: Region, contents: Box<Universe>, grab: (u32, u32), at: (u32, u32) },
+    /// The clipboard follows the cursor, with its top-left corner at `at`,
+    /// until a click puts it down.
+    Pasting { at: (u32, u32) },
+}
+
+/// The selection tool's state, and its clipboard.
+pub struct Selection {
+    tool: Tool,
+    /// The cells last copied or cut.
+    clipboard: Option<Universe>,
+}
+
+impl Default for Selection {
+    fn default() -> Selection {
+        Selection::new()
+    }
+}
+
+impl Selection {
+    pub fn new() -> Selection {
+        Selection { tool: Tool::Idle, clipboard: None }
+    }
+
+    /// Whether the clipboard is following the cursor, so clicks go to this
+    /// tool even when another is chosen.
+    pub fn is_pasting(&self) -> bool {
+        matches!(self.tool, Tool::Pasting { .. })
+    }
+
+    /// The rectangle to outline, if any.
+    pub fn region(&self, universe: &Universe) -> Option<Region> {
+        match self.tool {
+            Tool::Idle => None,
+            Tool::Selecting { anchor, corner } => Some(Region::spanning(anchor, corner)),
+            Tool::Selected(region) => Some(region),
+            Tool::Moving { region, grab, at, .. } => Some(moved(universe, region, grab, at)),
+            Tool::Pasting { at } => self.clipboard.as_ref().map(|clipboard| Region {
+                row: at.0,
+                col: at.1,
+                height: clipboard.height(),
+                width: clipboard.width(),
+            }),
+        }
+    }
+
+    /// The cells to draw faintly over the board, and the cell to put
+    /// their top-left corner on: the contents being moved or pasted.
+    pub fn ghost(&self, universe: &Universe) -> Option<(&Universe, (u32, u32))> {
+        match self.tool {
+            Tool::Moving { region, ref contents, grab, at } => {
+                let region = moved(universe, region, grab, at);
+                Some((contents, (region.row, region.col)))
+            }
+            Tool::Pasting { at } => self.clipboard.as_ref().map(|clipboard| (clipboard, at)),
+            _ => None,
+        }
+    }
+
+    /// The mouse went down on a cell. Inside the selection this grabs its
+    /// contents to move them, while pasting it puts the clipboard down, and
+    /// otherwise it starts a new selection.
+    pub fn press(&mut self, universe: &mut Universe, cell: (u32, u32)) {
+        self.tool = match mem::replace(&mut self.tool, Tool::Idle) {
+            Tool::Pasting { .. } => match self.clipboard {
+                Some(ref clipboard) => {
+                    universe.paste(clipboard, cell.0, cell.1, PasteMode::Overwrite);
+                    Tool::Selected(Region { row: cell.0, col: cell.1, height: clipboard.height(), width: clipboard.width() })
+                }
+                None => Tool::Idle,
+            },
+            Tool::Selected(region) if region.contains(universe, cell.0, cell.1) => {
+                let contents = Box::new(universe.copy_region(region.row, region.col, region.height, region.width));
+                Tool::Moving { region, contents, grab: cell, at: cell }
+            }
+            _ => Tool::Selecting { anchor: cell, corner: cell },
+        };
+    }
+
+    /// The mouse moved onto a cell, with or without a button down. Returns
+    /// whether what's drawn for the tool changed.
+    pub fn hover(&mut self, cell: (u32, u32)) -> bool {
+        let last = match self.tool {
+            Tool::Selecting { ref mut corner, .. } => corner,
+            Tool::Moving { ref mut at, .. } => at,
+            Tool::Pasting { ref mut at } => at,
+            _ => return false,
+        };
+        mem::replace(last, cell) != cell
+    }
+
+    /// The mouse came up on a cell, finishing a selection or a move.
+    pub fn release(&mut self, universe: &mut Universe, cell: (u32, u32)) {
+        self.hover(cell);
+        self.tool = match mem::replace(&mut self.tool, Tool::Idle) {
+            Tool::Selecting { anchor, corner } => Tool::Selected(Region::spanning(anchor, corner)),
+            Tool::Moving { region, grab, at, .. } => {
+                let to = moved(universe, region, grab, at);
+                if to != region {
+                    universe.move_region(region.row, region.col, region.height, region.width, to.row, to.col);
+                }
+                Tool::Selected(to)
+            }
+            tool => tool,
+        };
+    }
+
+    /// Copy the selected cells to the clipboard. Returns false if nothing
+    /// is selected.
+    pub fn copy(&mut self, universe: &Universe) -> bool {
+        match self.tool {
+            Tool::Selected(region) => {
+                self.clipboard = Some(universe.copy_region(region.row, region.col, region.height, region.width));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Copy the selected cells to the clipboard and clear them from the
+    /// board. Returns false if nothing is selected.
+    pub fn cut(&mut self, universe: &mut Universe) -> bool {
+        if !self.copy(universe) {
+            return false;
+        }
+        if let Tool::Selected(region) = self.tool {
+            universe.clear_region(region.row, region.col, region.height, region.width);
+        }
+        true
+    }
+
+    /// Have the clipboard follow the cursor, starting from `cell`, until a
+    /// click puts it down. Returns false if the clipboard is empty.
+    pub fn start_paste(&mut self, cell: (u32, u32)) -> bool {
+        if self.clipboard.is_none() {
+            return false;
+        }
+        self.tool = Tool::Pasting { at: cell };
+        true
+    }
+
+    /// Drop the selection, or whatever is being moved or pasted, leaving
+    /// the board as it is. Returns false if there was nothing to drop.
+    pub fn cancel(&mut self) -> bool {
+        !matches!(mem::replace(&mut self.tool, Tool::Idle), Tool::Idle)
+    }
+}
+
+/// Where `region` ends up when dragged from cell `grab` to cell `at`.
+fn moved(universe: &Universe, region: Region, grab: (u32, u32), at: (u32, u32)) -> Region {
+    let shift = |from: u32, grab: u32, at: u32, size: u32| {
+        (i64::from(from) + i64::from(at) - i64::from(grab)).rem_euclid(i64::from(size)) as u32
+    };
+    Region {
+        row: shift(region.row, grab.0, at.0, universe.height()),
+        col: shift(region.col, grab.1, at.1, universe.width()),
+        ..region
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wireworld::WireworldPattern;
+
+    fn universe_with(width: u32, height: u32, cells: &[(u32, u32)]) -> Universe {
+        let mut universe = Universe::new();
+        universe.set_width(width);
+        universe.set_height(height);
+        universe.set_cells(cells).unwrap();
+        universe
+    }
+
+    #[test]
+    fn select_copy_and_paste() {
+        let mut universe = universe_with(8, 8, &[(1, 1), (1, 2), (2, 2)]);
+        let mut selection = Selection::new();
+        assert!(!selection.copy(&universe));
+        assert!(!selection.start_paste((0, 0)));
+
+        // Dragged up and left, from corner to corner.
+        selection.press(&mut universe, (2, 3));
+        assert!(selection.hover((1, 1)));
+        assert!(!selection.hover((1, 1)));
+        selection.release(&mut universe, (1, 1));
+        let region = Region { row: 1, col: 1, height: 2, width: 3 };
+        assert_eq!(selection.region(&universe), Some(region));
+
+        assert!(selection.copy(&universe));
+        assert!(selection.start_paste((5, 5)));
+        assert!(selection.is_pasting());
+        selection.hover((6, 4));
+        assert_eq!(selection.ghost(&universe).map(|(_, at)| at), Some((6, 4)));
+        selection.press(&mut universe, (6, 4));
+        assert_eq!(universe.iter_live().collect::<Vec<_>>(), [(1, 1), (1, 2), (2, 2), (6, 4), (6, 5), (7, 5)]);
+        assert_eq!(selection.region(&universe), Some(Region { row: 6, col: 4, ..region }));
+
+        // Cutting leaves the clipboard for pasting again.
+        assert!(selection.cut(&mut universe));
+        assert_eq!(universe.population(), 3);
+        assert!(selection.start_paste((0, 0)));
+        assert!(selection.cancel());
+        assert!(!selection.cancel());
+        assert_eq!(universe.population(), 3);
+        assert!(selection.clipboard.is_some());
+    }
+
+    #[test]
+    fn dragging_the_selection_moves_it() {
+        let cells = [(1, 1), (1, 2), (2, 2), (5, 5)];
+        let mut universe = universe_with(8, 8, &cells);
+        let mut selection = Selection::new();
+        selection.press(&mut universe, (1, 1));
+        selection.release(&mut universe, (2, 2));
+
+        // Grabbed inside and dropped two down and four right.
+        selection.press(&mut universe, (2, 1));
+        assert!(matches!(selection.tool, Tool::Moving { .. }));
+        selection.hover((4, 5));
+        assert_eq!(universe.population(), 4);
+        selection.release(&mut universe, (4, 5));
+        let region = Region { row: 3, col: 5, height: 2, width: 2 };
+        assert_eq!(selection.region(&universe), Some(region));
+
+        // The same as cutting and pasting at the drop.
+        let mut expected = universe_with(8, 8, &cells);
+        let clipboard = expected.copy_region(1, 1, 2, 2);
+        expected.clear_region(1, 1, 2, 2);
+        expected.paste(&clipboard, 3, 5, PasteMode::Overwrite);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+
+        // A click outside starts over.
+        selection.press(&mut universe, (0, 0));
+        assert!(matches!(selection.tool, Tool::Selecting { .. }));
+        assert!(selection.cancel());
+        assert_eq!(selection.region(&universe), None);
+    }
+
+    #[test]
+    fn wireworld_keeps_its_wires() {
+        let clock = |row: u32, col: u32| {
+            let mut universe = universe_with(16, 12, &[]);
+            universe.insert_wireworld_pattern(WireworldPattern::Clock, row, col);
+            universe
+        };
+        let states = |universe: &Universe| universe.iter_occupied().map(|(row, col)| (row, col, universe.cell_state(row, col))).collect::<Vec<_>>();
+        let mut universe = clock(1, 1);
+        let mut selection = Selection::new();
+        selection.press(&mut universe, (1, 1));
+        selection.release(&mut universe, (3, 9));
+
+        // Dragged five down and three right, wires and all.
+        selection.press(&mut universe, (2, 2));
+        let (ghost, _) = selection.ghost(&universe).unwrap();
+        assert_eq!(ghost.iter_occupied().count(), states(&universe).len());
+        selection.release(&mut universe, (7, 5));
+        assert_eq!(states(&universe), states(&clock(6, 4)));
+
+        // Cut, then pasted back where it was.
+        assert!(selection.cut(&mut universe));
+        assert_eq!(states(&universe), vec![]);
+        assert!(selection.start_paste((1, 1)));
+        selection.press(&mut universe, (1, 1));
+        assert_eq!(states(&universe), states(&clock(1, 1)));
+    }
+
+    #[test]
+    fn regions_wrap() {
+        let universe = universe_with(8, 8, &[]);
+        let region = Region { row: 6, col: 7, height: 3, width: 2 };
+        assert!(region.contains(&universe, 0, 0));
+        assert!(region.contains(&universe, 7, 7));
+        assert!(!region.contains(&universe, 1, 0));
+        assert!(!region.contains(&universe, 6, 1));
+        assert_eq!(Region::spanning((4, 1), (2, 3)), Region { row: 2, col: 1, height: 3, width: 3 });
+    }
+}
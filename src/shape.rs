@@ -0,0 +1,9 @@
+/// A finite arrangement of live cells, as read from or written to one of the
+/// pattern file formats.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Shape {
+    pub width: u32,
+    pub height: u32,
+    /// Row and column of each live cell, relative to the top-left corner.
+    pub cells: Vec<(u32, u32)>,
+}
@@ -0,0 +1,174 @@
+//! Sixteen cells at a time for the interior of `Universe::tick`.
+//!
+//! With the `simd128` target feature, `Lanes` is a wasm `v128` and each
+//! operation is one instruction. Elsewhere it is a plain byte array with the
+//! same behavior, so the chunked tick can be tested natively.
+
+extern crate cfg_if;
+
+use rule::Rule;
+
+/// How many cells one `Lanes` holds.
+pub const LANES: usize = 16;
+
+cfg_if::cfg_if! {
+    if #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))] {
+        use core::arch::wasm32::*;
+
+        /// Sixteen bytes, one per cell.
+        #[derive(Clone, Copy)]
+        pub struct Lanes(v128);
+
+        impl Lanes {
+            /// The sixteen bytes of `bytes` starting at `offset`.
+            #[inline]
+            pub fn load(bytes: &[u8], offset: usize) -> Lanes {
+                let bytes = &bytes[offset..offset + LANES];
+                // Safe because the slice above is exactly sixteen bytes, and
+                // `v128_load` doesn't need them aligned.
+                Lanes(unsafe { v128_load(bytes.as_ptr() as *const v128) })
+            }
+
+            #[inline]
+            pub fn add(self, other: Lanes) -> Lanes {
+                Lanes(u8x16_add(self.0, other.0))
+            }
+
+            /// Look each lane up in `table`, as an index below sixteen.
+            #[inline]
+            pub fn lookup(self, table: Lanes) -> Lanes {
+                Lanes(u8x16_swizzle(table.0, self.0))
+            }
+
+            /// `if_nonzero` where this lane is nonzero, otherwise `if_zero`.
+            #[inline]
+            pub fn select(self, if_nonzero: Lanes, if_zero: Lanes) -> Lanes {
+                let mask = u8x16_ne(self.0, u8x16_splat(0));
+                Lanes(v128_bitselect(if_nonzero.0, if_zero.0, mask))
+            }
+
+            /// Bit `i` is set if lane `i` is nonzero.
+            #[inline]
+            pub fn bitmask(self) -> u16 {
+                u8x16_bitmask(u8x16_ne(self.0, u8x16_splat(0)))
+            }
+        }
+    } else {
+        /// Sixteen bytes, one per cell.
+        #[derive(Clone, Copy)]
+        pub struct Lanes([u8; LANES]);
+
+        impl Lanes {
+            /// The sixteen bytes of `bytes` starting at `offset`.
+            #[inline]
+            pub fn load(bytes: &[u8], offset: usize) -> Lanes {
+                let mut lanes = [0; LANES];
+                lanes.copy_from_slice(&bytes[offset..offset + LANES]);
+                Lanes(lanes)
+            }
+
+            #[inline]
+            pub fn add(self, other: Lanes) -> Lanes {
+                let mut lanes = self.0;
+                for (lane, other) in lanes.iter_mut().zip(other.0.iter()) {
+                    *lane = lane.wrapping_add(*other);
+                }
+                Lanes(lanes)
+            }
+
+            /// Look each lane up in `table`, as an index below sixteen.
+            #[inline]
+            pub fn lookup(self, table: Lanes) -> Lanes {
+                let mut lanes = [0; LANES];
+                for (lane, &index) in lanes.iter_mut().zip(self.0.iter()) {
+                    *lane = table.0.get(index as usize).cloned().unwrap_or(0);
+                }
+                Lanes(lanes)
+            }
+
+            /// `if_nonzero` where this lane is nonzero, otherwise `if_zero`.
+            #[inline]
+            pub fn select(self, if_nonzero: Lanes, if_zero: Lanes) -> Lanes {
+                let mut lanes = if_zero.0;
+                for (i, lane) in lanes.iter_mut().enumerate() {
+                    if self.0[i] != 0 {
+                        *lane = if_nonzero.0[i];
+                    }
+                }
+                Lanes(lanes)
+            }
+
+            /// Bit `i` is set if lane `i` is nonzero.
+            #[inline]
+            pub fn bitmask(self) -> u16 {
+                self.0.iter().enumerate().fold(0, |mask, (i, &lane)| mask | ((lane != 0) as u16) << i)
+            }
+        }
+    }
+}
+
+/// The rule as two lookup tables from a neighbor count to the next state,
+/// one for live cells and one for dead ones.
+pub struct RuleTables {
+    survival: Lanes,
+    birth: Lanes,
+}
+
+impl RuleTables {
+    pub fn new(rule: &Rule) -> RuleTables {
+        let table = |mask: u16| {
+            let mut bytes = [0; LANES];
+            for (count, byte) in bytes.iter_mut().enumerate().take(9) {
+                *byte = (mask >> count & 1) as u8;
+            }
+            Lanes::load(&bytes, 0)
+        };
+        let (birth, survival) = rule.masks();
+        RuleTables {
+            survival: table(survival),
+            birth: table(birth),
+        }
+    }
+}
+
+/// The next states of the sixteen cells of `middle` starting at `col`, as a
+/// bitmask, given the rows above and below it with one byte per cell.
+///
+/// `col` must be at least one and `col + LANES` at most the row's length
+/// less one, so that every neighbor is in the row.
+#[inline]
+pub fn next_states(rules: &RuleTables, above: &[u8], middle: &[u8], below: &[u8], col: usize) -> u16 {
+    let counts = Lanes::load(above, col - 1)
+        .add(Lanes::load(above, col))
+        .add(Lanes::load(above, col + 1))
+        .add(Lanes::load(middle, col - 1))
+        .add(Lanes::load(middle, col + 1))
+        .add(Lanes::load(below, col - 1))
+        .add(Lanes::load(below, col))
+        .add(Lanes::load(below, col + 1));
+    let cells = Lanes::load(middle, col);
+    cells.select(counts.lookup(rules.survival), counts.lookup(rules.birth)).bitmask()
+}
+
+/// The sixteen cells of `bytes` starting at `col` as a bitmask.
+#[inline]
+pub fn pack(bytes: &[u8], col: usize) -> u16 {
+    Lanes::load(bytes, col).bitmask()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_states_of_a_blinker() {
+        let rules = RuleTables::new(&Rule::conway());
+        let mut rows = vec![vec![0u8; LANES + 2]; 3];
+        // A horizontal blinker centered on column 5 of the middle row.
+        rows[1][4..7].copy_from_slice(&[1, 1, 1]);
+        let middle = next_states(&rules, &rows[0], &rows[1], &rows[2], 1);
+        assert_eq!(middle, 1 << 4);
+        let above = next_states(&rules, &[0; LANES + 2], &rows[0], &rows[1], 1);
+        assert_eq!(above, 1 << 4);
+    }
+}
@@ -0,0 +1,310 @@
+//! Soup searches: running many random boards to see how long they last
+//! before they settle into ash.
+
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::*;
+
+use rng::Rng;
+use universe::Universe;
+
+/// The longest period of ash, the still lifes and oscillators a soup ends
+/// in, that counts as settled. Ash that takes longer to repeat, such as
+/// a glider going round a torus, runs until the generation cap.
+pub const MAX_ASH_PERIOD: usize = 30;
+
+/// How many of the longest-lived soups a report keeps.
+pub const TOP_SOUPS: usize = 10;
+
+/// How one soup turned out.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SoupResult {
+    /// The seed to replay the soup with `Universe::new_random`.
+    pub seed: u64,
+    /// Generations until the soup died out or its ash started repeating, or
+    /// the cap if it never did.
+    pub lifespan: u32,
+    pub final_population: u32,
+    /// Whether the soup was still going at the generation cap.
+    pub capped: bool,
+}
+
+/// What a soup search found.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SoupReport {
+    soups: u32,
+    total_lifespan: u64,
+    capped: u32,
+    /// The longest-lived soups, longest first, earliest first on a tie.
+    top: Vec<SoupResult>,
+}
+
+#[wasm_bindgen]
+impl SoupReport {
+    /// How many soups were run.
+    pub fn soups(&self) -> u32 {
+        self.soups
+    }
+
+    /// The mean lifespan over all the soups, or 0 without any.
+    pub fn mean_lifespan(&self) -> f64 {
+        if self.soups == 0 {
+            return 0.0;
+        }
+        self.total_lifespan as f64 / self.soups as f64
+    }
+
+    pub fn max_lifespan(&self) -> u32 {
+        self.top.first().map_or(0, |soup| soup.lifespan)
+    }
+
+    /// The seed of the longest-lived soup, if any were run.
+    pub fn longest_lived_seed(&self) -> Option<u64> {
+        self.top.first().map(|soup| soup.seed)
+    }
+
+    /// How many soups were still going at the generation cap.
+    pub fn capped(&self) -> u32 {
+        self.capped
+    }
+
+    /// The `TOP_SOUPS` longest-lived soups, longest first.
+    pub fn top(&self) -> Vec<SoupResult> {
+        self.top.clone()
+    }
+}
+
+/// A soup search in progress, run a soup at a time so a page can spread it
+/// over several tasks. Every soup reuses the same board.
+pub struct SoupSearch {
+    universe: Universe,
+    density: f64,
+    max_generations: u32,
+    /// Where each soup's seed comes from.
+    seeds: Rng,
+    count: u32,
+    report: SoupReport,
+}
+
+impl SoupSearch {
+    /// A search of `count` soups on a `width`×`height` torus, each cell
+    /// alive with probability `density`, run for up to `max_generations`.
+    /// The soups' seeds all come from `seed`, so the same search always
+    /// finds the same.
+    pub fn new(count: u32, width: u32, height: u32, density: f64, max_generations: u32, seed: u64) -> Result<SoupSearch, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("universe must not be empty, but is {}x{}", width, height));
+        }
+        Ok(SoupSearch {
+            universe: Universe::empty(width, height)?,
+            density,
+            max_generations,
+            seeds: Rng::new(seed),
+            count,
+            report: SoupReport {
+                soups: 0,
+                total_lifespan: 0,
+                capped: 0,
+                top: vec![],
+            },
+        })
+    }
+
+    /// How many soups have been run, out of `count`.
+    pub fn done(&self) -> u32 {
+        self.report.soups
+    }
+
+    #[cfg(feature = "web")]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done() >= self.count
+    }
+
+    /// Run the next soup, unless they have all been run.
+    pub fn run_next(&mut self) -> Option<SoupResult> {
+        if self.is_finished() {
+            return None;
+        }
+        let seed = self.seeds.next_u64();
+        self.universe.fill_random(self.density, seed);
+        let (lifespan, capped) = settle(&mut self.universe, self.max_generations);
+        let soup = SoupResult {
+            seed,
+            lifespan,
+            final_population: self.universe.population(),
+            capped,
+        };
+
+        let report = &mut self.report;
+        report.soups += 1;
+        report.total_lifespan += lifespan as u64;
+        if capped {
+            report.capped += 1;
+        }
+        let rank = report.top.iter().position(|top| top.lifespan < lifespan).unwrap_or(report.top.len());
+        if rank < TOP_SOUPS {
+            report.top.insert(rank, soup);
+            report.top.truncate(TOP_SOUPS);
+        }
+        Some(soup)
+    }
+
+    /// What the search has found so far.
+    pub fn report(&self) -> &SoupReport {
+        &self.report
+    }
+}
+
+/// Tick `universe` until it dies out or comes back to one of its last
+/// `MAX_ASH_PERIOD` states, up to `max_generations` generations. Returns
+/// the generation the end state began, and whether the cap came first.
+///
+/// States are told apart by their hash alone, so a collision could end a
+/// soup early, if very rarely.
+fn settle(universe: &mut Universe, max_generations: u32) -> (u32, bool) {
+    let mut recent = VecDeque::with_capacity(MAX_ASH_PERIOD);
+    recent.push_back(universe.cells_fingerprint());
+    for generation in 0..max_generations {
+        if universe.population() == 0 {
+            return (generation, false);
+        }
+        universe.tick();
+        let hash = universe.cells_fingerprint();
+        if let Some(i) = recent.iter().rposition(|&seen| seen == hash) {
+            let period = (recent.len() - i) as u32;
+            return (generation + 1 - period, false);
+        }
+        if recent.len() == MAX_ASH_PERIOD {
+            recent.pop_front();
+        }
+        recent.push_back(hash);
+    }
+    (max_generations, universe.population() != 0)
+}
+
+/// Run `count` random soups on a `width`×`height` torus, each cell alive
+/// with probability `density`, for up to `max_generations` each, and report
+/// how long they lasted. The soups' seeds come from `seed`, and the
+/// longest-lived can be replayed with `Universe::new_random`.
+///
+/// This runs to the end in one go; on a page, `search_soups` keeps the tab
+/// responsive instead.
+#[wasm_bindgen]
+pub fn run_soups(count: u32, width: u32, height: u32, density: f64, max_generations: u32, seed: u64) -> Result<SoupReport, String> {
+    let mut search = SoupSearch::new(count, width, height, density, max_generations, seed)?;
+    while search.run_next().is_some() {}
+    Ok(search.report().clone())
+}
+
+/// How long a page's soup search runs before letting the page have a turn,
+/// in milliseconds.
+#[cfg(feature = "web")]
+const CHUNK_MS: f64 = 50.0;
+
+/// Run a soup search like `run_soups`, a few soups at a time between the
+/// page's other work so the tab doesn't freeze. After each batch,
+/// `on_progress` is called with how many soups are done and how many there
+/// are in all. Resolves to the `SoupReport`.
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn search_soups(
+    count: u32,
+    width: u32,
+    height: u32,
+    density: f64,
+    max_generations: u32,
+    seed: u64,
+    on_progress: Option<js_sys::Function>,
+) -> Result<js_sys::Promise, JsValue> {
+    let search = SoupSearch::new(count, width, height, density, max_generations, seed)?;
+    let mut search = Some(search);
+    Ok(js_sys::Promise::new(&mut |resolve, reject| {
+        run_chunk(search.take().unwrap(), resolve, reject, on_progress.clone());
+    }))
+}
+
+/// Run soups for up to `CHUNK_MS`, report progress, then carry on in a new
+/// task, or resolve with the report once they're all done.
+#[cfg(feature = "web")]
+fn run_chunk(mut search: SoupSearch, resolve: js_sys::Function, reject: js_sys::Function, on_progress: Option<js_sys::Function>) {
+    use utils::window;
+    use wasm_bindgen::JsCast;
+
+    let performance = window().performance().unwrap();
+    let start = performance.now();
+    while performance.now() - start < CHUNK_MS && search.run_next().is_some() {}
+    if let Some(ref on_progress) = on_progress {
+        if let Err(err) = on_progress.call2(&JsValue::NULL, &search.done().into(), &search.count().into()) {
+            let _ = reject.call1(&JsValue::NULL, &err);
+            return;
+        }
+    }
+    if search.is_finished() {
+        let _ = resolve.call1(&JsValue::NULL, &search.report().clone().into());
+        return;
+    }
+    let failed = reject.clone();
+    let next = Closure::once_into_js(move || run_chunk(search, resolve, reject, on_progress));
+    if let Err(err) = window().set_timeout_with_callback(next.unchecked_ref()) {
+        let _ = failed.call1(&JsValue::NULL, &err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ash_and_extinction_settle() {
+        // A blinker repeats every other generation from the start.
+        let mut blinker = Universe::empty(8, 8).unwrap();
+        blinker.set_cells(&[(3, 2), (3, 3), (3, 4)]).unwrap();
+        assert_eq!(settle(&mut blinker, 100), (0, false));
+
+        // A lone pair dies in a generation.
+        let mut pair = Universe::empty(8, 8).unwrap();
+        pair.set_cells(&[(3, 2), (3, 3)]).unwrap();
+        assert_eq!(settle(&mut pair, 100), (1, false));
+
+        // A glider takes 32 generations to come round an 8×8 torus, longer
+        // than ash may repeat in.
+        let mut glider = Universe::empty(8, 8).unwrap();
+        glider.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]).unwrap();
+        assert_eq!(settle(&mut glider, 100), (100, true));
+    }
+
+    #[test]
+    fn searches_are_reproducible() {
+        let report = run_soups(30, 16, 16, 0.4, 500, 7).unwrap();
+        assert_eq!(report, run_soups(30, 16, 16, 0.4, 500, 7).unwrap());
+        assert_ne!(report, run_soups(30, 16, 16, 0.4, 500, 8).unwrap());
+        assert_eq!(report.soups(), 30);
+        assert_eq!(report.top().len(), TOP_SOUPS);
+        assert!(report.top().windows(2).all(|pair| pair[0].lifespan >= pair[1].lifespan));
+        assert_eq!(report.max_lifespan(), report.top()[0].lifespan);
+        assert!(report.mean_lifespan() <= report.max_lifespan() as f64);
+        assert!(report.capped() <= report.soups());
+
+        // The longest-lived soup plays out the same again from its seed.
+        let best = report.top()[0];
+        assert_eq!(report.longest_lived_seed(), Some(best.seed));
+        let mut replay = Universe::new_random(16, 16, 0.4, best.seed);
+        assert_eq!(settle(&mut replay, 500), (best.lifespan, best.capped));
+        assert_eq!(replay.population(), best.final_population);
+    }
+
+    #[test]
+    fn empty_searches() {
+        let report = run_soups(0, 16, 16, 0.4, 500, 7).unwrap();
+        assert_eq!((report.soups(), report.mean_lifespan(), report.longest_lived_seed()), (0, 0.0, None));
+        let report = run_soups(5, 16, 16, 0.0, 500, 7).unwrap();
+        assert_eq!((report.soups(), report.max_lifespan(), report.capped()), (5, 0, 0));
+        assert!(run_soups(5, 0, 16, 0.4, 500, 7).is_err());
+    }
+}
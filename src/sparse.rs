@@ -0,0 +1,371 @@
+//! A universe that stores only its live cells, for huge boards that are
+//! mostly empty.
+
+use std::collections::{BTreeSet, HashMap};
+
+use wasm_bindgen::prelude::*;
+
+use render::Viewport;
+use rle;
+use rule::Rule;
+use shape::Shape;
+use universe::{Cell, Topology};
+
+/// A universe with the same rules as `Universe`, but which keeps a set of
+/// its live cells instead of a grid. Ticking only visits the live cells and
+/// their neighbors, so a glider on a 4096x4096 board costs the same as on a
+/// small one.
+///
+/// Rules that bring cells to life with no neighbors would fill the whole
+/// board, so they aren't allowed.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SparseUniverse {
+    width: u32,
+    height: u32,
+    /// The live cells as `(row, col)`, so they iterate in row-major order.
+    live: BTreeSet<(u32, u32)>,
+    rule: Rule,
+    topology: Topology,
+    generation: u64,
+    births: u32,
+    deaths: u32,
+}
+
+#[wasm_bindgen]
+impl SparseUniverse {
+    /// An empty universe of the given size.
+    pub fn new(width: u32, height: u32) -> SparseUniverse {
+        SparseUniverse {
+            width,
+            height,
+            live: BTreeSet::new(),
+            rule: Rule::default(),
+            topology: Topology::Torus,
+            generation: 0,
+            births: 0,
+            deaths: 0,
+        }
+    }
+
+    /// Create a universe sized exactly to a pattern in the RLE format,
+    /// running the rule in its header if it names one.
+    pub fn from_rle(rle: &str) -> Result<SparseUniverse, String> {
+        let mut universe = SparseUniverse::from_shape(&rle::parse(rle)?);
+        if let Some(rule) = rle::rule_field(rle) {
+            universe.set_rule(rule)?;
+        }
+        Ok(universe)
+    }
+
+    /// Insert a pattern in the RLE format with its top-left corner at `row`
+    /// and `col`, wrapping around the edges like `Universe::insert_rle`.
+    pub fn insert_rle(&mut self, row: u32, col: u32, rle: &str) -> Result<(), String> {
+        self.insert_shape(row, col, &rle::parse(rle)?)
+    }
+
+    /// Write the live cells in the RLE format, cropped to their bounding box.
+    pub fn to_rle(&self) -> String {
+        rle::write(&self.live_shape(), &self.rule)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> u32 {
+        self.live.len() as u32
+    }
+
+    /// The number of cells born in the last generation.
+    pub fn last_births(&self) -> u32 {
+        self.births
+    }
+
+    /// The number of cells that died in the last generation.
+    pub fn last_deaths(&self) -> u32 {
+        self.deaths
+    }
+
+    /// The rule in canonical `B3/S23` notation.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Set the rule from `B3/S23` or `23/3` notation. Rules with `B0` are
+    /// rejected.
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), String> {
+        self.rule = sparse_rule(rulestring)?;
+        Ok(())
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    pub fn is_alive(&self, row: u32, col: u32) -> bool {
+        self.live.contains(&(row, col))
+    }
+
+    pub fn toggle_cell(&mut self, row: u32, col: u32) -> Result<(), String> {
+        self.check_bounds(row, col)?;
+        if !self.live.remove(&(row, col)) {
+            self.live.insert((row, col));
+        }
+        Ok(())
+    }
+
+    /// Advance one generation.
+    pub fn tick(&mut self) {
+        let mut counts: HashMap<(u32, u32), u8> = HashMap::with_capacity(self.live.len() * 8);
+        for &(row, col) in self.live.iter() {
+            for neighbor in self.neighbors(row, col) {
+                *counts.entry(neighbor).or_insert(0) += 1;
+            }
+        }
+
+        let mut next = BTreeSet::new();
+        self.births = 0;
+        for (&cell, &count) in counts.iter() {
+            let state = if self.live.contains(&cell) { Cell::Alive } else { Cell::Dead };
+            if self.rule.next(state, count) == Cell::Alive {
+                next.insert(cell);
+                self.births += (state == Cell::Dead) as u32;
+            }
+        }
+        // Live cells with no neighbors aren't in `counts`.
+        if self.rule.next(Cell::Alive, 0) == Cell::Alive {
+            next.extend(self.live.iter().filter(|cell| !counts.contains_key(cell)));
+        }
+        self.deaths = self.live.iter().filter(|cell| !next.contains(cell)).count() as u32;
+
+        self.live = next;
+        self.generation += 1;
+    }
+
+    /// Advance `generations` generations and return the population.
+    pub fn tick_many(&mut self, generations: u32) -> u32 {
+        for _ in 0..generations {
+            self.tick();
+        }
+        self.population()
+    }
+
+    /// The live cells as interleaved `row, col` pairs.
+    #[cfg(feature = "web")]
+    pub fn live_cells(&self) -> js_sys::Uint32Array {
+        let coords: Vec<u32> = self.iter_live().flat_map(|(row, col)| vec![row, col]).collect();
+        js_sys::Uint32Array::from(&coords[..])
+    }
+
+    /// The live cells in view as interleaved pairs of rows and columns
+    /// counted from the viewport's origin, ready to be drawn at
+    /// `Viewport::zoom` pixels each.
+    #[cfg(feature = "web")]
+    pub fn live_cells_in(&self, viewport: &Viewport) -> js_sys::Uint32Array {
+        let coords: Vec<u32> = self.visible_live(viewport).flat_map(|(drow, dcol)| vec![drow, dcol]).collect();
+        js_sys::Uint32Array::from(&coords[..])
+    }
+}
+
+impl SparseUniverse {
+    /// Set cells to be alive by their row and column. Nothing is changed if
+    /// any cell is off the board.
+    pub fn set_cells(&mut self, cells: &[(u32, u32)]) -> Result<(), String> {
+        for &(row, col) in cells {
+            self.check_bounds(row, col)?;
+        }
+        self.live.extend(cells.iter().cloned());
+        Ok(())
+    }
+
+    /// The row and column of every live cell, in row-major order.
+    pub fn iter_live(&self) -> impl ExactSizeIterator<Item = (u32, u32)> + '_ {
+        self.live.iter().cloned()
+    }
+
+    /// The live cells within `viewport`, as rows and columns from its
+    /// origin. Only the live cells are visited, not the cells in view.
+    pub fn visible_live<'a>(&'a self, viewport: &Viewport) -> impl Iterator<Item = (u32, u32)> + 'a {
+        let (rows, cols) = viewport.visible_cells();
+        let (origin_row, origin_col) = (viewport.origin_row(), viewport.origin_col());
+        let (width, height) = (self.width as u64, self.height as u64);
+        self.iter_live().filter_map(move |(row, col)| {
+            let drow = (row as u64 + height - origin_row as u64 % height) % height;
+            let dcol = (col as u64 + width - origin_col as u64 % width) % width;
+            if drow < rows as u64 && dcol < cols as u64 {
+                Some((drow as u32, dcol as u32))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub(crate) fn from_shape(shape: &Shape) -> SparseUniverse {
+        let mut universe = SparseUniverse::new(shape.width, shape.height);
+        universe.place_shape(0, 0, shape);
+        universe
+    }
+
+    /// Set the shape's cells alive with its top-left corner at `row` and
+    /// `col`, wrapping around the edges.
+    pub(crate) fn place_shape(&mut self, row: u32, col: u32, shape: &Shape) {
+        for &(r, c) in shape.cells.iter() {
+            self.live.insert(((row % self.height + r) % self.height, (col % self.width + c) % self.width));
+        }
+    }
+
+    fn insert_shape(&mut self, row: u32, col: u32, shape: &Shape) -> Result<(), String> {
+        if shape.width > self.width || shape.height > self.height {
+            return Err(format!(
+                "pattern of size {}x{} does not fit in a {}x{} universe",
+                shape.width, shape.height, self.width, self.height
+            ));
+        }
+        self.place_shape(row, col, shape);
+        Ok(())
+    }
+
+    /// The live cells cropped to their bounding box.
+    fn live_shape(&self) -> Shape {
+        let (mut top, mut left, mut bottom, mut right) = (self.height, self.width, 0, 0);
+        for (row, col) in self.iter_live() {
+            top = u32::min(top, row);
+            left = u32::min(left, col);
+            bottom = u32::max(bottom, row + 1);
+            right = u32::max(right, col + 1);
+        }
+        if top >= bottom {
+            return Shape::default();
+        }
+        Shape {
+            width: right - left,
+            height: bottom - top,
+            cells: self.iter_live().map(|(row, col)| (row - top, col - left)).collect(),
+        }
+    }
+
+    fn check_bounds(&self, row: u32, col: u32) -> Result<(), String> {
+        if row >= self.height {
+            return Err(format!("row {} is outside the universe, which has {} rows", row, self.height));
+        }
+        if col >= self.width {
+            return Err(format!("column {} is outside the universe, which has {} columns", col, self.width));
+        }
+        Ok(())
+    }
+
+    /// The eight neighbors of a cell, wrapping or cut off at the edges like
+    /// `Universe`. On a torus narrower than three cells, a neighbor can
+    /// appear more than once, or be the cell itself.
+    fn neighbors(&self, row: u32, col: u32) -> impl Iterator<Item = (u32, u32)> {
+        let (width, height, wrap) = (self.width, self.height, self.topology == Topology::Torus);
+        let step = move |value: u32, delta: i32, len: u32| match (delta, wrap) {
+            (-1, _) if value > 0 => Some(value - 1),
+            (-1, true) => Some(len - 1),
+            (1, _) if value + 1 < len => Some(value + 1),
+            (1, true) => Some(0),
+            (0, _) => Some(value),
+            _ => None,
+        };
+        const OFFSETS: [(i32, i32); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+        OFFSETS.iter().filter_map(move |&(drow, dcol)| Some((step(row, drow, height)?, step(col, dcol, width)?)))
+    }
+}
+
+/// Parse a rule, rejecting the ones a sparse universe can't run.
+pub(crate) fn sparse_rule(rulestring: &str) -> Result<Rule, String> {
+    let rule = Rule::parse(rulestring)?;
+    if rule.next(Cell::Dead, 0) == Cell::Alive {
+        return Err(format!("rule '{}' brings empty space to life, so it can't run sparse", rule));
+    }
+    if rule.states() > 2 || rule.is_extended() {
+        return Err(format!("rule '{}' isn't a classic two-state rule, so it can't run sparse", rule));
+    }
+    Ok(rule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use builder::UniverseBuilder;
+    use universe::Universe;
+
+    #[test]
+    fn matches_dense_universe() {
+        for &(width, height) in [(1, 1), (2, 5), (20, 20), (33, 17)].iter() {
+            for &topology in [Topology::Torus, Topology::Bounded].iter() {
+                for (seed, rule) in ["B3/S23", "B36/S23", "B2/S", "B3/S012345678"].iter().enumerate() {
+                    let builder = UniverseBuilder::new()
+                        .width(width)
+                        .height(height)
+                        .rule(rule)
+                        .topology(topology)
+                        .density(0.3)
+                        .seed(seed as u64);
+                    let mut dense: Universe = builder.build().unwrap();
+                    let mut sparse = builder.build_sparse().unwrap();
+                    for generation in 0..20 {
+                        let context = format!("{}x{} {:?} {} at {}", width, height, topology, rule, generation);
+                        assert_eq!(sparse.iter_live().collect::<Vec<_>>(), dense.iter_live().collect::<Vec<_>>(), "{}", context);
+                        dense.tick();
+                        sparse.tick();
+                        assert_eq!(
+                            (sparse.last_births(), sparse.last_deaths()),
+                            (dense.last_births(), dense.last_deaths()),
+                            "{}",
+                            context
+                        );
+                    }
+                    assert_eq!(sparse.population(), dense.population());
+                    assert_eq!(sparse.to_rle(), dense.to_rle());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn glider_in_a_huge_space() {
+        let glider = "x = 3, y = 3\nbo$2bo$3o!";
+        let mut universe = SparseUniverse::new(4096, 4096);
+        universe.insert_rle(4094, 4094, glider).unwrap();
+        assert_eq!(universe.tick_many(4 * 4096), 5);
+        // A glider moves one cell diagonally every four generations, so it
+        // is back where it started after crossing the whole torus.
+        assert_eq!(universe.iter_live().collect::<Vec<_>>(), vec![(0, 0), (0, 4094), (0, 4095), (4094, 4095), (4095, 0)]);
+        assert_eq!(universe.generation(), 4 * 4096);
+    }
+
+    #[test]
+    fn rle_and_viewport() {
+        let universe = SparseUniverse::from_rle("x = 3, y = 1\n3o!").unwrap();
+        assert_eq!((universe.width(), universe.height()), (3, 1));
+        assert_eq!(universe.to_rle(), "x = 3, y = 1, rule = B3/S23\n3o!\n");
+        let universe = SparseUniverse::from_rle("x = 3, y = 1, rule = B36/S23\n3o!").unwrap();
+        assert_eq!(universe.rule(), "B36/S23");
+
+        let mut universe = SparseUniverse::new(100, 100);
+        universe.set_cells(&[(0, 0), (50, 50), (99, 99)]).unwrap();
+        assert!(universe.set_cells(&[(100, 0)]).is_err());
+        // Ten by ten cells in view, starting one up and left of the corner.
+        let mut viewport = Viewport::new(60, 60, 5);
+        viewport.pan(&Universe::empty(100, 100).unwrap(), -1, -1);
+        assert_eq!(universe.visible_live(&viewport).collect::<Vec<_>>(), vec![(1, 1), (0, 0)]);
+
+        assert!(universe.set_rule("B0/S8").is_err());
+        assert!(UniverseBuilder::new().rule("B03/S23").build_sparse().is_err());
+    }
+}
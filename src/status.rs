@@ -0,0 +1,148 @@
+//! The one-line status bar: generation, population, rule, board and the
+//! cell under the mouse.
+
+use universe::Universe;
+use utils::*;
+
+/// The least time between updates of the status bar while the board is
+/// running, in milliseconds.
+const RENDER_INTERVAL_MS: f64 = 250.0;
+
+/// `n` with a comma between each group of three digits, as in `1,204`.
+pub fn with_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// The status of `universe` as one line, such as `gen 1,204 · pop 5,312 ·
+/// lifespan 1,103 (settled) · rule B3/S23 · torus 256×256 · (r 12, c 87)`,
+/// with the lifespan while the universe tracks it and ending with the cell
+/// under the mouse when there is one.
+pub fn status_line(universe: &Universe, cursor: Option<(u32, u32)>) -> String {
+    let lifespan = if universe.settle_tracking() {
+        format!(
+            " · lifespan {}{}",
+            with_thousands(universe.lifespan_so_far()),
+            if universe.is_settled() { " (settled)" } else { "" },
+        )
+    } else {
+        String::new()
+    };
+    let mut line = format!(
+        "gen {} · pop {}{} · rule {} · {} {}×{}",
+        with_thousands(universe.generation()),
+        with_thousands(universe.population() as u64),
+        lifespan,
+        universe.rule(),
+        universe.topology().name(),
+        universe.width(),
+        universe.height(),
+    );
+    if let Some((row, col)) = cursor {
+        line.push_str(&format!(" · (r {}, c {})", row, col));
+    }
+    line
+}
+
+/// Keeps the page's status element, if it has one, showing `status_line`
+/// and the latest message, such as why the board paused.
+pub struct StatusBar {
+    element: Option<web_sys::Element>,
+    message: String,
+    last_render: Option<f64>,
+}
+
+impl StatusBar {
+    pub fn new(element: Option<web_sys::Element>) -> StatusBar {
+        StatusBar {
+            element,
+            message: String::new(),
+            last_render: None,
+        }
+    }
+
+    /// Show `message` after the status line from the next render on. An
+    /// empty message clears it.
+    pub fn set_message(&mut self, message: &str) {
+        self.message = message.to_string();
+    }
+
+    /// Update the element now.
+    pub fn render(&mut self, universe: &Universe, cursor: Option<(u32, u32)>) {
+        let now = window().performance().unwrap().now();
+        self.last_render = Some(now);
+        if let Some(ref element) = self.element {
+            let mut line = status_line(universe, cursor);
+            if !self.message.is_empty() {
+                line.push_str(" · ");
+                line.push_str(&self.message);
+            }
+            element.set_text_content(Some(&line));
+        }
+    }
+
+    /// Update the element, unless it was updated less than
+    /// `RENDER_INTERVAL_MS` ago. For calling every frame.
+    pub fn render_throttled(&mut self, universe: &Universe, cursor: Option<(u32, u32)>) {
+        if self.is_due(window().performance().unwrap().now()) {
+            self.render(universe, cursor);
+        }
+    }
+
+    fn is_due(&self, now: f64) -> bool {
+        self.last_render.is_none_or(|last| now - last >= RENDER_INTERVAL_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use universe::Topology;
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(with_thousands(0), "0");
+        assert_eq!(with_thousands(999), "999");
+        assert_eq!(with_thousands(1000), "1,000");
+        assert_eq!(with_thousands(1204), "1,204");
+        assert_eq!(with_thousands(65536), "65,536");
+        assert_eq!(with_thousands(123456789), "123,456,789");
+        assert_eq!(with_thousands(u64::MAX), "18,446,744,073,709,551,615");
+    }
+
+    #[test]
+    fn status_lines() {
+        let mut universe = Universe::new();
+        universe.set_width(256);
+        universe.set_height(256);
+        universe.set_cells(&[(1, 1), (1, 2), (1, 3)]).unwrap();
+        assert_eq!(status_line(&universe, None), "gen 0 · pop 3 · rule B3/S23 · torus 256×256");
+        universe.set_settle_tracking(true);
+        universe.tick_many(150);
+        assert_eq!(status_line(&universe, None), "gen 150 · pop 3 · lifespan 0 · rule B3/S23 · torus 256×256");
+        universe.tick_many(1054);
+        assert_eq!(status_line(&universe, None), "gen 1,204 · pop 3 · lifespan 0 (settled) · rule B3/S23 · torus 256×256");
+        universe.set_topology(Topology::Bounded);
+        universe.set_rule("B36/S23").unwrap();
+        assert_eq!(
+            status_line(&universe, Some((12, 87))),
+            "gen 1,204 · pop 3 · lifespan 0 (settled) · rule B36/S23 · bounded 256×256 · (r 12, c 87)"
+        );
+    }
+
+    #[test]
+    fn renders_a_few_times_a_second() {
+        let mut bar = StatusBar::new(None);
+        assert!(bar.is_due(0.0));
+        bar.last_render = Some(1000.0);
+        assert!(!bar.is_due(1100.0));
+        assert!(bar.is_due(1250.0));
+    }
+}
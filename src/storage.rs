@@ -0,0 +1,68 @@
+//! Saving the board in `localStorage`, so it survives a reload.
+
+use wasm_bindgen::prelude::*;
+
+use base64;
+use universe::Universe;
+use utils::*;
+
+/// The key the page's Save and Load buttons use.
+pub const DEFAULT_KEY: &str = "wasm-game-of-life";
+
+fn local_storage() -> Result<web_sys::Storage, JsValue> {
+    window()
+        .local_storage()?
+        .ok_or_else(|| JsValue::from_str("localStorage is not available"))
+}
+
+/// Save the size, rule, generation and cells under `key`, as JSON with the
+/// cells packed like `Universe::cells_copy` and in base64.
+pub fn save(key: &str, universe: &Universe) -> Result<(), JsValue> {
+    let state = js_sys::Object::new();
+    let set = |name: &str, value: JsValue| js_sys::Reflect::set(&state, &name.into(), &value).map(|_| ());
+    set("width", universe.width().into())?;
+    set("height", universe.height().into())?;
+    set("rule", universe.rule().into())?;
+    set("generation", (universe.generation() as f64).into())?;
+    set("cells", base64::encode(&universe.cells_copy()).into())?;
+    let json = String::from(js_sys::JSON::stringify(&state)?);
+    // Typically a `QuotaExceededError` when the board is too big.
+    local_storage()?.set_item(key, &json).map_err(|err| {
+        let reason = err.dyn_ref::<js_sys::Error>().map_or_else(|| format!("{:?}", err), |err| String::from(err.message()));
+        JsValue::from_str(&format!("couldn't save the board as '{}': {}", key, reason))
+    })
+}
+
+/// Replace the board with the one saved under `key`, as an edit that can
+/// be undone. Nothing changes if there is no such board or it is malformed.
+pub fn load(key: &str, universe: &mut Universe) -> Result<(), JsValue> {
+    let json = local_storage()?
+        .get_item(key)?
+        .ok_or_else(|| JsValue::from_str(&format!("no board is saved as '{}'", key)))?;
+    let state = js_sys::JSON::parse(&json)?;
+    let field = |name: &str| -> Result<JsValue, JsValue> {
+        let value = js_sys::Reflect::get(&state, &name.into())?;
+        if value.is_undefined() {
+            return Err(JsValue::from_str(&format!("the board saved as '{}' has no {}", key, name)));
+        }
+        Ok(value)
+    };
+    let number = |name: &str| -> Result<f64, JsValue> {
+        field(name)?
+            .as_f64()
+            .filter(|n| n.fract() == 0.0 && *n >= 0.0)
+            .ok_or_else(|| JsValue::from_str(&format!("the {} of the board saved as '{}' is not a whole number", name, key)))
+    };
+    let text = |name: &str| -> Result<String, JsValue> {
+        field(name)?
+            .as_string()
+            .ok_or_else(|| JsValue::from_str(&format!("the {} of the board saved as '{}' is not text", name, key)))
+    };
+    let (width, height) = (number("width")?, number("height")?);
+    if width > u32::MAX as f64 || height > u32::MAX as f64 {
+        return Err(JsValue::from_str(&format!("the board saved as '{}' is too big", key)));
+    }
+    let cells = base64::decode(&text("cells")?)?;
+    universe.load_saved(width as u32, height as u32, number("generation")? as u64, &text("rule")?, &cells)?;
+    Ok(())
+}
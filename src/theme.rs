@@ -0,0 +1,127 @@
+//! Color presets for drawing the universe.
+
+use wasm_bindgen::prelude::*;
+
+/// A preset set of grid, dead cell and live cell colors.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    Light,
+    Dark,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Light, Theme::Dark, Theme::HighContrast];
+
+    /// The name used for the theme in the page's theme picker.
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::ALL.iter().cloned().find(|theme| theme.name() == name)
+    }
+
+    /// The grid, dead cell and live cell colors.
+    pub fn colors(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Theme::Light => ("#CCCCCC", "#FFFFFF", "#000000"),
+            Theme::Dark => ("#333333", "#111111", "#E0E0E0"),
+            Theme::HighContrast => ("#FFFF00", "#000000", "#FFFFFF"),
+        }
+    }
+}
+
+/// Check that `color` could be a CSS color. Only empty strings are
+/// rejected; anything else is left for the canvas to interpret.
+pub fn validate_color(color: &str) -> Result<(), String> {
+    if color.trim().is_empty() {
+        return Err("color must not be empty".to_string());
+    }
+    Ok(())
+}
+
+/// The RGBA bytes of a `#rgb` or `#rrggbb` color, or `None` for any other
+/// CSS color syntax.
+pub fn parse_hex_color(color: &str) -> Option<[u8; 4]> {
+    let hex = color.trim().strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgba = [0xFF; 4];
+            for (i, c) in hex.char_indices() {
+                rgba[i] = channel(&hex[i..i + c.len_utf8()])? * 0x11;
+            }
+            Some(rgba)
+        }
+        6 => Some([channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?, 0xFF]),
+        _ => None,
+    }
+}
+
+/// The color a fraction `t` of the way from `from` to `to`.
+pub fn mix(from: [u8; 4], to: [u8; 4], t: f64) -> [u8; 4] {
+    let mut rgba = [0; 4];
+    for (i, channel) in rgba.iter_mut().enumerate() {
+        *channel = (from[i] as f64 + (to[i] as f64 - from[i] as f64) * t).round() as u8;
+    }
+    rgba
+}
+
+/// An RGBA color in `#rrggbb` notation, dropping the alpha.
+#[cfg(any(feature = "web", test))]
+pub fn to_hex_color(rgba: [u8; 4]) -> String {
+    format!("#{:02X}{:02X}{:02X}", rgba[0], rgba[1], rgba[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        assert!(validate_color("").is_err());
+        assert!(validate_color("  ").is_err());
+        assert!(validate_color("#123456").is_ok());
+        assert!(validate_color("rebeccapurple").is_ok());
+        assert!(validate_color("rgb(1, 2, 3)").is_ok());
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex_color("#CCCCCC"), Some([0xCC, 0xCC, 0xCC, 0xFF]));
+        assert_eq!(parse_hex_color("#f0a"), Some([0xFF, 0x00, 0xAA, 0xFF]));
+        assert_eq!(parse_hex_color("#12345"), None);
+        assert_eq!(parse_hex_color("#GGGGGG"), None);
+        assert_eq!(parse_hex_color("white"), None);
+        for &theme in Theme::ALL.iter() {
+            let (grid, dead, alive) = theme.colors();
+            assert!(parse_hex_color(grid).is_some() && parse_hex_color(dead).is_some() && parse_hex_color(alive).is_some());
+        }
+    }
+
+    #[test]
+    fn mixes() {
+        let black = [0x00, 0x00, 0x00, 0xFF];
+        let white = [0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(mix(black, white, 0.0), black);
+        assert_eq!(mix(black, white, 1.0), white);
+        assert_eq!(to_hex_color(mix(black, white, 0.5)), "#808080");
+    }
+
+    #[test]
+    fn names() {
+        for &theme in Theme::ALL.iter() {
+            assert_eq!(Theme::from_name(theme.name()), Some(theme));
+        }
+        assert_eq!(Theme::from_name("sepia"), None);
+    }
+}
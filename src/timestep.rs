@@ -0,0 +1,137 @@
+//! Ticking at a set number of generations per second, whatever the
+//! display's refresh rate.
+
+/// The most time one frame catches up on, in milliseconds. After a long
+/// pause or a slow frame the rest is dropped, so a board that ticks slower
+/// than it's asked to doesn't fall further behind every frame.
+pub const MAX_CATCH_UP_MS: f64 = 250.0;
+
+/// The fastest rate a timestep runs at, in generations per second. Faster
+/// rates are slowed to it.
+pub const MAX_GPS: f64 = 10_000.0;
+
+/// The most generations one frame ticks, however far behind it is, so a
+/// fast rate on a slow board can't hold up the page for long.
+pub const MAX_TICKS_PER_FRAME: u32 = 256;
+
+/// Turns animation frame timestamps into how many generations to tick, so
+/// the board advances `gps` generations every second on average.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedTimestep {
+    gps: f64,
+    /// The timestamp of the last frame, in milliseconds.
+    last: Option<f64>,
+    /// Time not yet ticked for, in milliseconds.
+    accumulated: f64,
+}
+
+impl FixedTimestep {
+    /// A timestep of `gps` generations per second, or `None` unless it's
+    /// above zero. Rates above `MAX_GPS`, infinity among them, run at
+    /// `MAX_GPS`.
+    pub fn new(gps: f64) -> Option<FixedTimestep> {
+        if gps.is_nan() || gps <= 0.0 {
+            return None;
+        }
+        Some(FixedTimestep {
+            gps: f64::min(gps, MAX_GPS),
+            last: None,
+            accumulated: 0.0,
+        })
+    }
+
+    pub fn gps(&self) -> f64 {
+        self.gps
+    }
+
+    /// How many generations to tick for a frame at `now` milliseconds: as
+    /// many as fit in the time since the last frame plus what was left
+    /// over, up to `MAX_CATCH_UP_MS` of them and never more than
+    /// `MAX_TICKS_PER_FRAME`. The first frame after a `reset` only starts
+    /// the clock.
+    pub fn advance(&mut self, now: f64) -> u32 {
+        let elapsed = match self.last.replace(now) {
+            Some(last) => (now - last).clamp(0.0, MAX_CATCH_UP_MS),
+            None => 0.0,
+        };
+        let step = 1000.0 / self.gps;
+        self.accumulated = f64::min(self.accumulated + elapsed, MAX_CATCH_UP_MS);
+        let ticks = (self.accumulated / step).floor();
+        if ticks > f64::from(MAX_TICKS_PER_FRAME) {
+            self.accumulated = 0.0;
+            return MAX_TICKS_PER_FRAME;
+        }
+        self.accumulated -= ticks * step;
+        ticks as u32
+    }
+
+    /// Start the clock again from the next frame, so the time since the
+    /// last one, such as a pause, isn't caught up on.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.accumulated = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_rate_at_any_refresh_rate() {
+        for &hz in [60.0, 144.0, 30.0].iter() {
+            let mut timestep = FixedTimestep::new(60.0).unwrap();
+            let frame = 1000.0 / hz;
+            let ticks: u32 = (0..=hz as u32 * 10).map(|i| timestep.advance(i as f64 * frame)).sum();
+            // Ten seconds at 60 generations per second, give or take a
+            // tick left in the accumulator.
+            assert!((599..=600).contains(&ticks), "{} Hz ticked {}", hz, ticks);
+        }
+    }
+
+    #[test]
+    fn leftover_time_carries_over() {
+        let mut timestep = FixedTimestep::new(10.0).unwrap();
+        assert_eq!(timestep.advance(1000.0), 0);
+        assert_eq!(timestep.advance(1060.0), 0);
+        assert_eq!(timestep.advance(1120.0), 1);
+        assert_eq!(timestep.advance(1250.0), 1);
+        assert_eq!(timestep.advance(1300.0), 1);
+        // A clock going backwards ticks nothing.
+        assert_eq!(timestep.advance(1200.0), 0);
+    }
+
+    #[test]
+    fn catch_up_is_capped() {
+        let mut timestep = FixedTimestep::new(40.0).unwrap();
+        timestep.advance(0.0);
+        // Five seconds late only catches up on the first quarter second.
+        assert_eq!(timestep.advance(5000.0), 10);
+        assert_eq!(timestep.advance(5025.0), 1);
+
+        // A reset drops the time since the last frame altogether.
+        timestep.reset();
+        assert_eq!(timestep.advance(60_000.0), 0);
+        assert_eq!(timestep.advance(60_100.0), 4);
+    }
+
+    #[test]
+    fn extreme_rates() {
+        for &gps in [0.0, -5.0, f64::NAN, f64::NEG_INFINITY].iter() {
+            assert!(FixedTimestep::new(gps).is_none(), "{}", gps);
+        }
+        for &gps in [f64::INFINITY, 1e12, f64::MAX].iter() {
+            let mut timestep = FixedTimestep::new(gps).unwrap();
+            assert_eq!(timestep.gps(), MAX_GPS);
+            timestep.advance(0.0);
+            assert_eq!(timestep.advance(1000.0), MAX_TICKS_PER_FRAME);
+            // The time it couldn't tick for is dropped, not saved up.
+            assert_eq!(timestep.advance(1001.0), 10);
+        }
+
+        // Too slow to ever tick is allowed, and ticks nothing.
+        let mut crawl = FixedTimestep::new(f64::MIN_POSITIVE).unwrap();
+        crawl.advance(0.0);
+        assert_eq!(crawl.advance(1e9), 0);
+    }
+}
@@ -0,0 +1,76 @@
+//! Trails behind cells that died recently, like a long exposure.
+
+/// Trails fainter than this aren't drawn.
+pub const MIN_TRAIL: f64 = 1.0 / 64.0;
+
+/// When each cell was last alive, for a trail behind it that fades by half
+/// every `half_life` generations once it dies. Only deaths are recorded, so
+/// keeping it up to date needs no pass over the board.
+#[derive(Clone, Debug)]
+pub struct Trail {
+    half_life: u32,
+    last_alive: Vec<Option<u64>>,
+}
+
+impl Trail {
+    /// Trails for `len` cells that halve every `half_life` generations. A
+    /// half-life of zero is treated as one.
+    pub fn new(len: usize, half_life: u32) -> Trail {
+        Trail {
+            half_life: u32::max(half_life, 1),
+            last_alive: vec![None; len],
+        }
+    }
+
+    pub fn half_life(&self) -> u32 {
+        self.half_life
+    }
+
+    /// Note that the cell at `idx` died after `generation`, the last one it
+    /// was alive in.
+    pub fn record_death(&mut self, idx: usize, generation: u64) {
+        self.last_alive[idx] = Some(generation);
+    }
+
+    /// How strong the trail of the dead cell at `idx` is at `generation`,
+    /// halving from 1 every half-life since it was last alive, down to 0
+    /// once it's fainter than `MIN_TRAIL` or if it has never died.
+    pub fn intensity(&self, idx: usize, generation: u64) -> f64 {
+        let last_alive = match self.last_alive[idx] {
+            Some(last_alive) => last_alive,
+            None => return 0.0,
+        };
+        let elapsed = generation.saturating_sub(last_alive) as f64;
+        let intensity = f64::powf(0.5, elapsed / self.half_life as f64);
+        if intensity < MIN_TRAIL {
+            0.0
+        } else {
+            intensity
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halves_every_half_life() {
+        let mut trail = Trail::new(2, 2);
+        trail.record_death(0, 10);
+        assert_eq!(trail.intensity(0, 10), 1.0);
+        assert!((trail.intensity(0, 11) - f64::sqrt(0.5)).abs() < 1e-12);
+        assert_eq!(trail.intensity(0, 12), 0.5);
+        assert_eq!(trail.intensity(0, 14), 0.25);
+        // 1/64 is the faintest drawn, at six half-lives.
+        assert_eq!(trail.intensity(0, 22), MIN_TRAIL);
+        assert_eq!(trail.intensity(0, 23), 0.0);
+        // A cell that has never died leaves no trail.
+        assert_eq!(trail.intensity(1, 12), 0.0);
+
+        // Dying again starts over.
+        trail.record_death(0, 20);
+        assert!((trail.intensity(0, 21) - f64::sqrt(0.5)).abs() < 1e-12);
+        assert_eq!(Trail::new(1, 0).half_life(), 1);
+    }
+}
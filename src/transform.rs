@@ -0,0 +1,172 @@
+use wasm_bindgen::prelude::*;
+
+use shape::Shape;
+
+/// One of the eight symmetries of a square, applied to a pattern before it
+/// is placed. Rotations are clockwise.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirror left to right.
+    FlipHorizontal,
+    /// Mirror top to bottom.
+    FlipVertical,
+    /// Mirror across the diagonal from the top-left corner.
+    Transpose,
+    /// Mirror across the diagonal from the top-right corner.
+    AntiTranspose,
+}
+
+impl Transform {
+    /// Decompose into a horizontal flip, done first, followed by some number
+    /// of quarter turns.
+    fn to_parts(self) -> (bool, u8) {
+        match self {
+            Transform::Identity => (false, 0),
+            Transform::Rotate90 => (false, 1),
+            Transform::Rotate180 => (false, 2),
+            Transform::Rotate270 => (false, 3),
+            Transform::FlipHorizontal => (true, 0),
+            Transform::AntiTranspose => (true, 1),
+            Transform::FlipVertical => (true, 2),
+            Transform::Transpose => (true, 3),
+        }
+    }
+
+    fn from_parts(flip: bool, turns: u8) -> Transform {
+        match (flip, turns % 4) {
+            (false, 0) => Transform::Identity,
+            (false, 1) => Transform::Rotate90,
+            (false, 2) => Transform::Rotate180,
+            (false, 3) => Transform::Rotate270,
+            (true, 0) => Transform::FlipHorizontal,
+            (true, 1) => Transform::AntiTranspose,
+            (true, 2) => Transform::FlipVertical,
+            _ => Transform::Transpose,
+        }
+    }
+
+    /// The transform that applies `self` and then `next`.
+    pub fn then(self, next: Transform) -> Transform {
+        let (flip1, turns1) = self.to_parts();
+        let (flip2, turns2) = next.to_parts();
+        // Flipping after a rotation is the same as flipping before the
+        // opposite rotation.
+        let turns1 = if flip2 { 4 - turns1 } else { turns1 };
+        Transform::from_parts(flip1 != flip2, turns1 + turns2)
+    }
+
+    /// This transform followed by another quarter turn.
+    pub fn rotated(self) -> Transform {
+        self.then(Transform::Rotate90)
+    }
+
+    /// This transform followed by a horizontal flip.
+    pub fn flipped(self) -> Transform {
+        self.then(Transform::FlipHorizontal)
+    }
+
+    /// Apply the transform, keeping the pattern's bounding box anchored at
+    /// the top-left corner.
+    pub(crate) fn apply(self, shape: &Shape) -> Shape {
+        let (flip, turns) = self.to_parts();
+        let mut width = shape.width;
+        let mut height = shape.height;
+        let mut cells = shape.cells.clone();
+
+        if flip {
+            for cell in cells.iter_mut() {
+                cell.1 = width - 1 - cell.1;
+            }
+        }
+        for _ in 0..turns {
+            for cell in cells.iter_mut() {
+                *cell = (cell.1, height - 1 - cell.0);
+            }
+            let (w, h) = (height, width);
+            width = w;
+            height = h;
+        }
+
+        cells.sort();
+        Shape {
+            width,
+            height,
+            cells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Transform; 8] = [
+        Transform::Identity,
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+        Transform::Transpose,
+        Transform::AntiTranspose,
+    ];
+
+    fn l_shape() -> Shape {
+        // O.
+        // O.
+        // OO
+        Shape {
+            width: 2,
+            height: 3,
+            cells: vec![(0, 0), (1, 0), (2, 0), (2, 1)],
+        }
+    }
+
+    #[test]
+    fn apply() {
+        let shape = l_shape();
+        let rotated = Transform::Rotate90.apply(&shape);
+        assert_eq!((rotated.width, rotated.height), (3, 2));
+        assert_eq!(rotated.cells, vec![(0, 0), (0, 1), (0, 2), (1, 0)]);
+
+        let flipped = Transform::FlipHorizontal.apply(&shape);
+        assert_eq!(flipped.cells, vec![(0, 1), (1, 1), (2, 0), (2, 1)]);
+
+        let flipped = Transform::FlipVertical.apply(&shape);
+        assert_eq!(flipped.cells, vec![(0, 0), (0, 1), (1, 0), (2, 0)]);
+
+        let transposed = Transform::Transpose.apply(&shape);
+        assert_eq!(transposed.cells, vec![(0, 0), (0, 1), (0, 2), (1, 2)]);
+
+        let transposed = Transform::AntiTranspose.apply(&shape);
+        assert_eq!(transposed.cells, vec![(0, 0), (1, 0), (1, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn four_quarter_turns_are_identity() {
+        let shape = l_shape();
+        let mut transform = Transform::Identity;
+        let mut rotated = shape.clone();
+        for _ in 0..4 {
+            transform = transform.rotated();
+            rotated = Transform::Rotate90.apply(&rotated);
+        }
+        assert_eq!(transform, Transform::Identity);
+        assert_eq!(rotated, shape);
+    }
+
+    #[test]
+    fn then_matches_applying_in_sequence() {
+        let shape = l_shape();
+        for &a in ALL.iter() {
+            for &b in ALL.iter() {
+                assert_eq!(a.then(b).apply(&shape), b.apply(&a.apply(&shape)), "{:?} then {:?}", a, b);
+            }
+        }
+    }
+}
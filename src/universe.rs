@@ -1,149 +1,857 @@
-extern crate wasm_bindgen;
-extern crate web_sys;
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
 
-use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+#[cfg(any(feature = "web", feature = "serde", test))]
+use base64;
+use bitset::BitSet;
+use hashlife::Hashlife;
+use heatmap::Heatmap;
+use activity::{Activity, DEFAULT_SETTLE_WINDOW};
+use trail::Trail;
+use patterns::Pattern;
+use shape::Shape;
+use transform::Transform;
+use rle;
+use plaintext;
+use life106;
+use pattern_file::{self, ExportFormat};
+use bitparallel;
+use rule::Rule;
+use simd;
+use raster;
+use rng::Rng;
+use noise::Noise;
+use ant::{Ant, AntMode, Direction};
+use brush::{self, BrushShape};
+use theme::{self, Theme};
+use render;
+use wireworld::{self, WireworldPattern};
+use immigration;
+use neighborhood::{self, Neighborhood, NeighborhoodKind};
+use margolus;
+#[cfg(feature = "web")]
+use canvas;
+
+#[wasm_bindgen]
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Cell {
     Dead = 0,
     Alive = 1,
 }
 
-impl Cell {
-    fn toggle(&mut self) {
-        *self = match *self {
-            Cell::Dead => Cell::Alive,
-            Cell::Alive => Cell::Dead,
-        };
+/// Where the existing cells land when a universe is resized.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Center,
+}
+
+/// How pasted cells combine with the cells already in the universe.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasteMode {
+    /// Replace the destination cells, dead or alive.
+    Overwrite,
+    /// Keep destination cells alive and add the pasted live cells.
+    Or,
+    /// Toggle destination cells where the pasted cells are alive.
+    Xor,
+    /// Keep destination cells alive only where the pasted cells are alive.
+    And,
+}
+
+/// How cells on the edges of the universe see their neighbors.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Topology {
+    /// Opposite edges are joined, so patterns wrap around.
+    Torus,
+    /// Cells outside the grid are permanently dead.
+    Bounded,
+}
+
+impl Topology {
+    /// The name used for the topology in the page's status bar.
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Torus => "torus",
+            Topology::Bounded => "bounded",
+        }
+    }
+}
+
+/// How `tick` computes the next generation. Both give the same result.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Count each cell's neighbors in turn. Simple, and kept as the
+    /// reference the others are tested against.
+    CellByCell,
+    /// Work on rows as 64-bit words, adding up the neighbors of 64 cells at
+    /// once with boolean logic.
+    BitParallel,
+}
+
+/// Which cellular automaton a universe runs.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Mode {
+    /// The rule set with `set_rule`, Life-like or Generations.
+    #[default]
+    Life,
+    /// Wireworld's electrons, tails and conductors, whatever the rule.
+    Wireworld,
+    /// The rule with two colors of live cell, each newborn taking the
+    /// color of most of its parents.
+    Immigration,
+    /// The reversible Critters block rule on the Margolus neighborhood,
+    /// whatever the rule. Needs an even width and height.
+    Critters,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 4] = [Mode::Life, Mode::Wireworld, Mode::Immigration, Mode::Critters];
+
+    /// The name used for the mode in the page's mode picker.
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Life => "life",
+            Mode::Wireworld => "wireworld",
+            Mode::Immigration => "immigration",
+            Mode::Critters => "critters",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Mode> {
+        Mode::ALL.iter().cloned().find(|mode| mode.name() == name)
+    }
+
+    /// Whether cells in `state` are alive, and count as neighbors.
+    #[inline]
+    pub(crate) fn is_live(self, state: u8) -> bool {
+        match self {
+            Mode::Immigration => state != 0,
+            Mode::Life | Mode::Wireworld | Mode::Critters => state == 1,
+        }
     }
 }
 
+/// A board of cells and the rule that runs them.
+///
+/// Cells are given by row and then column, and rectangles by their top row,
+/// left column, height and width, in that order.
+#[wasm_bindgen]
 pub struct Universe {
     width: u32,
     height: u32,
-    cells: Vec<Cell>,
+    cells: BitSet,
+    /// Back buffer the next generation is written into by `tick`.
+    next: BitSet,
+    /// The state of every cell under a Generations rule or in Wireworld:
+    /// 0 for dead, 1 for alive and higher for the other states. `cells`
+    /// still holds the live cells, and many edits only change those, so
+    /// `sync_states` catches up with them.
+    states: Option<Vec<u8>>,
+    rule: Rule,
+    mode: Mode,
+    topology: Topology,
+    algorithm: Algorithm,
+    generation: u64,
+    births: u32,
+    deaths: u32,
+    /// Whether the last tick left every cell in the state it was in.
+    still: bool,
+    /// The cells as they were when the universe was constructed.
+    initial: Snapshot,
+    /// States to return to with `undo`, oldest first.
+    undo: VecDeque<Edit>,
+    /// States undone, most recent last, to return to with `redo`.
+    redo: Vec<Edit>,
+    history_depth: usize,
+    /// The cells and their states before each recent tick, oldest first,
+    /// for `step_back`.
+    rewind: VecDeque<(BitSet, Option<Vec<u8>>)>,
+    rewind_capacity: usize,
+    /// How many generations each cell has been alive, if tracked.
+    ages: Option<Vec<u16>>,
+    /// How often each cell has changed recently, if tracked.
+    heatmap: Option<Heatmap>,
+    /// When each cell was last alive, if trails are left behind.
+    trail: Option<Trail>,
+    /// The population over recent generations, to tell when it settles.
+    activity: Activity,
+    /// Whether `activity` is kept up to date every tick.
+    settle_tracking: bool,
+    /// The population the last tick left, while settle tracking and until
+    /// anything else changes the cells, so the next tick can work out its
+    /// own from the births and deaths instead of counting.
+    ticked_population: Option<u32>,
+    /// Cells that have changed since the universe was last drawn.
+    changed: Vec<u32>,
+    /// Whether more of the board may have changed than `changed` records.
+    needs_full_redraw: bool,
+    /// Whether the cells have been edited since the last tick, so the next
+    /// tick should save them for `undo`.
+    edited: bool,
+    /// The width and height of each cell on the canvas, in pixels.
+    cell_size: u32,
+    grid_color: String,
+    dead_color: String,
+    alive_color: String,
+    grid_visible: bool,
+    /// Called after each tick with the new generation's statistics.
+    #[cfg(feature = "web")]
+    tick_callback: Option<js_sys::Function>,
+    /// Called after each tick with the indices of the cells that changed.
+    #[cfg(feature = "web")]
+    changed_callback: Option<js_sys::Function>,
+    /// Called for every cell on every tick in place of the rule.
+    #[cfg(feature = "web")]
+    dynamic_rule: Option<js_sys::Function>,
+    /// Langton's ants on the board, in the order they move.
+    ants: Vec<Ant>,
+    ant_mode: AntMode,
+    /// Rules to take turns with and how many generations each runs for,
+    /// if the rule changes on a schedule.
+    schedule: Vec<(Rule, u32)>,
+    /// Random events on top of the rule, and where their randomness comes
+    /// from.
+    noise: Noise,
+    noise_rng: Rng,
 }
 
-impl Universe {
+/// The default number of edits that can be undone.
+const HISTORY_DEPTH: usize = 100;
 
-    pub const CELL_SIZE: u32 = 5; // px
-    pub const GRID_COLOR: &'static str = "#CCCCCC";
-    pub const DEAD_COLOR: &'static str = "#FFFFFF";
-    pub const ALIVE_COLOR: &'static str = "#000000";
+/// What starts a board in a URL hash, telling it apart from other hashes
+/// like `#worker`.
+#[cfg(any(feature = "web", test))]
+const HASH_PREFIX: &str = "life=";
 
-    fn get_index(&self, row: u32, column: u32) -> usize {
-        (row * self.width + column) as usize
-    }
+/// The width and height of `Universe::new`.
+pub(crate) const DEFAULT_SIZE: u32 = 128;
 
-    fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
-        let mut count = 0;
+/// The outcome of `Universe::run_until_stable`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StabilityResult {
+    /// Generations that changed the board before it settled, or the cap if
+    /// it never did.
+    pub generations: u32,
+    /// Whether a generation left the board unchanged before the cap.
+    pub stable: bool,
+}
 
-        let north = if row == 0 {
-            self.height - 1
-        } else {
-            row - 1
-        };
+/// Why a universe has stopped changing, as found by `Universe::settled`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Settled {
+    /// No cells are alive.
+    Extinct,
+    /// The last tick left every cell as it was.
+    Stable,
+}
 
-        let south = if row == self.height - 1 {
-            0
-        } else {
-            row + 1
-        };
+impl Settled {
+    /// How the universe ended up, for a status message.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Settled::Extinct => "extinct",
+            Settled::Stable => "stabilized",
+        }
+    }
+}
 
-        let west = if column == 0 {
-            self.width - 1
-        } else {
-            column - 1
-        };
+/// When a running universe should stop by itself.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutoPause {
+    /// Keep running whatever happens.
+    Off,
+    /// Stop once no cells are alive.
+    Extinction,
+    /// Stop once no cells are alive, or a tick changes nothing.
+    ExtinctionOrStable,
+}
 
-        let east = if column == self.width - 1 {
-            0
-        } else {
-            column + 1
-        };
+impl AutoPause {
+    /// Whether a universe that has settled this way should stop.
+    pub fn pauses_for(self, settled: Settled) -> bool {
+        match (self, settled) {
+            (AutoPause::Off, _) => false,
+            (AutoPause::Extinction, Settled::Extinct) => true,
+            (AutoPause::Extinction, Settled::Stable) => false,
+            (AutoPause::ExtinctionOrStable, _) => true,
+        }
+    }
+}
 
-        let nw = self.get_index(north, west);
-        count += self.cells[nw] as u8;
+/// A copy of the cells of a universe.
+#[derive(Clone, Debug, Default)]
+struct Snapshot {
+    width: u32,
+    height: u32,
+    cells: BitSet,
+    /// The states of the cells, if the rule has more than two.
+    states: Option<Vec<u8>>,
+}
 
-        let n = self.get_index(north, column);
-        count += self.cells[n] as u8;
+/// A state saved in the undo history.
+#[derive(Clone, Debug)]
+struct Edit {
+    snapshot: Snapshot,
+    generation: u64,
+}
 
-        let ne = self.get_index(north, east);
-        count += self.cells[ne] as u8;
+/// What a generation changes, tallied while it's worked out and taken on
+/// when it becomes the current one.
+struct Tally {
+    births: u32,
+    deaths: u32,
+    /// Whether any cell changes state, even without being born or dying,
+    /// like a cell fading out under a Generations rule.
+    altered: bool,
+    /// The cells that changed, unless the whole board is to be redrawn.
+    changed: Vec<u32>,
+    /// The next states, for boards that keep more than two.
+    states: Option<Vec<u8>>,
+    /// Whether the page's custom rule failed, so it has to be dropped.
+    #[cfg(feature = "web")]
+    rule_failed: bool,
+}
 
-        let w = self.get_index(row, west);
-        count += self.cells[w] as u8;
+impl Tally {
+    /// A tally adding to the cells already `changed`.
+    fn new(changed: Vec<u32>) -> Tally {
+        Tally {
+            births: 0,
+            deaths: 0,
+            altered: false,
+            changed,
+            states: None,
+            #[cfg(feature = "web")]
+            rule_failed: false,
+        }
+    }
+}
 
-        let e = self.get_index(row, east);
-        count += self.cells[e] as u8;
+/// Public methods, exported to JavaScript.
+#[wasm_bindgen]
+impl Universe {
+    pub fn tick(&mut self) {
+        #[cfg(feature = "profile")]
+        let _timer = ::utils::Timer::new("Universe::tick");
 
-        let sw = self.get_index(south, west);
-        count += self.cells[sw] as u8;
+        if let Some(rule) = self.scheduled_rule() {
+            self.rule = rule;
+        }
+        self.sync_states();
+        // Ticks aren't undoable on their own, but undoing after running
+        // should go back to the last edited state.
+        if self.edited {
+            self.save_for_undo();
+            self.edited = false;
+        }
+        if self.rewind_capacity > 0 {
+            if self.rewind.len() == self.rewind_capacity {
+                self.rewind.pop_front();
+            }
+            self.rewind.push_back((self.cells.clone(), self.states.clone()));
+        }
 
-        let s = self.get_index(south, column);
-        count += self.cells[s] as u8;
+        let mut next = mem::take(&mut self.next);
+        let mut tally = Tally::new(mem::take(&mut self.changed));
+        self.compute_next_into(&mut next, &mut tally);
+        self.commit(next, tally);
+    }
 
-        let se = self.get_index(south, east);
-        count += self.cells[se] as u8;
+    /// Jump `generations` generations ahead with hashlife, which can cover
+    /// millions of generations of a repetitive pattern in moments.
+    ///
+    /// Only bounded universes are supported. Hashlife runs the cells on an
+    /// unbounded plane and keeps the ones that end up on the board, so the
+    /// result matches ticking as long as the pattern stays clear of the
+    /// edges. Errors in modes other than Life, with noise, if the rule has
+    /// `B0` or decaying states, or hashlife runs out of nodes, leaving the
+    /// universe as it was.
+    pub fn fast_forward(&mut self, generations: u64) -> Result<(), String> {
+        if self.topology == Topology::Torus {
+            return Err("fast_forward needs a bounded universe, not a torus".to_string());
+        }
+        if self.mode != Mode::Life {
+            return Err(format!("fast_forward can't run {}", self.mode.name()));
+        }
+        if !self.schedule.is_empty() {
+            return Err("fast_forward can't follow a rule schedule".to_string());
+        }
+        if !self.ants.is_empty() {
+            return Err("fast_forward can't move ants".to_string());
+        }
+        if !self.noise.is_quiet() {
+            return Err("fast_forward can't add noise".to_string());
+        }
+        let mut life = self.to_hashlife()?;
+        life.fast_forward(generations)?;
 
-        count
+        if self.edited {
+            self.save_for_undo();
+            self.edited = false;
+        }
+        self.place_hashlife(&life);
+        self.births = 0;
+        self.deaths = 0;
+        self.still = false;
+        self.rewind.clear();
+        self.reset_ages();
+        self.redraw_all();
+        Ok(())
     }
 
-    pub fn tick(&mut self) {
-        // let _timer = Timer::new("Universe::tick");
+    /// The live cells on a hashlife plane, with the top-left cell at the
+    /// origin.
+    pub fn to_hashlife(&self) -> Result<Hashlife, String> {
+        let mut life = Hashlife::new();
+        life.set_rule(&self.rule.to_string())?;
+        life.set_generation(self.generation);
+        for (row, col) in self.iter_live() {
+            life.set_cell(row as i64, col as i64)?;
+        }
+        Ok(life)
+    }
 
-        let mut next = self.cells.clone();
+    /// A bounded universe of the given size with the cells of a hashlife
+    /// plane that fall within it, from the origin.
+    pub fn from_hashlife(life: &Hashlife, width: u32, height: u32) -> Result<Universe, String> {
+        let mut universe = Universe::empty(width, height)?;
+        universe.rule = Rule::parse(&life.rule()).unwrap_or_default();
+        universe.topology = Topology::Bounded;
+        universe.place_hashlife(life);
+        universe.save_initial();
+        Ok(universe)
+    }
 
-        for row in 0..self.height {
-            for col in 0..self.width {
-                let idx = self.get_index(row, col);
-                let cell = self.cells[idx];
-                let live_neighbors = self.live_neighbor_count(row, col);
-
-                let next_cell = match (cell, live_neighbors) {
-                    // Rule 1: Any live cell with fewer than two live neighbours
-                    // dies, as if caused by underpopulation.
-                    (Cell::Alive, x) if x < 2 => Cell::Dead,
-                    // Rule 2: Any live cell with two or three live neighbours
-                    // lives on to the next generation.
-                    (Cell::Alive, 2) | (Cell::Alive, 3) => Cell::Alive,
-                    // Rule 3: Any live cell with more than three live
-                    // neighbours dies, as if by overpopulation.
-                    (Cell::Alive, x) if x > 3 => Cell::Dead,
-                    // Rule 4: Any dead cell with exactly three live neighbours
-                    // becomes a live cell, as if by reproduction.
-                    (Cell::Dead, 3) => Cell::Alive,
-                    // All other cells remain in the same state.
-                    (otherwise, _) => otherwise,
-                };
+    /// Advance `generations` generations in one call and return the final
+    /// population.
+    pub fn tick_many(&mut self, generations: u32) -> u32 {
+        for _ in 0..generations {
+            self.tick();
+        }
+        self.population()
+    }
 
-                next[idx] = next_cell;
+    /// Tick until a generation leaves the board unchanged, running at most
+    /// `max_generations` generations.
+    ///
+    /// Oscillators never count as stable, since their next state always
+    /// differs from the current one.
+    pub fn run_until_stable(&mut self, max_generations: u32) -> StabilityResult {
+        for generations in 0..max_generations {
+            self.tick();
+            if self.still {
+                return StabilityResult {
+                    generations,
+                    stable: true,
+                };
             }
         }
+        StabilityResult {
+            generations: max_generations,
+            stable: false,
+        }
+    }
 
-        self.cells = next;
+    /// Tick up to `max_period` generations looking for a return to a state
+    /// seen since the call, and report the length of the cycle.
+    ///
+    /// Still lifes have period 1. On a torus, spaceships also come back
+    /// once they have travelled all the way around, so a glider on an n×n
+    /// torus reports a period of 4n.
+    pub fn detect_period(&mut self, max_period: u32) -> Option<u32> {
+        let mut seen = vec![(self.cells.fingerprint(), self.cells.clone(), self.states.clone())];
+        for _ in 0..max_period {
+            self.tick();
+            let hash = self.cells.fingerprint();
+            // Compare the cells too, so a hash collision can't report a
+            // false period, and the other states, which the hash leaves out.
+            let (cells, states) = (&self.cells, &self.states);
+            if let Some(i) = seen.iter().rposition(|&(h, ref c, ref s)| h == hash && c == cells && s == states) {
+                return Some((seen.len() - i) as u32);
+            }
+            seen.push((hash, self.cells.clone(), self.states.clone()));
+        }
+        None
     }
 
     pub fn new() -> Universe {
-        let width = 128;
-        let height = 128;
+        let mut universe = Universe::empty(DEFAULT_SIZE, DEFAULT_SIZE).expect("the default size fits");
+        universe.fill_default();
+        universe.save_initial();
+        universe
+    }
 
-        let cells = (0..width * height)
-            .map(|i| {
-                if i % 2 == 0 || i % 7 == 0 {
-                    Cell::Alive
-                } else {
-                    Cell::Dead
-                }
-            })
-            .collect();
+    /// Create a universe where each cell is alive with probability
+    /// `density`. The same seed always produces the same universe.
+    ///
+    /// Panics if the universe has more cells than a `u32` can count;
+    /// `UniverseBuilder` returns an error instead.
+    pub fn new_random(width: u32, height: u32, density: f64, seed: u64) -> Universe {
+        let mut universe = Universe::empty(width, height).unwrap_or_else(|err| panic!("{}", err));
+        universe.fill_random(density, seed);
+        universe.save_initial();
+        universe
+    }
 
-        Universe {
-            width,
-            height,
-            cells,
+    /// Replace every cell with one that is alive with probability `density`,
+    /// clamped to `[0, 1]`, and reset the generation and statistics.
+    pub fn randomize(&mut self, density: f64, seed: u64) {
+        self.record_edit();
+        self.fill_random(density, seed);
+    }
+
+    /// Kill every cell and reset the generation and statistics to zero.
+    pub fn clear(&mut self) {
+        self.record_edit();
+        self.clear_cells();
+    }
+
+    /// Go back to the state before the last edit, or the last edited state
+    /// if the universe has ticked since. Returns whether there was anything
+    /// to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop_back() {
+            Some(edit) => {
+                let current = self.edit();
+                self.redo.push(current);
+                self.restore_edit(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reapply the last undone edit. Returns whether there was anything to
+    /// redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(edit) => {
+                let current = self.edit();
+                self.undo.push_back(current);
+                self.restore_edit(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The width and height each cell is drawn at, in pixels.
+    pub fn cell_size(&self) -> u32 {
+        self.cell_size
+    }
+
+    /// Draw each cell `px` pixels square, at least one. The canvas has to be
+    /// set up again afterwards.
+    pub fn set_cell_size(&mut self, px: u32) {
+        self.cell_size = u32::max(px, 1);
+        self.redraw_all();
+    }
+
+    /// Whether the gridlines are drawn. When hidden, they are painted in the
+    /// dead cell color, so the cells stay where they were.
+    pub fn grid_visible(&self) -> bool {
+        self.grid_visible
+    }
+
+    /// Show or hide the gridlines. The grid has to be drawn again
+    /// afterwards.
+    pub fn set_grid_visible(&mut self, visible: bool) {
+        self.grid_visible = visible;
+        self.redraw_all();
+    }
+
+    /// The CSS color of the gridlines.
+    pub fn grid_color(&self) -> String {
+        self.grid_color.clone()
+    }
+
+    pub fn set_grid_color(&mut self, color: &str) -> Result<(), String> {
+        theme::validate_color(color)?;
+        self.grid_color = color.to_string();
+        Ok(())
+    }
+
+    /// The CSS color of dead cells.
+    pub fn dead_color(&self) -> String {
+        self.dead_color.clone()
+    }
+
+    pub fn set_dead_color(&mut self, color: &str) -> Result<(), String> {
+        theme::validate_color(color)?;
+        self.dead_color = color.to_string();
+        self.redraw_all();
+        Ok(())
+    }
+
+    /// The CSS color of live cells.
+    pub fn alive_color(&self) -> String {
+        self.alive_color.clone()
+    }
+
+    pub fn set_alive_color(&mut self, color: &str) -> Result<(), String> {
+        theme::validate_color(color)?;
+        self.alive_color = color.to_string();
+        self.redraw_all();
+        Ok(())
+    }
+
+    /// Use all the colors of a preset theme.
+    pub fn set_theme(&mut self, theme: Theme) {
+        let (grid, dead, alive) = theme.colors();
+        self.grid_color = grid.to_string();
+        self.dead_color = dead.to_string();
+        self.alive_color = alive.to_string();
+        self.redraw_all();
+    }
+
+    /// Write the board into `buffer` as RGBA pixels, one per cell, ready for
+    /// `putImageData`. The buffer must hold exactly `width * height * 4`
+    /// bytes.
+    pub fn render_to_image_data(&self, buffer: &mut [u8]) -> Result<(), String> {
+        render::write_pixels(self, buffer)
+    }
+
+    /// Like `render_to_image_data`, but with each cell drawn as a square
+    /// block with gridlines between, filling a canvas set up by the page.
+    /// The buffer must hold `((cell_size + 1) * width + 1) * ((cell_size +
+    /// 1) * height + 1) * 4` bytes.
+    pub fn render_to_image_data_scaled(&self, buffer: &mut [u8]) -> Result<(), String> {
+        render::write_scaled_pixels(self, buffer)
+    }
+
+    /// A new canvas, not on the page, with the whole board drawn at one
+    /// pixel per cell, whatever part of it the page shows.
+    #[cfg(feature = "web")]
+    pub fn render_to_canvas(&self) -> Result<web_sys::HtmlCanvasElement, JsValue> {
+        canvas::draw_whole_board(self)
+    }
+
+    /// Whether the whole board must be repainted, because it changed in a
+    /// way `changed_cells` doesn't cover, such as an edit or a resize.
+    pub fn needs_full_redraw(&self) -> bool {
+        self.needs_full_redraw
+    }
+
+    /// Pointer to the indices of the cells that changed state since
+    /// `mark_drawn`, as `row * width + col`. The list may repeat a cell. It
+    /// is only complete when `needs_full_redraw` is false.
+    pub fn changed_cells(&self) -> *const u32 {
+        self.changed.as_ptr()
+    }
+
+    /// The number of entries in `changed_cells`.
+    pub fn changed_cells_len(&self) -> u32 {
+        self.changed.len() as u32
+    }
+
+    /// Forget the changed cells, once the board has been drawn.
+    pub fn mark_drawn(&mut self) {
+        self.changed.clear();
+        self.needs_full_redraw = false;
+    }
+
+    /// Start counting how many generations each cell has been alive.
+    /// Cells alive now start at an age of one.
+    pub fn enable_age_tracking(&mut self) {
+        if self.ages.is_none() {
+            let ages = (0..self.cells.len()).map(|idx| self.cells.get(idx) as u16).collect();
+            self.ages = Some(ages);
+        }
+    }
+
+    pub fn disable_age_tracking(&mut self) {
+        self.ages = None;
+        self.redraw_all();
+    }
+
+    pub fn age_tracking(&self) -> bool {
+        self.ages.is_some()
+    }
+
+    /// How many generations the cell has been alive, counting the current
+    /// one, or zero if it is dead. Cells that were edited to life count as
+    /// newborn, and every live cell is age one without age tracking.
+    pub fn age_at(&self, row: u32, col: u32) -> u16 {
+        let idx = self.get_index(row, col);
+        if !self.cells.get(idx) {
+            return 0;
+        }
+        match self.ages {
+            Some(ref ages) => u16::max(ages[idx], 1),
+            None => 1,
+        }
+    }
+
+    /// Start counting how often each cell changes, halving the counts every
+    /// `decay_interval` generations.
+    pub fn enable_heatmap(&mut self, decay_interval: u32) {
+        self.heatmap = Some(Heatmap::new(self.cells.len(), decay_interval));
+    }
+
+    pub fn disable_heatmap(&mut self) {
+        self.heatmap = None;
+        self.redraw_all();
+    }
+
+    pub fn heatmap_enabled(&self) -> bool {
+        self.heatmap.is_some()
+    }
+
+    /// How active the cell has been recently, from 0 (unchanged) to 1
+    /// (changing every generation). Always 0 without the heatmap.
+    pub fn activity_at(&self, row: u32, col: u32) -> f64 {
+        match self.heatmap {
+            Some(ref heatmap) => heatmap.intensity(self.get_index(row, col)),
+            None => 0.0,
+        }
+    }
+
+    /// Leave a trail behind cells as they die, fading by half every
+    /// `half_life` generations. Zero turns trails off.
+    pub fn set_trail(&mut self, half_life: u32) {
+        self.trail = match half_life {
+            0 => None,
+            _ => Some(Trail::new(self.cells.len(), half_life)),
+        };
+        self.redraw_all();
+    }
+
+    /// The generations a trail takes to fade by half, or 0 without trails.
+    pub fn trail_half_life(&self) -> u32 {
+        self.trail.as_ref().map_or(0, Trail::half_life)
+    }
+
+    /// How strong the trail at the cell is, 1 while it's alive and fading
+    /// toward 0 after it dies. Always 0 for dead cells without trails.
+    pub fn trail_at(&self, row: u32, col: u32) -> f64 {
+        let idx = self.get_index(row, col);
+        if self.cells.get(idx) {
+            return 1.0;
+        }
+        match self.trail {
+            Some(ref trail) => trail.intensity(idx, self.generation),
+            None => 0.0,
+        }
+    }
+
+    /// Watch the population every tick for `is_settled` and
+    /// `lifespan_so_far`, starting from the current generation, or stop.
+    /// Off by default, since it counts the population after every edit.
+    pub fn set_settle_tracking(&mut self, enabled: bool) {
+        if enabled && !self.settle_tracking {
+            let population = self.population();
+            self.activity.record(self.generation, population);
+            self.ticked_population = Some(population);
+        }
+        self.settle_tracking = enabled;
+    }
+
+    pub fn settle_tracking(&self) -> bool {
+        self.settle_tracking
+    }
+
+    /// The generation since which the population has only repeated with a
+    /// short period: for a methuselah that has settled, its lifespan, and
+    /// for a board still going, about now. Only kept up to date while
+    /// `set_settle_tracking` is on.
+    pub fn lifespan_so_far(&self) -> u64 {
+        self.activity.lifespan_so_far()
+    }
+
+    /// Whether the population has only repeated for the settle window of
+    /// generations. Unlike `settled`, oscillators and escaping gliders
+    /// count as settled too.
+    pub fn is_settled(&self) -> bool {
+        self.activity.is_settled()
+    }
+
+    /// Set how many generations the population has to repeat for before
+    /// `is_settled`, 200 by default.
+    pub fn set_settle_window(&mut self, generations: u32) {
+        self.activity.set_window(generations as u64);
+    }
+
+    /// Keep the cells from before each of the last `capacity` ticks, so
+    /// they can be stepped back through with `step_back`. A capacity of zero
+    /// turns this off.
+    pub fn enable_history(&mut self, capacity: u32) {
+        self.rewind_capacity = capacity as usize;
+        while self.rewind.len() > self.rewind_capacity {
+            self.rewind.pop_front();
+        }
+    }
+
+    /// The number of ticks that can be stepped back through.
+    pub fn history_len(&self) -> u32 {
+        self.rewind.len() as u32
+    }
+
+    /// Return to the cells before the last tick and decrement the
+    /// generation. Returns false if there is no earlier tick to go back to.
+    ///
+    /// Edits, `undo`, `redo`, `reset` and resizing forget the ticks before
+    /// them.
+    pub fn step_back(&mut self) -> bool {
+        match self.rewind.pop_back() {
+            Some((cells, states)) => {
+                self.ticked_population = None;
+                self.cells = cells;
+                self.states = states;
+                self.redraw_all();
+                self.generation -= 1;
+                self.births = 0;
+                self.deaths = 0;
+                self.still = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The number of edits that can be undone.
+    pub fn history_depth(&self) -> u32 {
+        self.history_depth as u32
+    }
+
+    /// Limit the number of edits that can be undone, forgetting the oldest
+    /// ones beyond it.
+    pub fn set_history_depth(&mut self, depth: u32) {
+        self.history_depth = depth as usize;
+        while self.undo.len() > self.history_depth {
+            self.undo.pop_front();
         }
+        self.redo.truncate(self.history_depth);
+    }
+
+    /// Restore the size and cells the universe had when it was constructed,
+    /// and reset the generation and statistics to zero.
+    pub fn reset(&mut self) {
+        self.rewind.clear();
+        let initial = self.initial.clone();
+        self.restore(initial);
+        self.generation = 0;
+        self.births = 0;
+        self.deaths = 0;
+        self.still = false;
     }
 
     pub fn width(&self) -> u32 {
@@ -154,70 +862,4524 @@ impl Universe {
         self.height
     }
 
-    pub fn toggle_cell(&mut self, row: u32, column: u32) {
-        let idx = self.get_index(row, column);
-        self.cells[idx].toggle();
+    /// The number of generations elapsed since the universe was created or
+    /// last resized.
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
-    pub fn init_canvas(&self, canvas: &web_sys::HtmlCanvasElement) {
-        canvas.set_width((Self::CELL_SIZE + 1) * self.width + 1);
-        canvas.set_height((Self::CELL_SIZE + 1) * self.height + 1);
+    /// Pointer to the current generation's cells, packed one bit per cell
+    /// into 64-bit words in row-major order. The cell at `row` and `col` is
+    /// bit `i % 8` of byte `i / 8`, where `i = row * width + col`. The
+    /// pointer changes after every `tick`.
+    pub fn cells(&self) -> *const u64 {
+        self.cells.as_ptr()
     }
 
-    pub fn draw_grid(&self, ctx: &web_sys::CanvasRenderingContext2d) {
-        ctx.set_stroke_style(JsValue::from_str(Self::GRID_COLOR).as_ref());
-        ctx.begin_path();
+    /// The current generation's cells as a `Uint8Array` over wasm memory,
+    /// packed the same way as `cells`. This is a view, not a copy: it goes
+    /// stale after the next `tick` or edit, and is detached if wasm memory
+    /// grows, so read it right away and don't keep it.
+    #[cfg(feature = "web")]
+    pub fn cells_view(&self) -> js_sys::Uint8Array {
+        unsafe { js_sys::Uint8Array::view(self.cells.as_bytes()) }
+    }
 
-        // Vertical lines.
-        for i in 0..self.width {
-            ctx.move_to((i * (Self::CELL_SIZE + 1) + 1) as f64, 0.0);
-            ctx.line_to((i * (Self::CELL_SIZE + 1) + 1) as f64, ((Self::CELL_SIZE + 1) * self.height + 1) as f64);
-        }
+    /// A copy of the current generation's cells, packed the same way as
+    /// `cells`. Safe to keep around.
+    pub fn cells_copy(&self) -> Vec<u8> {
+        self.cells.to_bytes()
+    }
 
-        // Horizontal lines.
-        for j in 0..self.height {
-            ctx.move_to(0.0, (j * (Self::CELL_SIZE + 1) + 1) as f64);
-            ctx.line_to(((Self::CELL_SIZE + 1) * self.width + 1) as f64, (j * (Self::CELL_SIZE + 1) + 1) as f64);
-        }
+    /// The live cells as interleaved `row, col` pairs, which is much smaller
+    /// than the whole board when it is sparse.
+    #[cfg(feature = "web")]
+    pub fn live_cells(&self) -> js_sys::Uint32Array {
+        js_sys::Uint32Array::from(&self.live_cell_coords()[..])
+    }
 
-        ctx.stroke();
+    /// The ants as interleaved `row, col, direction` triples, in the order
+    /// they move, with directions numbered as in `Direction`.
+    #[cfg(feature = "web")]
+    pub fn ants(&self) -> js_sys::Uint32Array {
+        let triples: Vec<u32> = self.ants.iter().flat_map(|ant| vec![ant.row, ant.col, ant.direction as u32]).collect();
+        js_sys::Uint32Array::from(&triples[..])
     }
 
-    pub fn draw_cells(&self, ctx: &web_sys::CanvasRenderingContext2d) {
-        // Alive cells.
-        ctx.set_fill_style(JsValue::from_str(Self::ALIVE_COLOR).as_ref());
-        for row in 0..self.height {
-            for col in 0..self.width {
-                let idx = self.get_index(row, col);
-                if self.cells[idx] != Cell::Alive {
-                    continue;
-                }
+    /// Call `callback` after every tick with an object of the new
+    /// `generation`, `population`, `births` and `deaths`. The universe is
+    /// busy during the call, so the callback can't use it; anything it
+    /// throws is logged to the console rather than stopping the tick.
+    #[cfg(feature = "web")]
+    pub fn set_tick_callback(&mut self, callback: js_sys::Function) {
+        self.tick_callback = Some(callback);
+    }
+
+    #[cfg(feature = "web")]
+    pub fn clear_tick_callback(&mut self) {
+        self.tick_callback = None;
+    }
+
+    /// Call `callback` after every tick with a `Uint32Array` of the indices
+    /// of the cells that changed, in increasing order. Called like the tick
+    /// callback, after it.
+    #[cfg(feature = "web")]
+    pub fn set_changed_callback(&mut self, callback: js_sys::Function) {
+        self.changed_callback = Some(callback);
+    }
+
+    #[cfg(feature = "web")]
+    pub fn clear_changed_callback(&mut self) {
+        self.changed_callback = None;
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> u32 {
+        self.live_cell_count()
+    }
+
+    /// The number of cells `iter_live` yields, for preallocating.
+    pub fn live_cell_count(&self) -> u32 {
+        self.iter_live().len() as u32
+    }
 
-                ctx.fill_rect(
-                    (col * (Self::CELL_SIZE + 1) + 1) as f64,
-                    (row * (Self::CELL_SIZE + 1) + 1) as f64,
-                    Self::CELL_SIZE as f64,
-                    Self::CELL_SIZE as f64
-                );
+    /// The number of live cells in a rectangle, which wraps around the edges
+    /// of the universe like `copy_region`'s, as does a corner past them. A
+    /// size larger than the universe is clamped to it.
+    pub fn count_live_in_rect(&self, row: u32, col: u32, height: u32, width: u32) -> u32 {
+        let (row, col) = (row % self.height, col % self.width);
+        let mut count = 0;
+        for r in 0..u32::min(height, self.height) {
+            for c in 0..u32::min(width, self.width) {
+                let idx = self.get_index((row + r) % self.height, (col + c) % self.width);
+                count += self.cells.get(idx) as u32;
             }
         }
+        count
+    }
 
-        // Dead cells.
-        ctx.set_fill_style(JsValue::from_str(Self::DEAD_COLOR).as_ref());
-        for row in 0..self.height {
-            for col in 0..self.width {
-                let idx = self.get_index(row, col);
-                if self.cells[idx] != Cell::Dead {
-                    continue;
-                }
-
-                ctx.fill_rect(
-                    (col * (Self::CELL_SIZE + 1) + 1) as f64,
-                    (row * (Self::CELL_SIZE + 1) + 1) as f64,
-                    Self::CELL_SIZE as f64,
-                    Self::CELL_SIZE as f64
-                );
+    /// The fraction of live cells in each `block`×`block` tile, row by row,
+    /// for minimaps. The tiles start at the top-left corner and the last
+    /// row and column of them are cut short where `block` doesn't divide
+    /// the size, so there are `ceil(height / block)` rows of
+    /// `ceil(width / block)`. A `block` of 0 counts as 1.
+    pub fn density_grid(&self, block: u32) -> Vec<f32> {
+        let block = block.max(1);
+        let rows = self.height.div_ceil(block);
+        let cols = self.width.div_ceil(block);
+        let mut counts = vec![0u32; (rows * cols) as usize];
+        for (row, col) in self.iter_live() {
+            counts[(row / block * cols + col / block) as usize] += 1;
+        }
+        let mut grid = Vec::with_capacity(counts.len());
+        for tile_row in 0..rows {
+            let tile_height = u32::min(block, self.height - tile_row * block);
+            for tile_col in 0..cols {
+                let tile_width = u32::min(block, self.width - tile_col * block);
+                let count = counts[(tile_row * cols + tile_col) as usize];
+                grid.push(count as f32 / (tile_height * tile_width) as f32);
             }
         }
+        grid
+    }
+
+    /// The number of cells born in the last generation.
+    pub fn last_births(&self) -> u32 {
+        self.births
+    }
+
+    /// The number of cells that died in the last generation.
+    pub fn last_deaths(&self) -> u32 {
+        self.deaths
+    }
+
+    /// Set the width of the universe.
+    ///
+    /// Resets all cells to the dead state and the generation and statistics
+    /// to zero. A width giving more cells than `resize` allows leaves the
+    /// universe as it was.
+    pub fn set_width(&mut self, width: u32) {
+        let height = self.height;
+        if self.resize(width, height, Anchor::TopLeft).is_ok() {
+            self.clear_cells();
+        }
+    }
+
+    /// Set the height of the universe.
+    ///
+    /// Resets all cells to the dead state and the generation and statistics
+    /// to zero. A height giving more cells than `resize` allows leaves the
+    /// universe as it was.
+    pub fn set_height(&mut self, height: u32) {
+        let width = self.width;
+        if self.resize(width, height, Anchor::TopLeft).is_ok() {
+            self.clear_cells();
+        }
+    }
+
+    /// Resize the universe, keeping the cells where the old and new grids
+    /// overlap. Growing pads with dead cells and shrinking crops, with
+    /// `anchor` deciding where the old content lands. The generation is
+    /// preserved.
+    ///
+    /// A size with more cells than a `u32` can count is an error, and
+    /// leaves the universe as it was.
+    pub fn resize(&mut self, width: u32, height: u32, anchor: Anchor) -> Result<(), String> {
+        let len = cell_count(width, height)?;
+        let (row_offset, col_offset) = match anchor {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Center => (
+                (height as i64 - self.height as i64) / 2,
+                (width as i64 - self.width as i64) / 2,
+            ),
+        };
+
+        let (cells, states) = self.moved_cells(len, width, |row, col| {
+            let new_row = row as i64 + row_offset;
+            let new_col = col as i64 + col_offset;
+            if new_row >= 0 && new_row < height as i64 && new_col >= 0 && new_col < width as i64 {
+                Some((new_row as u32, new_col as u32))
+            } else {
+                None
+            }
+        });
+        self.restore(Snapshot { width, height, cells, states });
+        self.rewind.clear();
+        Ok(())
+    }
+
+    /// Shrink the universe to the bounding box of the cells that aren't
+    /// dead, with `margin` dead cells around it, as an edit that can be
+    /// undone. The universe never grows, so the margin is narrower where the
+    /// board is too small for it. An empty board is left alone.
+    pub fn crop_to_content(&mut self, margin: u32) {
+        let (row, col, height, width) = match self.content_box() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let new_width = width.saturating_add(margin.saturating_mul(2)).min(self.width);
+        let new_height = height.saturating_add(margin.saturating_mul(2)).min(self.height);
+        let (top, left) = ((new_height - height) / 2, (new_width - width) / 2);
+        self.record_edit();
+        let len = new_width as usize * new_height as usize;
+        let mut cells = BitSet::new(len);
+        let mut states = self.states.as_ref().map(|_| vec![0; len]);
+        for r in 0..height {
+            for c in 0..width {
+                let from = self.get_index((row + r) % self.height, (col + c) % self.width);
+                let to = ((top + r) * new_width + left + c) as usize;
+                cells.set(to, self.cells.get(from));
+                if let (Some(states), Some(old)) = (states.as_mut(), self.states.as_ref()) {
+                    states[to] = old[from];
+                }
+            }
+        }
+        self.restore(Snapshot { width: new_width, height: new_height, cells, states });
+    }
+
+    /// Switch a cell between dead and alive, or in Wireworld, cycle it
+    /// from empty to conductor to electron head and back to empty.
+    pub fn toggle_cell(&mut self, row: u32, column: u32) {
+        self.record_edit();
+        let idx = self.get_index(row, column);
+        match self.mode {
+            Mode::Life | Mode::Immigration | Mode::Critters => self.cells.toggle(idx),
+            Mode::Wireworld => {
+                let state = wireworld::cycled(self.state(idx));
+                self.put_state(idx, state);
+            }
+        }
+    }
+
+    /// Like `toggle_cell`, but returns an error instead of touching the
+    /// wrong cell when the coordinates are off the board.
+    pub fn try_toggle_cell(&mut self, row: u32, column: u32) -> Result<(), String> {
+        self.check_bounds(row, column)?;
+        self.toggle_cell(row, column);
+        Ok(())
+    }
+
+    /// Set a single cell dead or alive.
+    pub fn set_cell(&mut self, row: u32, column: u32, cell: Cell) {
+        self.record_edit();
+        let idx = self.get_index(row, column);
+        self.put_state(idx, cell as u8);
+    }
+
+    /// Like `set_cell`, but returns an error if the coordinates are off the
+    /// board.
+    pub fn try_set_cell(&mut self, row: u32, column: u32, cell: Cell) -> Result<(), String> {
+        self.check_bounds(row, column)?;
+        self.set_cell(row, column, cell);
+        Ok(())
+    }
+
+    /// Set every cell on the straight line between two cells, both ends
+    /// included, dead or alive.
+    pub fn set_line(&mut self, r0: u32, c0: u32, r1: u32, c1: u32, cell: Cell) {
+        self.record_edit();
+        for (row, col) in raster::line(r0 as i64, c0 as i64, r1 as i64, c1 as i64) {
+            let idx = self.get_index(row as u32, col as u32);
+            self.put_state(idx, cell as u8);
+        }
+    }
+
+    /// Set every cell on the edges of a rectangle, given two opposite
+    /// corners, dead or alive.
+    pub fn set_rect_outline(&mut self, r0: u32, c0: u32, r1: u32, c1: u32, cell: Cell) {
+        self.set_raster(raster::rect(r0 as i64, c0 as i64, r1 as i64, c1 as i64, false), cell);
+    }
+
+    /// Set every cell in a rectangle, given two opposite corners, dead or
+    /// alive.
+    pub fn set_rect_filled(&mut self, r0: u32, c0: u32, r1: u32, c1: u32, cell: Cell) {
+        self.set_raster(raster::rect(r0 as i64, c0 as i64, r1 as i64, c1 as i64, true), cell);
+    }
+
+    /// Set the cells of an ellipse centered on a cell, reaching `rows`
+    /// cells above and below it and `cols` to either side, dead or alive:
+    /// all of them if `filled`, or else its outline. The part past the
+    /// edges wraps around on a torus and is left off when bounded.
+    pub fn set_ellipse(&mut self, row: u32, col: u32, rows: u32, cols: u32, filled: bool, cell: Cell) {
+        self.set_raster(raster::ellipse(row as i64, col as i64, rows as i64, cols as i64, filled), cell);
+    }
+
+    /// Set the cells a brush of `shape` covers centered on a cell, reaching
+    /// `radius` cells from it, dead or alive. The part past the edges wraps
+    /// or is left off as in `set_ellipse`.
+    pub fn set_disc(&mut self, row: u32, col: u32, radius: u32, shape: BrushShape, cell: Cell) {
+        let cells = brush::footprint(radius, shape).into_iter().map(|(r, c)| (row as i64 + r, col as i64 + c));
+        self.set_raster(cells.collect(), cell);
+    }
+
+    /// The rule in canonical `B3/S23` notation.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Set the rule from a rulestring in either `B3/S23` or `23/3` notation,
+    /// e.g. `B36/S23` for HighLife, with an optional number of states for a
+    /// Generations rule, e.g. `B2/S/3` for Brian's Brain.
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), String> {
+        self.rule = Rule::parse(rulestring)?;
+        self.schedule.clear();
+        #[cfg(feature = "web")]
+        {
+            self.dynamic_rule = None;
+        }
+        self.sync_states();
+        self.redraw_all();
+        Ok(())
+    }
+
+    /// Replace the rule with a two-state one worked out by the JavaScript
+    /// function `rule(alive, neighbors)`, which returns 1 if the cell is
+    /// alive in the next generation and 0 if not. Calling into JavaScript
+    /// for every cell would be slow, so it is called once for each
+    /// combination of `alive` and `neighbors` now and the answers kept in a
+    /// table, the one a rulestring describes: ticks run as fast as under
+    /// any other rule, and `rule()` reads back as, say, `B3/S23`.
+    ///
+    /// Neighbors are counted in the current rule's shape of neighborhood.
+    /// Anything `rule` throws is rethrown, leaving the rule as it was.
+    #[cfg(feature = "web")]
+    pub fn set_custom_rule(&mut self, rule: js_sys::Function) -> Result<(), JsValue> {
+        let kind = self.rule.neighborhood().kind;
+        self.rule = Rule::from_fn(kind, |alive, count| {
+            let next = rule.call2(&JsValue::NULL, &(alive as u8).into(), &count.into())?;
+            match next.as_f64() {
+                Some(next) => Ok(next != 0.0),
+                None => Err(JsValue::from_str(&format!("custom rule returned {:?}, not a number", next))),
+            }
+        })?;
+        self.dynamic_rule = None;
+        self.sync_states();
+        self.redraw_all();
+        Ok(())
+    }
+
+    /// Like `set_custom_rule`, but calls `rule(state, neighbors)` for every
+    /// cell on every tick instead of building a table, for rules whose
+    /// answer can change, like random ones. That is far slower. In Life,
+    /// `state` is any of the rule's states and `rule` returns the next one.
+    ///
+    /// If `rule` throws or returns something other than a state, the error
+    /// is logged, the board stays as it was for that tick, and ticks after
+    /// it go back to the universe's own rule. `set_rule` does too.
+    #[cfg(feature = "web")]
+    pub fn set_custom_rule_dynamic(&mut self, rule: js_sys::Function) {
+        self.dynamic_rule = Some(rule);
+    }
+
+    /// Put a Langton's ant on the board, heading `direction`. Each tick it
+    /// turns right on a dead cell or left on a live one, flips the cell,
+    /// and steps forward, wrapping around the edges whatever the topology.
+    /// Ants move one after another in the order they were added, each
+    /// seeing the cells flipped by those before it, and any number can
+    /// share a cell.
+    pub fn add_ant(&mut self, row: u32, column: u32, direction: Direction) -> Result<(), String> {
+        self.check_bounds(row, column)?;
+        self.ants.push(Ant { row, col: column, direction });
+        self.redraw_all();
+        Ok(())
+    }
+
+    /// Take away the ant at `index` in the order they move. Returns false
+    /// if there is no such ant.
+    pub fn remove_ant(&mut self, index: u32) -> bool {
+        if index as usize >= self.ants.len() {
+            return false;
+        }
+        self.ants.remove(index as usize);
+        self.redraw_all();
+        true
+    }
+
+    pub fn clear_ants(&mut self) {
+        self.ants.clear();
+        self.redraw_all();
+    }
+
+    pub fn ant_count(&self) -> u32 {
+        self.ants.len() as u32
+    }
+
+    pub fn ant_mode(&self) -> AntMode {
+        self.ant_mode
+    }
+
+    /// Whether ants walk while the rule runs, or with `AntMode::Alone`,
+    /// instead of it.
+    pub fn set_ant_mode(&mut self, mode: AntMode) {
+        self.ant_mode = mode;
+    }
+
+    /// The rule the next tick runs: the one set with `set_rule`, or the
+    /// one the schedule has reached.
+    pub fn current_rule(&self) -> String {
+        self.scheduled_rule().unwrap_or(self.rule).to_string()
+    }
+
+    /// Take turns between rules as in `set_rule_schedule`, given as an
+    /// array of `[rulestring, generations]` pairs.
+    #[cfg(feature = "web")]
+    #[wasm_bindgen(js_name = set_rule_schedule)]
+    pub fn set_rule_schedule_js(&mut self, schedule: js_sys::Array) -> Result<(), JsValue> {
+        let mut entries = Vec::with_capacity(schedule.length() as usize);
+        for entry in schedule.iter() {
+            let entry = js_sys::Array::from(&entry);
+            match (entry.get(0).as_string(), entry.get(1).as_f64()) {
+                (Some(rule), Some(generations)) if generations >= 0.0 => entries.push((rule, generations as u32)),
+                _ => return Err(JsValue::from_str("a rule schedule entry must be a [rulestring, generations] pair")),
+            }
+        }
+        self.set_rule_schedule(entries).map_err(JsValue::from)
+    }
+
+    /// Go back to the one rule in effect, as `set_rule` does.
+    pub fn clear_rule_schedule(&mut self) {
+        self.schedule.clear();
+    }
+
+    /// Add random events to every tick, each with a chance per cell: that
+    /// a birth fails, that a live cell dies anyway, and that a dead one
+    /// comes to life anyway. Chances are clamped to between 0 and 1. All
+    /// zero turns the noise off, and ticks run exactly as they do without
+    /// it. Only rules with two states are affected.
+    ///
+    /// The events come from a generator seeded with `set_noise_seed`, so
+    /// the same seed gives the same run.
+    pub fn set_noise(&mut self, birth_failure: f64, spontaneous_death: f64, spontaneous_birth: f64) {
+        self.noise = Noise::new(birth_failure, spontaneous_death, spontaneous_birth);
+    }
+
+    /// Restart the noise's random events from `seed`.
+    pub fn set_noise_seed(&mut self, seed: u64) {
+        self.noise_rng = Rng::new(seed);
+    }
+
+    /// The shape of the neighborhood the rule counts.
+    pub fn neighborhood(&self) -> NeighborhoodKind {
+        self.rule.neighborhood().kind
+    }
+
+    /// Count a different shape of neighborhood under the same rule, which
+    /// fails if the rule asks for more neighbors than the shape has, like
+    /// B5 with the four von Neumann neighbors. The rulestring records the
+    /// shape, as in `B3/S23V`.
+    pub fn set_neighborhood(&mut self, kind: NeighborhoodKind) -> Result<(), String> {
+        self.rule = self.rule.with_neighborhood(kind)?;
+        self.redraw_all();
+        Ok(())
+    }
+
+    /// How many states a cell can be in: 2, more for a Generations rule,
+    /// 4 in Wireworld or 3 in Immigration.
+    pub fn state_count(&self) -> u16 {
+        match self.mode {
+            Mode::Life => self.rule.states(),
+            Mode::Critters => 2,
+            Mode::Wireworld => wireworld::STATES,
+            Mode::Immigration => immigration::STATES,
+        }
+    }
+
+    /// The state of a cell: 0 for dead, 1 for alive, and 2 upwards for the
+    /// decaying states of a Generations rule. In Wireworld and Immigration,
+    /// the states are those in the `wireworld` and `immigration` modules.
+    pub fn cell_state(&self, row: u32, column: u32) -> u8 {
+        self.state(self.get_index(row, column))
+    }
+
+    /// How many of a cell's neighbors are alive, in the rule's
+    /// neighborhood and the board's topology.
+    pub fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
+        match self.topology {
+            _ if self.rule.neighborhood() != Neighborhood::moore() => {
+                let neighbors = self.neighbor_indices(row, column);
+                neighbors.into_iter().filter(|&idx| self.cells.get(idx)).count() as u8
+            }
+            Topology::Torus => self.torus_neighbor_count(row, column),
+            Topology::Bounded => self.bounded_neighbor_count(row, column),
+        }
+    }
+
+    /// Set a cell to any of the states in `state_count`.
+    pub fn set_cell_state(&mut self, row: u32, column: u32, state: u8) -> Result<(), String> {
+        self.check_bounds(row, column)?;
+        if u16::from(state) >= self.state_count() {
+            return Err(format!("state {} is out of range for {} states", state, self.state_count()));
+        }
+        self.record_edit();
+        let idx = self.get_index(row, column);
+        self.put_state(idx, state);
+        Ok(())
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Switch to another cellular automaton. Live cells stay alive, as
+    /// Wireworld's electron heads or Immigration's first color, and any
+    /// other states are cleared. Critters needs a board of even width and
+    /// height to cut into blocks.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), String> {
+        if mode == Mode::Critters && (!self.width.is_multiple_of(2) || !self.height.is_multiple_of(2)) {
+            return Err(format!("Critters needs an even width and height, not {}x{}", self.width, self.height));
+        }
+        self.switch_mode(mode);
+        Ok(())
+    }
+
+    /// Run the generation before this one, which only Critters, being
+    /// reversible, can do. Ticking and then running back leaves the board
+    /// exactly as it was.
+    pub fn tick_reverse(&mut self) -> Result<(), String> {
+        if self.mode != Mode::Critters {
+            return Err(format!("{} can't run backwards", self.mode.name()));
+        }
+        if self.generation == 0 {
+            return Err("there is no generation before generation 0".to_string());
+        }
+        if self.edited {
+            self.save_for_undo();
+            self.edited = false;
+        }
+        let mut next = mem::take(&mut self.next);
+        self.generation -= 1;
+        // The board kept for rewinding past the last tick, if there is one,
+        // is the one about to be worked out.
+        self.rewind.pop_back();
+        let inverse = margolus::inverse(&margolus::CRITTERS).expect("Critters is reversible");
+        let mut tally = Tally::new(mem::take(&mut self.changed));
+        self.tick_blocks(&mut next, &inverse, &mut tally);
+        self.apply_tally(tally);
+        self.finish_tick(next);
+        Ok(())
+    }
+
+    /// Stamp a Wireworld circuit with its top-left corner at the given
+    /// cell, switching to Wireworld if needed. Cells that fall off the edge
+    /// wrap around to the other side.
+    pub fn insert_wireworld_pattern(&mut self, pattern: WireworldPattern, row: u32, col: u32) {
+        self.switch_mode(Mode::Wireworld);
+        self.record_edit();
+        for (r, c, state) in pattern.cells() {
+            let idx = self.get_index((row % self.height + r) % self.height, (col % self.width + c) % self.width);
+            self.put_state(idx, state);
+        }
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm;
+    }
+
+    /// Create a universe sized exactly to a pattern in the RLE format,
+    /// running the rule in its header if it names one.
+    pub fn from_rle(rle: &str) -> Result<Universe, String> {
+        let mut universe = Universe::from_shape(&rle::parse(rle)?)?;
+        if let Some(rule) = rle::rule_field(rle) {
+            universe.set_rule(rule)?;
+        }
+        Ok(universe)
+    }
+
+    /// Insert a pattern in the RLE format with its top-left corner at
+    /// `row` and `col`, making its live cells alive. The pattern is
+    /// transformed first, so its bounding box always starts at `row` and
+    /// `col`.
+    ///
+    /// Placement wraps around the edges of the universe. A pattern larger
+    /// than the universe is rejected rather than clipped.
+    pub fn insert_rle(&mut self, row: u32, col: u32, rle: &str, transform: Transform) -> Result<(), String> {
+        let shape = rle::parse(rle)?;
+        self.insert_shape(row, col, &transform.apply(&shape))
+    }
+
+    /// Stamp a pattern from the library with its top-left corner at `row`
+    /// and `col`. The pattern is transformed and placed like `insert_rle`.
+    pub fn insert_pattern(&mut self, pattern: Pattern, row: u32, col: u32, transform: Transform) -> Result<(), String> {
+        self.insert_shape(row, col, &transform.apply(&pattern.shape()))
+    }
+
+    /// Stamp the pattern in a file named `name`, in the RLE, plaintext or
+    /// Life 1.06 format, centered on the board. The format is chosen by the
+    /// file's extension, or else its contents. A pattern larger than the
+    /// board grows it, keeping the board's cells centered too.
+    pub fn insert_pattern_file(&mut self, name: &str, text: &str) -> Result<(), String> {
+        let shape = pattern_file::parse(name, text)?;
+        let (width, height) = (self.width.max(shape.width), self.height.max(shape.height));
+        cell_count(width, height)?;
+        self.record_edit();
+        if (width, height) != (self.width, self.height) {
+            self.resize(width, height, Anchor::Center)?;
+        }
+        let row = (self.height - shape.height) / 2;
+        let col = (self.width - shape.width) / 2;
+        for &(r, c) in shape.cells.iter() {
+            let idx = self.get_index(row + r, col + c);
+            self.cells.set(idx, true);
+        }
+        Ok(())
+    }
+
+    /// Copy a rectangle of cells into a new universe with the same rule,
+    /// mode and topology, and the cells in the same states. The rectangle
+    /// wraps around the edges of the universe, and a size larger than the
+    /// universe is clamped to it.
+    pub fn copy_region(&self, row: u32, col: u32, height: u32, width: u32) -> Universe {
+        let (row, col) = (row % self.height, col % self.width);
+        let (height, width) = (height.min(self.height), width.min(self.width));
+        let mut copy = Universe::empty(width, height).expect("a region fits in its universe");
+        copy.rule = self.rule;
+        copy.topology = self.topology;
+        // A piece of a Critters board too odd to cut into blocks runs Life,
+        // which has the same two states.
+        let _ = copy.set_mode(self.mode);
+        copy.sync_states();
+        for r in 0..height {
+            for c in 0..width {
+                let idx = copy.get_index(r, c);
+                copy.put_state(idx, self.state(self.get_index((row + r) % self.height, (col + c) % self.width)));
+            }
+        }
+        copy.save_initial();
+        copy
+    }
+
+    /// Paste another universe with its top-left corner at `row` and `col`,
+    /// combining cells according to `mode`. Placement wraps around the edges
+    /// of the universe, and the part of `other` that does not fit is
+    /// clipped. Cells keep their states if both universes run the same mode
+    /// and number of states; otherwise only live cells are pasted, in state
+    /// 1.
+    pub fn paste(&mut self, other: &Universe, row: u32, col: u32, mode: PasteMode) {
+        self.record_edit();
+        self.paste_cells(other, row, col, mode);
+    }
+
+    /// Kill every cell in a rectangle, which wraps around the edges of the
+    /// universe like `copy_region`'s.
+    pub fn clear_region(&mut self, row: u32, col: u32, height: u32, width: u32) {
+        self.record_edit();
+        self.clear_cells_in(row, col, height, width);
+    }
+
+    /// Move the cells in a rectangle so its top-left corner is at `to_row`
+    /// and `to_col`, overwriting what was there. The same as cutting the
+    /// rectangle and pasting it back, but undone in one step.
+    pub fn move_region(&mut self, row: u32, col: u32, height: u32, width: u32, to_row: u32, to_col: u32) {
+        let contents = self.copy_region(row, col, height, width);
+        self.record_edit();
+        self.clear_cells_in(row, col, height, width);
+        self.paste_cells(&contents, to_row, to_col, PasteMode::Overwrite);
+    }
+
+    fn clear_cells_in(&mut self, row: u32, col: u32, height: u32, width: u32) {
+        let (row, col) = (row % self.height, col % self.width);
+        for r in 0..u32::min(height, self.height) {
+            for c in 0..u32::min(width, self.width) {
+                let idx = self.get_index((row + r) % self.height, (col + c) % self.width);
+                self.put_state(idx, 0);
+            }
+        }
+    }
+
+    fn paste_cells(&mut self, other: &Universe, row: u32, col: u32, mode: PasteMode) {
+        let same_states = other.mode == self.mode && other.state_count() == self.state_count();
+        let (row, col) = (row % self.height, col % self.width);
+        for r in 0..u32::min(other.height, self.height) {
+            for c in 0..u32::min(other.width, self.width) {
+                let from = other.get_index(r, c);
+                let src = if same_states { other.state(from) } else { other.cells.get(from) as u8 };
+                let idx = self.get_index((row + r) % self.height, (col + c) % self.width);
+                let dst = self.state(idx);
+                // As on bits, with any state but 0 standing for alive.
+                let state = match mode {
+                    PasteMode::Overwrite => src,
+                    PasteMode::Or if src != 0 => src,
+                    PasteMode::Or => dst,
+                    PasteMode::Xor if src == 0 => dst,
+                    PasteMode::Xor if dst == 0 => src,
+                    PasteMode::Xor => 0,
+                    PasteMode::And if src != 0 => dst,
+                    PasteMode::And => 0,
+                };
+                self.put_state(idx, state);
+            }
+        }
+    }
+
+    /// Move every live cell `drow` rows down and `dcol` columns right. Cells
+    /// wrap around on a torus and fall off the edge when bounded.
+    pub fn translate(&mut self, drow: i32, dcol: i32) {
+        self.record_edit();
+        let (height, width) = (self.height as i64, self.width as i64);
+        let topology = self.topology;
+        let (cells, states) = self.moved_cells(self.cells.len(), self.width, |row, col| {
+            let mut new_row = row as i64 + drow as i64;
+            let mut new_col = col as i64 + dcol as i64;
+            match topology {
+                Topology::Torus => {
+                    new_row = new_row.rem_euclid(height);
+                    new_col = new_col.rem_euclid(width);
+                }
+                Topology::Bounded => {
+                    if new_row < 0 || new_row >= height || new_col < 0 || new_col >= width {
+                        return None;
+                    }
+                }
+            }
+            Some((new_row as u32, new_col as u32))
+        });
+        self.next = mem::replace(&mut self.cells, cells);
+        self.states = states;
+    }
+
+    /// Write the live cells in the RLE format, cropped to their bounding box.
+    ///
+    /// A universe with no live cells produces a zero-extent pattern.
+    pub fn to_rle(&self) -> String {
+        rle::write(&self.live_shape(), &self.rule)
+    }
+
+    /// Write a rectangular region of the universe in the RLE format. The
+    /// region wraps around the edges of the universe like `insert_rle`.
+    pub fn region_to_rle(&self, row: u32, col: u32, height: u32, width: u32) -> String {
+        rle::write(&self.region_shape(row, col, height, width), &self.rule)
+    }
+
+    /// Write the live cells in the given format, cropped to their bounding
+    /// box.
+    pub fn export(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Rle => self.to_rle(),
+            ExportFormat::Plaintext => self.to_plaintext(),
+            ExportFormat::Life106 => self.to_life106(),
+        }
+    }
+
+    /// A name for a file of the board in the given format, with its size
+    /// and generation, like `life-128x128-gen450.rle`.
+    pub fn export_file_name(&self, format: ExportFormat) -> String {
+        self.file_name(format.extension())
+    }
+
+    /// Create a universe sized exactly to a pattern in the plaintext
+    /// `.cells` format.
+    pub fn from_plaintext(text: &str) -> Result<Universe, String> {
+        plaintext::parse(text).and_then(|shape| Universe::from_shape(&shape))
+    }
+
+    /// Write the live cells in the plaintext `.cells` format, cropped to
+    /// their bounding box.
+    pub fn to_plaintext(&self) -> String {
+        plaintext::write(&self.live_shape())
+    }
+
+    /// Create a universe sized exactly to a pattern in the Life 1.06
+    /// format.
+    pub fn from_life106(text: &str) -> Result<Universe, String> {
+        life106::parse(text).and_then(|shape| Universe::from_shape(&shape))
+    }
+
+    /// Write the live cells in the Life 1.06 format, relative to the
+    /// top-left corner of their bounding box.
+    pub fn to_life106(&self) -> String {
+        life106::write(&self.live_shape())
+    }
+}
+
+impl Universe {
+    /// The next generation under the rule, a cell for each on the board in
+    /// `out`, without ticking. Noise and ants, which only change the board
+    /// as it's ticked, are left out, as is a rule schedule's next rule.
+    pub fn compute_next(&self, out: &mut [Cell]) -> Result<(), String> {
+        if out.len() != self.cells.len() {
+            return Err(format!("{} cells don't fit in a buffer of {}", self.cells.len(), out.len()));
+        }
+        let mut next = BitSet::new(self.cells.len());
+        self.compute_next_into(&mut next, &mut Tally::new(vec![]));
+        for (idx, cell) in out.iter_mut().enumerate() {
+            *cell = if next.get(idx) { Cell::Alive } else { Cell::Dead };
+        }
+        Ok(())
+    }
+
+    /// A hash of the live cells alone, for noticing a board come back to
+    /// an earlier state.
+    pub(crate) fn cells_fingerprint(&self) -> u64 {
+        self.cells.fingerprint()
+    }
+
+    /// A hash of the size, generation and cells, for cheaply noticing that
+    /// the board changed.
+    #[cfg(feature = "web")]
+    pub(crate) fn fingerprint(&self) -> u64 {
+        let size = ((self.width as u64) << 32 | self.height as u64).wrapping_mul(0x0100_0000_01b3);
+        self.cells.fingerprint() ^ size ^ self.generation.rotate_left(17)
+    }
+
+    /// A file name with the board's size and generation and the given
+    /// extension.
+    pub(crate) fn file_name(&self, extension: &str) -> String {
+        format!("life-{}x{}-gen{}.{}", self.width, self.height, self.generation, extension)
+    }
+
+    /// Replace the board with a saved one, packed like `cells_copy`, as an
+    /// edit that can be undone. Nothing changes if the rule is malformed or
+    /// the bytes don't hold a board of the given size.
+    #[cfg(any(feature = "web", test))]
+    pub(crate) fn load_saved(&mut self, width: u32, height: u32, generation: u64, rule: &str, bytes: &[u8]) -> Result<(), String> {
+        let rule = Rule::parse(rule)?;
+        if width == 0 || height == 0 || width.checked_mul(height).is_none() {
+            return Err(format!("a board of {}x{} cells can't be loaded", width, height));
+        }
+        let cells = BitSet::from_bytes(width as usize * height as usize, bytes)
+            .ok_or_else(|| format!("{} bytes do not hold a {}x{} board", bytes.len(), width, height))?;
+        self.record_edit();
+        self.restore(Snapshot { width, height, cells, states: None });
+        self.rule = rule;
+        self.generation = generation;
+        self.births = 0;
+        self.deaths = 0;
+        self.still = false;
+        Ok(())
+    }
+
+    /// The size, rule and cells as a compact string that can go in a URL
+    /// hash, like `life=64x64.B3/S23.AAAg`. The cells are packed like
+    /// `cells_copy`, without the trailing empty bytes, in URL-safe base64.
+    #[cfg(any(feature = "web", test))]
+    pub fn encode_to_hash(&self) -> String {
+        let mut bytes = self.cells_copy();
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        format!("{}{}x{}.{}.{}", HASH_PREFIX, self.width, self.height, self.rule, base64::encode_url(&bytes))
+    }
+
+    /// Replace the board with one from `encode_to_hash`, with or without the
+    /// leading `#`, as an edit that can be undone. Nothing changes if the
+    /// hash is malformed.
+    #[cfg(any(feature = "web", test))]
+    pub(crate) fn load_hash(&mut self, hash: &str) -> Result<(), String> {
+        let malformed = || format!("'{}' is not a board", hash);
+        let encoded = hash.trim_start_matches('#').strip_prefix(HASH_PREFIX).ok_or_else(malformed)?;
+        // Larger than Life rules have dots of their own.
+        let (size, rule, cells) = match encoded.split_once('.').and_then(|(size, rest)| Some((size, rest.rsplit_once('.')?))) {
+            Some((size, (rule, cells))) => (size, rule, cells),
+            None => return Err(malformed()),
+        };
+        let (width, height) = match size.split_once('x') {
+            Some((width, height)) => (
+                width.parse::<u32>().map_err(|_| malformed())?,
+                height.parse::<u32>().map_err(|_| malformed())?,
+            ),
+            None => return Err(malformed()),
+        };
+        let mut bytes = base64::decode_url(cells)?;
+        let len = (width as usize * height as usize).div_ceil(8);
+        if bytes.len() > len {
+            return Err(format!("{} bytes do not hold a {}x{} board", bytes.len(), width, height));
+        }
+        bytes.resize(len, 0);
+        self.load_saved(width, height, 0, rule, &bytes)
+    }
+
+    /// Call the tick and changed-cells callbacks, if there are any.
+    #[cfg(feature = "web")]
+    fn notify_tick(&self) {
+        let report = |result: Result<JsValue, JsValue>| {
+            if let Err(error) = result {
+                web_sys::console::error_2(&"tick callback threw:".into(), &error);
+            }
+        };
+        if let Some(ref callback) = self.tick_callback {
+            let stats = js_sys::Object::new();
+            let fields = [
+                ("generation", self.generation as f64),
+                ("population", self.population() as f64),
+                ("births", self.births as f64),
+                ("deaths", self.deaths as f64),
+            ];
+            for &(key, value) in fields.iter() {
+                js_sys::Reflect::set(&stats, &key.into(), &value.into()).unwrap();
+            }
+            report(callback.call1(&JsValue::NULL, &stats));
+        }
+        if let Some(ref callback) = self.changed_callback {
+            let changed: Vec<u32> = self.next.xor(&self.cells).ones().map(|idx| idx as u32).collect();
+            report(callback.call1(&JsValue::NULL, &js_sys::Uint32Array::from(&changed[..])));
+        }
+    }
+
+    /// Replace the board with one computed elsewhere, in the layout of
+    /// `cells_copy`, as if it had ticked to `generation`. Changed cells
+    /// are tracked for drawing as they are by `tick`.
+    #[cfg(any(feature = "web", test))]
+    pub(crate) fn load(&mut self, width: u32, height: u32, generation: u64, bytes: &[u8]) -> Result<(), String> {
+        let len = width as usize * height as usize;
+        let cells = BitSet::from_bytes(len, bytes)
+            .ok_or_else(|| format!("{} bytes do not hold a {}x{} board", bytes.len(), width, height))?;
+        if self.edited {
+            self.save_for_undo();
+            self.edited = false;
+        }
+        if (width, height) == (self.width, self.height) {
+            let before = mem::replace(&mut self.cells, cells);
+            self.ticked_population = None;
+            self.births = 0;
+            self.deaths = 0;
+            for idx in before.xor(&self.cells).ones() {
+                if self.cells.get(idx) {
+                    self.births += 1;
+                } else {
+                    self.deaths += 1;
+                    if let Some(ref mut trail) = self.trail {
+                        trail.record_death(idx, generation.saturating_sub(1));
+                    }
+                }
+                self.changed.push(idx as u32);
+            }
+            self.still = self.births == 0 && self.deaths == 0;
+            if self.changed.len() > len / 4 {
+                self.redraw_all();
+            }
+            // Cells that were alive before and after count as alive all
+            // along, which they may not have been.
+            let elapsed = generation.saturating_sub(self.generation).min(u16::MAX as u64) as u16;
+            if let Some(ref mut ages) = self.ages {
+                for (idx, age) in ages.iter_mut().enumerate() {
+                    *age = match (before.get(idx), self.cells.get(idx)) {
+                        (true, true) => age.saturating_add(elapsed),
+                        (false, true) => 1,
+                        (_, false) => 0,
+                    };
+                }
+            }
+            if let Some(ref mut heatmap) = self.heatmap {
+                heatmap.record(&before, &self.cells);
+            }
+        } else {
+            self.restore(Snapshot { width, height, cells, states: None });
+            self.births = 0;
+            self.deaths = 0;
+            self.still = false;
+        }
+        self.generation = generation;
+        Ok(())
+    }
+
+    /// Whether the board has died out, or the last tick left it exactly as
+    /// it was. Oscillators never settle, since each tick changes them.
+    pub fn settled(&self) -> Option<Settled> {
+        if self.population() == 0 {
+            Some(Settled::Extinct)
+        } else if self.generation > 0 && self.still {
+            Some(Settled::Stable)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn get_index(&self, row: u32, column: u32) -> usize {
+        debug_assert!(row < self.height && column < self.width, "cell ({}, {}) is outside the {}x{} universe", row, column, self.width, self.height);
+        (row * self.width + column) as usize
+    }
+
+    /// The smallest rectangle holding every live cell, as its top row, left
+    /// column, height and width, or `None` if there are none.
+    ///
+    /// On a torus a pattern straddling an edge is boxed across the seam, so
+    /// the rectangle can run past the bottom or right edge and wrap around,
+    /// like `region_to_rle`. Each axis takes the smallest span that covers
+    /// its live cells, preferring one that doesn't wrap when there is a tie.
+    pub fn bounding_box(&self) -> Option<(u32, u32, u32, u32)> {
+        self.box_around(self.cells.ones())
+    }
+
+    /// The mean row and column of the live cells, or `None` if there are
+    /// none.
+    ///
+    /// On a torus each axis is averaged as an angle around the circle it
+    /// wraps into, so a pattern crossing a seam is centered across it
+    /// rather than half a board away. When the cells are spread evenly all
+    /// the way round, that angle is meaningless and the plain mean is used.
+    pub fn center_of_mass(&self) -> Option<(f64, f64)> {
+        let count = self.iter_live().len();
+        if count == 0 {
+            return None;
+        }
+        let mut rows = MeanAccumulator::new(self.height);
+        let mut cols = MeanAccumulator::new(self.width);
+        for (row, col) in self.iter_live() {
+            rows.add(row);
+            cols.add(col);
+        }
+        let wrap = self.topology == Topology::Torus;
+        Some((rows.mean(count, wrap), cols.mean(count, wrap)))
+    }
+
+    /// The ants, in the order they move.
+    pub fn iter_ants(&self) -> impl ExactSizeIterator<Item = &Ant> + '_ {
+        self.ants.iter()
+    }
+
+    /// The row and column of every live cell, in row-major order.
+    pub fn iter_live(&self) -> impl ExactSizeIterator<Item = (u32, u32)> + '_ {
+        let width = self.width;
+        self.cells.ones().map(move |idx| (idx as u32 / width, idx as u32 % width))
+    }
+
+    /// The row and column of every cell that isn't dead, in row-major
+    /// order: the live cells, and those in any other state but 0.
+    #[cfg(any(feature = "web", test))]
+    pub(crate) fn iter_occupied(&self) -> Box<dyn Iterator<Item = (u32, u32)> + '_> {
+        let width = self.width;
+        match self.states {
+            Some(ref states) => Box::new(
+                states.iter().enumerate().filter(|&(_, &state)| state != 0).map(move |(idx, _)| (idx as u32 / width, idx as u32 % width)),
+            ),
+            None => Box::new(self.iter_live()),
+        }
+    }
+
+    #[cfg(any(feature = "web", test))]
+    fn live_cell_coords(&self) -> Vec<u32> {
+        let mut coords = Vec::with_capacity(self.iter_live().len() * 2);
+        for (row, col) in self.iter_live() {
+            coords.push(row);
+            coords.push(col);
+        }
+        coords
+    }
+
+    /// Continue a painted stroke from one cell to the next, setting cells to
+    /// `state`, as part of the same undoable edit as the stroke's first
+    /// cell. The line is the one `stroke_line` gives.
+    #[cfg(any(feature = "web", test))]
+    pub(crate) fn extend_line(&mut self, from: (u32, u32), to: (u32, u32), state: u8) {
+        let cells = self.stroke_line(from, to);
+        self.paint_cells(&cells, state);
+    }
+
+    /// The cells on a line from one cell to another, both included, for a
+    /// painted stroke. The line takes the short way around the edges, so
+    /// dragging across the seam of a panned view doesn't paint across the
+    /// whole board.
+    #[cfg(any(feature = "web", test))]
+    pub(crate) fn stroke_line(&self, from: (u32, u32), to: (u32, u32)) -> Vec<(u32, u32)> {
+        let (height, width) = (self.height as i64, self.width as i64);
+        let shortest = |from: u32, to: u32, size: i64| {
+            let delta = (to as i64 - from as i64).rem_euclid(size);
+            if delta > size / 2 { delta - size } else { delta }
+        };
+        let drow = shortest(from.0, to.0, height);
+        let dcol = shortest(from.1, to.1, width);
+        let (r0, c0) = (from.0 as i64, from.1 as i64);
+        raster::line(r0, c0, r0 + drow, c0 + dcol)
+            .into_iter()
+            .map(|(row, col)| (row.rem_euclid(height) as u32, col.rem_euclid(width) as u32))
+            .collect()
+    }
+
+    /// Set cells to `state` as part of the same undoable edit as the last,
+    /// for painting a stroke a bit at a time.
+    #[cfg(any(feature = "web", test))]
+    pub(crate) fn paint_cells(&mut self, cells: &[(u32, u32)], state: u8) {
+        for &(row, col) in cells {
+            let idx = self.get_index(row, col);
+            self.put_state(idx, state);
+        }
+        self.rewind.clear();
+        self.redraw_all();
+        self.edited = true;
+    }
+
+    /// Where a cell given by coordinates that may be past the edges is on
+    /// the board: wrapped around on a torus, and nowhere when bounded.
+    pub(crate) fn on_board(&self, row: i64, col: i64) -> Option<(u32, u32)> {
+        let (height, width) = (self.height as i64, self.width as i64);
+        match self.topology {
+            Topology::Torus => Some((row.rem_euclid(height) as u32, col.rem_euclid(width) as u32)),
+            Topology::Bounded if row >= 0 && row < height && col >= 0 && col < width => Some((row as u32, col as u32)),
+            Topology::Bounded => None,
+        }
+    }
+
+    /// Set the cells from `raster`, placed by `on_board`, in one edit.
+    fn set_raster(&mut self, cells: Vec<(i64, i64)>, cell: Cell) {
+        self.record_edit();
+        for (row, col) in cells {
+            if let Some((row, col)) = self.on_board(row, col) {
+                let idx = self.get_index(row, col);
+                self.put_state(idx, cell as u8);
+            }
+        }
+    }
+
+    pub(crate) fn check_bounds(&self, row: u32, column: u32) -> Result<(), String> {
+        if row >= self.height {
+            return Err(format!("row {} is outside the universe, which has {} rows", row, self.height));
+        }
+        if column >= self.width {
+            return Err(format!("column {} is outside the universe, which has {} columns", column, self.width));
+        }
+        Ok(())
+    }
+
+    fn bounded_neighbor_count(&self, row: u32, column: u32) -> u8 {
+        let mut count = 0;
+        for r in row.saturating_sub(1)..=u32::min(row + 1, self.height - 1) {
+            for c in column.saturating_sub(1)..=u32::min(column + 1, self.width - 1) {
+                if r != row || c != column {
+                    count += self.cells.get(self.get_index(r, c)) as u8;
+                }
+            }
+        }
+        count
+    }
+
+    fn torus_neighbor_count(&self, row: u32, column: u32) -> u8 {
+        let mut count = 0;
+
+        let north = if row == 0 {
+            self.height - 1
+        } else {
+            row - 1
+        };
+
+        let south = if row == self.height - 1 {
+            0
+        } else {
+            row + 1
+        };
+
+        let west = if column == 0 {
+            self.width - 1
+        } else {
+            column - 1
+        };
+
+        let east = if column == self.width - 1 {
+            0
+        } else {
+            column + 1
+        };
+
+        let nw = self.get_index(north, west);
+        count += self.cells.get(nw) as u8;
+
+        let n = self.get_index(north, column);
+        count += self.cells.get(n) as u8;
+
+        let ne = self.get_index(north, east);
+        count += self.cells.get(ne) as u8;
+
+        let w = self.get_index(row, west);
+        count += self.cells.get(w) as u8;
+
+        let e = self.get_index(row, east);
+        count += self.cells.get(e) as u8;
+
+        let sw = self.get_index(south, west);
+        count += self.cells.get(sw) as u8;
+
+        let s = self.get_index(south, column);
+        count += self.cells.get(s) as u8;
+
+        let se = self.get_index(south, east);
+        count += self.cells.get(se) as u8;
+
+        count
+    }
+
+    /// Count the live neighbors of a cell that is not on an edge.
+    #[inline]
+    fn interior_neighbor_count(&self, idx: usize) -> u8 {
+        let width = self.width as usize;
+        let cells = &self.cells;
+        cells.get(idx - width - 1) as u8
+            + cells.get(idx - width) as u8
+            + cells.get(idx - width + 1) as u8
+            + cells.get(idx - 1) as u8
+            + cells.get(idx + 1) as u8
+            + cells.get(idx + width - 1) as u8
+            + cells.get(idx + width) as u8
+            + cells.get(idx + width + 1) as u8
+    }
+
+    /// Work out the next generation under the rule into `next`, tallying
+    /// what changes, without changing anything yet.
+    fn compute_next_into(&self, next: &mut BitSet, tally: &mut Tally) {
+        match self.algorithm {
+            _ if !self.ants.is_empty() && self.ant_mode == AntMode::Alone => next.clone_from(&self.cells),
+            _ if self.mode == Mode::Critters => self.tick_blocks(next, &margolus::CRITTERS, tally),
+            #[cfg(feature = "web")]
+            _ if self.dynamic_rule.is_some() && self.mode == Mode::Life => self.tick_dynamic(next, tally),
+            // Only the general path knows about other states and wider
+            // neighborhoods.
+            _ if self.states.is_some() || self.counts_wide_neighborhoods() => self.tick_states(next, tally),
+            Algorithm::CellByCell => {
+                // Interior cells never wrap, so their neighbors are at fixed
+                // offsets.
+                if cfg!(feature = "simd") {
+                    self.tick_interior_simd(next, tally);
+                } else {
+                    self.tick_interior(next, tally);
+                }
+                self.tick_edges(next, tally);
+            }
+            Algorithm::BitParallel => self.tick_bit_parallel(next, tally),
+        }
+    }
+
+    /// Make `next`, worked out by `compute_next_into`, the current
+    /// generation, then let noise and ants change it.
+    fn commit(&mut self, mut next: BitSet, tally: Tally) {
+        self.apply_tally(tally);
+        if !self.noise.is_quiet() && self.states.is_none() {
+            self.add_noise(&mut next);
+        }
+        if !self.ants.is_empty() {
+            self.move_ants(&mut next);
+        }
+        self.generation += 1;
+        self.finish_tick(next);
+    }
+
+    /// Take on the births, deaths, changed cells and states of a generation
+    /// about to become the current one.
+    fn apply_tally(&mut self, tally: Tally) {
+        self.births = tally.births;
+        self.deaths = tally.deaths;
+        self.still = !tally.altered && tally.births == 0 && tally.deaths == 0;
+        self.changed = tally.changed;
+        if let Some(states) = tally.states {
+            self.states = Some(states);
+        }
+        #[cfg(feature = "web")]
+        if tally.rule_failed {
+            self.dynamic_rule = None;
+        }
+    }
+
+    /// Cells on the edges wrap around to the opposite side.
+    fn tick_edges(&self, next: &mut BitSet, tally: &mut Tally) {
+        for row in 0..self.height {
+            if row == 0 || row == self.height - 1 {
+                for col in 0..self.width {
+                    self.update_edge_cell(row, col, next, tally);
+                }
+            } else if self.width > 0 {
+                self.update_edge_cell(row, 0, next, tally);
+                if self.width > 1 {
+                    self.update_edge_cell(row, self.width - 1, next, tally);
+                }
+            }
+        }
+    }
+
+    /// The whole board with `bitparallel`, a row at a time, then the births
+    /// and deaths from the words that changed.
+    fn tick_bit_parallel(&self, next: &mut BitSet, tally: &mut Tally) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        if width == 0 || height == 0 {
+            return;
+        }
+        let wrap = self.topology == Topology::Torus;
+        let read = |cells: &BitSet, row: usize, words: &mut Vec<u64>| cells.read_bits(row * width, width, words);
+        let empty = vec![0; width.div_ceil(64)];
+        let (mut above, mut row, mut below, mut out) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        if wrap {
+            read(&self.cells, height - 1, &mut above);
+        } else {
+            above.clone_from(&empty);
+        }
+        read(&self.cells, 0, &mut row);
+        for r in 0..height {
+            if r + 1 < height {
+                read(&self.cells, r + 1, &mut below);
+            } else if wrap {
+                read(&self.cells, 0, &mut below);
+            } else {
+                below.clone_from(&empty);
+            }
+            let rows = bitparallel::Rows { above: &above, row: &row, below: &below, width, wrap };
+            bitparallel::next_row(&rows, &self.rule, &mut out);
+            next.write_bits(r * width, width, &out);
+
+            for (k, (&old, &new)) in row.iter().zip(out.iter()).enumerate() {
+                let mut changes = old ^ new;
+                tally.births += (changes & new).count_ones();
+                tally.deaths += (changes & old).count_ones();
+                if self.needs_full_redraw {
+                    continue;
+                }
+                let start = (r * width + k * 64) as u32;
+                while changes != 0 {
+                    tally.changed.push(start + changes.trailing_zeros());
+                    changes &= changes - 1;
+                }
+            }
+
+            mem::swap(&mut above, &mut row);
+            mem::swap(&mut row, &mut below);
+        }
+    }
+
+    fn tick_interior(&self, next: &mut BitSet, tally: &mut Tally) {
+        for row in 1..self.height.saturating_sub(1) {
+            for col in 1..self.width.saturating_sub(1) {
+                let idx = self.get_index(row, col);
+                let live_neighbors = self.interior_neighbor_count(idx);
+                self.update_cell(idx, live_neighbors, next, tally);
+            }
+        }
+    }
+
+    /// `tick_interior`, sixteen cells at a time. Each row and its neighbors
+    /// are unpacked to a byte per cell, and the columns left over at the end
+    /// of a row take the scalar path.
+    fn tick_interior_simd(&self, next: &mut BitSet, tally: &mut Tally) {
+        let width = self.width as usize;
+        if self.height < 3 || width < simd::LANES + 2 {
+            return self.tick_interior(next, tally);
+        }
+        let rules = simd::RuleTables::new(&self.rule);
+        let unpack = |cells: &BitSet, row: usize, bytes: &mut Vec<u8>| {
+            bytes.clear();
+            bytes.extend((row * width..(row + 1) * width).map(|idx| cells.get(idx) as u8));
+        };
+        let (mut above, mut middle, mut below) = (Vec::new(), Vec::new(), Vec::new());
+        unpack(&self.cells, 0, &mut middle);
+        unpack(&self.cells, 1, &mut below);
+        for row in 1..self.height as usize - 1 {
+            mem::swap(&mut above, &mut middle);
+            mem::swap(&mut middle, &mut below);
+            unpack(&self.cells, row + 1, &mut below);
+
+            let mut col = 1;
+            while col + simd::LANES < width {
+                let states = simd::next_states(&rules, &above, &middle, &below, col);
+                let mut changes = states ^ simd::pack(&middle, col);
+                let start = row * width + col;
+                for i in 0..simd::LANES {
+                    next.set(start + i, states >> i & 1 != 0);
+                }
+                while changes != 0 {
+                    let i = changes.trailing_zeros() as usize;
+                    changes &= changes - 1;
+                    if states >> i & 1 != 0 {
+                        tally.births += 1;
+                    } else {
+                        tally.deaths += 1;
+                    }
+                    if !self.needs_full_redraw {
+                        tally.changed.push((start + i) as u32);
+                    }
+                }
+                col += simd::LANES;
+            }
+            for col in col..width - 1 {
+                let idx = row * width + col;
+                let live_neighbors = self.interior_neighbor_count(idx);
+                self.update_cell(idx, live_neighbors, next, tally);
+            }
+        }
+    }
+
+    /// Write the next state of the cell at `idx` into `next`, counting it
+    /// if it is born or dies.
+    #[inline]
+    fn update_cell(&self, idx: usize, live_neighbors: u8, next: &mut BitSet, tally: &mut Tally) {
+        let cell = self.cell(idx);
+        let next_cell = self.rule.next(cell, live_neighbors);
+        match (cell, next_cell) {
+            (Cell::Dead, Cell::Alive) => tally.births += 1,
+            (Cell::Alive, Cell::Dead) => tally.deaths += 1,
+            _ => return next.set(idx, next_cell == Cell::Alive),
+        }
+        if !self.needs_full_redraw {
+            tally.changed.push(idx as u32);
+        }
+        next.set(idx, next_cell == Cell::Alive);
+    }
+
+    fn update_edge_cell(&self, row: u32, col: u32, next: &mut BitSet, tally: &mut Tally) {
+        let idx = self.get_index(row, col);
+        let live_neighbors = self.live_neighbor_count(row, col);
+        self.update_cell(idx, live_neighbors, next, tally);
+    }
+
+    /// Whether the rule is a Larger than Life rule, which only Life and
+    /// Immigration follow.
+    fn counts_wide_neighborhoods(&self) -> bool {
+        self.rule.is_extended() && (self.mode == Mode::Life || self.mode == Mode::Immigration)
+    }
+
+    /// Make `next` the current generation, and bring everything that
+    /// follows the cells up to date.
+    fn finish_tick(&mut self, next: BitSet) {
+        self.next = mem::replace(&mut self.cells, next);
+        if self.settle_tracking {
+            // Following on from the last tick, the births and deaths give
+            // the population without counting every cell again.
+            let before = match self.ticked_population {
+                Some(population) => population,
+                None => self.next.count_ones() as u32,
+            };
+            let after = before + self.births - self.deaths;
+            debug_assert_eq!(after, self.population(), "births and deaths don't add up to the population");
+            self.activity.record_tick(self.generation, before, after);
+            self.ticked_population = Some(after);
+        }
+        if let Some(ref mut trail) = self.trail {
+            // Only the cells that changed can have just died, and they're
+            // listed unless the whole board is being redrawn anyway.
+            let changed: Vec<usize> = if self.needs_full_redraw {
+                self.next.xor(&self.cells).ones().collect()
+            } else {
+                self.changed.iter().map(|&idx| idx as usize).collect()
+            };
+            for idx in changed {
+                if self.next.get(idx) && !self.cells.get(idx) {
+                    trail.record_death(idx, self.generation - 1);
+                }
+            }
+        }
+        // Past a point, repainting everything is cheaper than repainting
+        // the changes one at a time.
+        if self.changed.len() > self.cells.len() / 4 {
+            self.redraw_all();
+        }
+
+        if let Some(ref mut ages) = self.ages {
+            for (idx, age) in ages.iter_mut().enumerate() {
+                *age = if self.cells.get(idx) { age.saturating_add(1) } else { 0 };
+            }
+        }
+        if let Some(ref mut heatmap) = self.heatmap {
+            heatmap.record(&self.next, &self.cells);
+        }
+        #[cfg(feature = "web")]
+        self.notify_tick();
+    }
+
+    /// Switch to `mode`, which any board can run.
+    fn switch_mode(&mut self, mode: Mode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.states = None;
+            self.sync_states();
+            self.redraw_all();
+        }
+    }
+
+    /// A generation of the rule set with `set_custom_rule_dynamic`, calling
+    /// it for each cell. If it fails the board stays as it was, and the
+    /// rule is dropped.
+    #[cfg(feature = "web")]
+    fn tick_dynamic(&self, next: &mut BitSet, tally: &mut Tally) {
+        let rule = match self.dynamic_rule {
+            Some(ref rule) => rule.clone(),
+            None => return,
+        };
+        let states: Vec<u8> = (0..self.cells.len()).map(|idx| self.state(idx)).collect();
+        let live: Vec<u8> = (0..self.cells.len()).map(|idx| self.cells.get(idx) as u8).collect();
+        let wrap = self.topology == Topology::Torus;
+        let counts = neighborhood::counts(self.width, self.height, wrap, &self.rule.neighborhood(), &live);
+        let count = self.state_count();
+        let mut next_states = Vec::with_capacity(states.len());
+        for (&state, &neighbors) in states.iter().zip(counts.iter()) {
+            let result = rule.call2(&JsValue::NULL, &state.into(), &neighbors.into()).and_then(|next| match next.as_f64() {
+                Some(next) if next >= 0.0 && next < f64::from(count) && next.fract() == 0.0 => Ok(next as u8),
+                _ => Err(JsValue::from_str(&format!("custom rule returned {:?}, not a state below {}", next, count))),
+            });
+            match result {
+                Ok(next_state) => next_states.push(next_state),
+                Err(error) => {
+                    web_sys::console::error_2(&"custom rule failed:".into(), &error);
+                    tally.rule_failed = true;
+                    next.clone_from(&self.cells);
+                    return;
+                }
+            }
+        }
+        if self.states.is_some() {
+            tally.states = Some(next_states.clone());
+        }
+        for (idx, (state, next_state)) in states.into_iter().zip(next_states).enumerate() {
+            next.set(idx, next_state == 1);
+            if next_state == state {
+                continue;
+            }
+            tally.altered = true;
+            match (state == 1, next_state == 1) {
+                (false, true) => tally.births += 1,
+                (true, false) => tally.deaths += 1,
+                _ => {}
+            }
+            if !self.needs_full_redraw {
+                tally.changed.push(idx as u32);
+            }
+        }
+    }
+
+    /// Apply the noise to the generation in `next`, keeping the births,
+    /// deaths and changed cells in step.
+    fn add_noise(&mut self, next: &mut BitSet) {
+        for idx in 0..self.cells.len() {
+            let (was_alive, alive) = (self.cells.get(idx), next.get(idx));
+            let noisy = self.noise.apply(&mut self.noise_rng, was_alive, alive);
+            if noisy == alive {
+                continue;
+            }
+            next.set(idx, noisy);
+            match (was_alive, noisy) {
+                (false, false) => self.births -= 1,
+                (false, true) => self.births += 1,
+                (true, false) => self.deaths += 1,
+                (true, true) => self.deaths -= 1,
+            }
+            if was_alive == alive && !self.needs_full_redraw {
+                self.changed.push(idx as u32);
+            }
+        }
+        // Noise only runs on boards of two states, where births and deaths
+        // are every change.
+        self.still = self.births == 0 && self.deaths == 0;
+    }
+
+    /// Move each ant in turn over the generation in `next`, keeping the
+    /// births, deaths and changed cells in step.
+    fn move_ants(&mut self, next: &mut BitSet) {
+        // Ants always move on, so the board never stands still.
+        self.still = false;
+        for i in 0..self.ants.len() {
+            let ant = &mut self.ants[i];
+            let idx = (ant.row * self.width + ant.col) as usize;
+            let alive = next.get(idx);
+            let flipped = ant.step(alive, self.width, self.height);
+            next.set(idx, flipped);
+            match (self.cells.get(idx), flipped) {
+                (false, false) => self.births -= 1,
+                (false, true) => self.births += 1,
+                (true, false) => self.deaths += 1,
+                (true, true) => self.deaths -= 1,
+            }
+            if let Some(ref mut states) = self.states {
+                states[idx] = flipped as u8;
+            }
+            if !self.needs_full_redraw {
+                self.changed.push(idx as u32);
+            }
+        }
+    }
+
+    /// A generation of a block rule, with the blocks offset by a cell on
+    /// odd generations.
+    fn tick_blocks(&self, next: &mut BitSet, table: &margolus::Table, tally: &mut Tally) {
+        let offset = (self.generation % 2) as u32;
+        margolus::step(&self.cells, self.width, self.height, offset, table, next);
+        for idx in 0..self.cells.len() {
+            match (self.cells.get(idx), next.get(idx)) {
+                (false, true) => tally.births += 1,
+                (true, false) => tally.deaths += 1,
+                _ => continue,
+            }
+            if !self.needs_full_redraw {
+                tally.changed.push(idx as u32);
+            }
+        }
+    }
+
+    /// A generation of a Generations rule, Wireworld, Immigration or a
+    /// Larger than Life rule, a cell at a time. Only live cells count as
+    /// neighbors.
+    fn tick_states(&self, next: &mut BitSet, tally: &mut Tally) {
+        // Two-state rules have no `states` of their own, so borrow them from
+        // the live cells.
+        let keep_states = self.states.is_some();
+        let cells = &self.cells;
+        let mut states = self.states.clone().unwrap_or_else(|| (0..cells.len()).map(|idx| cells.get(idx) as u8).collect());
+        // Newborns in Immigration look at their parents' colors, as they
+        // were before `states` started changing.
+        let previous = self.states.as_deref().unwrap_or(&[]);
+        // Wide neighborhoods are counted for the whole board up front.
+        let count_wide = |occupied: &dyn Fn(u8) -> bool| {
+            let occupied: Vec<u8> = states.iter().map(|&state| occupied(state) as u8).collect();
+            let wrap = self.topology == Topology::Torus;
+            neighborhood::counts(self.width, self.height, wrap, &self.rule.neighborhood(), &occupied)
+        };
+        let (wide, wide_second) = match self.counts_wide_neighborhoods() {
+            true if self.mode == Mode::Immigration => {
+                (count_wide(&|state| state != 0), count_wide(&|state| state == immigration::SECOND))
+            }
+            true => (count_wide(&|state| state == 1), vec![]),
+            false => (vec![], vec![]),
+        };
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let state = states[idx];
+                let live_neighbors = match wide.get(idx) {
+                    Some(&count) => count,
+                    None => u16::from(self.live_neighbor_count(row, col)),
+                };
+                let next_state = match self.mode {
+                    Mode::Life | Mode::Critters => self.rule.next_state(state, live_neighbors),
+                    Mode::Wireworld => wireworld::next_state(state, live_neighbors as u8),
+                    Mode::Immigration => {
+                        let second = if let Some(&count) = wide_second.get(idx) {
+                            count
+                        } else if state == 0 && live_neighbors > 0 {
+                            let neighbors = self.neighbor_indices(row, col);
+                            neighbors.into_iter().filter(|&i| previous[i] == immigration::SECOND).count() as u16
+                        } else {
+                            0
+                        };
+                        immigration::next_state(&self.rule, state, live_neighbors, second)
+                    }
+                };
+                let (was_live, live) = (self.mode.is_live(state), self.mode.is_live(next_state));
+                next.set(idx, live);
+                if next_state == state {
+                    continue;
+                }
+                tally.altered = true;
+                match (was_live, live) {
+                    (false, true) => tally.births += 1,
+                    (true, false) => tally.deaths += 1,
+                    _ => {}
+                }
+                if !self.needs_full_redraw {
+                    tally.changed.push(idx as u32);
+                }
+                states[idx] = next_state;
+            }
+        }
+        if keep_states {
+            tally.states = Some(states);
+        }
+    }
+
+    /// The indices of a cell's neighbors under the rule, leaving out those
+    /// beyond the edges of a bounded universe.
+    fn neighbor_indices(&self, row: u32, column: u32) -> Vec<usize> {
+        let (height, width) = (self.height as i64, self.width as i64);
+        let neighborhood = self.rule.neighborhood();
+        let radius = i64::from(neighborhood.radius);
+        let mut indices = Vec::with_capacity(neighborhood.size() as usize);
+        for dr in -radius..=radius {
+            let (lo, hi) = neighborhood.span(dr as i32);
+            for dc in i64::from(lo)..=i64::from(hi) {
+                let (r, c) = (row as i64 + dr, column as i64 + dc);
+                if dr == 0 && dc == 0 {
+                    continue;
+                }
+                match self.topology {
+                    Topology::Torus => indices.push(self.get_index(r.rem_euclid(height) as u32, c.rem_euclid(width) as u32)),
+                    Topology::Bounded if r >= 0 && r < height && c >= 0 && c < width => {
+                        indices.push(self.get_index(r as u32, c as u32))
+                    }
+                    Topology::Bounded => {}
+                }
+            }
+        }
+        indices
+    }
+
+    /// Set the cell at `idx` to `state`, keeping `cells` and `states` in
+    /// step.
+    fn put_state(&mut self, idx: usize, state: u8) {
+        self.cells.set(idx, self.mode.is_live(state));
+        if let Some(ref mut states) = self.states {
+            states[idx] = state;
+        }
+    }
+
+    /// The live cells and the states, if there are any, moved to where
+    /// `to` gives, or dropped where it gives `None`, on a board of `len`
+    /// cells and `width` columns.
+    fn moved_cells<F>(&self, len: usize, width: u32, to: F) -> (BitSet, Option<Vec<u8>>)
+    where
+        F: Fn(u32, u32) -> Option<(u32, u32)>,
+    {
+        let index = |(row, col): (u32, u32)| row as usize * width as usize + col as usize;
+        let mut cells = BitSet::new(len);
+        for (row, col) in self.iter_live() {
+            if let Some(to) = to(row, col) {
+                cells.set(index(to), true);
+            }
+        }
+        let states = self.states.as_ref().map(|old| {
+            let mut states = vec![0; len];
+            for (idx, &state) in old.iter().enumerate().filter(|&(_, &state)| state != 0) {
+                let (row, col) = ((idx / self.width as usize) as u32, (idx % self.width as usize) as u32);
+                if let Some(to) = to(row, col) {
+                    states[index(to)] = state;
+                }
+            }
+            states
+        });
+        (cells, states)
+    }
+
+    /// Bring `states` in line with the rule and the live cells: there are
+    /// only states with more than two of them, cells brought to life by an
+    /// edit are in state 1, cells killed by one are in state 0, and a change
+    /// of size or rule forgets the states that no longer fit.
+    fn sync_states(&mut self) {
+        let count = self.state_count();
+        if count <= 2 {
+            self.states = None;
+            return;
+        }
+        let (cells, mode) = (&self.cells, self.mode);
+        let states = self.states.get_or_insert_with(Vec::new);
+        if states.len() != cells.len() {
+            states.clear();
+            states.resize(cells.len(), 0);
+        }
+        for (idx, state) in states.iter_mut().enumerate() {
+            let live = cells.get(idx);
+            if u16::from(*state) >= count || mode.is_live(*state) != live {
+                *state = live as u8;
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.clone(),
+            states: self.states.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.ticked_population = None;
+        self.width = snapshot.width;
+        self.height = snapshot.height;
+        self.next = BitSet::new(snapshot.cells.len());
+        self.cells = snapshot.cells;
+        self.states = snapshot.states;
+        let (width, height) = (self.width, self.height);
+        self.ants.retain(|ant| ant.row < height && ant.col < width);
+        self.sync_states();
+        self.reset_ages();
+        self.redraw_all();
+    }
+
+    /// The cells that changed since the universe was last drawn, as listed
+    /// by `changed_cells`.
+    #[cfg(any(feature = "web", test))]
+    pub(crate) fn changed(&self) -> &[u32] {
+        &self.changed
+    }
+
+    fn redraw_all(&mut self) {
+        self.needs_full_redraw = true;
+        self.changed.clear();
+    }
+
+    /// Treat every live cell as newborn and forget recent activity and
+    /// trails, keeping them in step with the size of the universe.
+    fn reset_ages(&mut self) {
+        if self.ages.is_some() {
+            self.ages = None;
+            self.enable_age_tracking();
+        }
+        if let Some(decay_interval) = self.heatmap.as_ref().map(Heatmap::decay_interval) {
+            self.enable_heatmap(decay_interval);
+        }
+        let half_life = self.trail_half_life();
+        if half_life > 0 {
+            self.set_trail(half_life);
+        }
+    }
+
+    fn edit(&self) -> Edit {
+        Edit {
+            snapshot: self.snapshot(),
+            generation: self.generation,
+        }
+    }
+
+    fn restore_edit(&mut self, edit: Edit) {
+        self.restore(edit.snapshot);
+        self.generation = edit.generation;
+        self.births = 0;
+        self.deaths = 0;
+        self.still = false;
+        self.rewind.clear();
+        // Ticking from here should keep this state undoable too.
+        self.edited = true;
+    }
+
+    /// Push the current state onto the undo history, dropping the oldest
+    /// entry if it is full. Any undone edits can no longer be redone.
+    fn save_for_undo(&mut self) {
+        self.redo.clear();
+        if self.history_depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.history_depth {
+            self.undo.pop_front();
+        }
+        let current = self.edit();
+        self.undo.push_back(current);
+    }
+
+    /// Save the current state for `undo` ahead of an edit.
+    fn record_edit(&mut self) {
+        self.ticked_population = None;
+        self.save_for_undo();
+        self.rewind.clear();
+        self.redraw_all();
+        self.edited = true;
+    }
+
+    fn clear_cells(&mut self) {
+        self.ticked_population = None;
+        self.cells.clear();
+        if let Some(ref mut states) = self.states {
+            states.iter_mut().for_each(|state| *state = 0);
+        }
+        self.generation = 0;
+        self.births = 0;
+        self.deaths = 0;
+        self.still = false;
+        self.reset_ages();
+    }
+
+    /// The starting pattern of `Universe::new`.
+    pub(crate) fn fill_default(&mut self) {
+        self.ticked_population = None;
+        for i in 0..self.cells.len() {
+            self.cells.set(i, i % 2 == 0 || i % 7 == 0);
+        }
+    }
+
+    pub(crate) fn fill_random(&mut self, density: f64, seed: u64) {
+        let density = density.clamp(0.0, 1.0);
+        let mut rng = Rng::new(seed);
+        self.clear_cells();
+        for idx in 0..self.cells.len() {
+            self.cells.set(idx, rng.next_f64() < density);
+        }
+    }
+
+    /// Create a universe of the given size with every cell dead, or return
+    /// an error if it has more cells than a `u32` can count.
+    pub(crate) fn empty(width: u32, height: u32) -> Result<Universe, String> {
+        let len = cell_count(width, height)?;
+        let mut universe = Universe {
+            width,
+            height,
+            cells: BitSet::new(len),
+            next: BitSet::new(len),
+            states: None,
+            rule: Rule::default(),
+            mode: Mode::Life,
+            topology: Topology::Torus,
+            algorithm: Algorithm::CellByCell,
+            generation: 0,
+            births: 0,
+            deaths: 0,
+            still: false,
+            initial: Snapshot {
+                width,
+                height,
+                cells: BitSet::new(len),
+                states: None,
+            },
+            undo: VecDeque::new(),
+            redo: vec![],
+            history_depth: HISTORY_DEPTH,
+            rewind: VecDeque::new(),
+            rewind_capacity: 0,
+            ages: None,
+            heatmap: None,
+            trail: None,
+            activity: Activity::new(DEFAULT_SETTLE_WINDOW),
+            settle_tracking: false,
+            ticked_population: None,
+            changed: vec![],
+            needs_full_redraw: true,
+            edited: false,
+            cell_size: render::DEFAULT_CELL_SIZE,
+            grid_color: String::new(),
+            dead_color: String::new(),
+            alive_color: String::new(),
+            grid_visible: true,
+            #[cfg(feature = "web")]
+            tick_callback: None,
+            #[cfg(feature = "web")]
+            changed_callback: None,
+            #[cfg(feature = "web")]
+            dynamic_rule: None,
+            ants: Vec::new(),
+            ant_mode: AntMode::default(),
+            schedule: Vec::new(),
+            noise: Noise::default(),
+            noise_rng: Rng::new(0),
+        };
+        universe.set_theme(Theme::Light);
+        Ok(universe)
+    }
+
+    /// The state of the cell at `idx`, as returned by `get_index`.
+    #[inline]
+    pub(crate) fn cell(&self, idx: usize) -> Cell {
+        if self.cells.get(idx) {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    /// The state of the cell at `idx`, as in `cell_state`.
+    #[inline]
+    pub(crate) fn state(&self, idx: usize) -> u8 {
+        let live = self.cells.get(idx);
+        let stored = self.states.as_ref().and_then(|states| states.get(idx)).cloned();
+        match stored {
+            Some(state) if u16::from(state) < self.state_count() && self.mode.is_live(state) == live => state,
+            _ => live as u8,
+        }
+    }
+
+    /// How many cells are in `state`, such as each color in Immigration.
+    pub fn population_of(&self, state: u8) -> u32 {
+        match self.states {
+            Some(_) => (0..self.cells.len()).filter(|&idx| self.state(idx) == state).count() as u32,
+            None if state == 1 => self.population(),
+            None if state == 0 => self.cells.len() as u32 - self.population(),
+            None => 0,
+        }
+    }
+
+    /// Get the dead and alive values of the entire universe.
+    pub fn get_cells(&self) -> Vec<Cell> {
+        (0..self.cells.len()).map(|idx| self.cell(idx)).collect()
+    }
+
+    /// Take turns between rules: each entry is a rulestring and the number
+    /// of generations it runs for before the next one takes over, going
+    /// back to the first after the last. Which rule runs depends only on
+    /// the generation, counting from generation 0, so it carries on the
+    /// same after undoing, stepping back or loading. `set_rule` ends the
+    /// schedule.
+    ///
+    /// Errors, changing nothing, if the schedule is empty, an entry runs
+    /// for no generations, or a rulestring is malformed.
+    pub fn set_rule_schedule(&mut self, schedule: Vec<(String, u32)>) -> Result<(), String> {
+        if schedule.is_empty() {
+            return Err("a rule schedule needs at least one rule".to_string());
+        }
+        let mut rules = Vec::with_capacity(schedule.len());
+        for (rulestring, generations) in schedule {
+            if generations == 0 {
+                return Err(format!("rule '{}' must run for at least one generation", rulestring));
+            }
+            rules.push((Rule::parse(&rulestring)?, generations));
+        }
+        self.schedule = rules;
+        #[cfg(feature = "web")]
+        {
+            self.dynamic_rule = None;
+        }
+        Ok(())
+    }
+
+    /// The rule the schedule has reached at this generation, if there is a
+    /// schedule.
+    fn scheduled_rule(&self) -> Option<Rule> {
+        let cycle: u64 = self.schedule.iter().map(|&(_, generations)| u64::from(generations)).sum();
+        if cycle == 0 {
+            return None;
+        }
+        let mut at = self.generation % cycle;
+        for &(rule, generations) in self.schedule.iter() {
+            if at < u64::from(generations) {
+                return Some(rule);
+            }
+            at -= u64::from(generations);
+        }
+        None
+    }
+
+    /// Set cells to be alive in a universe by passing the row and column
+    /// of each cell as an array. Nothing is changed if any cell is off the
+    /// board.
+    pub fn set_cells(&mut self, cells: &[(u32, u32)]) -> Result<(), String> {
+        for &(row, col) in cells {
+            self.check_bounds(row, col)?;
+        }
+        self.record_edit();
+        for (row, col) in cells.iter().cloned() {
+            let idx = self.get_index(row, col);
+            self.cells.set(idx, true);
+        }
+        Ok(())
+    }
+
+    /// Like `bounding_box`, but around every cell that isn't dead, such as
+    /// Wireworld's conductors and tails or the dying cells of a Generations
+    /// rule.
+    fn content_box(&self) -> Option<(u32, u32, u32, u32)> {
+        match self.states {
+            Some(ref states) => self.box_around(states.iter().enumerate().filter(|&(_, &state)| state != 0).map(|(idx, _)| idx)),
+            None => self.bounding_box(),
+        }
+    }
+
+    /// The smallest box holding the cells at `indices`, taking the short
+    /// way across the seams of a torus.
+    fn box_around(&self, indices: impl Iterator<Item = usize>) -> Option<(u32, u32, u32, u32)> {
+        let mut rows = vec![false; self.height as usize];
+        let mut cols = vec![false; self.width as usize];
+        for idx in indices {
+            rows[idx / self.width as usize] = true;
+            cols[idx % self.width as usize] = true;
+        }
+        let wrap = self.topology == Topology::Torus;
+        let (top, height) = occupied_span(&rows, wrap)?;
+        let (left, width) = occupied_span(&cols, wrap)?;
+        Some((top, left, height, width))
+    }
+
+    /// The live cells within the given region, relative to its top-left
+    /// corner.
+    pub(crate) fn region_shape(&self, row: u32, col: u32, height: u32, width: u32) -> Shape {
+        let (row, col) = (row % self.height, col % self.width);
+        let width = u32::min(width, self.width);
+        let height = u32::min(height, self.height);
+        let mut cells = vec![];
+        for r in 0..height {
+            for c in 0..width {
+                let idx = self.get_index((row + r) % self.height, (col + c) % self.width);
+                if self.cells.get(idx) {
+                    cells.push((r, c));
+                }
+            }
+        }
+        Shape {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// The live cells cropped to their bounding box.
+    pub(crate) fn live_shape(&self) -> Shape {
+        let mut top = self.height;
+        let mut left = self.width;
+        let mut bottom = 0;
+        let mut right = 0;
+        for (row, col) in self.iter_live() {
+            top = u32::min(top, row);
+            left = u32::min(left, col);
+            bottom = u32::max(bottom, row + 1);
+            right = u32::max(right, col + 1);
+        }
+        if top >= bottom {
+            return Shape::default();
+        }
+        Shape {
+            width: right - left,
+            height: bottom - top,
+            cells: self.iter_live().map(|(row, col)| (row - top, col - left)).collect(),
+        }
+    }
+
+    /// Replace the cells with those of a hashlife plane within the board,
+    /// and take its generation.
+    fn place_hashlife(&mut self, life: &Hashlife) {
+        self.ticked_population = None;
+        self.cells.clear();
+        let (height, width) = (self.height as i64, self.width as i64);
+        for (row, col) in life.live_cells() {
+            if (0..height).contains(&row) && (0..width).contains(&col) {
+                let idx = self.get_index(row as u32, col as u32);
+                self.cells.set(idx, true);
+            }
+        }
+        self.generation = life.generation();
+    }
+
+    pub(crate) fn from_shape(shape: &Shape) -> Result<Universe, String> {
+        let mut universe = Universe::empty(shape.width, shape.height)?;
+        universe.place_shape(0, 0, shape);
+        universe.save_initial();
+        Ok(universe)
+    }
+
+    /// Set the shape's cells alive with its top-left corner at `row` and
+    /// `col`, wrapping around the edges, without recording an edit.
+    pub(crate) fn place_shape(&mut self, row: u32, col: u32, shape: &Shape) {
+        self.ticked_population = None;
+        for &(r, c) in shape.cells.iter() {
+            let idx = self.get_index((row % self.height + r) % self.height, (col % self.width + c) % self.width);
+            self.cells.set(idx, true);
+        }
+    }
+
+    /// Make the current cells the ones `reset` goes back to.
+    pub(crate) fn save_initial(&mut self) {
+        self.initial = self.snapshot();
+    }
+
+    pub(crate) fn insert_shape(&mut self, row: u32, col: u32, shape: &Shape) -> Result<(), String> {
+        if shape.width > self.width || shape.height > self.height {
+            return Err(format!(
+                "pattern of size {}x{} does not fit in a {}x{} universe",
+                shape.width, shape.height, self.width, self.height
+            ));
+        }
+        self.record_edit();
+        for &(r, c) in shape.cells.iter() {
+            let idx = self.get_index((row % self.height + r) % self.height, (col % self.width + c) % self.width);
+            self.cells.set(idx, true);
+        }
+        Ok(())
+    }
+}
+
+/// The version of the form `Universe` is serialized in. Readers reject
+/// anything newer, since its fields may mean something else.
+#[cfg(feature = "serde")]
+pub const SERDE_VERSION: u32 = 2;
+
+/// The form a `Universe` is serialized in: everything needed to carry on
+/// ticking, but not the history, colors or callbacks.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SavedUniverse {
+    version: u32,
+    width: u32,
+    height: u32,
+    rule: Rule,
+    /// Added in version 2. Earlier versions are all Life.
+    #[serde(default)]
+    mode: Mode,
+    topology: Topology,
+    generation: u64,
+    /// Packed like `Universe::cells_copy`, in base64.
+    cells: String,
+    /// A byte per cell in base64, if the mode or rule has more than two
+    /// states. Added in version 2.
+    #[serde(default)]
+    states: Option<String>,
+    /// The noise and the state of its generator, if there is any noise.
+    #[serde(default)]
+    noise: Option<(Noise, u64)>,
+    /// The rule schedule, if there is one.
+    #[serde(default)]
+    schedule: Vec<(Rule, u32)>,
+    #[serde(default)]
+    ants: Vec<Ant>,
+    #[serde(default)]
+    ant_mode: AntMode,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Universe {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let saved = SavedUniverse {
+            version: SERDE_VERSION,
+            width: self.width,
+            height: self.height,
+            rule: self.rule,
+            mode: self.mode,
+            topology: self.topology,
+            generation: self.generation,
+            cells: base64::encode(&self.cells.to_bytes()),
+            states: self.states.as_ref().map(|states| base64::encode(states)),
+            noise: if self.noise.is_quiet() { None } else { Some((self.noise, self.noise_rng.state())) },
+            schedule: self.schedule.clone(),
+            ants: self.ants.clone(),
+            ant_mode: self.ant_mode,
+        };
+        saved.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Universe {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Universe, D::Error> {
+        use serde::de::Error;
+
+        let saved = SavedUniverse::deserialize(deserializer)?;
+        if saved.version == 0 || saved.version > SERDE_VERSION {
+            return Err(D::Error::custom(format!(
+                "universe format version {} is not supported, only 1 to {}",
+                saved.version, SERDE_VERSION
+            )));
+        }
+        let (width, height) = (saved.width, saved.height);
+        if width == 0 || height == 0 || width.checked_mul(height).is_none() {
+            return Err(D::Error::custom(format!("a board of {}x{} cells can't be loaded", width, height)));
+        }
+        let bytes = base64::decode(&saved.cells).map_err(D::Error::custom)?;
+        let cells = BitSet::from_bytes(width as usize * height as usize, &bytes)
+            .ok_or_else(|| D::Error::custom(format!("{} bytes do not hold a {}x{} board", bytes.len(), width, height)))?;
+        if saved.mode == Mode::Critters && (!width.is_multiple_of(2) || !height.is_multiple_of(2)) {
+            return Err(D::Error::custom(format!("Critters needs an even width and height, not {}x{}", width, height)));
+        }
+        let mut universe = Universe::empty(width, height).map_err(D::Error::custom)?;
+        universe.cells = cells;
+        universe.rule = saved.rule;
+        universe.mode = saved.mode;
+        if let Some(ref states) = saved.states {
+            let states = base64::decode(states).map_err(D::Error::custom)?;
+            if states.len() != universe.cells.len() {
+                return Err(D::Error::custom(format!("{} states do not fit a {}x{} board", states.len(), width, height)));
+            }
+            let count = universe.state_count();
+            if let Some(&state) = states.iter().find(|&&state| u16::from(state) >= count) {
+                return Err(D::Error::custom(format!("state {} is out of range for {} states", state, count)));
+            }
+            universe.states = Some(states);
+        }
+        universe.sync_states();
+        universe.topology = saved.topology;
+        universe.generation = saved.generation;
+        if let Some((noise, state)) = saved.noise {
+            universe.noise = Noise::new(noise.birth_failure, noise.spontaneous_death, noise.spontaneous_birth);
+            universe.noise_rng = Rng::from_state(state);
+        }
+        if saved.schedule.iter().any(|&(_, generations)| generations == 0) {
+            return Err(D::Error::custom("a rule in the schedule runs for no generations"));
+        }
+        universe.schedule = saved.schedule;
+        if let Some(ant) = saved.ants.iter().find(|ant| ant.row >= height || ant.col >= width) {
+            return Err(D::Error::custom(format!("an ant at ({}, {}) is off the board", ant.row, ant.col)));
+        }
+        universe.ants = saved.ants;
+        universe.ant_mode = saved.ant_mode;
+        universe.save_initial();
+        Ok(universe)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[wasm_bindgen]
+impl Universe {
+    /// The size, rule, mode, topology, generation and cells as JSON, with
+    /// the cells and any further states in base64 and a `version` for
+    /// `from_json` to check.
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|err| err.to_string())
+    }
+
+    /// A universe from the JSON written by `to_json`.
+    pub fn from_json(json: &str) -> Result<Universe, String> {
+        serde_json::from_str(json).map_err(|err| err.to_string())
+    }
+}
+
+/// Sums for the plain and circular means of positions along one axis.
+struct MeanAccumulator {
+    len: u32,
+    sum: f64,
+    sin: f64,
+    cos: f64,
+}
+
+impl MeanAccumulator {
+    fn new(len: u32) -> MeanAccumulator {
+        MeanAccumulator { len, sum: 0.0, sin: 0.0, cos: 0.0 }
+    }
+
+    fn add(&mut self, position: u32) {
+        let angle = position as f64 / self.len as f64 * std::f64::consts::TAU;
+        self.sum += position as f64;
+        self.sin += angle.sin();
+        self.cos += angle.cos();
+    }
+
+    /// The mean of `count` positions, taken around the circle if `wrap` is
+    /// set, in `[0, len)`.
+    fn mean(&self, count: usize, wrap: bool) -> f64 {
+        let count = count as f64;
+        if !wrap || f64::hypot(self.sin, self.cos) < 1e-9 * count {
+            return self.sum / count;
+        }
+        let angle = f64::atan2(self.sin, self.cos).rem_euclid(std::f64::consts::TAU);
+        (angle / std::f64::consts::TAU * self.len as f64) % self.len as f64
+    }
+}
+
+/// The start and length of the shortest span covering every `true` in
+/// `occupied`, which may wrap past the end if `wrap` is set, or `None` if
+/// there are none.
+fn occupied_span(occupied: &[bool], wrap: bool) -> Option<(u32, u32)> {
+    let first = occupied.iter().position(|&o| o)?;
+    let last = occupied.iter().rposition(|&o| o)?;
+    let (mut start, mut len) = (first, last - first + 1);
+    if wrap {
+        // Leaving out the widest gap between live lines instead of the one
+        // around the seam gives a shorter span that wraps.
+        let mut previous = first;
+        for (i, _) in occupied.iter().enumerate().skip(first + 1).filter(|&(_, &o)| o) {
+            let span = occupied.len() - (i - previous - 1);
+            if span < len {
+                start = i;
+                len = span;
+            }
+            previous = i;
+        }
+    }
+    Some((start as u32, len as u32))
+}
+
+impl Default for Universe {
+    fn default() -> Universe {
+        Universe::new()
+    }
+}
+
+/// Draws the board with `◻` for dead and `◼` for live cells, one line per
+/// row. The alternate flag, `{:#}`, uses `.` and `#` instead.
+impl fmt::Display for Universe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (dead, alive) = if f.alternate() { ('.', '#') } else { ('◻', '◼') };
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                write!(f, "{}", if self.cells.get(idx) { alive } else { dead })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the board as drawn by `Display`, in either style. Trailing blank
+/// lines are ignored, but every row must have the same number of cells.
+impl FromStr for Universe {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Universe, String> {
+        let mut rows: Vec<&str> = text.lines().map(str::trim_end).collect();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        if rows.is_empty() {
+            return Err("universe has no rows".to_string());
+        }
+
+        let width = rows[0].chars().count();
+        if width == 0 {
+            return Err("universe has no columns".to_string());
+        }
+        let mut universe = Universe::empty(width as u32, rows.len() as u32)?;
+        for (row, line) in rows.iter().enumerate() {
+            let len = line.chars().count();
+            if len != width {
+                return Err(format!("row {} has {} cells, but row 1 has {}", row + 1, len, width));
+            }
+            for (col, ch) in line.chars().enumerate() {
+                let alive = match ch {
+                    '◻' | '.' => false,
+                    '◼' | '#' => true,
+                    c => return Err(format!(
+                        "unexpected character '{}' at row {}, column {}; expected '◻', '◼', '.' or '#'",
+                        c, row + 1, col + 1
+                    )),
+                };
+                let idx = universe.get_index(row as u32, col as u32);
+                universe.cells.set(idx, alive);
+            }
+        }
+        universe.save_initial();
+        Ok(universe)
+    }
+}
+
+/// The number of cells on a `width`×`height` board, or an error if there
+/// are more than a `u32` can count, as cell indices must be.
+fn cell_count(width: u32, height: u32) -> Result<usize, String> {
+    width
+        .checked_mul(height)
+        .map(|count| count as usize)
+        .ok_or_else(|| format!("universe of {}x{} has too many cells", width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use builder::UniverseBuilder;
+
+    fn universe_with(width: u32, height: u32, cells: &[(u32, u32)]) -> Universe {
+        let mut universe = Universe::new();
+        universe.set_width(width);
+        universe.set_height(height);
+        universe.set_cells(cells).unwrap();
+        universe
+    }
+
+    #[test]
+    fn set_size_clears_cells() {
+        let universe = universe_with(6, 4, &[]);
+        assert_eq!(universe.width(), 6);
+        assert_eq!(universe.height(), 4);
+        assert_eq!(universe.get_cells().len(), 24);
+        assert!(universe.get_cells().iter().all(|&c| c == Cell::Dead));
+    }
+
+    #[test]
+    fn generation_counts_ticks() {
+        let mut universe = universe_with(6, 6, &[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        assert_eq!(universe.generation(), 0);
+        universe.tick();
+        universe.tick();
+        assert_eq!(universe.generation(), 2);
+
+        // Editing cells while paused keeps the count.
+        universe.toggle_cell(0, 0);
+        universe.tick();
+        assert_eq!(universe.generation(), 3);
+
+        universe.set_width(8);
+        assert_eq!(universe.generation(), 0);
+    }
+
+    #[test]
+    fn blinker_statistics() {
+        let mut universe = universe_with(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        assert_eq!(universe.population(), 3);
+        for _ in 0..4 {
+            universe.tick();
+            assert_eq!(universe.population(), 3);
+            assert_eq!(universe.last_births(), 2);
+            assert_eq!(universe.last_deaths(), 2);
+        }
+    }
+
+    #[test]
+    fn tick_swaps_buffers() {
+        let mut universe = universe_with(16, 16, &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let front = universe.cells();
+        universe.tick();
+        let back = universe.cells();
+        assert_ne!(front, back);
+        universe.tick();
+        assert_eq!(universe.cells(), front);
+
+        // A glider moves one cell diagonally every four generations.
+        for _ in 2..100 {
+            universe.tick();
+        }
+        let expected = universe_with(16, 16, &[(9, 10), (10, 11), (11, 9), (11, 10), (11, 11)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+        assert!(universe.cells() == front || universe.cells() == back);
+    }
+
+    #[test]
+    fn compute_next_is_the_next_tick() {
+        let setups: [fn(&mut Universe); 7] = [
+            |_| {},
+            |universe| universe.set_topology(Topology::Bounded),
+            |universe| universe.set_algorithm(Algorithm::BitParallel),
+            |universe| universe.set_rule("B2/S/4").unwrap(),
+            |universe| universe.set_rule("R2,C0,M1,S6..9,B5..7,NM").unwrap(),
+            |universe| universe.set_mode(Mode::Critters).unwrap(),
+            |universe| universe.set_mode(Mode::Immigration).unwrap(),
+        ];
+        for (i, setup) in setups.iter().enumerate() {
+            let mut universe = universe_with(20, 12, &[]);
+            setup(&mut universe);
+            universe.fill_random(0.4, i as u64);
+            // Past the first generation, for decaying states and the blocks
+            // of odd generations.
+            universe.tick();
+            let (generation, cells, changed) = (universe.generation(), universe.get_cells(), universe.changed.clone());
+            let mut next = vec![Cell::Dead; 240];
+            universe.compute_next(&mut next).unwrap();
+            assert_eq!((universe.generation(), universe.get_cells(), &universe.changed), (generation, cells, &changed));
+            universe.tick();
+            assert_eq!(universe.get_cells(), next, "setup {}", i);
+        }
+        assert!(Universe::new().compute_next(&mut [Cell::Dead; 3]).is_err());
+    }
+
+    /// Byte-per-cell implementation of `tick`, as it was before cells were
+    /// bit-packed.
+    fn reference_tick(width: u32, height: u32, cells: &[Cell]) -> Vec<Cell> {
+        let rule = Rule::conway();
+        let mut next = cells.to_vec();
+        for row in 0..height {
+            for col in 0..width {
+                let mut count = 0;
+                for &dr in [height - 1, 0, 1].iter() {
+                    for &dc in [width - 1, 0, 1].iter() {
+                        if dr == 0 && dc == 0 {
+                            continue;
+                        }
+                        let r = (row + dr) % height;
+                        let c = (col + dc) % width;
+                        count += cells[(r * width + c) as usize] as u8;
+                    }
+                }
+                let idx = (row * width + col) as usize;
+                next[idx] = rule.next(cells[idx], count);
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn bit_packed_matches_reference() {
+        let (width, height) = (37, 23);
+        let mut universe = universe_with(width, height, &[]);
+        let mut seed: u32 = 0x2545_f491;
+        for idx in 0..(width * height) as usize {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            universe.cells.set(idx, seed & 3 == 0);
+        }
+
+        let mut expected = universe.get_cells();
+        for _ in 0..50 {
+            universe.tick();
+            expected = reference_tick(width, height, &expected);
+            assert_eq!(universe.get_cells(), expected);
+        }
+    }
+
+    #[test]
+    fn interior_pass_matches_wrapping_count() {
+        let sizes = [(1, 1), (1, 7), (9, 1), (2, 2), (3, 3), (5, 11), (17, 13)];
+        let mut seed: u32 = 0x9e37_79b9;
+        for &(width, height) in sizes.iter() {
+            let mut universe = universe_with(width, height, &[]);
+            for idx in 0..(width * height) as usize {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                universe.cells.set(idx, seed & 1 == 0);
+            }
+
+            for _ in 0..10 {
+                // Count every cell the way edge cells are counted.
+                let mut expected = universe_with(width, height, &[]);
+                for row in 0..height {
+                    for col in 0..width {
+                        let idx = universe.get_index(row, col);
+                        let count = universe.live_neighbor_count(row, col);
+                        let cell = universe.rule.next(universe.cell(idx), count);
+                        expected.cells.set(idx, cell == Cell::Alive);
+                    }
+                }
+
+                universe.tick();
+                assert_eq!(universe.get_cells(), expected.get_cells(), "{}x{}", width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn tick_many() {
+        let glider = [(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)];
+        let mut universe = universe_with(8, 8, &glider);
+        let mut expected = universe_with(8, 8, &glider);
+        for _ in 0..4 {
+            expected.tick();
+        }
+
+        assert_eq!(universe.tick_many(4), 5);
+        assert_eq!(universe.generation(), 4);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+        let moved = universe_with(8, 8, &[(2, 3), (3, 4), (4, 2), (4, 3), (4, 4)]);
+        assert_eq!(universe.get_cells(), moved.get_cells());
+    }
+
+    #[test]
+    fn run_until_stable_settles() {
+        // An L-tromino fills in to a block after one generation.
+        let mut universe = universe_with(6, 6, &[(1, 1), (1, 2), (2, 1)]);
+        let result = universe.run_until_stable(10);
+        assert_eq!(result, StabilityResult { generations: 1, stable: true });
+        assert_eq!(universe.population(), 4);
+
+        // A glider crashing into the edge of a bounded universe leaves a block.
+        let mut universe = universe_with(8, 8, &[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        universe.set_topology(Topology::Bounded);
+        let result = universe.run_until_stable(100);
+        assert_eq!(result, StabilityResult { generations: 19, stable: true });
+        assert_eq!(universe.population(), 4);
+
+        // A dying cell beside a block neither is born nor dies while it
+        // fades, but the board isn't stable until it's gone.
+        let mut universe = universe_with(6, 6, &[(1, 1), (1, 2), (2, 1), (2, 2)]);
+        universe.set_rule("B3/S23/4").unwrap();
+        universe.set_cell_state(4, 4, 2).unwrap();
+        let result = universe.run_until_stable(10);
+        assert_eq!(result, StabilityResult { generations: 2, stable: true });
+        assert_eq!(universe.cell_state(4, 4), 0);
+    }
+
+    #[test]
+    fn settled() {
+        // A diagonal of three shrinks to one cell, then dies out.
+        let mut universe = universe_with(6, 6, &[(1, 1), (2, 2), (3, 3)]);
+        assert_eq!(universe.settled(), None);
+        universe.tick();
+        assert_eq!((universe.population(), universe.settled()), (1, None));
+        universe.tick();
+        assert_eq!(universe.settled(), Some(Settled::Extinct));
+
+        // An L-tromino becomes a block, which the next tick leaves alone.
+        let mut universe = universe_with(6, 6, &[(1, 1), (1, 2), (2, 1)]);
+        universe.tick();
+        assert_eq!(universe.settled(), None);
+        universe.tick();
+        assert_eq!(universe.settled(), Some(Settled::Stable));
+
+        // Nor does a block with a cell fading out beside it.
+        let mut universe = universe_with(6, 6, &[(1, 1), (1, 2), (2, 1), (2, 2)]);
+        universe.set_rule("B3/S23/4").unwrap();
+        universe.set_cell_state(4, 4, 2).unwrap();
+        for _ in 0..2 {
+            universe.tick();
+            assert_eq!(universe.settled(), None);
+        }
+        universe.tick();
+        assert_eq!(universe.settled(), Some(Settled::Stable));
+
+        // A blinker repeats every other generation, but never settles.
+        let mut universe = universe_with(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        for _ in 0..4 {
+            universe.tick();
+            assert_eq!(universe.settled(), None);
+        }
+    }
+
+    #[test]
+    fn auto_pause_modes() {
+        assert!(!AutoPause::Off.pauses_for(Settled::Extinct));
+        assert!(AutoPause::Extinction.pauses_for(Settled::Extinct));
+        assert!(!AutoPause::Extinction.pauses_for(Settled::Stable));
+        assert!(AutoPause::ExtinctionOrStable.pauses_for(Settled::Stable));
+    }
+
+    #[test]
+    fn r_pentomino_settles_at_1103() {
+        // Big enough that the escaping gliders don't come round again before
+        // the settle window is up.
+        let mut universe = Universe::empty(1024, 1024).unwrap();
+        universe.set_algorithm(Algorithm::BitParallel);
+        universe.insert_pattern(Pattern::RPentomino, 510, 510, Transform::Identity).unwrap();
+        universe.set_settle_tracking(true);
+        while !universe.is_settled() && universe.generation() < 2000 {
+            universe.tick();
+        }
+        assert_eq!(universe.lifespan_so_far(), 1103);
+        assert_eq!(universe.generation(), 1103 + 200);
+        assert_eq!(universe.population(), 116);
+
+        // Still lifes and blinkers settle from the start.
+        let mut blinker = Universe::empty(8, 8).unwrap();
+        blinker.set_cells(&[(3, 2), (3, 3), (3, 4)]).unwrap();
+        blinker.set_settle_window(10);
+        blinker.tick_many(10);
+        assert!(!blinker.is_settled());
+        blinker.set_settle_tracking(true);
+        blinker.tick_many(10);
+        assert!(blinker.is_settled());
+        assert_eq!(blinker.lifespan_so_far(), 10);
+
+        // A block added between ticks changes the population as much as a
+        // tick would.
+        blinker.set_cells(&[(6, 6), (6, 7), (7, 6), (7, 7)]).unwrap();
+        blinker.tick_many(5);
+        assert!(!blinker.is_settled());
+        assert_eq!(blinker.lifespan_so_far(), 21);
+        blinker.tick_many(6);
+        assert!(blinker.is_settled());
+    }
+
+    #[test]
+    fn run_until_stable_hits_cap() {
+        let mut universe = universe_with(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        let result = universe.run_until_stable(50);
+        assert_eq!(result, StabilityResult { generations: 50, stable: false });
+        assert_eq!(universe.generation(), 50);
+
+        // The r-pentomino's debris includes blinkers, so it never stops
+        // changing by this definition.
+        let mut universe = universe_with(64, 64, &[]);
+        universe.set_topology(Topology::Bounded);
+        universe.insert_pattern(Pattern::RPentomino, 30, 30, Transform::Identity).unwrap();
+        let result = universe.run_until_stable(2000);
+        assert_eq!(result, StabilityResult { generations: 2000, stable: false });
+    }
+
+    #[test]
+    fn detect_period() {
+        let period = |pattern, size| {
+            let mut universe = universe_with(size, size, &[]);
+            universe.insert_pattern(pattern, 2, 2, Transform::Identity).unwrap();
+            universe.detect_period(100)
+        };
+        assert_eq!(period(Pattern::Blinker, 8), Some(2));
+        assert_eq!(period(Pattern::Pulsar, 20), Some(3));
+        assert_eq!(period(Pattern::Pentadecathlon, 20), Some(15));
+        // The glider comes back after crossing the whole torus diagonally.
+        assert_eq!(period(Pattern::Glider, 8), Some(32));
+        assert_eq!(period(Pattern::Glider, 10), Some(40));
+        let mut glider = universe_with(10, 10, &[]);
+        glider.insert_pattern(Pattern::Glider, 2, 2, Transform::Identity).unwrap();
+        assert_eq!(glider.detect_period(39), None);
+
+        let mut block = universe_with(6, 6, &[(1, 1), (1, 2), (2, 1), (2, 2)]);
+        assert_eq!(block.detect_period(10), Some(1));
+        assert_eq!(block.generation(), 1);
+    }
+
+    #[test]
+    fn undo_toggle() {
+        let mut universe = universe_with(4, 4, &[]);
+        universe.toggle_cell(1, 2);
+        assert_eq!(universe.population(), 1);
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 0);
+        assert!(universe.redo());
+        assert_eq!(universe.cell(universe.get_index(1, 2)), Cell::Alive);
+        assert!(!universe.redo());
+
+        // A new edit forgets what was undone.
+        assert!(universe.undo());
+        universe.toggle_cell(0, 0);
+        assert!(!universe.redo());
+    }
+
+    #[test]
+    fn undo_clear() {
+        let block = [(1, 1), (1, 2), (2, 1), (2, 2)];
+        let mut universe = universe_with(6, 6, &block);
+        let cells = universe.get_cells();
+        universe.tick();
+        universe.clear();
+        assert_eq!(universe.population(), 0);
+        assert_eq!(universe.generation(), 0);
+        assert!(universe.undo());
+        assert_eq!(universe.get_cells(), cells);
+        assert_eq!(universe.generation(), 1);
+    }
+
+    #[test]
+    fn undo_skips_ticks() {
+        let glider = [(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)];
+        let mut universe = universe_with(8, 8, &glider);
+        let cells = universe.get_cells();
+        universe.tick_many(4);
+        assert!(universe.undo());
+        assert_eq!(universe.get_cells(), cells);
+        assert_eq!(universe.generation(), 0);
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 0);
+        assert!(!universe.undo());
+    }
+
+    #[test]
+    fn history_depth() {
+        let mut universe = universe_with(4, 4, &[]);
+        universe.set_history_depth(3);
+        for col in 0..4 {
+            universe.toggle_cell(0, col);
+        }
+        for _ in 0..3 {
+            assert!(universe.undo());
+        }
+        assert!(!universe.undo());
+        assert_eq!(universe.population(), 1);
+
+        universe.set_history_depth(0);
+        universe.toggle_cell(3, 3);
+        assert!(!universe.undo());
+    }
+
+    #[test]
+    fn step_back() {
+        let mut universe = Universe::new_random(32, 32, 0.4, 7);
+        universe.enable_history(10);
+        let mut states = vec![universe.get_cells()];
+        for _ in 0..5 {
+            universe.tick();
+            states.push(universe.get_cells());
+        }
+        assert_eq!(universe.history_len(), 5);
+        for generation in (0..5).rev() {
+            assert!(universe.step_back());
+            assert_eq!(universe.generation(), generation as u64);
+            assert_eq!(universe.get_cells(), states[generation]);
+        }
+        assert!(!universe.step_back());
+
+        // Stepping back and ticking again gives the same states.
+        universe.tick_many(5);
+        assert_eq!(universe.get_cells(), states[5]);
+    }
+
+    #[test]
+    fn step_back_evicts_oldest() {
+        let mut universe = Universe::new_random(16, 16, 0.4, 3);
+        universe.enable_history(3);
+        let mut states = vec![universe.get_cells()];
+        for _ in 0..6 {
+            universe.tick();
+            states.push(universe.get_cells());
+        }
+        assert_eq!(universe.history_len(), 3);
+        for _ in 0..3 {
+            assert!(universe.step_back());
+        }
+        assert!(!universe.step_back());
+        assert_eq!(universe.generation(), 3);
+        assert_eq!(universe.get_cells(), states[3]);
+
+        universe.enable_history(0);
+        universe.tick();
+        assert_eq!(universe.history_len(), 0);
+    }
+
+    #[test]
+    fn block_ages() {
+        let mut universe = universe_with(6, 6, &[(1, 1), (1, 2), (2, 1), (2, 2)]);
+        assert_eq!(universe.age_at(1, 1), 1);
+        universe.enable_age_tracking();
+        for age in 2..10 {
+            universe.tick();
+            assert_eq!(universe.age_at(1, 1), age);
+            assert_eq!(universe.age_at(2, 2), age);
+            assert_eq!(universe.age_at(0, 0), 0);
+        }
+    }
+
+    #[test]
+    fn blinker_ages() {
+        let mut universe = universe_with(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        universe.enable_age_tracking();
+        for generation in 1..10 {
+            universe.tick();
+            assert_eq!(universe.age_at(2, 2), generation + 1);
+            for &(row, col) in [(1, 2), (3, 2), (2, 1), (2, 3)].iter() {
+                assert!(universe.age_at(row, col) <= 1);
+            }
+        }
+        universe.disable_age_tracking();
+        universe.tick();
+        assert_eq!(universe.age_at(2, 2), 1);
+    }
+
+    #[test]
+    fn heatmap() {
+        let block = [(1, 1), (1, 2), (2, 1), (2, 2)];
+        let blinker = [(6, 5), (6, 6), (6, 7)];
+        let mut universe = universe_with(10, 10, &[]);
+        universe.set_cells(&block).unwrap();
+        universe.set_cells(&blinker).unwrap();
+        universe.enable_heatmap(4);
+        universe.tick_many(20);
+
+        for row in 0..4 {
+            for col in 0..4 {
+                assert_eq!(universe.activity_at(row, col), 0.0);
+            }
+        }
+        assert!(universe.activity_at(5, 6) > 0.0);
+        assert!(universe.activity_at(6, 5) > 0.0);
+        assert_eq!(universe.activity_at(6, 6), 0.0);
+
+        universe.disable_heatmap();
+        assert_eq!(universe.activity_at(5, 6), 0.0);
+    }
+
+    #[test]
+    fn trails() {
+        let mut universe = universe_with(10, 10, &[(2, 1), (2, 2), (2, 3)]);
+        universe.set_trail(1);
+        universe.tick();
+        // The ends of the blinker just died, and keep half their trail.
+        assert_eq!(universe.trail_at(2, 1), 0.5);
+        assert_eq!(universe.trail_at(2, 2), 1.0);
+        assert_eq!(universe.trail_at(0, 0), 0.0);
+        universe.tick();
+        assert_eq!(universe.trail_at(2, 1), 1.0);
+        assert_eq!(universe.trail_at(1, 2), 0.5);
+
+        // A lone cell's trail keeps fading after it's gone.
+        universe.clear();
+        assert_eq!(universe.trail_at(1, 2), 0.0);
+        universe.set_cells(&[(5, 5)]).unwrap();
+        universe.set_trail(2);
+        universe.tick_many(3);
+        assert!((universe.trail_at(5, 5) - f64::powf(0.5, 1.5)).abs() < 1e-12);
+        universe.randomize(0.0, 1);
+        assert_eq!(universe.trail_at(5, 5), 0.0);
+        assert_eq!(universe.trail_half_life(), 2);
+
+        universe.set_trail(0);
+        assert_eq!(universe.trail_half_life(), 0);
+    }
+
+    #[test]
+    fn changed_cells() {
+        let mut universe = universe_with(10, 10, &[(2, 1), (2, 2), (2, 3)]);
+        assert!(universe.needs_full_redraw());
+        universe.mark_drawn();
+        universe.tick();
+        assert!(!universe.needs_full_redraw());
+        let mut changed = universe.changed().to_vec();
+        changed.sort();
+        assert_eq!(changed, vec![12, 21, 23, 32]);
+
+        // Changes pile up until the board is drawn.
+        universe.tick();
+        assert_eq!(universe.changed_cells_len(), 8);
+        universe.mark_drawn();
+        assert_eq!(universe.changed_cells_len(), 0);
+
+        universe.toggle_cell(0, 0);
+        assert!(universe.needs_full_redraw());
+        universe.mark_drawn();
+        universe.clear();
+        assert!(universe.needs_full_redraw());
+    }
+
+    #[test]
+    fn fast_forward_matches_ticking() {
+        let r_pentomino = [(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)];
+        let centered: Vec<_> = r_pentomino.iter().map(|&(row, col)| (row + 300, col + 300)).collect();
+        let mut universe = universe_with(600, 600, &centered);
+        assert!(universe.fast_forward(10).is_err());
+        universe.set_topology(Topology::Bounded);
+        let mut ticked = universe_with(600, 600, &centered);
+        ticked.set_topology(Topology::Bounded);
+
+        universe.fast_forward(200).unwrap();
+        ticked.tick_many(200);
+        assert_eq!(universe.get_cells(), ticked.get_cells());
+        assert_eq!(universe.generation(), 200);
+        assert!(universe.needs_full_redraw());
+
+        // The gliders escape, but not off a board this size.
+        universe.fast_forward(903).unwrap();
+        assert_eq!(universe.population(), 116);
+        universe.undo();
+        assert_eq!(universe.generation(), 0);
+
+        let copy = Universe::from_hashlife(&ticked.to_hashlife().unwrap(), 600, 600).unwrap();
+        assert_eq!(copy.get_cells(), ticked.get_cells());
+        assert_eq!(copy.generation(), 200);
+    }
+
+    #[test]
+    fn fast_forward_only_runs_life() {
+        for &mode in Mode::ALL[1..].iter() {
+            let mut universe = Universe::new_random(32, 32, 0.3, 4);
+            universe.set_topology(Topology::Bounded);
+            universe.set_mode(mode).unwrap();
+            let before = universe.cells_copy();
+            assert!(universe.fast_forward(10).is_err(), "{:?}", mode);
+            assert_eq!(universe.cells_copy(), before);
+            assert_eq!(universe.generation(), 0);
+        }
+    }
+
+    #[test]
+    fn bit_parallel_matches_cell_by_cell() {
+        // Widths around word boundaries, where rows are split across words
+        // and wrap from the middle of one.
+        let sizes = [(1, 1), (2, 3), (63, 5), (64, 4), (65, 6), (100, 1), (127, 3), (128, 7)];
+        for &(width, height) in sizes.iter() {
+            for &topology in [Topology::Torus, Topology::Bounded].iter() {
+                for (seed, rule) in ["B3/S23", "B36/S23", "B0123478/S01234678", "B2/S"].iter().enumerate() {
+                    let random = |algorithm| {
+                        let mut universe = UniverseBuilder::new()
+                            .width(width)
+                            .height(height)
+                            .rule(rule)
+                            .topology(topology)
+                            .algorithm(algorithm)
+                            .density(0.4)
+                            .seed(seed as u64)
+                            .build()
+                            .unwrap();
+                        universe.mark_drawn();
+                        universe
+                    };
+                    let mut reference = random(Algorithm::CellByCell);
+                    let mut universe = random(Algorithm::BitParallel);
+                    for generation in 0..10 {
+                        reference.tick();
+                        universe.tick();
+                        let context = format!("{}x{} {:?} {} at {}", width, height, topology, rule, generation);
+                        assert_eq!(universe.cells, reference.cells, "{}", context);
+                        assert_eq!(
+                            (universe.last_births(), universe.last_deaths()),
+                            (reference.last_births(), reference.last_deaths()),
+                            "{}",
+                            context
+                        );
+                        // The cell-by-cell tick does the edges last, so the
+                        // same cells change in a different order.
+                        let mut changed = reference.changed.clone();
+                        changed.sort_unstable();
+                        assert_eq!(universe.changed, changed, "{}", context);
+                        reference.mark_drawn();
+                        universe.mark_drawn();
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn simd_interior_matches_scalar() {
+        // Widths on either side of a multiple of sixteen, so rows end with
+        // and without columns left over for the scalar path.
+        for &(width, height) in [(18, 3), (33, 7), (47, 20), (64, 16), (100, 9)].iter() {
+            for seed in 0..20 {
+                let random = |rule: &str| {
+                    let mut universe = Universe::new();
+                    universe.set_width(width);
+                    universe.set_height(height);
+                    universe.set_rule(rule).unwrap();
+                    universe.fill_random(0.1 + seed as f64 * 0.04, seed);
+                    universe.mark_drawn();
+                    universe
+                };
+                let rule = if seed % 2 == 0 { "B3/S23" } else { "B0136/S0278" };
+                let (scalar, simd) = (random(rule), random(rule));
+                let mut scalar_next = BitSet::new(scalar.cells.len());
+                let mut simd_next = BitSet::new(simd.cells.len());
+                let (mut scalar_tally, mut simd_tally) = (Tally::new(vec![]), Tally::new(vec![]));
+                scalar.tick_interior(&mut scalar_next, &mut scalar_tally);
+                simd.tick_interior_simd(&mut simd_next, &mut simd_tally);
+                assert_eq!(simd_next, scalar_next, "{}x{} seed {}", width, height, seed);
+                assert_eq!((simd_tally.births, simd_tally.deaths), (scalar_tally.births, scalar_tally.deaths));
+                assert_eq!(simd_tally.changed, scalar_tally.changed);
+            }
+        }
+    }
+
+    #[test]
+    fn load_saved_is_undoable() {
+        let mut universe = universe_with(10, 10, &[(2, 1), (2, 2), (2, 3)]);
+        let saved = universe_with(3, 2, &[(0, 0), (1, 2)]);
+        universe.load_saved(3, 2, 42, "B36/S23", &saved.cells_copy()).unwrap();
+        assert_eq!((universe.width(), universe.height(), universe.generation()), (3, 2, 42));
+        assert_eq!(universe.iter_live().collect::<Vec<_>>(), vec![(0, 0), (1, 2)]);
+        assert_eq!(universe.rule(), "B36/S23");
+
+        // The wrong number of bytes, or a bad rule, changes nothing.
+        assert!(universe.load_saved(4, 4, 0, "B3/S23", &saved.cells_copy()).is_err());
+        assert!(universe.load_saved(3, 2, 0, "B9/S", &saved.cells_copy()).is_err());
+        assert!(universe.load_saved(0, 2, 0, "B3/S23", &[]).is_err());
+        assert_eq!(universe.generation(), 42);
+
+        assert!(universe.undo());
+        assert_eq!((universe.width(), universe.height()), (10, 10));
+        assert_eq!(universe.population(), 3);
+    }
+
+    #[test]
+    fn insert_pattern_file_centers_and_grows() {
+        let mut universe = universe_with(9, 9, &[(0, 0)]);
+        universe.insert_pattern_file("glider.cells", ".O\n..O\nOOO\n").unwrap();
+        assert_eq!(universe.iter_live().collect::<Vec<_>>(), vec![(0, 0), (3, 4), (4, 5), (5, 3), (5, 4), (5, 5)]);
+
+        // A 1x12 row is wider than the board, which grows to 12x9 around it.
+        universe.insert_pattern_file("row.rle", "x = 12, y = 1\n12o!").unwrap();
+        assert_eq!((universe.width(), universe.height()), (12, 9));
+        assert!(universe.iter_live().any(|cell| cell == (0, 1)));
+        assert_eq!(universe.population(), 6 + 12 - 1);
+        assert!(universe.undo());
+        assert!(universe.undo());
+        assert_eq!((universe.width(), universe.population()), (9, 1));
+
+        assert!(universe.insert_pattern_file("bad.rle", "x = 1, y = 1\nq!").is_err());
+        assert_eq!(universe.population(), 1);
+
+        // Growing keeps every state, such as Wireworld's wires.
+        let mut wires = universe_with(9, 3, &[]);
+        wires.insert_wireworld_pattern(WireworldPattern::Clock, 0, 0);
+        wires.insert_pattern_file("corners.rle", "x = 12, y = 5\no10bo4$o10bo!").unwrap();
+        assert_eq!((wires.width(), wires.height()), (12, 5));
+        assert_eq!(wires.population_of(wireworld::CONDUCTOR), 9);
+        assert_eq!(wires.population_of(wireworld::TAIL), 1);
+        assert_eq!(wires.population_of(wireworld::HEAD), 5);
+    }
+
+    #[test]
+    fn export_names_files_by_size_and_generation() {
+        let mut universe = universe_with(8, 6, &[(1, 1), (1, 2), (1, 3)]);
+        universe.tick_many(3);
+        assert_eq!(universe.export_file_name(ExportFormat::Rle), "life-8x6-gen3.rle");
+        assert_eq!(universe.export_file_name(ExportFormat::Plaintext), "life-8x6-gen3.cells");
+        assert_eq!(universe.export(ExportFormat::Rle), universe.to_rle());
+        assert_eq!(universe.export(ExportFormat::Plaintext), universe.to_plaintext());
+        assert_eq!(universe.export(ExportFormat::Life106), universe.to_life106());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips() {
+        // Wider than the default, so the size must come from the data.
+        let mut universe = Universe::new();
+        universe.resize(200, 128, Anchor::TopLeft).unwrap();
+        universe.set_rule("B36/S23").unwrap();
+        universe.set_topology(Topology::Bounded);
+        universe.tick_many(5);
+        let check = |copy: &Universe| {
+            assert_eq!((copy.width(), copy.height(), copy.generation()), (200, 128, 5));
+            assert_eq!(copy.rule(), "B36/S23");
+            assert_eq!(copy.topology(), Topology::Bounded);
+            assert_eq!(copy.cells_copy(), universe.cells_copy());
+        };
+
+        let json = universe.to_json().unwrap();
+        assert!(json.starts_with("{\"version\":2,"), "{}", json);
+        check(&Universe::from_json(&json).unwrap());
+        let bytes = bincode::serialize(&universe).unwrap();
+        check(&bincode::deserialize::<Universe>(&bytes).unwrap());
+
+        // So does a rule schedule.
+        universe.set_rule_schedule(vec![("B3/S23".to_string(), 2), ("B2/S/3".to_string(), 1)]).unwrap();
+        let copy = Universe::from_json(&universe.to_json().unwrap()).unwrap();
+        assert_eq!(copy.current_rule(), "B2/S/3");
+
+        // Noise carries on where it left off.
+        universe.set_noise(0.1, 0.01, 0.01);
+        universe.set_noise_seed(3);
+        universe.tick();
+        let mut copy = Universe::from_json(&universe.to_json().unwrap()).unwrap();
+        universe.tick_many(5);
+        copy.tick_many(5);
+        assert_eq!(copy.cells_copy(), universe.cells_copy());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_other_versions() {
+        let json = universe_with(3, 3, &[(1, 1)]).to_json().unwrap();
+        let newer = json.replace("\"version\":2", "\"version\":3");
+        assert!(Universe::from_json(&newer).err().unwrap().contains("version 3"));
+        assert!(Universe::from_json(&json.replace("\"width\":3", "\"width\":20")).is_err());
+        assert!(Universe::from_json(&json.replace("B3/S23", "B9")).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_every_mode() {
+        let states = |universe: &Universe| (0..universe.cells.len()).map(|idx| universe.state(idx)).collect::<Vec<u8>>();
+        let check = |mut universe: Universe| {
+            let mut copy = Universe::from_json(&universe.to_json().unwrap()).unwrap();
+            assert_eq!(copy.mode(), universe.mode());
+            assert_eq!(states(&copy), states(&universe), "{:?}", universe.mode());
+            universe.tick_many(10);
+            copy.tick_many(10);
+            assert_eq!(states(&copy), states(&universe), "{:?}", universe.mode());
+        };
+
+        // Brian's Brain, with its dying cells.
+        let mut brain = Universe::new_random(32, 32, 0.3, 1);
+        brain.set_rule("B2/S/3").unwrap();
+        brain.tick_many(3);
+        check(brain);
+
+        let mut clock = wireworld_board(WireworldPattern::Clock.text());
+        clock.tick_many(3);
+        check(clock);
+
+        let mut immigration = Universe::new_random(32, 32, 0.4, 2);
+        immigration.set_mode(Mode::Immigration).unwrap();
+        for col in 0..32 {
+            immigration.set_cell_state(16, col, immigration::SECOND).unwrap();
+        }
+        immigration.tick_many(3);
+        check(immigration);
+
+        // Odd generations run on the offset blocks.
+        let mut critters = Universe::new_random(24, 16, 0.3, 3);
+        critters.set_mode(Mode::Critters).unwrap();
+        critters.tick_many(3);
+        check(critters);
+
+        // A version 1 board is Life.
+        let json = universe_with(3, 3, &[(1, 1)]).to_json().unwrap();
+        let old = json.replace("\"version\":2", "\"version\":1").replace(",\"mode\":\"Life\"", "").replace(",\"states\":null", "");
+        assert_ne!(old, json);
+        assert_eq!(Universe::from_json(&old).unwrap().mode(), Mode::Life);
+    }
+
+    #[test]
+    fn bounding_box_of_a_glider_in_a_corner() {
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        let mut universe = universe_with(20, 10, &glider);
+        assert_eq!(universe.bounding_box(), Some((0, 0, 3, 3)));
+        assert_eq!(universe_with(20, 10, &[]).bounding_box(), None);
+
+        // Moved up and left, the glider straddles both seams of the torus.
+        universe.translate(-1, -1);
+        assert_eq!(universe.bounding_box(), Some((9, 19, 3, 3)));
+        universe.set_topology(Topology::Bounded);
+        assert_eq!(universe.bounding_box(), Some((0, 0, 10, 20)));
+    }
+
+    #[test]
+    fn center_of_mass_across_seams() {
+        let close = |(row, col): (f64, f64), expected: (f64, f64)| {
+            (row - expected.0).abs() < 1e-9 && (col - expected.1).abs() < 1e-9
+        };
+        let blinker = [(4, 9), (5, 9), (6, 9)];
+        let mut universe = universe_with(20, 10, &blinker);
+        assert!(close(universe.center_of_mass().unwrap(), (5.0, 9.0)));
+        assert_eq!(universe_with(20, 10, &[]).center_of_mass(), None);
+
+        // Straddling the corner, the cells average to the corner itself.
+        universe = universe_with(20, 10, &[(0, 0), (9, 19), (0, 19), (9, 0)]);
+        let (row, col) = universe.center_of_mass().unwrap();
+        assert!((row - 9.5).abs() < 1e-9 && (col - 19.5).abs() < 1e-9, "{:?}", (row, col));
+        universe.set_topology(Topology::Bounded);
+        assert!(close(universe.center_of_mass().unwrap(), (4.5, 9.5)));
+
+        // Two cells half a board apart have no circular mean.
+        let opposite = universe_with(20, 10, &[(0, 0), (5, 10)]);
+        assert!(close(opposite.center_of_mass().unwrap(), (2.5, 5.0)));
+
+        // A blinker crossing the bottom edge stays centered on its middle.
+        let crossing = universe_with(20, 10, &[(9, 3), (0, 3), (1, 3)]);
+        let (row, col) = crossing.center_of_mass().unwrap();
+        assert!(row.min(10.0 - row) < 1e-9 && (col - 3.0).abs() < 1e-9, "{:?}", (row, col));
+    }
+
+    #[test]
+    fn crop_to_content_keeps_a_margin() {
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        let mut universe = universe_with(20, 10, &glider);
+        universe.translate(-1, -1);
+        universe.crop_to_content(2);
+        assert_eq!((universe.width(), universe.height()), (7, 7));
+        assert_eq!(universe.bounding_box(), Some((2, 2, 3, 3)));
+        assert_eq!(universe.region_to_rle(2, 2, 3, 3), universe_with(3, 3, &glider).to_rle());
+
+        // The margin is cut short rather than growing the board.
+        universe.crop_to_content(10);
+        assert_eq!((universe.width(), universe.height()), (7, 7));
+        assert!(universe.undo());
+        assert!(universe.undo());
+        assert_eq!((universe.width(), universe.height()), (20, 10));
+
+        let mut empty = universe_with(20, 10, &[]);
+        empty.crop_to_content(1);
+        assert_eq!((empty.width(), empty.height()), (20, 10));
+    }
+
+    #[test]
+    fn crop_to_content_keeps_every_state() {
+        let mut clock = universe_with(30, 20, &[]);
+        clock.insert_wireworld_pattern(WireworldPattern::Clock, 5, 5);
+        clock.tick_many(2);
+        let counts = |universe: &Universe| {
+            let mut counts = [0; 4];
+            for idx in 0..universe.cells.len() {
+                counts[universe.state(idx) as usize] += 1;
+            }
+            counts[1..].to_vec()
+        };
+        let before = counts(&clock);
+        let period = clock.detect_period(20);
+        assert!(period.is_some());
+        clock.crop_to_content(1);
+        assert!(clock.width() < 30 && clock.height() < 20);
+        assert_eq!(counts(&clock), before);
+        assert_eq!(clock.detect_period(20), period);
+
+        // The dying cells of a Generations rule are kept too.
+        let mut brain = universe_with(30, 20, &[(12, 5), (12, 6)]);
+        brain.set_rule("B2/S/3").unwrap();
+        brain.set_cell_state(13, 5, 2).unwrap();
+        brain.set_cell_state(13, 6, 2).unwrap();
+        brain.crop_to_content(0);
+        assert_eq!((brain.width(), brain.height()), (2, 2));
+        assert_eq!((brain.cell_state(0, 0), brain.cell_state(1, 1)), (1, 2));
+    }
+
+    #[test]
+    fn hash_round_trips() {
+        let empty = universe_with(7, 5, &[]);
+        assert_eq!(empty.encode_to_hash(), "life=7x5.B3/S23.");
+        let mut universe = universe_with(10, 10, &[(2, 1), (2, 2), (2, 3)]);
+        universe.load_hash(&format!("#{}", empty.encode_to_hash())).unwrap();
+        assert_eq!((universe.width(), universe.height(), universe.population()), (7, 5, 0));
+
+        let mut random = Universe::new_random(33, 17, 0.4, 7);
+        random.set_rule("B36/S23").unwrap();
+        universe.load_hash(&random.encode_to_hash()).unwrap();
+        assert_eq!((universe.width(), universe.height()), (33, 17));
+        assert_eq!(universe.cells_copy(), random.cells_copy());
+        assert_eq!(universe.rule(), "B36/S23");
+
+        for &rule in ["B2/S013V", "R5,C0,M1,S34..58,B34..45,NM"].iter() {
+            random.set_rule(rule).unwrap();
+            universe.load_hash(&random.encode_to_hash()).unwrap();
+            assert_eq!(universe.rule(), rule);
+            assert_eq!(universe.cells_copy(), random.cells_copy());
+        }
+    }
+
+    #[test]
+    fn malformed_hashes_change_nothing() {
+        let mut universe = universe_with(10, 10, &[(2, 1), (2, 2), (2, 3)]);
+        for hash in ["", "#worker", "life=", "life=10x10.B3/S23", "life=10.B3/S23.", "life=axb.B3/S23.", "life=0x5.B3/S23.", "life=2x2.B3/S23.AAA", "life=4x4.B9.", "life=4x4.B3/S23.!"].iter() {
+            assert!(universe.load_hash(hash).is_err(), "{}", hash);
+        }
+        assert_eq!((universe.width(), universe.population()), (10, 3));
+    }
+
+    #[test]
+    fn load_matches_ticking() {
+        let mut elsewhere = universe_with(10, 10, &[(2, 1), (2, 2), (2, 3)]);
+        elsewhere.tick_many(3);
+        let mut universe = universe_with(10, 10, &[(2, 1), (2, 2), (2, 3)]);
+        universe.mark_drawn();
+        universe.load(10, 10, 3, &elsewhere.cells_copy()).unwrap();
+        assert_eq!(universe.get_cells(), elsewhere.get_cells());
+        assert_eq!(universe.generation(), 3);
+        assert_eq!((universe.last_births(), universe.last_deaths()), (2, 2));
+        assert!(!universe.needs_full_redraw());
+        assert_eq!(universe.changed_cells_len(), 4);
+
+        // A different size starts over.
+        universe.load(4, 2, 7, &[0b1001_0000]).unwrap();
+        assert_eq!(universe.iter_live().collect::<Vec<_>>(), vec![(1, 0), (1, 3)]);
+        assert!(universe.needs_full_redraw());
+        assert!(universe.load(4, 4, 0, &[0]).is_err());
+    }
+
+    #[test]
+    fn busy_board_needs_full_redraw() {
+        let mut universe = Universe::new_random(16, 16, 0.5, 1);
+        universe.mark_drawn();
+        universe.tick();
+        assert!(universe.needs_full_redraw());
+        assert_eq!(universe.changed_cells_len(), 0);
+    }
+
+    #[test]
+    fn render_to_image_data() {
+        let universe = universe_with(3, 3, &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let mut buffer = vec![0; 3 * 3 * 4];
+        universe.render_to_image_data(&mut buffer).unwrap();
+        let (o, x) = ([0xFF, 0xFF, 0xFF, 0xFF], [0x00, 0x00, 0x00, 0xFF]);
+        let expected: Vec<u8> = [o, x, o, o, o, x, x, x, x].iter().flat_map(|p| p.iter().cloned()).collect();
+        assert_eq!(buffer, expected);
+
+        assert!(universe.render_to_image_data(&mut [0; 4]).is_err());
+
+        let size = (universe.cell_size() + 1) * 3 + 1;
+        let mut buffer = vec![0; (size * size * 4) as usize];
+        universe.render_to_image_data_scaled(&mut buffer).unwrap();
+        let pixel = |x: u32, y: u32| buffer[(y * size + x) as usize * 4];
+        assert_eq!(pixel(0, 0), 0xCC);
+        assert_eq!(pixel(universe.cell_size() + 1, 3), 0xCC);
+        // The top-middle cell is alive, its neighbor to the left is dead.
+        assert_eq!(pixel(universe.cell_size() + 2, 1), 0x00);
+        assert_eq!(pixel(universe.cell_size(), 1), 0xFF);
+
+        // Hidden gridlines take the dead cell color without moving the cells.
+        let mut universe = universe;
+        universe.set_grid_visible(false);
+        universe.render_to_image_data_scaled(&mut buffer).unwrap();
+        let pixel = |x: u32, y: u32| buffer[(y * size + x) as usize * 4];
+        assert_eq!(pixel(0, 0), 0xFF);
+        assert_eq!(pixel(universe.cell_size() + 2, 1), 0x00);
+    }
+
+    #[test]
+    fn colors() {
+        let mut universe = universe_with(4, 4, &[]);
+        assert_eq!(universe.alive_color(), "#000000");
+        universe.set_theme(Theme::Dark);
+        assert_eq!(universe.dead_color(), "#111111");
+
+        universe.set_alive_color("rebeccapurple").unwrap();
+        assert_eq!(universe.alive_color(), "rebeccapurple");
+        assert!(universe.set_alive_color("").is_err());
+        assert!(universe.set_grid_color(" ").is_err());
+        assert_eq!(universe.alive_color(), "rebeccapurple");
+
+        // Colors that aren't hex fall back to the light theme for images.
+        universe.toggle_cell(0, 0);
+        let mut buffer = vec![0; 4 * 4 * 4];
+        universe.render_to_image_data(&mut buffer).unwrap();
+        assert_eq!(&buffer[0..8], &[0x00, 0x00, 0x00, 0xFF, 0x11, 0x11, 0x11, 0xFF]);
+    }
+
+    #[test]
+    fn bounded_corner_has_three_neighbors() {
+        let mut universe = universe_with(4, 4, &[(0, 1), (1, 0), (1, 1), (3, 3), (0, 3), (3, 0)]);
+        assert_eq!(universe.live_neighbor_count(0, 0), 6);
+        universe.set_topology(Topology::Bounded);
+        assert_eq!(universe.topology(), Topology::Bounded);
+        assert_eq!(universe.live_neighbor_count(0, 0), 3);
+    }
+
+    #[test]
+    fn glider_at_the_wall() {
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+
+        // On the torus the glider re-enters from the opposite side and is
+        // back where it started after crossing the universe once.
+        let mut torus = universe_with(8, 8, &glider);
+        torus.tick_many(32);
+        assert_eq!(torus.get_cells(), universe_with(8, 8, &glider).get_cells());
+
+        // Against a wall it collapses into a block in the corner.
+        let mut bounded = universe_with(8, 8, &glider);
+        bounded.set_topology(Topology::Bounded);
+        bounded.tick_many(32);
+        let block = universe_with(8, 8, &[(6, 6), (6, 7), (7, 6), (7, 7)]);
+        assert_eq!(bounded.get_cells(), block.get_cells());
+    }
+
+    #[test]
+    fn resize_top_left() {
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        let mut universe = universe_with(5, 5, &glider);
+        universe.tick();
+        universe.resize(10, 6, Anchor::TopLeft).unwrap();
+        assert_eq!((universe.width(), universe.height()), (10, 6));
+        assert_eq!(universe.generation(), 1);
+        let expected = universe_with(10, 6, &[(1, 0), (1, 2), (2, 1), (2, 2), (3, 1)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+
+        universe.resize(2, 3, Anchor::TopLeft).unwrap();
+        let expected = universe_with(2, 3, &[(1, 0), (2, 1)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn resize_center() {
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        let mut universe = universe_with(3, 3, &glider);
+        universe.resize(7, 9, Anchor::Center).unwrap();
+        let expected = universe_with(7, 9, &[(3, 3), (4, 4), (5, 2), (5, 3), (5, 4)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+
+        universe.resize(3, 1, Anchor::Center).unwrap();
+        let expected = universe_with(3, 1, &[(0, 2)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn too_many_cells() {
+        assert!(Universe::empty(u32::MAX, 2).is_err());
+        assert!(Universe::empty(65_536, 65_536).is_err());
+        assert!(Universe::from_rle("x = 65536, y = 65536\no!").is_err());
+
+        let mut universe = universe_with(4, 4, &[(1, 1)]);
+        assert!(universe.resize(u32::MAX, 2, Anchor::TopLeft).is_err());
+        universe.set_width(u32::MAX);
+        universe.set_height(1 << 31);
+        assert_eq!((universe.width(), universe.height()), (4, 4));
+        assert_eq!(universe.get_cells(), universe_with(4, 4, &[(1, 1)]).get_cells());
+    }
+
+    #[test]
+    fn random_is_reproducible() {
+        let a = Universe::new_random(32, 16, 0.3, 7);
+        let b = Universe::new_random(32, 16, 0.3, 7);
+        let c = Universe::new_random(32, 16, 0.3, 8);
+        assert_eq!(a.get_cells(), b.get_cells());
+        assert_ne!(a.get_cells(), c.get_cells());
+        assert!(a.population() > 100 && a.population() < 200);
+
+        let mut universe = universe_with(32, 16, &[]);
+        universe.tick();
+        universe.randomize(0.3, 7);
+        assert_eq!(universe.get_cells(), a.get_cells());
+        assert_eq!(universe.generation(), 0);
+    }
+
+    #[test]
+    fn random_density_is_clamped() {
+        assert_eq!(Universe::new_random(8, 8, -1.0, 1).population(), 0);
+        assert_eq!(Universe::new_random(8, 8, 2.0, 1).population(), 64);
+        assert_eq!(Universe::new_random(8, 8, f64::NAN, 1).population(), 0);
+    }
+
+    #[test]
+    fn clear() {
+        let mut universe = Universe::new();
+        universe.tick_many(3);
+        universe.clear();
+        assert_eq!(universe.population(), 0);
+        assert_eq!(universe.generation(), 0);
+        assert_eq!((universe.width(), universe.height()), (128, 128));
+    }
+
+    #[test]
+    fn reset_restores_initial_pattern() {
+        let glider = "x = 3, y = 3\nbob$2bo$3o!";
+        let mut universe = Universe::from_rle(glider).unwrap();
+        universe.resize(8, 8, Anchor::TopLeft).unwrap();
+        universe.tick_many(5);
+        universe.reset();
+        assert_eq!(universe.generation(), 0);
+        assert_eq!(universe.get_cells(), Universe::from_rle(glider).unwrap().get_cells());
+
+        let mut universe = Universe::new_random(16, 16, 0.5, 3);
+        universe.tick_many(5);
+        universe.clear();
+        universe.reset();
+        assert_eq!(universe.get_cells(), Universe::new_random(16, 16, 0.5, 3).get_cells());
+    }
+
+    #[test]
+    fn toggle_cell() {
+        let mut universe = universe_with(4, 4, &[]);
+        universe.toggle_cell(1, 2);
+        assert_eq!(universe.get_cells()[universe.get_index(1, 2)], Cell::Alive);
+        universe.toggle_cell(1, 2);
+        assert_eq!(universe.get_cells()[universe.get_index(1, 2)], Cell::Dead);
+    }
+
+    #[test]
+    fn cell_copies() {
+        let universe = universe_with(5, 5, &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(universe.cells_copy(), vec![0b1000_0010, 0b0001_1100, 0, 0]);
+        assert_eq!(universe.live_cell_coords(), vec![0, 1, 1, 2, 2, 0, 2, 1, 2, 2]);
+    }
+
+    #[test]
+    fn iter_live_matches_scan() {
+        for seed in 0..5 {
+            let universe = Universe::new_random(67, 13, 0.3, seed);
+            let mut expected = vec![];
+            for row in 0..universe.height() {
+                for col in 0..universe.width() {
+                    if universe.cell(universe.get_index(row, col)) == Cell::Alive {
+                        expected.push((row, col));
+                    }
+                }
+            }
+            assert_eq!(universe.iter_live().len(), expected.len());
+            assert_eq!(universe.live_cell_count() as usize, expected.len());
+            assert_eq!(universe.iter_live().collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn set_cell() {
+        let mut universe = universe_with(4, 4, &[]);
+        // Unlike toggling, setting the same state twice leaves it alone.
+        universe.set_cell(1, 2, Cell::Alive);
+        universe.set_cell(1, 2, Cell::Alive);
+        assert_eq!(universe.get_cells()[universe.get_index(1, 2)], Cell::Alive);
+        universe.set_cell(1, 2, Cell::Dead);
+        universe.set_cell(1, 2, Cell::Dead);
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    fn set_line() {
+        let mut universe = universe_with(6, 4, &[(0, 0)]);
+        universe.set_line(1, 0, 3, 5, Cell::Alive);
+        assert_eq!(format!("{:#}", universe), "#.....\n##....\n..##..\n....##\n");
+        universe.set_line(2, 2, 2, 3, Cell::Dead);
+        assert_eq!(format!("{:#}", universe), "#.....\n##....\n......\n....##\n");
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 7);
+    }
+
+    #[test]
+    fn set_rects() {
+        let mut universe = universe_with(6, 4, &[(1, 2)]);
+        universe.set_rect_outline(3, 4, 0, 1, Cell::Alive);
+        assert_eq!(format!("{:#}", universe), ".####.\n.##.#.\n.#..#.\n.####.\n");
+        universe.set_rect_filled(1, 1, 2, 4, Cell::Dead);
+        assert_eq!(format!("{:#}", universe), ".####.\n......\n......\n.####.\n");
+        universe.set_rect_filled(2, 5, 2, 5, Cell::Alive);
+        assert_eq!(universe.population(), 9);
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 8);
+    }
+
+    #[test]
+    fn set_ellipse_wraps_or_clips() {
+        // Centered on the top-right corner, across the seams.
+        let mut universe = universe_with(7, 5, &[]);
+        universe.set_ellipse(0, 6, 1, 2, false, Cell::Alive);
+        assert_eq!(format!("{:#}", universe), ".#..#..\n#....##\n.......\n.......\n#....##\n");
+        universe.set_ellipse(2, 3, 0, 0, true, Cell::Alive);
+        assert_eq!(universe.population(), 9);
+        assert!(universe.undo());
+
+        universe.set_topology(Topology::Bounded);
+        universe.clear();
+        universe.set_ellipse(0, 6, 1, 2, true, Cell::Alive);
+        assert_eq!(format!("{:#}", universe), "....###\n.....##\n.......\n.......\n.......\n");
+    }
+
+    #[test]
+    fn set_disc_wraps() {
+        let mut universe = universe_with(7, 6, &[]);
+        universe.set_disc(0, 0, 1, BrushShape::Square, Cell::Alive);
+        assert_eq!(format!("{:#}", universe), "##....#\n##....#\n.......\n.......\n.......\n##....#\n");
+        // Only the corners are left after the circle.
+        universe.set_disc(0, 0, 1, BrushShape::Circle, Cell::Dead);
+        assert_eq!(format!("{:#}", universe), ".......\n.#....#\n.......\n.......\n.......\n.#....#\n");
+        universe.set_disc(3, 3, 0, BrushShape::Circle, Cell::Alive);
+        assert_eq!(universe.population(), 5);
+    }
+
+    #[test]
+    fn extend_line_wraps_the_short_way() {
+        let mut universe = universe_with(10, 3, &[]);
+        universe.set_cell(1, 8, Cell::Alive);
+        universe.extend_line((1, 8), (1, 1), Cell::Alive as u8);
+        assert_eq!(format!("{:#}", universe), "..........\n##......##\n..........\n");
+        // The whole stroke is undone at once.
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    fn out_of_bounds_cells() {
+        let mut universe = universe_with(4, 3, &[]);
+        // Column 4 would otherwise land on the start of the next row.
+        assert_eq!(universe.try_toggle_cell(0, 4), Err("column 4 is outside the universe, which has 4 columns".to_string()));
+        assert_eq!(universe.try_toggle_cell(3, 0), Err("row 3 is outside the universe, which has 3 rows".to_string()));
+        assert!(universe.try_set_cell(u32::MAX, u32::MAX, Cell::Alive).is_err());
+        assert!(universe.set_cells(&[(0, 0), (1, 4)]).is_err());
+        assert_eq!(universe.population(), 0);
+
+        assert!(universe.try_toggle_cell(2, 3).is_ok());
+        assert!(universe.try_set_cell(1, 0, Cell::Alive).is_ok());
+        assert!(universe.try_set_cell(2, 3, Cell::Dead).is_ok());
+        assert_eq!(universe.get_cells(), universe_with(4, 3, &[(1, 0)]).get_cells());
+    }
+
+    #[test]
+    fn glider_tick() {
+        let mut universe = universe_with(6, 6, &[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        let expected = universe_with(6, 6, &[(2, 1), (2, 3), (3, 2), (3, 3), (4, 2)]);
+        universe.tick();
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn from_rle() {
+        let universe = Universe::from_rle("x = 3, y = 3\nbob$2bo$3o!").unwrap();
+        let expected = universe_with(3, 3, &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn insert_rle_wraps_around_edges() {
+        let mut universe = universe_with(5, 5, &[]);
+        universe.insert_rle(4, 3, "x = 3, y = 3\nbob$2bo$3o!", Transform::Identity).unwrap();
+        let expected = universe_with(5, 5, &[(4, 4), (0, 0), (1, 3), (1, 4), (1, 0)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let universe = universe_with(8, 8, &[(0, 0), (0, 7), (3, 2), (7, 7)]);
+        let copy = Universe::from_rle(&universe.to_rle()).unwrap();
+        assert_eq!(copy.get_cells(), universe.get_cells());
+        assert_eq!(copy.rule(), "B3/S23");
+
+        let mut universe = universe_with(8, 8, &[(0, 0), (0, 7), (3, 2), (7, 7)]);
+        universe.set_rule("B36/S23").unwrap();
+        let copy = Universe::from_rle(&universe.to_rle()).unwrap();
+        assert_eq!(copy.get_cells(), universe.get_cells());
+        assert_eq!(copy.rule(), "B36/S23");
+
+        assert!(Universe::from_rle("x = 1, y = 1, rule = B9/S23\no!").is_err());
+    }
+
+    #[test]
+    fn to_rle_crops_to_live_cells() {
+        let universe = universe_with(8, 8, &[(2, 3), (2, 4), (4, 3)]);
+        assert_eq!(universe.to_rle(), "x = 2, y = 3, rule = B3/S23\n2o2$o!\n");
+        assert_eq!(universe_with(8, 8, &[]).to_rle(), "x = 0, y = 0, rule = B3/S23\n!\n");
+    }
+
+    #[test]
+    fn region_to_rle() {
+        let universe = universe_with(8, 8, &[(2, 3), (2, 4), (4, 3)]);
+        assert_eq!(universe.region_to_rle(2, 4, 2, 2), "x = 2, y = 2, rule = B3/S23\no!\n");
+
+        // Regions take their height before their width, like the box
+        // around the cells.
+        let (row, col, height, width) = universe.bounding_box().unwrap();
+        assert_eq!((height, width), (3, 2));
+        assert_eq!(universe.region_to_rle(row, col, height, width), "x = 2, y = 3, rule = B3/S23\n2o2$o!\n");
+        assert_eq!(universe.count_live_in_rect(row, col, height, width), 3);
+    }
+
+    #[test]
+    fn plaintext_round_trip() {
+        let universe = Universe::from_plaintext("!Name: Glider\n.O.\n..O\nOOO\n").unwrap();
+        let expected = universe_with(3, 3, &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+        assert_eq!(universe.to_plaintext(), ".O.\n..O\nOOO\n");
+    }
+
+    #[test]
+    fn life106_round_trip() {
+        let universe = universe_with(4, 4, &[(0, 3), (1, 0), (3, 2)]);
+        let copy = Universe::from_life106(&universe.to_life106()).unwrap();
+        assert_eq!(copy.get_cells(), universe.get_cells());
+        assert_eq!(Universe::from_life106("#Life 1.06\n").unwrap().get_cells().len(), 0);
+    }
+
+    #[test]
+    fn stamped_glider_moves() {
+        let mut universe = universe_with(10, 10, &[]);
+        universe.insert_pattern(Pattern::Glider, 8, 8, Transform::Identity).unwrap();
+        assert_eq!(universe.population(), 5);
+        universe.tick_many(4);
+        let expected = universe_with(10, 10, &[(9, 0), (0, 1), (1, 9), (1, 0), (1, 1)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+
+        // A corner past the edges wraps like any other.
+        let mut universe = universe_with(10, 10, &[]);
+        universe.insert_pattern(Pattern::Glider, u32::MAX, u32::MAX, Transform::Identity).unwrap();
+        let mut expected = universe_with(10, 10, &[]);
+        expected.insert_pattern(Pattern::Glider, 5, 5, Transform::Identity).unwrap();
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn rotated_glider_moves_down_left() {
+        let mut universe = universe_with(10, 10, &[]);
+        universe.insert_pattern(Pattern::Glider, 2, 5, Transform::Rotate90).unwrap();
+        universe.tick_many(4);
+        let mut expected = universe_with(10, 10, &[]);
+        expected.insert_pattern(Pattern::Glider, 3, 4, Transform::Rotate90).unwrap();
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn copy_region() {
+        // A blinker straddling the bottom-right corner.
+        let mut universe = universe_with(8, 8, &[(7, 6), (7, 7), (7, 0)]);
+        universe.set_rule("B36/S23").unwrap();
+        let mut copy = universe.copy_region(6, 5, 3, 5);
+        assert_eq!((copy.width(), copy.height()), (5, 3));
+        assert_eq!(copy.rule(), "B36/S23");
+        assert_eq!(copy.get_cells(), universe_with(5, 3, &[(1, 1), (1, 2), (1, 3)]).get_cells());
+
+        copy.tick();
+        assert_eq!(copy.get_cells(), universe_with(5, 3, &[(0, 2), (1, 2), (2, 2)]).get_cells());
+        assert_eq!(universe.get_cells(), universe_with(8, 8, &[(7, 6), (7, 7), (7, 0)]).get_cells());
+        assert_eq!(universe.generation(), 0);
+
+        let clamped = universe.copy_region(0, 0, 100, 100);
+        assert_eq!((clamped.width(), clamped.height()), (8, 8));
+    }
+
+    #[test]
+    fn count_live_in_rect() {
+        // A blinker straddling the bottom-right corner, and a glider.
+        let cells = [(7, 6), (7, 7), (7, 0), (1, 2), (2, 3), (3, 1), (3, 2), (3, 3)];
+        let universe = universe_with(8, 8, &cells);
+        assert_eq!(universe.count_live_in_rect(0, 0, 8, 8), universe.population());
+        assert_eq!(universe.count_live_in_rect(3, 5, 100, 100), universe.population());
+        // Crossing both seams is the same as counting the pieces either side.
+        let across = universe.count_live_in_rect(6, 6, 4, 3);
+        let pieces = universe.count_live_in_rect(6, 6, 2, 2)
+            + universe.count_live_in_rect(6, 0, 2, 1)
+            + universe.count_live_in_rect(0, 6, 2, 2)
+            + universe.count_live_in_rect(0, 0, 2, 1);
+        assert_eq!(across, 3);
+        assert_eq!(across, pieces);
+        assert_eq!(universe.count_live_in_rect(1, 1, 3, 3), 5);
+        assert_eq!(universe.count_live_in_rect(4, 4, 0, 3), 0);
+
+        // A corner off the board wraps onto it.
+        assert_eq!(universe.count_live_in_rect(9, 9, 3, 3), universe.count_live_in_rect(1, 1, 3, 3));
+        assert_eq!(universe.count_live_in_rect(u32::MAX, u32::MAX, 5, 5), universe.count_live_in_rect(7, 7, 5, 5));
+        assert_eq!(universe.region_to_rle(u32::MAX, u32::MAX, 5, 5), universe.region_to_rle(7, 7, 5, 5));
+    }
+
+    #[test]
+    fn density_grid() {
+        // 5 wide and 3 high in tiles of 2: a row of 2×2, 2×2 and 1×2 tiles,
+        // then a row of 2×1, 2×1 and 1×1.
+        let universe = universe_with(5, 3, &[(0, 0), (1, 1), (0, 2), (0, 4), (1, 4), (2, 4)]);
+        assert_eq!(universe.density_grid(2), [0.5, 0.25, 1.0, 0.0, 0.0, 1.0]);
+        let whole = universe.density_grid(10);
+        assert_eq!(whole, [universe.population() as f32 / 15.0]);
+        let cells = universe.density_grid(1);
+        assert_eq!(cells.len(), 15);
+        assert_eq!(universe.density_grid(0), cells);
+        assert_eq!(cells.iter().filter(|&&density| density == 1.0).count() as u32, universe.population());
+    }
+
+    fn paste_into(mode: PasteMode) -> Vec<Cell> {
+        // .OO.
+        // ....
+        let mut universe = universe_with(4, 2, &[(0, 1), (0, 2)]);
+        // OO
+        // O.
+        let other = universe_with(2, 2, &[(0, 0), (0, 1), (1, 0)]);
+        universe.paste(&other, 0, 2, mode);
+        universe.get_cells()
+    }
+
+    #[test]
+    fn move_region_is_cut_and_paste() {
+        // A glider and a cell, with the glider moved down and right across
+        // the bottom-right corner.
+        let cells = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2), (4, 4)];
+        let mut moved = universe_with(8, 8, &cells);
+        moved.move_region(0, 0, 3, 3, 6, 6);
+        let mut expected = universe_with(8, 8, &cells);
+        let clipboard = expected.copy_region(0, 0, 3, 3);
+        expected.clear_region(0, 0, 3, 3);
+        expected.paste(&clipboard, 6, 6, PasteMode::Overwrite);
+        assert_eq!(moved.get_cells(), expected.get_cells());
+        assert_eq!(moved.iter_live().collect::<Vec<_>>(), [(0, 0), (0, 6), (0, 7), (4, 4), (6, 7), (7, 0)]);
+        // One undo puts the whole move back.
+        moved.enable_history(4);
+        moved.move_region(4, 4, 1, 1, 5, 5);
+        assert!(moved.undo());
+        assert_eq!(moved.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn paste_modes() {
+        let overwrite = universe_with(4, 2, &[(0, 1), (0, 2), (0, 3), (1, 2)]);
+        assert_eq!(paste_into(PasteMode::Overwrite), overwrite.get_cells());
+        let or = universe_with(4, 2, &[(0, 1), (0, 2), (0, 3), (1, 2)]);
+        assert_eq!(paste_into(PasteMode::Or), or.get_cells());
+        let xor = universe_with(4, 2, &[(0, 1), (0, 3), (1, 2)]);
+        assert_eq!(paste_into(PasteMode::Xor), xor.get_cells());
+        let and = universe_with(4, 2, &[(0, 1), (0, 2)]);
+        assert_eq!(paste_into(PasteMode::And), and.get_cells());
+    }
+
+    #[test]
+    fn paste_overwrite_clears_dead_cells() {
+        let mut universe = universe_with(3, 3, &[(0, 0), (1, 1), (2, 2)]);
+        let other = universe_with(2, 2, &[(0, 1)]);
+        universe.paste(&other, 0, 0, PasteMode::Overwrite);
+        assert_eq!(universe.get_cells(), universe_with(3, 3, &[(0, 1), (2, 2)]).get_cells());
+    }
+
+    #[test]
+    fn paste_wraps_and_clips() {
+        let mut universe = universe_with(3, 3, &[]);
+        let other = universe_with(5, 4, &[(0, 0), (0, 1), (2, 2), (3, 0), (0, 4)]);
+        universe.paste(&other, 2, 2, PasteMode::Or);
+        assert_eq!(universe.get_cells(), universe_with(3, 3, &[(2, 2), (2, 0), (1, 1)]).get_cells());
+    }
+
+    #[test]
+    fn translate() {
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        let mut universe = universe_with(6, 5, &glider);
+        universe.translate(5, 6);
+        assert_eq!(universe.get_cells(), universe_with(6, 5, &glider).get_cells());
+        universe.translate(-10, 18);
+        assert_eq!(universe.get_cells(), universe_with(6, 5, &glider).get_cells());
+
+        universe.translate(1, 1);
+        let moved = universe_with(6, 5, &[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        assert_eq!(universe.get_cells(), moved.get_cells());
+
+        universe.translate(2, -2);
+        let wrapped = universe_with(6, 5, &[(3, 0), (4, 1), (0, 5), (0, 0), (0, 1)]);
+        assert_eq!(universe.get_cells(), wrapped.get_cells());
+    }
+
+    #[test]
+    fn translate_bounded_drops_cells() {
+        let mut universe = universe_with(4, 4, &[(0, 0), (2, 2), (3, 3)]);
+        universe.set_topology(Topology::Bounded);
+        universe.translate(1, 0);
+        assert_eq!(universe.get_cells(), universe_with(4, 4, &[(1, 0), (3, 2)]).get_cells());
+    }
+
+    #[test]
+    fn insert_rle_rejects_oversized_patterns() {
+        let mut universe = universe_with(2, 2, &[]);
+        assert!(universe.insert_rle(0, 0, "x = 3, y = 1\n3o!", Transform::Identity).is_err());
+
+        let mut universe = universe_with(3, 1, &[]);
+        assert!(universe.insert_rle(0, 0, "x = 3, y = 1\n3o!", Transform::Identity).is_ok());
+        assert!(universe.insert_rle(0, 0, "x = 3, y = 1\n3o!", Transform::Rotate90).is_err());
+    }
+
+    #[test]
+    fn blinker_under_seeds() {
+        let mut life = universe_with(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        life.tick();
+        let expected = universe_with(5, 5, &[(1, 2), (2, 2), (3, 2)]);
+        assert_eq!(life.get_cells(), expected.get_cells());
+
+        let mut seeds = universe_with(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        seeds.set_rule("B2/S").unwrap();
+        assert_eq!(seeds.rule(), "B2/S");
+        seeds.tick();
+        let expected = universe_with(5, 5, &[(1, 1), (1, 3), (3, 1), (3, 3)]);
+        assert_eq!(seeds.get_cells(), expected.get_cells());
+    }
+
+    #[test]
+    fn larger_than_life_of_radius_one_is_classic_life() {
+        for &topology in [Topology::Torus, Topology::Bounded].iter() {
+            let mut classic = Universe::new_random(32, 24, 0.4, 7);
+            let mut ltl = Universe::new_random(32, 24, 0.4, 7);
+            classic.set_topology(topology);
+            ltl.set_topology(topology);
+            ltl.set_rule("R1,C0,M0,S2..3,B3..3,NM").unwrap();
+            for _ in 0..20 {
+                classic.tick();
+                ltl.tick();
+                assert_eq!(ltl.get_cells(), classic.get_cells(), "{:?}", topology);
+            }
+            assert_eq!(ltl.births, classic.births);
+        }
+    }
+
+    #[test]
+    fn blinker_under_von_neumann_neighborhood() {
+        // Its middle cell has two neighbors beside it and survives, the ends
+        // only one, and no dead cell touches three.
+        let mut universe = universe_with(7, 7, &[(2, 1), (2, 2), (2, 3)]);
+        universe.set_neighborhood(NeighborhoodKind::VonNeumann).unwrap();
+        assert_eq!(universe.rule(), "B3/S23V");
+        let trace: Vec<&[(u32, u32)]> = vec![&[(2, 2)], &[], &[]];
+        for (generation, expected) in trace.into_iter().enumerate() {
+            universe.tick();
+            assert_eq!(universe.get_cells(), universe_with(7, 7, expected).get_cells(), "generation {}", generation + 1);
+        }
+        assert_eq!(universe.live_neighbor_count(2, 2), 0);
+    }
+
+    #[test]
+    fn set_neighborhood_checks_the_rule() {
+        let mut universe = universe_with(7, 7, &[(2, 1), (2, 2), (2, 3)]);
+        universe.set_rule("B36/S23").unwrap();
+        assert!(universe.set_neighborhood(NeighborhoodKind::VonNeumann).is_err());
+        assert_eq!(universe.neighborhood(), NeighborhoodKind::Moore);
+        universe.set_neighborhood(NeighborhoodKind::Hexagonal).unwrap();
+        assert_eq!(universe.rule(), "B36/S23H");
+        // The hexagon leaves out the top right and bottom left corners.
+        assert_eq!(universe.live_neighbor_count(1, 2), 2);
+        assert_eq!(universe.live_neighbor_count(3, 2), 2);
+        assert_eq!(universe.live_neighbor_count(1, 1), 2);
+        assert!(universe.set_rule("B7/S2H").is_err());
+        universe.set_neighborhood(NeighborhoodKind::Moore).unwrap();
+        assert_eq!(universe.rule(), "B36/S23");
+    }
+
+    #[test]
+    fn bugs_blob_survives() {
+        // A random blob settles into one of the rule's namesake bugs, which
+        // keeps its size as it crawls.
+        let mut rng = Rng::new(0);
+        let blob: Vec<(u32, u32)> = (24..38).flat_map(|row| (24..38).map(move |col| (row, col))).collect();
+        let cells: Vec<(u32, u32)> = blob.into_iter().filter(|_| rng.next_f64() < 0.5).collect();
+        let mut bugs = universe_with(64, 64, &cells);
+        bugs.set_rule("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        for generation in 0..100 {
+            bugs.tick();
+            if generation >= 80 {
+                assert!((60..=70).contains(&bugs.population()), "{}", bugs.population());
+            }
+        }
+    }
+
+    #[test]
+    fn brians_brain_spaceship_flies() {
+        // The smallest Brian's Brain spaceship: a live pair leading a dying
+        // pair, moving one cell per generation.
+        let mut universe = universe_with(12, 16, &[(12, 5), (12, 6)]);
+        universe.set_rule("B2/S/3").unwrap();
+        assert_eq!(universe.state_count(), 3);
+        let dying = [universe.get_index(13, 5), universe.get_index(13, 6)];
+        for &idx in dying.iter() {
+            universe.states.as_mut().unwrap()[idx] = 2;
+        }
+        for generation in 1..=10 {
+            universe.tick();
+            assert_eq!((universe.last_births(), universe.last_deaths()), (2, 2));
+            let states: Vec<(u32, u32, u8)> = (0..16)
+                .flat_map(|row| (0..12).map(move |col| (row, col)))
+                .map(|(row, col)| (row, col, universe.cell_state(row, col)))
+                .filter(|&(_, _, state)| state != 0)
+                .collect();
+            // It wraps around from the top row.
+            let row = (12 + 16 - generation) % 16;
+            let mut expected = vec![(row, 5, 1), (row, 6, 1), ((row + 1) % 16, 5, 2), ((row + 1) % 16, 6, 2)];
+            expected.sort();
+            assert_eq!(states, expected, "generation {}", generation);
+        }
+    }
+
+    #[test]
+    fn decaying_states_follow_edits_and_rule_changes() {
+        let mut universe = universe_with(8, 8, &[(3, 3), (3, 4)]);
+        universe.set_rule("B2/S/4").unwrap();
+        universe.tick();
+        universe.tick();
+        assert_eq!(universe.cell_state(3, 3), 3);
+        universe.set_cell(3, 3, Cell::Alive);
+        assert_eq!(universe.cell_state(3, 3), 1);
+        assert!(universe.undo());
+        assert_eq!(universe.cell_state(3, 3), 3);
+
+        // Conway's rule forgets the decaying cells, and the image only shows
+        // the live ones.
+        universe.set_rule("B3/S23").unwrap();
+        assert_eq!(universe.cell_state(3, 3), 0);
+        let mut pixels = vec![0; 8 * 8 * 4];
+        universe.render_to_image_data(&mut pixels).unwrap();
+        assert!(pixels.chunks(4).all(|pixel| pixel == render::DEAD_RGBA || pixel == render::ALIVE_RGBA));
+    }
+
+    /// A bounded Wireworld board with a one-cell border around `circuit`.
+    fn wireworld_board(circuit: &str) -> Universe {
+        let cells = wireworld::parse(circuit).unwrap();
+        let height = circuit.lines().count() as u32 + 2;
+        let width = circuit.lines().map(|line| line.len()).max().unwrap() as u32 + 2;
+        let mut universe = universe_with(width, height, &[]);
+        universe.set_topology(Topology::Bounded);
+        universe.set_mode(Mode::Wireworld).unwrap();
+        for (row, col, state) in cells {
+            universe.set_cell_state(row + 1, col + 1, state).unwrap();
+        }
+        universe
+    }
+
+    /// The generations, within `generations`, at which the cell is a head.
+    fn heads_at(universe: &mut Universe, row: u32, col: u32, generations: u32) -> Vec<u32> {
+        (1..=generations)
+            .filter(|_| {
+                universe.tick();
+                universe.cell_state(row, col) == wireworld::HEAD
+            })
+            .collect()
+    }
+
+    #[test]
+    fn wireworld_clock_circulates() {
+        // An electron, a head and its tail, goes round a loop of six cells.
+        let mut ring = wireworld_board(".BA.\nC..C\n.CC.");
+        assert_eq!(ring.population(), 1);
+        assert_eq!(ring.detect_period(20), Some(6));
+
+        // The clock sends an electron down its wire every time round.
+        let mut clock = wireworld_board(WireworldPattern::Clock.text());
+        assert_eq!(heads_at(&mut clock, 2, 9, 30), vec![6, 12, 18, 24, 30]);
+    }
+
+    #[test]
+    fn wireworld_gates() {
+        let diode = WireworldPattern::Diode.text();
+        let mut forward = wireworld_board(&diode.replacen("CCCCC", "BACCC", 1));
+        assert_eq!(heads_at(&mut forward, 2, 10, 20), vec![8]);
+        let mut backward = wireworld_board(&diode.replacen("CCCC\n", "CCAB\n", 1));
+        assert_eq!(heads_at(&mut backward, 2, 1, 20), Vec::<u32>::new());
+
+        // An electron on the top input, the bottom one, or both.
+        let xor = WireworldPattern::XorGate.text();
+        let top = format!("BA{}", &xor[2..]);
+        let bottom = format!("{}BA{}", &xor[..xor.len() - 4], &xor[xor.len() - 2..]);
+        let both = format!("BA{}", &bottom[2..]);
+        let output = |circuit: &str| heads_at(&mut wireworld_board(circuit), 5, 14, 40);
+        assert_eq!(output(&top), vec![12]);
+        assert_eq!(output(&bottom), vec![12]);
+        assert_eq!(output(&both), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn wireworld_editing() {
+        let mut universe = universe_with(6, 6, &[(1, 1)]);
+        universe.set_mode(Mode::Wireworld).unwrap();
+        assert_eq!(universe.state_count(), 4);
+        assert_eq!(universe.cell_state(1, 1), wireworld::HEAD);
+        universe.toggle_cell(2, 2);
+        assert_eq!(universe.cell_state(2, 2), wireworld::CONDUCTOR);
+        universe.toggle_cell(2, 2);
+        assert_eq!(universe.cell_state(2, 2), wireworld::HEAD);
+        universe.toggle_cell(2, 2);
+        assert_eq!(universe.cell_state(2, 2), wireworld::EMPTY);
+        universe.set_cell_state(3, 3, wireworld::CONDUCTOR).unwrap();
+        universe.set_cell(3, 3, Cell::Dead);
+        assert_eq!(universe.cell_state(3, 3), wireworld::EMPTY);
+        assert!(universe.set_cell_state(3, 3, 4).is_err());
+
+        // Going back to Life keeps only the heads.
+        universe.insert_wireworld_pattern(WireworldPattern::Diode, 2, 0);
+        universe.set_mode(Mode::Life).unwrap();
+        assert_eq!(universe.state_count(), 2);
+        assert_eq!(universe.iter_live().collect::<Vec<_>>(), vec![(1, 1)]);
+
+        // A corner past the edges wraps like any other.
+        let mut wrapped = universe_with(10, 10, &[]);
+        wrapped.insert_wireworld_pattern(WireworldPattern::Diode, u32::MAX, u32::MAX);
+        let mut expected = universe_with(10, 10, &[]);
+        expected.insert_wireworld_pattern(WireworldPattern::Diode, 5, 5);
+        assert_eq!(wrapped.states, expected.states);
+    }
+
+    /// Every cell that isn't dead, with its state.
+    fn occupied(universe: &Universe) -> Vec<(u32, u32, u8)> {
+        let mut cells = vec![];
+        for row in 0..universe.height() {
+            for col in 0..universe.width() {
+                let state = universe.cell_state(row, col);
+                if state != 0 {
+                    cells.push((row, col, state));
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn wireworld_moves_and_copies_keep_states() {
+        let clock = || {
+            let mut universe = universe_with(16, 12, &[]);
+            universe.insert_wireworld_pattern(WireworldPattern::Clock, 1, 1);
+            universe
+        };
+        let original = occupied(&clock());
+        let shifted = |rows: i64, cols: i64| -> Vec<(u32, u32, u8)> {
+            original.iter().map(|&(row, col, state)| ((row as i64 + rows) as u32, (col as i64 + cols) as u32, state)).collect()
+        };
+
+        let mut universe = clock();
+        universe.translate(2, 3);
+        assert_eq!(occupied(&universe), shifted(2, 3));
+        universe.translate(-2, -3);
+        assert_eq!(occupied(&universe), original);
+
+        let mut universe = clock();
+        universe.resize(20, 14, Anchor::Center).unwrap();
+        assert_eq!(occupied(&universe), shifted(1, 2));
+        universe.resize(16, 12, Anchor::TopLeft).unwrap();
+        assert_eq!(occupied(&universe), shifted(1, 2));
+
+        let copy = clock().copy_region(1, 1, 3, 9);
+        assert_eq!(copy.mode(), Mode::Wireworld);
+        assert_eq!(occupied(&copy), shifted(-1, -1));
+        let mut board = universe_with(16, 12, &[]);
+        board.set_mode(Mode::Wireworld).unwrap();
+        board.paste(&copy, 5, 4, PasteMode::Overwrite);
+        assert_eq!(occupied(&board), shifted(4, 3));
+        board.paste(&copy, 5, 4, PasteMode::Or);
+        assert_eq!(occupied(&board), shifted(4, 3));
+
+        let mut universe = clock();
+        universe.move_region(1, 1, 3, 9, 6, 5);
+        assert_eq!(occupied(&universe), shifted(5, 4));
+        assert_eq!(universe.detect_period(20), clock().detect_period(20));
+    }
+
+    #[test]
+    fn immigration_births_take_the_majority_color() {
+        let mut universe = universe_with(5, 5, &[]);
+        universe.set_mode(Mode::Immigration).unwrap();
+        universe.set_cell_state(2, 1, immigration::FIRST).unwrap();
+        universe.set_cell_state(2, 2, immigration::SECOND).unwrap();
+        universe.set_cell_state(2, 3, immigration::FIRST).unwrap();
+        let colors = |universe: &Universe| {
+            (0..5).map(|row| (0..5).map(|col| universe.cell_state(row, col).to_string()).collect::<String>()).collect::<Vec<_>>()
+        };
+
+        // The newborns above and below have two first-color parents, and
+        // the survivor in the middle keeps its color.
+        universe.tick();
+        assert_eq!(colors(&universe), vec!["00000", "00100", "00200", "00100", "00000"]);
+        assert_eq!((universe.last_births(), universe.last_deaths()), (2, 2));
+        universe.tick();
+        assert_eq!(colors(&universe), vec!["00000", "00000", "01210", "00000", "00000"]);
+        assert_eq!(universe.population(), 3);
+        assert_eq!(universe.population_of(immigration::SECOND), 1);
+    }
+
+    #[test]
+    fn immigration_keeps_to_one_color() {
+        let mut universe = Universe::new_random(32, 32, 0.4, 7);
+        universe.set_mode(Mode::Immigration).unwrap();
+        let mut life = universe.copy_region(0, 0, 32, 32);
+        for _ in 0..50 {
+            universe.tick();
+            life.tick();
+            assert_eq!(universe.population_of(immigration::SECOND), 0);
+            assert_eq!(universe.population_of(immigration::FIRST), life.population());
+        }
+    }
+
+    #[test]
+    fn rule_schedule_takes_turns() {
+        let mut scheduled = Universe::new_random(32, 32, 0.3, 5);
+        scheduled.set_rule_schedule(vec![("B3/S23".to_string(), 3), ("B2/S".to_string(), 2)]).unwrap();
+        scheduled.enable_history(4);
+        let mut by_hand = Universe::new_random(32, 32, 0.3, 5);
+        let mut life = Universe::new_random(32, 32, 0.3, 5);
+        for generation in 0..12 {
+            let expected = if generation % 5 < 3 { "B3/S23" } else { "B2/S" };
+            assert_eq!(scheduled.current_rule(), expected, "generation {}", generation);
+            by_hand.set_rule(expected).unwrap();
+            scheduled.tick();
+            by_hand.tick();
+            life.tick();
+            assert_eq!(scheduled.get_cells(), by_hand.get_cells(), "generation {}", generation + 1);
+            // Seeds first runs at generation 3.
+            assert_eq!(scheduled.get_cells() == life.get_cells(), generation < 3, "generation {}", generation + 1);
+        }
+
+        // Stepping back goes back to the rule of the earlier generation.
+        for _ in 0..4 {
+            assert!(scheduled.step_back());
+        }
+        assert_eq!((scheduled.generation(), scheduled.current_rule().as_str()), (8, "B2/S"));
+        scheduled.set_rule("B36/S23").unwrap();
+        assert_eq!(scheduled.current_rule(), "B36/S23");
+    }
+
+    #[test]
+    fn rule_schedule_errors() {
+        let mut universe = universe_with(8, 8, &[]);
+        universe.set_rule("B36/S23").unwrap();
+        assert!(universe.set_rule_schedule(vec![]).is_err());
+        assert!(universe.set_rule_schedule(vec![("B3/S23".to_string(), 0)]).is_err());
+        assert!(universe.set_rule_schedule(vec![("B3/S23".to_string(), 2), ("B9".to_string(), 1)]).is_err());
+        assert_eq!(universe.current_rule(), "B36/S23");
+        universe.set_rule_schedule(vec![("B3/S23".to_string(), 2)]).unwrap();
+        universe.set_topology(Topology::Bounded);
+        assert!(universe.fast_forward(10).is_err());
+        universe.clear_rule_schedule();
+        assert_eq!(universe.current_rule(), "B36/S23");
+    }
+
+    #[test]
+    fn langtons_ant_builds_its_highway() {
+        let mut universe = universe_with(128, 128, &[]);
+        universe.set_ant_mode(AntMode::Alone);
+        universe.add_ant(64, 64, Direction::North).unwrap();
+        universe.tick_many(11000);
+        assert_eq!(universe.iter_ants().collect::<Vec<_>>(), [&Ant { row: 78, col: 30, direction: Direction::South }]);
+        assert_eq!(universe.population(), 834);
+        // The highway repeats every 104 steps, two cells down and left.
+        universe.tick_many(104);
+        assert_eq!(universe.iter_ants().collect::<Vec<_>>(), [&Ant { row: 80, col: 28, direction: Direction::South }]);
+        assert_eq!(universe.population(), 846);
+    }
+
+    #[test]
+    fn ants_take_turns() {
+        let mut universe = universe_with(8, 8, &[]);
+        universe.set_ant_mode(AntMode::Alone);
+        universe.add_ant(3, 3, Direction::North).unwrap();
+        universe.add_ant(3, 3, Direction::North).unwrap();
+        assert!(universe.add_ant(8, 0, Direction::East).is_err());
+        // The first ant lights the cell and leaves east; the second finds it
+        // alive, darkens it and leaves west.
+        universe.tick();
+        assert_eq!(universe.population(), 0);
+        assert_eq!((universe.last_births(), universe.last_deaths()), (0, 0));
+        let ants: Vec<_> = universe.iter_ants().map(|ant| (ant.row, ant.col, ant.direction)).collect();
+        assert_eq!(ants, [(3, 4, Direction::East), (3, 2, Direction::West)]);
+        assert!(universe.remove_ant(0));
+        assert!(!universe.remove_ant(1));
+        assert_eq!(universe.ant_count(), 1);
+    }
+
+    #[test]
+    fn ants_walk_after_the_rule() {
+        let mut universe = universe_with(8, 8, &[(2, 1), (2, 2), (2, 3)]);
+        universe.add_ant(2, 2, Direction::North).unwrap();
+        // The blinker turns upright, then the ant darkens its middle.
+        universe.tick();
+        assert_eq!(universe.iter_live().collect::<Vec<_>>(), [(1, 2), (3, 2)]);
+        assert_eq!((universe.last_births(), universe.last_deaths()), (2, 3));
+        universe.clear_ants();
+        universe.set_ant_mode(AntMode::Alone);
+        universe.tick();
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    fn cropping_drops_ants_off_the_board() {
+        let mut universe = universe_with(200, 200, &[(2, 1), (2, 2), (2, 3)]);
+        universe.add_ant(100, 100, Direction::North).unwrap();
+        universe.add_ant(2, 2, Direction::North).unwrap();
+        universe.crop_to_content(1);
+        assert_eq!((universe.width(), universe.height()), (5, 3));
+        assert_eq!(universe.ant_count(), 1);
+        universe.tick();
+    }
+
+    #[test]
+    fn noise_is_reproducible() {
+        let run = |seed| {
+            let mut universe = Universe::new_random(32, 32, 0.3, 1);
+            universe.set_noise(0.2, 0.01, 0.005);
+            universe.set_noise_seed(seed);
+            (0..20).map(|_| {
+                universe.tick();
+                universe.cells_copy()
+            }).collect::<Vec<_>>()
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn noise_events() {
+        let mut universe = Universe::new_random(16, 16, 0.5, 1);
+        universe.set_noise(0.0, 1.0, 0.0);
+        universe.tick();
+        assert_eq!(universe.population(), 0);
+        assert_eq!(universe.last_births(), 0);
+
+        universe.set_noise(0.0, 0.0, 1.0);
+        universe.tick();
+        assert_eq!(universe.population(), 256);
+        assert_eq!(universe.last_births(), 256);
+
+        // Without noise nothing is drawn from the generator.
+        let mut quiet = Universe::new_random(16, 16, 0.5, 1);
+        let mut plain = Universe::new_random(16, 16, 0.5, 1);
+        quiet.set_noise(0.5, 0.5, 0.5);
+        quiet.set_noise(0.0, 0.0, 0.0);
+        let before = quiet.noise_rng.clone().next_u64();
+        for _ in 0..5 {
+            quiet.tick();
+            plain.tick();
+        }
+        assert_eq!(quiet.get_cells(), plain.get_cells());
+        assert_eq!(quiet.noise_rng.next_u64(), before);
+
+        // Hashlife has no noise to add.
+        let mut noisy = Universe::new_random(32, 32, 0.3, 1);
+        noisy.set_topology(Topology::Bounded);
+        noisy.set_noise(0.0, 1.0, 0.0);
+        assert!(noisy.fast_forward(10).is_err());
+        assert_eq!(noisy.generation(), 0);
+        noisy.set_noise(0.0, 0.0, 0.0);
+        assert!(noisy.fast_forward(10).is_ok());
+    }
+
+    #[test]
+    fn critters_runs_backwards() {
+        for seed in 0..4 {
+            let mut universe = Universe::new_random(24, 16, 0.3, seed);
+            universe.set_mode(Mode::Critters).unwrap();
+            let start = universe.cells_copy();
+            let mut boards = vec![start.clone()];
+            // Runs forward from both phases of the blocks and back again.
+            for _ in 0..9 {
+                universe.tick();
+                boards.push(universe.cells_copy());
+            }
+            assert_ne!(universe.cells_copy(), start);
+            while let Some(board) = boards.pop() {
+                assert_eq!(universe.cells_copy(), board, "seed {} generation {}", seed, universe.generation());
+                if universe.generation() > 0 {
+                    universe.tick_reverse().unwrap();
+                }
+            }
+            assert_eq!(universe.generation(), 0);
+            assert!(universe.tick_reverse().is_err());
+        }
+    }
+
+    #[test]
+    fn critters_moves_blocks() {
+        // A block with one live cell and the empty blocks around it all
+        // flip.
+        let mut universe = universe_with(4, 4, &[(0, 0)]);
+        universe.set_mode(Mode::Critters).unwrap();
+        universe.tick();
+        let expected: Vec<(u32, u32)> = (0..4).flat_map(|row| (0..4).map(move |col| (row, col))).filter(|&cell| cell != (0, 0)).collect();
+        assert_eq!(universe.get_cells(), universe_with(4, 4, &expected).get_cells());
+        assert_eq!((universe.last_births(), universe.last_deaths()), (15, 1));
+        // Offset by a cell, the full blocks empty, and the block wrapping
+        // around the corner, with three live cells, flips and turns.
+        universe.tick();
+        assert_eq!(universe.get_cells(), universe_with(4, 4, &[(3, 3)]).get_cells());
+    }
+
+    #[test]
+    fn critters_needs_even_sides() {
+        let mut universe = universe_with(5, 4, &[]);
+        assert!(universe.set_mode(Mode::Critters).is_err());
+        assert_eq!(universe.mode(), Mode::Life);
+        assert!(universe.tick_reverse().is_err());
+        universe.set_width(6);
+        universe.set_mode(Mode::Critters).unwrap();
+        assert!(universe.tick_reverse().is_err());
+    }
+
+    #[test]
+    fn set_rule_rejects_malformed_rules() {
+        let mut universe = universe_with(5, 5, &[]);
+        assert!(universe.set_rule("B9/S23").is_err());
+        assert_eq!(universe.rule(), "B3/S23");
+    }
+
+    #[test]
+    fn neighbors_wrap_around_edges() {
+        // A blinker straddling the corner must still oscillate on the torus.
+        let mut universe = universe_with(5, 5, &[(0, 4), (0, 0), (0, 1)]);
+        let expected = universe_with(5, 5, &[(4, 0), (0, 0), (1, 0)]);
+        universe.tick();
+        assert_eq!(universe.get_cells(), expected.get_cells());
+    }
+
+    fn assert_ticks(before: &str, after: &str) {
+        let mut universe: Universe = before.parse().unwrap();
+        universe.tick();
+        assert_eq!(format!("{:#}", universe), after);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let universe = universe_with(3, 2, &[(0, 1), (1, 2)]);
+        assert_eq!(universe.to_string(), "◻◼◻\n◻◻◼\n");
+        assert_eq!(format!("{:#}", universe), ".#.\n..#\n");
+        for text in &[universe.to_string(), format!("{:#}", universe)] {
+            let parsed: Universe = text.parse().unwrap();
+            assert_eq!(parsed.get_cells(), universe.get_cells());
+        }
+        let parsed: Universe = ".#.\n..#\n\n\n".parse().unwrap();
+        assert_eq!((parsed.width(), parsed.height()), (3, 2));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("".parse::<Universe>().err(), Some("universe has no rows".to_string()));
+        assert_eq!("\n\n".parse::<Universe>().err(), Some("universe has no rows".to_string()));
+        assert_eq!(".#.\n..\n".parse::<Universe>().err(), Some("row 2 has 2 cells, but row 1 has 3".to_string()));
+        assert_eq!(
+            ".#.\n.x.\n".parse::<Universe>().err(),
+            Some("unexpected character 'x' at row 2, column 2; expected '◻', '◼', '.' or '#'".to_string())
+        );
+    }
+
+    #[test]
+    fn blinker_text() {
+        assert_ticks(
+            ".....\n\
+             ..#..\n\
+             ..#..\n\
+             ..#..\n\
+             .....\n",
+            ".....\n\
+             .....\n\
+             .###.\n\
+             .....\n\
+             .....\n",
+        );
+    }
+
+    #[test]
+    fn toad_text() {
+        assert_ticks(
+            "......\n\
+             ......\n\
+             ..###.\n\
+             .###..\n\
+             ......\n\
+             ......\n",
+            "......\n\
+             ...#..\n\
+             .#..#.\n\
+             .#..#.\n\
+             ..#...\n\
+             ......\n",
+        );
+    }
+
+    #[test]
+    fn beacon_text() {
+        assert_ticks(
+            "......\n\
+             .##...\n\
+             .##...\n\
+             ...##.\n\
+             ...##.\n\
+             ......\n",
+            "......\n\
+             .##...\n\
+             .#....\n\
+             ....#.\n\
+             ...##.\n\
+             ......\n",
+        );
     }
 }
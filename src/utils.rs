@@ -22,9 +22,99 @@ pub fn document() -> web_sys::Document {
     window().document().unwrap()
 }
 
-pub fn request_animation_frame(f: &wasm_bindgen::prelude::Closure<dyn FnMut()>) {
+/// A callback for `requestAnimationFrame`, given the frame's timestamp.
+pub type FrameClosure = wasm_bindgen::prelude::Closure<dyn FnMut(f64)>;
+
+/// Call `f` before the next repaint, returning the handle to cancel it with.
+pub fn request_animation_frame(f: &FrameClosure) -> i32 {
     use wasm_bindgen::JsCast;
     window()
         .request_animation_frame(f.as_ref().unchecked_ref())
-        .expect("should register `requestAnimationFrame` OK");
+        .expect("should register `requestAnimationFrame` OK")
+}
+
+cfg_if::cfg_if! {
+    // Profiling hooks cost nothing unless the `profile` feature asks for
+    // them, so the call sites are gated on it too.
+    if #[cfg(feature = "profile")] {
+        extern crate js_sys;
+
+        use std::cell::RefCell;
+
+        /// Times its own lifetime with `console.time`, so each call shows up
+        /// in the devtools console under `label`.
+        pub struct Timer<'a> {
+            label: &'a str,
+        }
+
+        impl<'a> Timer<'a> {
+            pub fn new(label: &'a str) -> Timer<'a> {
+                web_sys::console::time_with_label(label);
+                Timer { label }
+            }
+        }
+
+        impl<'a> Drop for Timer<'a> {
+            fn drop(&mut self) {
+                web_sys::console::time_end_with_label(self.label);
+            }
+        }
+
+        /// The calls to one named span, and the total time they took.
+        struct Bucket {
+            name: &'static str,
+            calls: u32,
+            total_ms: f64,
+        }
+
+        thread_local! {
+            static BUCKETS: RefCell<Vec<Bucket>> = const { RefCell::new(Vec::new()) };
+        }
+
+        /// Adds its own lifetime to the bucket called `name`, without logging
+        /// anything, for code that runs too often for `Timer`.
+        pub struct Span {
+            name: &'static str,
+            start: f64,
+        }
+
+        impl Span {
+            pub fn new(name: &'static str) -> Span {
+                Span { name, start: window().performance().unwrap().now() }
+            }
+        }
+
+        impl Drop for Span {
+            fn drop(&mut self) {
+                let elapsed = window().performance().unwrap().now() - self.start;
+                BUCKETS.with(|buckets| {
+                    let mut buckets = buckets.borrow_mut();
+                    match buckets.iter_mut().find(|bucket| bucket.name == self.name) {
+                        Some(bucket) => {
+                            bucket.calls += 1;
+                            bucket.total_ms += elapsed;
+                        }
+                        None => buckets.push(Bucket { name: self.name, calls: 1, total_ms: elapsed }),
+                    }
+                });
+            }
+        }
+
+        /// Show the calls and time spent in each `Span` with `console.table`,
+        /// then start counting again.
+        pub fn log_spans() {
+            let table = js_sys::Object::new();
+            BUCKETS.with(|buckets| {
+                for bucket in buckets.borrow_mut().drain(..) {
+                    let row = js_sys::Object::new();
+                    let set = |key: &str, value: f64| js_sys::Reflect::set(&row, &key.into(), &value.into()).unwrap();
+                    set("calls", bucket.calls as f64);
+                    set("total ms", bucket.total_ms);
+                    set("mean ms", bucket.total_ms / bucket.calls as f64);
+                    js_sys::Reflect::set(&table, &bucket.name.into(), &row).unwrap();
+                }
+            });
+            web_sys::console::table_1(&table);
+        }
+    }
 }
@@ -0,0 +1,150 @@
+//! Wireworld, a cellular automaton for building circuits: electrons run
+//! along wires of conductor, one cell per generation.
+
+use wasm_bindgen::prelude::*;
+
+/// The states of a Wireworld cell, numbered as in Golly. Heads are the
+/// live cells, so counting live neighbors counts heads.
+pub const EMPTY: u8 = 0;
+pub const HEAD: u8 = 1;
+pub const TAIL: u8 = 2;
+pub const CONDUCTOR: u8 = 3;
+pub const STATES: u16 = 4;
+
+/// The colors of heads, tails and conductors as RGBA bytes. Empty cells
+/// are drawn in the dead cell color.
+pub const COLORS: [[u8; 4]; 3] = [
+    [0x3B, 0x82, 0xF6, 0xFF],
+    [0xEF, 0x44, 0x44, 0xFF],
+    [0xF5, 0xB8, 0x2E, 0xFF],
+];
+
+/// The state after `state`, given how many of the cell's neighbors are
+/// electron heads.
+#[inline]
+pub fn next_state(state: u8, heads: u8) -> u8 {
+    match state {
+        HEAD => TAIL,
+        TAIL => CONDUCTOR,
+        CONDUCTOR if heads == 1 || heads == 2 => HEAD,
+        state => state,
+    }
+}
+
+/// The state a click changes a cell to: empty to conductor to head, and
+/// anything else back to empty.
+pub fn cycled(state: u8) -> u8 {
+    match state {
+        EMPTY => CONDUCTOR,
+        CONDUCTOR => HEAD,
+        _ => EMPTY,
+    }
+}
+
+/// A named Wireworld circuit that can be stamped with
+/// `Universe::insert_wireworld_pattern`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireworldPattern {
+    /// A loop an electron circles, sending one down the wire leading off it
+    /// every 6 generations.
+    Clock,
+    /// Lets electrons through from left to right, but not back.
+    Diode,
+    /// Sends an electron out on the right when one arrives on exactly one
+    /// of the two wires on the left.
+    XorGate,
+}
+
+impl WireworldPattern {
+    pub const ALL: [WireworldPattern; 3] = [WireworldPattern::Clock, WireworldPattern::Diode, WireworldPattern::XorGate];
+
+    /// The name used for the pattern in the page's pattern picker.
+    pub fn name(self) -> &'static str {
+        match self {
+            WireworldPattern::Clock => "wireworld-clock",
+            WireworldPattern::Diode => "wireworld-diode",
+            WireworldPattern::XorGate => "wireworld-xor",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<WireworldPattern> {
+        WireworldPattern::ALL.iter().cloned().find(|pattern| pattern.name() == name)
+    }
+
+    /// The circuit as rows of `.` for empty, `A` for heads, `B` for tails
+    /// and `C` for conductors, the letters Golly uses.
+    pub fn text(self) -> &'static str {
+        match self {
+            WireworldPattern::Clock => "\
+.BA.
+C..CCCCCC
+.CC.",
+            WireworldPattern::Diode => "\
+....CC
+CCCCC.CCCC
+....CC",
+            WireworldPattern::XorGate => "\
+CCCC
+....C
+.....C
+......CCC
+........CCCCCC
+......CCC
+.....C
+....C
+CCCC",
+        }
+    }
+
+    /// The cells of the circuit that aren't empty, as row, column and state.
+    pub(crate) fn cells(self) -> Vec<(u32, u32, u8)> {
+        parse(self.text()).expect("library circuits are valid")
+    }
+}
+
+/// Read a circuit written as in `WireworldPattern::text`, returning the
+/// cells that aren't empty.
+pub fn parse(text: &str) -> Result<Vec<(u32, u32, u8)>, String> {
+    let mut cells = vec![];
+    for (row, line) in text.lines().enumerate() {
+        for (col, c) in line.trim_end().chars().enumerate() {
+            let state = match c {
+                '.' => continue,
+                'A' => HEAD,
+                'B' => TAIL,
+                'C' => CONDUCTOR,
+                c => return Err(format!("unexpected character '{}' in Wireworld circuit", c)),
+            };
+            cells.push((row as u32, col as u32, state));
+        }
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        assert_eq!(next_state(HEAD, 3), TAIL);
+        assert_eq!(next_state(TAIL, 1), CONDUCTOR);
+        assert_eq!(next_state(CONDUCTOR, 0), CONDUCTOR);
+        assert_eq!(next_state(CONDUCTOR, 1), HEAD);
+        assert_eq!(next_state(CONDUCTOR, 2), HEAD);
+        assert_eq!(next_state(CONDUCTOR, 3), CONDUCTOR);
+        assert_eq!(next_state(EMPTY, 2), EMPTY);
+        assert_eq!(cycled(cycled(cycled(EMPTY))), EMPTY);
+    }
+
+    #[test]
+    fn parses_circuits() {
+        assert_eq!(parse(".A\nBC").unwrap(), vec![(0, 1, HEAD), (1, 0, TAIL), (1, 1, CONDUCTOR)]);
+        assert!(parse("CX").is_err());
+        for &pattern in WireworldPattern::ALL.iter() {
+            assert_eq!(WireworldPattern::from_name(pattern.name()), Some(pattern));
+            assert!(!pattern.cells().is_empty());
+        }
+    }
+}
@@ -0,0 +1,712 @@
+//! Running the simulation in a Web Worker, so ticking a big board doesn't
+//! hold up drawing and input on the page.
+//!
+//! The page and the worker exchange plain objects with a `type` field. The
+//! page sends `init`, `tick`, `set_cell`, `load` and `snapshot` requests.
+//! The worker answers `init`, `tick` and `snapshot` with a `board` whose
+//! cells come in a transferred `ArrayBuffer`, laid out as by
+//! `Universe::cells_copy`. It sends `ready` once it can take requests.
+//!
+//! Where the browser can hand a canvas to a worker, the page also sends
+//! `attach` with an `OffscreenCanvas`, and `play` and `pause`. The worker
+//! then draws and runs its own animation loop. It reports the board now and
+//! then, and when it pauses, so the page does no work per frame.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use canvas;
+use utils::FrameClosure;
+use universe::{AutoPause, Cell, Settled, Topology, Universe};
+
+/// The least time between reports of a running board, in milliseconds.
+const REPORT_INTERVAL_MS: f64 = 250.0;
+
+/// A request from the page to the worker.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    /// Start over with a random board.
+    Init { width: u32, height: u32, density: f64, seed: u64 },
+    /// Advance up to `generations` generations, stopping early if the board
+    /// settles in a way `auto_pause` stops for.
+    Tick { generations: u32, auto_pause: AutoPause },
+    SetCell { row: u32, col: u32, cell: Cell },
+    /// Continue a painted stroke, as `Universe::extend_line`.
+    ExtendLine { from: (u32, u32), to: (u32, u32), cell: Cell },
+    /// Paint cells as part of a stroke, as `Universe::paint_cells`, for
+    /// brushes bigger than a cell.
+    Paint { cells: Vec<(u32, u32)>, cell: Cell },
+    /// Replace the board and how it looks with the page's, after it was
+    /// edited there.
+    Load {
+        width: u32,
+        height: u32,
+        generation: u64,
+        rule: String,
+        topology: Topology,
+        cell_size: u32,
+        grid_visible: bool,
+        /// The grid, dead cell and live cell colors.
+        colors: [String; 3],
+        cells: Vec<u8>,
+    },
+    /// Send the board back as it is.
+    Snapshot,
+    /// Draw on this canvas from now on.
+    Attach { canvas: web_sys::OffscreenCanvas },
+    /// Keep ticking every animation frame, as `Tick` does once.
+    Play { generations: u32, auto_pause: AutoPause },
+    /// Stop ticking, and send the board back.
+    Pause,
+}
+
+impl Request {
+    /// A `Load` of the page's universe.
+    pub fn load(universe: &Universe) -> Request {
+        Request::Load {
+            width: universe.width(),
+            height: universe.height(),
+            generation: universe.generation(),
+            rule: universe.rule(),
+            topology: universe.topology(),
+            cell_size: universe.cell_size(),
+            grid_visible: universe.grid_visible(),
+            colors: [universe.grid_color(), universe.dead_color(), universe.alive_color()],
+            cells: universe.cells_copy(),
+        }
+    }
+}
+
+/// The board as the worker sends it back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    pub width: u32,
+    pub height: u32,
+    pub generation: u64,
+    /// Why the worker stopped ticking early, if it did.
+    pub settled: Option<Settled>,
+    pub cells: Vec<u8>,
+}
+
+/// The worker's side: a universe that answers requests.
+pub struct Simulation {
+    universe: Universe,
+    /// Where to draw, once the page hands over its canvas.
+    view: Option<(web_sys::OffscreenCanvas, web_sys::OffscreenCanvasRenderingContext2d)>,
+    /// How far to tick each frame, while playing.
+    running: Option<(u32, AutoPause)>,
+}
+
+impl Simulation {
+    pub fn new() -> Simulation {
+        Simulation {
+            universe: Universe::new(),
+            view: None,
+            running: None,
+        }
+    }
+
+    /// Whether the worker is running its own animation loop.
+    pub fn running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Tick and draw one animation frame, stopping if the board settles.
+    pub fn frame(&mut self) -> Option<Board> {
+        let (generations, auto_pause) = self.running?;
+        let board = self.handle(Request::Tick { generations, auto_pause }).ok()??;
+        if board.settled.is_some() {
+            self.running = None;
+        }
+        Some(board)
+    }
+
+    /// Carry out a request, returning the board if the request wants it.
+    pub fn handle(&mut self, request: Request) -> Result<Option<Board>, String> {
+        let mut settled = None;
+        let mut redraw = false;
+        let reply = match request {
+            Request::Init { width, height, density, seed } => {
+                self.universe = Universe::new_random(width, height, density, seed);
+                redraw = true;
+                true
+            }
+            Request::Tick { generations, auto_pause } => {
+                for _ in 0..generations {
+                    self.universe.tick();
+                    settled = self.universe.settled().filter(|&how| auto_pause.pauses_for(how));
+                    if settled.is_some() {
+                        break;
+                    }
+                }
+                true
+            }
+            Request::SetCell { row, col, cell } => {
+                self.universe.try_set_cell(row, col, cell)?;
+                false
+            }
+            Request::ExtendLine { from, to, cell } => {
+                self.universe.check_bounds(from.0, from.1)?;
+                self.universe.check_bounds(to.0, to.1)?;
+                self.universe.extend_line(from, to, cell as u8);
+                false
+            }
+            Request::Paint { ref cells, cell } => {
+                for &(row, col) in cells {
+                    self.universe.check_bounds(row, col)?;
+                }
+                self.universe.paint_cells(cells, cell as u8);
+                false
+            }
+            Request::Load { width, height, generation, rule, topology, cell_size, grid_visible, colors, cells } => {
+                self.universe.load(width, height, generation, &cells)?;
+                self.universe.set_rule(&rule)?;
+                self.universe.set_topology(topology);
+                self.universe.set_cell_size(cell_size);
+                self.universe.set_grid_visible(grid_visible);
+                self.universe.set_grid_color(&colors[0])?;
+                self.universe.set_dead_color(&colors[1])?;
+                self.universe.set_alive_color(&colors[2])?;
+                redraw = true;
+                false
+            }
+            Request::Snapshot => true,
+            Request::Attach { canvas } => {
+                let context = canvas
+                    .get_context("2d")
+                    .ok()
+                    .and_then(|context| context)
+                    .and_then(|context| context.dyn_into::<web_sys::OffscreenCanvasRenderingContext2d>().ok())
+                    .ok_or("2d offscreen canvas context is not available")?;
+                self.view = Some((canvas, context));
+                redraw = true;
+                false
+            }
+            Request::Play { generations, auto_pause } => {
+                self.running = Some((generations, auto_pause));
+                false
+            }
+            Request::Pause => {
+                self.running = None;
+                true
+            }
+        };
+        self.draw(redraw);
+        if !reply {
+            return Ok(None);
+        }
+        Ok(Some(Board {
+            width: self.universe.width(),
+            height: self.universe.height(),
+            generation: self.universe.generation(),
+            settled,
+            cells: self.universe.cells_copy(),
+        }))
+    }
+
+    /// Draw whatever changed on the canvas, if there is one, or everything
+    /// with `redraw`.
+    fn draw(&mut self, redraw: bool) {
+        if let Some((ref canvas, ref context)) = self.view {
+            if redraw {
+                // A worker has no window to ask for its pixel ratio.
+                canvas::init_canvas(&self.universe, canvas, context, 1.0);
+                canvas::draw_board(&self.universe, context);
+            } else {
+                canvas::draw_delta(&self.universe, context);
+            }
+            canvas::draw_heatmap(&self.universe, context);
+        }
+        self.universe.mark_drawn();
+    }
+}
+
+fn get(message: &JsValue, key: &str) -> Result<JsValue, JsValue> {
+    js_sys::Reflect::get(message, &JsValue::from_str(key))
+}
+
+fn get_number(message: &JsValue, key: &str) -> Result<f64, JsValue> {
+    get(message, key)?
+        .as_f64()
+        .ok_or_else(|| JsValue::from_str(&format!("message needs a number for `{}`", key)))
+}
+
+fn get_string(message: &JsValue, key: &str) -> Result<String, JsValue> {
+    get(message, key)?
+        .as_string()
+        .ok_or_else(|| JsValue::from_str(&format!("message needs a string for `{}`", key)))
+}
+
+fn get_cell(message: &JsValue, key: &str) -> Result<Cell, JsValue> {
+    Ok(if get_number(message, key)? != 0.0 { Cell::Alive } else { Cell::Dead })
+}
+
+fn get_auto_pause(message: &JsValue) -> AutoPause {
+    match get_number(message, "auto_pause").unwrap_or(0.0) as u32 {
+        1 => AutoPause::Extinction,
+        2 => AutoPause::ExtinctionOrStable,
+        _ => AutoPause::Off,
+    }
+}
+
+fn get_bytes(message: &JsValue, key: &str) -> Result<Vec<u8>, JsValue> {
+    Ok(js_sys::Uint8Array::new(&get(message, key)?).to_vec())
+}
+
+fn set(message: &js_sys::Object, key: &str, value: &JsValue) {
+    js_sys::Reflect::set(message, &JsValue::from_str(key), value).unwrap();
+}
+
+fn message(kind: &str) -> js_sys::Object {
+    let message = js_sys::Object::new();
+    set(&message, "type", &JsValue::from_str(kind));
+    message
+}
+
+impl Request {
+    pub fn from_js(message: &JsValue) -> Result<Request, JsValue> {
+        let kind = get(message, "type")?.as_string().unwrap_or_default();
+        Ok(match kind.as_str() {
+            "init" => Request::Init {
+                width: get_number(message, "width")? as u32,
+                height: get_number(message, "height")? as u32,
+                density: get_number(message, "density").unwrap_or(0.5),
+                seed: get_number(message, "seed")? as u64,
+            },
+            "tick" => Request::Tick {
+                generations: get_number(message, "generations")? as u32,
+                auto_pause: get_auto_pause(message),
+            },
+            "set_cell" => Request::SetCell {
+                row: get_number(message, "row")? as u32,
+                col: get_number(message, "col")? as u32,
+                cell: get_cell(message, "cell")?,
+            },
+            "extend_line" => Request::ExtendLine {
+                from: (get_number(message, "from_row")? as u32, get_number(message, "from_col")? as u32),
+                to: (get_number(message, "to_row")? as u32, get_number(message, "to_col")? as u32),
+                cell: get_cell(message, "cell")?,
+            },
+            "paint" => Request::Paint {
+                cells: js_sys::Uint32Array::new(&get(message, "cells")?).to_vec().chunks_exact(2).map(|pair| (pair[0], pair[1])).collect(),
+                cell: get_cell(message, "cell")?,
+            },
+            "load" => Request::Load {
+                width: get_number(message, "width")? as u32,
+                height: get_number(message, "height")? as u32,
+                generation: get_number(message, "generation")? as u64,
+                rule: get(message, "rule")?.as_string().unwrap_or_else(|| "B3/S23".to_string()),
+                topology: if get_number(message, "topology").unwrap_or(0.0) != 0.0 { Topology::Bounded } else { Topology::Torus },
+                cell_size: get_number(message, "cell_size")? as u32,
+                grid_visible: get(message, "grid_visible")?.as_bool().unwrap_or(true),
+                colors: [
+                    get_string(message, "grid_color")?,
+                    get_string(message, "dead_color")?,
+                    get_string(message, "alive_color")?,
+                ],
+                cells: get_bytes(message, "cells")?,
+            },
+            "snapshot" => Request::Snapshot,
+            "attach" => Request::Attach {
+                canvas: get(message, "canvas")?.dyn_into()?,
+            },
+            "play" => Request::Play {
+                generations: get_number(message, "generations")? as u32,
+                auto_pause: get_auto_pause(message),
+            },
+            "pause" => Request::Pause,
+            _ => return Err(JsValue::from_str(&format!("unknown request '{}'", kind))),
+        })
+    }
+
+    /// The request as a message, and the buffer to transfer with it.
+    pub fn to_js(&self) -> (js_sys::Object, js_sys::Array) {
+        let transfer = js_sys::Array::new();
+        let message = match *self {
+            Request::Init { width, height, density, seed } => {
+                let message = message("init");
+                set(&message, "width", &width.into());
+                set(&message, "height", &height.into());
+                set(&message, "density", &density.into());
+                set(&message, "seed", &(seed as f64).into());
+                message
+            }
+            Request::Tick { generations, auto_pause } | Request::Play { generations, auto_pause } => {
+                let message = message(if let Request::Tick { .. } = *self { "tick" } else { "play" });
+                set(&message, "generations", &generations.into());
+                set(&message, "auto_pause", &(auto_pause as u32).into());
+                message
+            }
+            Request::SetCell { row, col, cell } => {
+                let message = message("set_cell");
+                set(&message, "row", &row.into());
+                set(&message, "col", &col.into());
+                set(&message, "cell", &(cell as u32).into());
+                message
+            }
+            Request::ExtendLine { from, to, cell } => {
+                let message = message("extend_line");
+                set(&message, "from_row", &from.0.into());
+                set(&message, "from_col", &from.1.into());
+                set(&message, "to_row", &to.0.into());
+                set(&message, "to_col", &to.1.into());
+                set(&message, "cell", &(cell as u32).into());
+                message
+            }
+            Request::Paint { ref cells, cell } => {
+                let message = message("paint");
+                let flat: Vec<u32> = cells.iter().flat_map(|&(row, col)| vec![row, col]).collect();
+                let cells = js_sys::Uint32Array::from(&flat[..]);
+                transfer.push(&cells.buffer());
+                set(&message, "cells", &cells);
+                set(&message, "cell", &(cell as u32).into());
+                message
+            }
+            Request::Load {
+                width,
+                height,
+                generation,
+                ref rule,
+                topology,
+                cell_size,
+                grid_visible,
+                ref colors,
+                ref cells,
+            } => {
+                let message = message("load");
+                set(&message, "width", &width.into());
+                set(&message, "height", &height.into());
+                set(&message, "generation", &(generation as f64).into());
+                set(&message, "rule", &JsValue::from_str(rule));
+                set(&message, "topology", &(topology as u32).into());
+                set(&message, "cell_size", &cell_size.into());
+                set(&message, "grid_visible", &grid_visible.into());
+                set(&message, "grid_color", &JsValue::from_str(&colors[0]));
+                set(&message, "dead_color", &JsValue::from_str(&colors[1]));
+                set(&message, "alive_color", &JsValue::from_str(&colors[2]));
+                let cells = js_sys::Uint8Array::from(&cells[..]);
+                transfer.push(&cells.buffer());
+                set(&message, "cells", &cells);
+                message
+            }
+            Request::Snapshot => message("snapshot"),
+            Request::Attach { ref canvas } => {
+                let message = message("attach");
+                transfer.push(canvas);
+                set(&message, "canvas", canvas);
+                message
+            }
+            Request::Pause => message("pause"),
+        };
+        (message, transfer)
+    }
+}
+
+impl Board {
+    pub fn from_js(message: &JsValue) -> Result<Board, JsValue> {
+        Ok(Board {
+            width: get_number(message, "width")? as u32,
+            height: get_number(message, "height")? as u32,
+            generation: get_number(message, "generation")? as u64,
+            settled: get(message, "settled")?.as_f64().map(|how| match how as u32 {
+                0 => Settled::Extinct,
+                _ => Settled::Stable,
+            }),
+            cells: get_bytes(message, "cells")?,
+        })
+    }
+
+    /// The board as a message, and the buffer to transfer with it.
+    pub fn to_js(&self) -> (js_sys::Object, js_sys::Array) {
+        let message = message("board");
+        set(&message, "width", &self.width.into());
+        set(&message, "height", &self.height.into());
+        set(&message, "generation", &(self.generation as f64).into());
+        if let Some(how) = self.settled {
+            set(&message, "settled", &(how as u32).into());
+        }
+        let cells = js_sys::Uint8Array::from(&self.cells[..]);
+        set(&message, "cells", &cells);
+        (message, js_sys::Array::of1(&cells.buffer()))
+    }
+}
+
+/// The worker's end: the simulation, and its animation loop while it runs
+/// one.
+struct Host {
+    scope: web_sys::DedicatedWorkerGlobalScope,
+    simulation: RefCell<Simulation>,
+    frame: RefCell<Option<FrameClosure>>,
+    /// Whether an animation frame has been asked for.
+    scheduled: RefCell<bool>,
+    /// When the page last heard how the running board is doing.
+    last_report: RefCell<f64>,
+}
+
+impl Host {
+    fn post(&self, board: &Board) -> Result<(), JsValue> {
+        let (message, transfer) = board.to_js();
+        self.scope.post_message_with_transfer(&message, &transfer)
+    }
+
+    fn on_message(&self, event: web_sys::MessageEvent) -> Result<(), JsValue> {
+        let request = Request::from_js(&event.data())?;
+        let board = self.simulation.borrow_mut().handle(request).map_err(|err| JsValue::from_str(&err))?;
+        if let Some(board) = board {
+            self.post(&board)?;
+        }
+        self.schedule()
+    }
+
+    fn on_frame(&self, timestamp: f64) -> Result<(), JsValue> {
+        *self.scheduled.borrow_mut() = false;
+        let board = self.simulation.borrow_mut().frame();
+        if let Some(board) = board {
+            let mut last_report = self.last_report.borrow_mut();
+            if board.settled.is_some() || timestamp - *last_report >= REPORT_INTERVAL_MS {
+                *last_report = timestamp;
+                self.post(&board)?;
+            }
+        }
+        self.schedule()
+    }
+
+    /// Ask for the next animation frame, if running and not already asked.
+    fn schedule(&self) -> Result<(), JsValue> {
+        if self.simulation.borrow().running() && !*self.scheduled.borrow() {
+            self.scope.request_animation_frame(self.frame.borrow().as_ref().unwrap().as_ref().unchecked_ref())?;
+            *self.scheduled.borrow_mut() = true;
+        }
+        Ok(())
+    }
+}
+
+/// Answer requests from the page, when running inside a worker.
+pub fn run() -> Result<(), JsValue> {
+    let host = Rc::new(Host {
+        scope: js_sys::global().dyn_into::<web_sys::DedicatedWorkerGlobalScope>()?,
+        simulation: RefCell::new(Simulation::new()),
+        frame: RefCell::new(None),
+        scheduled: RefCell::new(false),
+        last_report: RefCell::new(0.0),
+    });
+    let frame_host = host.clone();
+    *host.frame.borrow_mut() = Some(Closure::wrap(Box::new(move |timestamp| {
+        if let Err(err) = frame_host.on_frame(timestamp) {
+            web_sys::console::error_1(&err);
+        }
+    }) as Box<dyn FnMut(f64)>));
+    let message_host = host.clone();
+    let onmessage = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+        if let Err(err) = message_host.on_message(event) {
+            web_sys::console::error_1(&err);
+        }
+    }) as Box<dyn FnMut(web_sys::MessageEvent)>);
+    host.scope.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    onmessage.forget();
+    host.scope.post_message(&message("ready"))
+}
+
+/// The page's handle on a simulation running in a worker.
+pub struct Remote {
+    worker: web_sys::Worker,
+    ready: bool,
+    /// Whether a tick is on its way to the worker or back.
+    busy: bool,
+    /// The fingerprint of the page's board when the worker last had the
+    /// same one, or `None` if they may differ.
+    synced: Option<u64>,
+    /// Whether the worker draws on the page's canvas and runs its own loop.
+    draws: bool,
+}
+
+impl Remote {
+    /// Start a worker from the script at `url`, unless the browser can't.
+    pub fn spawn(url: &str) -> Option<Remote> {
+        if !js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("Worker")).unwrap_or(false) {
+            return None;
+        }
+        let worker = web_sys::Worker::new(url).ok()?;
+        Some(Remote {
+            worker,
+            ready: false,
+            busy: false,
+            synced: None,
+            draws: false,
+        })
+    }
+
+    pub fn worker(&self) -> &web_sys::Worker {
+        &self.worker
+    }
+
+    pub fn send(&self, request: &Request) -> Result<(), JsValue> {
+        let (message, transfer) = request.to_js();
+        self.worker.post_message_with_transfer(&message, &transfer)
+    }
+
+    /// Hand the worker a canvas to draw on, after which it animates by
+    /// itself between `Play` and `Pause`.
+    pub fn attach(&mut self, canvas: web_sys::OffscreenCanvas) -> Result<(), JsValue> {
+        self.send(&Request::Attach { canvas })?;
+        self.draws = true;
+        Ok(())
+    }
+
+    pub fn draws(&self) -> bool {
+        self.draws
+    }
+
+    /// Send the page's board, and how it looks, to the worker.
+    pub fn push(&mut self, universe: &Universe) -> Result<(), JsValue> {
+        self.send(&Request::load(universe))?;
+        self.mark_synced(universe);
+        Ok(())
+    }
+
+    /// Ask the worker to advance `generations` generations, first sending
+    /// it the page's board if that changed since they last matched. Does
+    /// nothing until the worker is ready or while a tick is unanswered.
+    pub fn request_tick(&mut self, universe: &Universe, generations: u32, auto_pause: AutoPause) -> Result<(), JsValue> {
+        if !self.ready || self.busy || generations == 0 {
+            return Ok(());
+        }
+        let fingerprint = universe.fingerprint();
+        if self.synced != Some(fingerprint) {
+            self.send(&Request::load(universe))?;
+        }
+        self.send(&Request::Tick { generations, auto_pause })?;
+        self.busy = true;
+        self.synced = Some(fingerprint);
+        Ok(())
+    }
+
+    /// Take a message from the worker, returning the board if it is one.
+    pub fn receive(&mut self, message: &JsValue) -> Result<Option<Board>, JsValue> {
+        let kind = get(message, "type")?.as_string().unwrap_or_default();
+        match kind.as_str() {
+            "ready" => {
+                self.ready = true;
+                Ok(None)
+            }
+            "board" => {
+                self.busy = false;
+                Board::from_js(message).map(Some)
+            }
+            _ => Err(JsValue::from_str("unknown message from the worker")),
+        }
+    }
+
+    /// Whether `universe` is still the board the worker last started from,
+    /// so its answer follows on from it.
+    pub fn matches(&self, universe: &Universe) -> bool {
+        self.synced == Some(universe.fingerprint())
+    }
+
+    /// Note that the page and the worker now have the same board.
+    pub fn mark_synced(&mut self, universe: &Universe) {
+        self.synced = Some(universe.fingerprint());
+    }
+
+    /// Note that the worker's board can't be trusted to match the page's,
+    /// so the next tick sends the page's first.
+    pub fn mark_stale(&mut self) {
+        self.synced = None;
+    }
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        self.worker.terminate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulation_follows_requests() {
+        let mut simulation = Simulation::new();
+        let board = simulation
+            .handle(Request::Init { width: 8, height: 6, density: 0.0, seed: 1 })
+            .unwrap()
+            .unwrap();
+        assert_eq!((board.width, board.height, board.generation), (8, 6, 0));
+        assert_eq!(board.cells, vec![0; 6]);
+
+        // A blinker, set a cell at a time.
+        for col in 1..4 {
+            let request = Request::SetCell { row: 2, col, cell: Cell::Alive };
+            assert_eq!(simulation.handle(request), Ok(None));
+        }
+        assert!(simulation.handle(Request::SetCell { row: 6, col: 0, cell: Cell::Alive }).is_err());
+        let board = simulation
+            .handle(Request::Tick { generations: 3, auto_pause: AutoPause::ExtinctionOrStable })
+            .unwrap()
+            .unwrap();
+        assert_eq!((board.generation, board.settled), (3, None));
+
+        let mut expected = Universe::empty(8, 6).unwrap();
+        expected.set_cells(&[(1, 2), (2, 2), (3, 2)]).unwrap();
+        assert_eq!(board.cells, expected.cells_copy());
+        assert_eq!(simulation.handle(Request::Snapshot).unwrap().unwrap(), board);
+    }
+
+    #[test]
+    fn simulation_loads_and_settles() {
+        let mut simulation = Simulation::new();
+        // A lone cell dies in the first generation.
+        let mut page = Universe::empty(4, 4).unwrap();
+        page.set_cells(&[(1, 1)]).unwrap();
+        page.set_topology(Topology::Bounded);
+        let cells = page.cells_copy();
+        page.load(4, 4, 10, &cells).unwrap();
+        assert_eq!(simulation.handle(Request::load(&page)), Ok(None));
+
+        let board = simulation
+            .handle(Request::Tick { generations: 5, auto_pause: AutoPause::Extinction })
+            .unwrap()
+            .unwrap();
+        assert_eq!((board.generation, board.settled), (11, Some(Settled::Extinct)));
+
+        // Without auto-pause, it ticks the whole way.
+        let board = simulation
+            .handle(Request::Tick { generations: 5, auto_pause: AutoPause::Off })
+            .unwrap()
+            .unwrap();
+        assert_eq!((board.generation, board.settled), (16, None));
+    }
+
+    #[test]
+    fn simulation_runs_its_own_loop() {
+        let mut simulation = Simulation::new();
+        simulation.handle(Request::Init { width: 6, height: 6, density: 0.0, seed: 1 }).unwrap();
+        let stroke = Request::ExtendLine { from: (2, 1), to: (2, 3), cell: Cell::Alive };
+        assert_eq!(simulation.handle(stroke), Ok(None));
+        assert!(simulation.handle(Request::ExtendLine { from: (0, 0), to: (0, 6), cell: Cell::Alive }).is_err());
+        // A brush paints nothing if any of it is off the board.
+        assert!(simulation.handle(Request::Paint { cells: vec![(4, 4), (6, 0)], cell: Cell::Alive }).is_err());
+        assert_eq!(simulation.handle(Request::Paint { cells: vec![(2, 2), (2, 3)], cell: Cell::Alive }), Ok(None));
+        assert_eq!(simulation.frame(), None);
+
+        let play = Request::Play { generations: 2, auto_pause: AutoPause::ExtinctionOrStable };
+        assert_eq!(simulation.handle(play), Ok(None));
+        assert!(simulation.running());
+        assert_eq!(simulation.frame().unwrap().generation, 2);
+        assert_eq!(simulation.frame().unwrap().generation, 4);
+        let board = simulation.handle(Request::Pause).unwrap().unwrap();
+        assert_eq!(board.generation, 4);
+        assert!(!simulation.running());
+        assert_eq!(simulation.frame(), None);
+
+        // Settling stops the loop.
+        simulation.handle(Request::SetCell { row: 2, col: 2, cell: Cell::Dead }).unwrap();
+        simulation.handle(Request::Play { generations: 1, auto_pause: AutoPause::Extinction }).unwrap();
+        assert_eq!(simulation.frame().unwrap().settled, Some(Settled::Extinct));
+        assert!(!simulation.running());
+    }
+}
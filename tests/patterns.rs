@@ -0,0 +1,81 @@
+//! Canonical Life patterns, checked natively through `set_cells` and
+//! `get_cells` so that no rendering is involved.
+
+extern crate wasm_game_of_life;
+
+use wasm_game_of_life::{Cell, Universe};
+
+fn universe_with(width: u32, height: u32, cells: &[(u32, u32)]) -> Universe {
+    let mut universe = Universe::new();
+    universe.set_width(width);
+    universe.set_height(height);
+    universe.set_cells(cells).unwrap();
+    universe
+}
+
+fn live_cells(universe: &Universe) -> Vec<(u32, u32)> {
+    let width = universe.width();
+    universe
+        .get_cells()
+        .iter()
+        .enumerate()
+        .filter(|&(_, &cell)| cell == Cell::Alive)
+        .map(|(idx, _)| (idx as u32 / width, idx as u32 % width))
+        .collect()
+}
+
+/// Check that the pattern changes every generation until `period`, when
+/// it returns to where it started.
+fn assert_period(cells: &[(u32, u32)], period: u32) {
+    let mut universe = universe_with(8, 8, cells);
+    let start = universe.get_cells();
+    for generation in 1..period {
+        universe.tick();
+        assert_ne!(universe.get_cells(), start, "repeated after {} generations", generation);
+    }
+    universe.tick();
+    assert_eq!(universe.get_cells(), start);
+}
+
+#[test]
+fn blinker() {
+    assert_period(&[(3, 2), (3, 3), (3, 4)], 2);
+}
+
+#[test]
+fn toad() {
+    assert_period(&[(3, 3), (3, 4), (3, 5), (4, 2), (4, 3), (4, 4)], 2);
+}
+
+#[test]
+fn beacon() {
+    assert_period(&[(2, 2), (2, 3), (3, 2), (4, 5), (5, 4), (5, 5)], 2);
+}
+
+#[test]
+fn block() {
+    assert_period(&[(3, 3), (3, 4), (4, 3), (4, 4)], 1);
+}
+
+#[test]
+fn glider() {
+    let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+    let mut universe = universe_with(16, 16, &glider);
+    for step in 1..=8 {
+        universe.tick_many(4);
+        let moved: Vec<_> = glider.iter().map(|&(row, col)| (row + step, col + step)).collect();
+        assert_eq!(live_cells(&universe), moved);
+    }
+}
+
+#[test]
+fn r_pentomino() {
+    // On an unbounded grid the r-pentomino has 121 cells at generation 100.
+    // By then it spans 24 rows and 50 columns, including the first escaping
+    // gliders. The torus has to leave a gap of at least two cells around
+    // that, or cells would start interacting across the wrap and change the
+    // count.
+    let mut universe = universe_with(64, 64, &[(30, 41), (30, 42), (31, 40), (31, 41), (32, 41)]);
+    universe.tick_many(100);
+    assert_eq!(universe.population(), 121);
+}
@@ -5,8 +5,11 @@
 extern crate wasm_bindgen_test;
 use wasm_bindgen_test::*;
 
+extern crate js_sys;
+extern crate wasm_bindgen;
 extern crate wasm_game_of_life;
-use wasm_game_of_life::Universe;
+extern crate web_sys;
+use wasm_game_of_life::{Cell, Universe};
 
 wasm_bindgen_test_configure!(run_in_browser);
 
@@ -20,7 +23,7 @@ pub fn input_spaceship() -> Universe {
     let mut universe = Universe::new();
     universe.set_width(6);
     universe.set_height(6);
-    universe.set_cells(&[(1,2), (2,3), (3,1), (3,2), (3,3)]);
+    universe.set_cells(&[(1,2), (2,3), (3,1), (3,2), (3,3)]).unwrap();
     universe
 }
 
@@ -29,7 +32,7 @@ pub fn expected_spaceship() -> Universe {
     let mut universe = Universe::new();
     universe.set_width(6);
     universe.set_height(6);
-    universe.set_cells(&[(2,1), (2,3), (3,2), (3,3), (4,2)]);
+    universe.set_cells(&[(2,1), (2,3), (3,2), (3,3), (4,2)]).unwrap();
     universe
 }
 
@@ -46,3 +49,734 @@ pub fn test_tick() {
     input_universe.tick();
     assert_eq!(&input_universe.get_cells(), &expected_universe.get_cells());
 }
+
+#[wasm_bindgen_test]
+pub fn test_new() {
+    let universe = Universe::new();
+    assert_eq!(universe.width(), 128);
+    assert_eq!(universe.height(), 128);
+    let cells = universe.get_cells();
+    assert_eq!(cells.len(), 128 * 128);
+    for (i, &cell) in cells.iter().enumerate() {
+        let alive = i % 2 == 0 || i % 7 == 0;
+        assert_eq!(cell, if alive { Cell::Alive } else { Cell::Dead });
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_blinker() {
+    let mut universe = Universe::new();
+    universe.set_width(5);
+    universe.set_height(5);
+    universe.set_cells(&[(2,1), (2,2), (2,3)]).unwrap();
+    universe.tick();
+    assert_eq!(format!("{:#}", universe), ".....\n..#..\n..#..\n..#..\n.....\n");
+    universe.tick();
+    assert_eq!(format!("{:#}", universe), ".....\n.....\n.###.\n.....\n.....\n");
+}
+
+#[wasm_bindgen_test]
+pub fn test_toggle_cell() {
+    let mut universe = Universe::new();
+    universe.set_width(4);
+    universe.set_height(4);
+    universe.toggle_cell(1, 2);
+    assert_eq!(universe.get_cells()[6], Cell::Alive);
+    universe.toggle_cell(1, 2);
+    assert_eq!(universe.get_cells()[6], Cell::Dead);
+}
+
+#[wasm_bindgen_test]
+pub fn test_resize_resets_cells() {
+    let mut universe = Universe::new();
+    universe.set_width(16);
+    assert_eq!(universe.width(), 16);
+    assert_eq!(universe.population(), 0);
+
+    universe.set_cells(&[(0,0), (1,1)]).unwrap();
+    universe.set_height(8);
+    assert_eq!(universe.height(), 8);
+    assert_eq!(universe.get_cells(), vec![Cell::Dead; 16 * 8]);
+}
+
+#[wasm_bindgen_test]
+pub fn test_changed_cells() {
+    let mut universe = Universe::new();
+    universe.set_width(5);
+    universe.set_height(5);
+    universe.set_cells(&[(2,1), (2,2), (2,3)]).unwrap();
+    universe.mark_drawn();
+
+    // The blinker's two ends die and two cells are born above and below
+    // its middle.
+    universe.tick();
+    assert!(!universe.needs_full_redraw());
+    assert_eq!(universe.changed_cells_len(), 4);
+}
+
+/// The page's canvas, created the first time a test asks for it.
+fn game_canvas() -> web_sys::HtmlCanvasElement {
+    use wasm_bindgen::JsCast;
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    let canvas = match document.get_element_by_id("game-of-life-canvas") {
+        Some(canvas) => canvas,
+        None => {
+            let canvas = document.create_element("canvas").unwrap();
+            canvas.set_id("game-of-life-canvas");
+            document.body().unwrap().append_child(&canvas).unwrap();
+            canvas
+        }
+    };
+    canvas.dyn_into::<web_sys::HtmlCanvasElement>().unwrap()
+}
+
+fn context(canvas: &web_sys::HtmlCanvasElement) -> web_sys::CanvasRenderingContext2d {
+    use wasm_bindgen::JsCast;
+
+    canvas
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<web_sys::CanvasRenderingContext2d>()
+        .unwrap()
+}
+
+#[wasm_bindgen_test]
+pub fn test_start_without_fps_panel() {
+    // Only the canvas: no FPS panel, stats or buttons.
+    let canvas = game_canvas();
+    assert!(wasm_game_of_life::start().is_ok());
+
+    assert!(canvas.width() > 0);
+    // The top-left pixel is on the grid, so something was painted.
+    let pixel = context(&canvas).get_image_data(0.0, 0.0, 1.0, 1.0).unwrap().data();
+    assert_ne!(pixel[3], 0);
+}
+
+#[wasm_bindgen_test]
+pub fn test_step_draws_after_tick() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::step().unwrap();
+
+    // The page starts from `Universe::new`, so after one step the canvas
+    // should show its second generation, not its first.
+    let mut expected = Universe::new();
+    let first = expected.get_cells();
+    expected.tick();
+    let second = expected.get_cells();
+    assert_ne!(first[..128], second[..128]);
+
+    let pitch = expected.cell_size() as f64 + 1.0;
+    let ctx = context(&canvas);
+    for col in 0..128 {
+        // The middle of the cell in the top row.
+        let x = col as f64 * pitch + pitch / 2.0;
+        let pixel = ctx.get_image_data(x, pitch / 2.0, 1.0, 1.0).unwrap().data();
+        let alive = pixel[0] < 0x80;
+        assert_eq!(alive, second[col] == Cell::Alive, "column {}", col);
+    }
+}
+
+#[wasm_bindgen_test]
+pub fn test_cell_accessors() {
+    let mut universe = Universe::new();
+    universe.set_width(5);
+    universe.set_height(5);
+    universe.set_cells(&[(0,1), (1,2), (2,0), (2,1), (2,2)]).unwrap();
+
+    let packed = vec![0b1000_0010, 0b0001_1100, 0, 0];
+    assert_eq!(universe.cells_view().to_vec(), packed);
+    assert_eq!(universe.cells_copy(), packed);
+    assert_eq!(universe.live_cells().to_vec(), vec![0, 1, 1, 2, 2, 0, 2, 1, 2, 2]);
+}
+
+/// Fire a mouse event at the middle of the cell in the top row and given
+/// column of the page's canvas.
+fn mouse_at(canvas: &web_sys::HtmlCanvasElement, event_type: &str, col: u32, button: i16, ctrl: bool) {
+    let pitch = 6.0;
+    let rect = canvas.get_bounding_client_rect();
+    let scale = rect.width() / canvas.width() as f64;
+    let init = web_sys::MouseEventInit::new();
+    init.set_bubbles(true);
+    init.set_button(button);
+    init.set_ctrl_key(ctrl);
+    init.set_client_x((rect.x() + (col as f64 * pitch + pitch / 2.0) * scale) as i32);
+    init.set_client_y((rect.y() + pitch / 2.0 * scale) as i32);
+    let event = web_sys::MouseEvent::new_with_mouse_event_init_dict(event_type, &init).unwrap();
+    canvas.dispatch_event(&event).unwrap();
+}
+
+fn alive_on_canvas(canvas: &web_sys::HtmlCanvasElement, col: u32) -> bool {
+    let pixel = context(canvas).get_image_data(col as f64 * 6.0 + 3.0, 3.0, 1.0, 1.0).unwrap().data();
+    pixel[0] < 0x80
+}
+
+#[wasm_bindgen_test]
+pub fn test_click_sets_and_erases() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::step().unwrap();
+
+    // Clicking a live cell leaves it alive rather than toggling it.
+    let col = (0..128).find(|&col| alive_on_canvas(&canvas, col)).unwrap();
+    mouse_at(&canvas, "mousedown", col, 0, false);
+    mouse_at(&canvas, "mouseup", col, 0, false);
+    assert!(alive_on_canvas(&canvas, col));
+
+    // Right click and Ctrl+click erase.
+    mouse_at(&canvas, "mousedown", col, 2, false);
+    mouse_at(&canvas, "mouseup", col, 2, false);
+    assert!(!alive_on_canvas(&canvas, col));
+    mouse_at(&canvas, "mousedown", col, 0, false);
+    mouse_at(&canvas, "mouseup", col, 0, false);
+    assert!(alive_on_canvas(&canvas, col));
+    mouse_at(&canvas, "mousedown", col, 0, true);
+    mouse_at(&canvas, "mouseup", col, 0, true);
+    assert!(!alive_on_canvas(&canvas, col));
+
+    // The old behaviour is still there behind a setting.
+    wasm_game_of_life::set_toggle_on_click(true).unwrap();
+    mouse_at(&canvas, "mousedown", col, 0, false);
+    mouse_at(&canvas, "mouseup", col, 0, false);
+    assert!(alive_on_canvas(&canvas, col));
+    mouse_at(&canvas, "mousedown", col, 0, false);
+    mouse_at(&canvas, "mouseup", col, 0, false);
+    assert!(!alive_on_canvas(&canvas, col));
+    wasm_game_of_life::set_toggle_on_click(false).unwrap();
+}
+
+#[wasm_bindgen_test]
+pub fn test_grid_toggle() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    // The leftmost column of pixels is all gridline.
+    let pixel = || context(&canvas).get_image_data(0.0, 3.0, 1.0, 1.0).unwrap().data().0;
+    let shown = pixel();
+
+    // Hidden, the gridline takes the dead cell color, and the cells stay put.
+    wasm_game_of_life::set_grid_visible(false).unwrap();
+    assert_eq!(pixel(), [0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_ne!(pixel(), shown);
+    let mut universe = Universe::new();
+    universe.tick();
+    let col = (0..128).find(|&col| universe.get_cells()[col] == Cell::Alive).unwrap() as u32;
+    wasm_game_of_life::step().unwrap();
+    assert!(alive_on_canvas(&canvas, col));
+
+    wasm_game_of_life::set_grid_visible(true).unwrap();
+    assert_eq!(pixel(), shown);
+}
+
+#[wasm_bindgen_test]
+pub fn test_change_highlight() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::set_change_highlight(5).unwrap();
+    let mut universe = Universe::new();
+    let before = universe.get_cells();
+    universe.tick();
+    let col = (0..128).find(|&col| before[col] == Cell::Dead && universe.get_cells()[col] == Cell::Alive).unwrap() as u32;
+    let pixel = || context(&canvas).get_image_data(col as f64 * 6.0 + 3.0, 3.0, 1.0, 1.0).unwrap().data().0;
+
+    // The newborn cell flashes until the highlight is turned off.
+    wasm_game_of_life::step().unwrap();
+    assert_eq!(pixel(), [0x22, 0xC5, 0x5E, 0xFF]);
+    wasm_game_of_life::set_change_highlight(0).unwrap();
+    assert_ne!(pixel(), [0x22, 0xC5, 0x5E, 0xFF]);
+    assert!(alive_on_canvas(&canvas, col));
+}
+
+#[wasm_bindgen_test]
+pub fn test_trail() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::set_trail(4).unwrap();
+    let mut universe = Universe::new();
+    let before = universe.get_cells();
+    universe.tick();
+    let col = (0..128).find(|&col| before[col] == Cell::Alive && universe.get_cells()[col] == Cell::Dead).unwrap() as u32;
+    let pixel = || context(&canvas).get_image_data(col as f64 * 6.0 + 3.0, 3.0, 1.0, 1.0).unwrap().data().0;
+
+    // The cell that just died is shaded between alive and dead.
+    wasm_game_of_life::step().unwrap();
+    let shade = pixel();
+    assert!(shade[0] > 0x10 && shade[0] < 0xF0, "{:?}", shade);
+    wasm_game_of_life::set_trail(0).unwrap();
+    assert_eq!(pixel(), [0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+#[wasm_bindgen_test]
+pub fn test_stamp_mode() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::pause().unwrap();
+    press("c");
+    assert!(wasm_game_of_life::start_stamp("nothing").is_err());
+    wasm_game_of_life::start_stamp("blinker").unwrap();
+    let pixel = |col: u32| context(&canvas).get_image_data(col as f64 * 6.0 + 3.0, 3.0, 1.0, 1.0).unwrap().data().0;
+
+    // The ghost follows the mouse, and a click leaves the blinker where it
+    // was.
+    mouse_at(&canvas, "mousemove", 20, 0, false);
+    assert_ne!(pixel(21), [0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(pixel(23), [0xFF, 0xFF, 0xFF, 0xFF]);
+    mouse_at(&canvas, "mousedown", 20, 0, false);
+    mouse_at(&canvas, "mouseup", 20, 0, false);
+    assert!((20..23).all(|col| alive_on_canvas(&canvas, col)));
+    assert!(!alive_on_canvas(&canvas, 19) && !alive_on_canvas(&canvas, 23));
+
+    // Out of stamp mode, a click paints a single cell.
+    press("Escape");
+    mouse_at(&canvas, "mousemove", 40, 0, false);
+    mouse_at(&canvas, "mousedown", 40, 0, false);
+    mouse_at(&canvas, "mouseup", 40, 0, false);
+    assert!(alive_on_canvas(&canvas, 40));
+    assert!(!alive_on_canvas(&canvas, 41));
+}
+
+#[wasm_bindgen_test]
+pub fn test_status_bar() {
+    game_canvas();
+    let document = web_sys::window().unwrap().document().unwrap();
+    let status = document.create_element("div").unwrap();
+    status.set_id("status");
+    document.body().unwrap().append_child(&status).unwrap();
+    wasm_game_of_life::start().unwrap();
+
+    // Stepping updates the bar at once, without waiting for a frame.
+    wasm_game_of_life::step().unwrap();
+    let text = status.text_content().unwrap();
+    assert!(text.starts_with("gen "), "{}", text);
+    assert!(text.contains(" · pop ") && text.contains(" · rule "), "{}", text);
+    status.remove();
+}
+
+#[wasm_bindgen_test]
+pub fn test_event_log() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::pause().unwrap();
+    press("c");
+    wasm_game_of_life::start_stamp("glider").unwrap();
+    mouse_at(&canvas, "mousedown", 7, 0, false);
+    mouse_at(&canvas, "mouseup", 7, 0, false);
+    wasm_game_of_life::stop_stamp().unwrap();
+
+    // The latest events come last, and clearing is logged too.
+    let events = js_sys::Array::from(&wasm_game_of_life::events().unwrap());
+    let message = |i: u32| js_sys::Reflect::get(&events.get(i), &"message".into()).unwrap().as_string().unwrap();
+    let len = events.length();
+    assert!(len >= 2);
+    assert_eq!(message(len - 2), "cleared");
+    assert_eq!(message(len - 1), "pattern 'glider' stamped at (0, 7)");
+    let generation = js_sys::Reflect::get(&events.get(len - 1), &"generation".into()).unwrap();
+    assert_eq!(generation.as_f64(), Some(0.0));
+}
+
+#[wasm_bindgen_test]
+pub fn test_pixel_ratio() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::set_pixel_ratio_override(2.0).unwrap();
+    let universe = Universe::new();
+    let pitch = universe.cell_size() + 1;
+    let width = pitch * universe.width() + 1;
+    assert_eq!(canvas.width(), 2 * width);
+    assert_eq!(canvas.style().get_property_value("width").unwrap(), format!("{}px", width));
+
+    // A click in the middle of a top row cell, in CSS pixels, lands on it.
+    let col = 5;
+    let rect = canvas.get_bounding_client_rect();
+    let click = |event_type: &str| {
+        let init = web_sys::MouseEventInit::new();
+        init.set_bubbles(true);
+        init.set_client_x((rect.x() + (col * pitch) as f64 + pitch as f64 / 2.0) as i32);
+        init.set_client_y((rect.y() + pitch as f64 / 2.0) as i32);
+        let event = web_sys::MouseEvent::new_with_mouse_event_init_dict(event_type, &init).unwrap();
+        canvas.dispatch_event(&event).unwrap();
+    };
+    press("c");
+    click("mousedown");
+    click("mouseup");
+    // Its middle, in canvas pixels, is drawn alive.
+    let middle = ((col * pitch) as f64 + pitch as f64 / 2.0) * 2.0;
+    let pixel = context(&canvas).get_image_data(middle, pitch as f64, 1.0, 1.0).unwrap().data();
+    assert!(pixel[0] < 0x80);
+
+    wasm_game_of_life::set_pixel_ratio_override(0.0).unwrap();
+}
+
+/// The page's play/pause button, created the first time a test asks for it.
+fn play_pause_button() -> web_sys::HtmlElement {
+    use wasm_bindgen::JsCast;
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    let button = match document.get_element_by_id("play-pause") {
+        Some(button) => button,
+        None => {
+            let button = document.create_element("button").unwrap();
+            button.set_id("play-pause");
+            document.body().unwrap().append_child(&button).unwrap();
+            button
+        }
+    };
+    button.dyn_into::<web_sys::HtmlElement>().unwrap()
+}
+
+fn press(key: &str) {
+    let init = web_sys::KeyboardEventInit::new();
+    init.set_key(key);
+    init.set_bubbles(true);
+    let event = web_sys::KeyboardEvent::new_with_keyboard_event_init_dict("keydown", &init).unwrap();
+    web_sys::window().unwrap().dispatch_event(&event).unwrap();
+}
+
+#[wasm_bindgen_test]
+pub fn test_keyboard_shortcuts() {
+    let canvas = game_canvas();
+    let button = play_pause_button();
+    wasm_game_of_life::start().unwrap();
+    assert_eq!(button.inner_html(), "▐▐");
+
+    // Space pauses and plays.
+    press(" ");
+    assert_eq!(button.inner_html(), "▶");
+    press(" ");
+    assert_eq!(button.inner_html(), "▐▐");
+    press(" ");
+
+    // `c` clears the board.
+    assert!((0..128).any(|col| alive_on_canvas(&canvas, col)));
+    press("c");
+    assert!(!(0..128).any(|col| alive_on_canvas(&canvas, col)));
+}
+
+#[wasm_bindgen_test]
+pub fn test_play_state() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::JsCast;
+
+    let canvas = game_canvas();
+    let button = play_pause_button();
+    wasm_game_of_life::start().unwrap();
+    assert!(wasm_game_of_life::is_playing().unwrap());
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let seen = events.clone();
+    let listener = Closure::wrap(Box::new(move |event: web_sys::CustomEvent| {
+        seen.borrow_mut().push(event.detail().as_bool().unwrap());
+    }) as Box<dyn FnMut(web_sys::CustomEvent)>);
+    canvas
+        .add_event_listener_with_callback("playstatechange", listener.as_ref().unchecked_ref())
+        .unwrap();
+
+    button.click();
+    assert!(!wasm_game_of_life::is_playing().unwrap());
+    assert_eq!(button.inner_html(), "▶");
+    button.click();
+    assert!(wasm_game_of_life::is_playing().unwrap());
+    assert_eq!(button.inner_html(), "▐▐");
+
+    // Asking for the state it is already in changes nothing.
+    wasm_game_of_life::play().unwrap();
+    wasm_game_of_life::pause().unwrap();
+    wasm_game_of_life::pause().unwrap();
+    wasm_game_of_life::toggle().unwrap();
+    assert!(wasm_game_of_life::is_playing().unwrap());
+    assert_eq!(*events.borrow(), vec![false, true, false, true]);
+
+    canvas
+        .remove_event_listener_with_callback("playstatechange", listener.as_ref().unchecked_ref())
+        .unwrap();
+}
+
+#[wasm_bindgen_test]
+pub fn test_tick_callback() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::JsCast;
+
+    let mut universe = Universe::new();
+    universe.set_width(5);
+    universe.set_height(5);
+    universe.set_cells(&[(2, 1), (2, 2), (2, 3)]).unwrap();
+
+    let stats = Rc::new(RefCell::new(Vec::new()));
+    let seen = stats.clone();
+    let on_tick = Closure::wrap(Box::new(move |stats: JsValue| {
+        let field = |key: &str| js_sys::Reflect::get(&stats, &key.into()).unwrap().as_f64().unwrap() as u32;
+        seen.borrow_mut().push((field("generation"), field("population"), field("births"), field("deaths")));
+    }) as Box<dyn FnMut(JsValue)>);
+    let changed = Rc::new(RefCell::new(Vec::new()));
+    let seen = changed.clone();
+    let on_changed = Closure::wrap(Box::new(move |cells: js_sys::Uint32Array| {
+        seen.borrow_mut().push(cells.to_vec());
+    }) as Box<dyn FnMut(js_sys::Uint32Array)>);
+    universe.set_tick_callback(on_tick.as_ref().unchecked_ref::<js_sys::Function>().clone());
+    universe.set_changed_callback(on_changed.as_ref().unchecked_ref::<js_sys::Function>().clone());
+
+    universe.tick();
+    universe.tick();
+    assert_eq!(*stats.borrow(), vec![(1, 3, 2, 2), (2, 3, 2, 2)]);
+    assert_eq!(*changed.borrow(), vec![vec![7, 11, 13, 17], vec![7, 11, 13, 17]]);
+
+    // A callback that throws doesn't stop the ticking.
+    universe.set_tick_callback(js_sys::Function::new_no_args("throw new Error('oops')"));
+    universe.tick();
+    assert_eq!(universe.generation(), 3);
+
+    universe.clear_tick_callback();
+    universe.clear_changed_callback();
+    universe.tick();
+    assert_eq!(changed.borrow().len(), 3);
+}
+
+#[wasm_bindgen_test]
+pub fn test_custom_rule() {
+    let conway = "return count == 3 || (alive && count == 2) ? 1 : 0";
+    let mut classic = Universe::new_random(20, 20, 0.4, 3);
+    let mut custom = Universe::new_random(20, 20, 0.4, 3);
+    custom.set_rule("B36/S23").unwrap();
+    custom.set_custom_rule(js_sys::Function::new_with_args("alive, count", conway)).unwrap();
+    assert_eq!(custom.rule(), "B3/S23");
+    let mut dynamic = Universe::new_random(20, 20, 0.4, 3);
+    dynamic.set_custom_rule_dynamic(js_sys::Function::new_with_args("alive, count", conway));
+    for _ in 0..10 {
+        classic.tick();
+        custom.tick();
+        dynamic.tick();
+        assert_eq!(custom.get_cells(), classic.get_cells());
+        assert_eq!(dynamic.get_cells(), classic.get_cells());
+    }
+
+    // What the function throws comes back out, and the rule stays.
+    let throws = js_sys::Function::new_with_args("alive, count", "throw new Error('oops')");
+    assert!(custom.set_custom_rule(throws.clone()).is_err());
+    assert_eq!(custom.rule(), "B3/S23");
+    // A dynamic rule that throws leaves the board alone and gives up.
+    dynamic.set_custom_rule_dynamic(throws);
+    let before = dynamic.get_cells();
+    dynamic.tick();
+    assert_eq!(dynamic.get_cells(), before);
+    dynamic.tick();
+    classic.tick();
+    assert_eq!(dynamic.get_cells(), classic.get_cells());
+}
+
+#[wasm_bindgen_test]
+pub fn test_save_and_load_state() {
+    let canvas = game_canvas();
+    let storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::pause().unwrap();
+    press("c");
+    mouse_at(&canvas, "mousedown", 3, 0, false);
+    mouse_at(&canvas, "mouseup", 3, 0, false);
+    assert!(alive_on_canvas(&canvas, 3));
+
+    wasm_game_of_life::save_state("test-board").unwrap();
+    assert!(storage.get_item("test-board").unwrap().unwrap().contains("\"width\":128"));
+    press("c");
+    assert!(!alive_on_canvas(&canvas, 3));
+    wasm_game_of_life::load_state("test-board").unwrap();
+    assert!(alive_on_canvas(&canvas, 3));
+
+    // A small board resizes the canvas: cells (0, 0) and (1, 2) of 3x2.
+    let small = r#"{"width":3,"height":2,"rule":"B3/S23","generation":5,"cells":"IQ=="}"#;
+    storage.set_item("test-board", small).unwrap();
+    wasm_game_of_life::load_state("test-board").unwrap();
+    assert_eq!((canvas.width(), canvas.height()), (19, 13));
+    assert!(alive_on_canvas(&canvas, 0));
+    assert!(!alive_on_canvas(&canvas, 1));
+
+    // Sizes that don't match the cells, and missing boards, are errors.
+    storage.set_item("test-board", &small.replace("\"width\":3", "\"width\":30")).unwrap();
+    assert!(wasm_game_of_life::load_state("test-board").is_err());
+    assert_eq!(canvas.width(), 19);
+    storage.remove_item("test-board").unwrap();
+    assert!(wasm_game_of_life::load_state("test-board").is_err());
+}
+
+#[wasm_bindgen_test]
+pub fn test_insert_pattern_file() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::pause().unwrap();
+    press("c");
+
+    // A drop without files does nothing.
+    let drop = web_sys::DragEvent::new("drop").unwrap();
+    canvas.dispatch_event(&drop).unwrap();
+    assert!(!alive_on_canvas(&canvas, 0));
+
+    // A row wider than the board grows it, and the canvas with it.
+    wasm_game_of_life::insert_pattern_file("row.rle", "x = 130, y = 1\n130o!").unwrap();
+    assert_eq!(canvas.width(), 130 * 6 + 1);
+    assert!(wasm_game_of_life::insert_pattern_file("bad.rle", "x = 1, y = 1\nq!").is_err());
+    assert_eq!(canvas.width(), 130 * 6 + 1);
+}
+
+#[wasm_bindgen_test]
+pub fn test_export_blob() {
+    let canvas = game_canvas();
+    wasm_game_of_life::start().unwrap();
+    wasm_game_of_life::pause().unwrap();
+    press("c");
+    mouse_at(&canvas, "mousedown", 3, 0, false);
+    mouse_at(&canvas, "mouseup", 3, 0, false);
+
+    let rle = "x = 1, y = 1, rule = B3/S23\no!\n";
+    let blob = wasm_game_of_life::export_blob(wasm_game_of_life::ExportFormat::Rle).unwrap();
+    assert_eq!(blob.size(), rle.len() as f64);
+    assert_eq!(blob.type_(), "text/plain");
+    let blob = wasm_game_of_life::export_blob(wasm_game_of_life::ExportFormat::Plaintext).unwrap();
+    assert_eq!(blob.size(), "O\n".len() as f64);
+}
+
+#[wasm_bindgen_test]
+pub fn test_render_to_canvas() {
+    let mut universe = Universe::new();
+    universe.set_width(40);
+    universe.set_height(30);
+    universe.set_cells(&[(2, 3)]).unwrap();
+    let canvas = universe.render_to_canvas().unwrap();
+    assert_eq!((canvas.width(), canvas.height()), (40, 30));
+    let pixel = context(&canvas).get_image_data(3.0, 2.0, 1.0, 1.0).unwrap().data();
+    assert!(pixel[0] < 0x80);
+    let pixel = context(&canvas).get_image_data(4.0, 2.0, 1.0, 1.0).unwrap().data();
+    assert!(pixel[0] > 0x80);
+
+    wasm_game_of_life::start().unwrap();
+    assert!(wasm_game_of_life::snapshot_png(true).is_ok());
+}
+
+fn new_element(tag: &str) -> web_sys::Element {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let element = document.create_element(tag).unwrap();
+    document.body().unwrap().append_child(&element).unwrap();
+    element
+}
+
+#[wasm_bindgen_test]
+pub fn test_two_apps() {
+    use wasm_bindgen::JsCast;
+
+    let config = |canvas: &web_sys::Element, button: &web_sys::Element, rule: &str, pattern: &str| {
+        let controls = js_sys::Object::new();
+        js_sys::Reflect::set(&controls, &"play-pause".into(), button).unwrap();
+        let config = js_sys::Object::new();
+        js_sys::Reflect::set(&config, &"canvas".into(), canvas).unwrap();
+        js_sys::Reflect::set(&config, &"controls".into(), &controls).unwrap();
+        js_sys::Reflect::set(&config, &"width".into(), &32.into()).unwrap();
+        js_sys::Reflect::set(&config, &"height".into(), &24.into()).unwrap();
+        js_sys::Reflect::set(&config, &"rule".into(), &rule.into()).unwrap();
+        js_sys::Reflect::set(&config, &"pattern".into(), &pattern.into()).unwrap();
+        config.into()
+    };
+    let (canvas_a, button_a) = (new_element("canvas"), new_element("button"));
+    let (canvas_b, button_b) = (new_element("canvas"), new_element("button"));
+    let a = wasm_game_of_life::init(config(&canvas_a, &button_a, "B3/S23", "glider")).unwrap();
+    let b = wasm_game_of_life::init(config(&canvas_b, &button_b, "B36/S23", "x = 3, y = 1\n3o!")).unwrap();
+    assert!(a.is_playing() && b.is_playing());
+    assert_eq!((a.rule(), b.rule()), ("B3/S23".to_string(), "B36/S23".to_string()));
+    assert_eq!((a.population(), b.population()), (5, 3));
+    assert_eq!(canvas_a.unchecked_ref::<web_sys::HtmlCanvasElement>().width(), 32 * 6 + 1);
+
+    // Each steps and pauses on its own.
+    a.step();
+    assert_eq!(a.generation(), 1);
+    assert!(!a.is_playing() && b.is_playing());
+    b.pause();
+    let generation = b.generation();
+    a.step();
+    a.step();
+    assert_eq!((a.generation(), b.generation()), (3, generation));
+
+    // Each button plays its own board, and only that one.
+    button_b.unchecked_ref::<web_sys::HtmlElement>().click();
+    assert!(!a.is_playing() && b.is_playing());
+    button_a.unchecked_ref::<web_sys::HtmlElement>().click();
+    assert!(a.is_playing());
+    b.clear();
+    assert_eq!((a.population(), b.population()), (5, 0));
+
+    // A config without a canvas, or with a rule that doesn't parse, is an
+    // error.
+    assert!(wasm_game_of_life::init(js_sys::Object::new().into()).is_err());
+    assert!(wasm_game_of_life::init(config(&canvas_b, &button_b, "not a rule", "glider")).is_err());
+    a.pause();
+}
+
+#[wasm_bindgen_test]
+pub fn test_destroy() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::JsCast;
+
+    // Stand in for `requestAnimationFrame`, so frames come when the test
+    // runs them and those still pending can be seen.
+    let window = web_sys::window().unwrap();
+    let (request, cancel) = (JsValue::from_str("requestAnimationFrame"), JsValue::from_str("cancelAnimationFrame"));
+    let originals = (js_sys::Reflect::get(&window, &request).unwrap(), js_sys::Reflect::get(&window, &cancel).unwrap());
+    let pending: Rc<RefCell<Vec<(i32, js_sys::Function)>>> = Rc::new(RefCell::new(vec![]));
+    let next_id = Rc::new(RefCell::new(0));
+    let requests = pending.clone();
+    let fake_request = Closure::wrap(Box::new(move |callback: js_sys::Function| {
+        *next_id.borrow_mut() += 1;
+        let id = *next_id.borrow();
+        requests.borrow_mut().push((id, callback));
+        id
+    }) as Box<dyn FnMut(js_sys::Function) -> i32>);
+    let cancels = pending.clone();
+    let fake_cancel = Closure::wrap(Box::new(move |id: i32| cancels.borrow_mut().retain(|&(pending, _)| pending != id)) as Box<dyn FnMut(i32)>);
+    js_sys::Reflect::set(&window, &request, fake_request.as_ref()).unwrap();
+    js_sys::Reflect::set(&window, &cancel, fake_cancel.as_ref()).unwrap();
+    let run_frames = |timestamp: f64| {
+        let frames: Vec<_> = pending.borrow_mut().drain(..).collect();
+        for (_, callback) in frames {
+            callback.call1(&JsValue::NULL, &timestamp.into()).unwrap();
+        }
+    };
+
+    let canvas = new_element("canvas");
+    let button = new_element("button");
+    let controls = js_sys::Object::new();
+    js_sys::Reflect::set(&controls, &"play-pause".into(), &button).unwrap();
+    let config = js_sys::Object::new();
+    js_sys::Reflect::set(&config, &"canvas".into(), &canvas).unwrap();
+    js_sys::Reflect::set(&config, &"controls".into(), &controls).unwrap();
+    js_sys::Reflect::set(&config, &"width".into(), &32.into()).unwrap();
+    js_sys::Reflect::set(&config, &"height".into(), &24.into()).unwrap();
+    js_sys::Reflect::set(&config, &"pattern".into(), &"glider".into()).unwrap();
+    let app = wasm_game_of_life::init(config.into()).unwrap();
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into().unwrap();
+
+    // Playing, each frame ticks and asks for the next.
+    run_frames(1000.0);
+    run_frames(2000.0);
+    assert_eq!(app.generation(), 2);
+    assert_eq!(pending.borrow().len(), 1);
+    mouse_at(&canvas, "mousedown", 3, 0, false);
+    mouse_at(&canvas, "mouseup", 3, 0, false);
+    assert_eq!(app.population(), 6);
+
+    app.destroy();
+    // The next frame was cancelled, and the canvas and button do nothing.
+    assert!(pending.borrow().is_empty());
+    mouse_at(&canvas, "mousedown", 5, 0, false);
+    mouse_at(&canvas, "mouseup", 5, 0, false);
+    assert!(!alive_on_canvas(&canvas, 5));
+    let label = button.inner_html();
+    button.unchecked_ref::<web_sys::HtmlElement>().click();
+    assert_eq!(button.inner_html(), label);
+    assert!(pending.borrow().is_empty());
+
+    js_sys::Reflect::set(&window, &request, &originals.0).unwrap();
+    js_sys::Reflect::set(&window, &cancel, &originals.1).unwrap();
+}
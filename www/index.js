@@ -1 +1,14 @@
-import "wasm-game-of-life";
\ No newline at end of file
+import { start, use_worker } from "wasm-game-of-life";
+
+// Test whether cell `i` is alive, given a `Uint8Array` view over the
+// bit-packed buffer returned by `Universe.cells()`.
+export const isAlive = (bytes, i) => (bytes[i >> 3] & (1 << (i & 7))) !== 0;
+
+start();
+
+// Open the page with `#worker` to tick in a Web Worker, keeping this thread
+// free for input on big boards. Where the browser supports
+// `OffscreenCanvas`, the worker draws the board too.
+if (window.location.hash === "#worker") {
+  use_worker("./worker.js");
+}
@@ -0,0 +1,4 @@
+// The async import of the wasm, as in `bootstrap.js`, for the side by side
+// demo.
+import("./side-by-side.js")
+  .catch(e => console.error("Error importing `side-by-side.js`:", e));
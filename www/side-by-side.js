@@ -0,0 +1,19 @@
+import { init } from "wasm-game-of-life";
+
+// Two boards on one page, each with its own rule and controls. The HighLife
+// board starts from a replicator, which only replicates under B36/S23.
+const board = (name, rule, pattern) => init({
+  canvas: `${name}-canvas`,
+  controls: {
+    "play-pause": `${name}-play-pause`,
+    "step": `${name}-step`,
+    "status": `${name}-status`,
+  },
+  width: 96,
+  height: 96,
+  rule,
+  pattern,
+});
+
+board("life", "B3/S23", "r-pentomino");
+board("highlife", "B36/S23", "x = 5, y = 5\n2b3o$bo2bo$o3bo$o2bo$3o!");
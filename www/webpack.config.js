@@ -1,14 +1,39 @@
 const CopyWebpackPlugin = require("copy-webpack-plugin");
 const path = require('path');
 
-module.exports = {
-  entry: "./bootstrap.js",
-  output: {
-    path: path.resolve(__dirname, "dist"),
-    filename: "bootstrap.js",
+module.exports = [
+  {
+    entry: "./bootstrap.js",
+    output: {
+      path: path.resolve(__dirname, "dist"),
+      filename: "bootstrap.js",
+    },
+    mode: "development",
+    plugins: [
+      new CopyWebpackPlugin(['index.html'])
+    ]
   },
-  mode: "development",
-  plugins: [
-    new CopyWebpackPlugin(['index.html'])
-  ]
-};
+  {
+    // Two boards on one page, started with `init` rather than `start`.
+    entry: "./side-by-side-bootstrap.js",
+    output: {
+      path: path.resolve(__dirname, "dist"),
+      filename: "side-by-side-bootstrap.js",
+    },
+    mode: "development",
+    plugins: [
+      new CopyWebpackPlugin(['side-by-side.html'])
+    ]
+  },
+  {
+    // The simulation worker loads the wasm on its own, with the chunk
+    // loading that works inside workers.
+    entry: "./worker.js",
+    target: "webworker",
+    output: {
+      path: path.resolve(__dirname, "dist"),
+      filename: "worker.js",
+    },
+    mode: "development",
+  }
+];
@@ -0,0 +1,5 @@
+// The simulation side of the page, when it ticks in a Web Worker. Like
+// `bootstrap.js`, this does the one async import the wasm needs.
+import("wasm-game-of-life")
+  .then(wasm => wasm.worker_main())
+  .catch(e => console.error("Error starting the simulation worker:", e));